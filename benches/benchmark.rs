@@ -203,6 +203,52 @@ fn bench_kdtree_k_nearest_search(c: &mut Criterion) {
     }
 }
 
+fn bench_kdtree_query_config(c: &mut Criterion) {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let mut group = c.benchmark_group("query_config");
+    const N: usize = 100000;
+    let kd_tree = KdTree::build_by_ordered_float(gen_points3d(N));
+    let configs = [
+        ("default", QueryConfig::default()),
+        (
+            "tie_break",
+            QueryConfig {
+                tie_break: DistanceTieBreak::FirstItem,
+                ..Default::default()
+            },
+        ),
+        (
+            "epsilon",
+            QueryConfig {
+                epsilon: 0.5,
+                ..Default::default()
+            },
+        ),
+        (
+            "instrument",
+            QueryConfig {
+                instrument: true,
+                ..Default::default()
+            },
+        ),
+    ];
+    for (name, config) in &configs {
+        group.bench_with_input(BenchmarkId::new("nearest", name), config, |b, config| {
+            b.iter(|| {
+                let i = rng.gen::<usize>() % kd_tree.len();
+                kd_tree.nearest_with_config(&kd_tree[i], config)
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("nearests", name), config, |b, config| {
+            b.iter(|| {
+                let i = rng.gen::<usize>() % kd_tree.len();
+                kd_tree.nearests_with_config(&kd_tree[i], 10, config)
+            });
+        });
+    }
+}
+
 fn bench_kdtree_within_radius(c: &mut Criterion) {
     use rand::Rng;
     let mut rng = rand::thread_rng();
@@ -254,7 +300,8 @@ criterion_group!(benches1, bench_kdtree_construction);
 criterion_group!(benches2, bench_kdtree_nearest_search);
 criterion_group!(benches3, bench_kdtree_k_nearest_search);
 criterion_group!(benches4, bench_kdtree_within_radius);
-criterion_main!(benches1, benches2, benches3, benches4);
+criterion_group!(benches5, bench_kdtree_query_config);
+criterion_main!(benches1, benches2, benches3, benches4, benches5);
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 struct TestItem<T> {
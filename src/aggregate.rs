@@ -0,0 +1,214 @@
+use crate::augment::per_subtree;
+use crate::{Aabb, KdPoint, KdScalar, KdTreeN, SubtreeBound, WeightedKdPoint};
+use generic_array::ArrayLength;
+
+/// A monoid to aggregate the items in a region by [`KdTreeAggN`].
+pub trait Monoid<T> {
+    type Value: Clone;
+    /// The aggregate of no items.
+    fn identity() -> Self::Value;
+    fn from_item(item: &T) -> Self::Value;
+    /// An associative operation whose identity is [`Self::identity`].
+    fn combine(value1: &Self::Value, value2: &Self::Value) -> Self::Value;
+}
+
+/// Counts the items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Count;
+
+impl<T> Monoid<T> for Count {
+    type Value = usize;
+    fn identity() -> usize {
+        0
+    }
+    fn from_item(_: &T) -> usize {
+        1
+    }
+    fn combine(value1: &usize, value2: &usize) -> usize {
+        value1 + value2
+    }
+}
+
+/// Sums the weights of the items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeightSum;
+
+impl<T: WeightedKdPoint> Monoid<T> for WeightSum {
+    type Value = T::Scalar;
+    fn identity() -> T::Scalar {
+        T::Scalar::zero()
+    }
+    fn from_item(item: &T) -> T::Scalar {
+        item.weight()
+    }
+    fn combine(value1: &T::Scalar, value2: &T::Scalar) -> T::Scalar {
+        *value1 + *value2
+    }
+}
+
+/// An owned kd-tree augmented with the aggregate by a [`Monoid`] and the bounding box per subtree,
+/// so that range-aggregate queries take the aggregates of the subtrees inside the range without visiting their items.
+/// # Example
+/// ```
+/// use kd_tree::{KdMap, KdTreeAgg, WeightSum};
+/// let kdmap: KdMap<[i32; 2], i32> = KdMap::build(vec![([0, 0], 1), ([1, 0], 2), ([0, 1], 3), ([5, 5], 4)]);
+/// let kdtree: KdTreeAgg<_, WeightSum> = KdTreeAgg::new(kdmap);
+/// assert_eq!(kdtree.aggregate_within(&[[0, 0], [1, 1]]), 6);
+/// assert_eq!(kdtree.aggregate_within_radius(&[5, 4], 2), 4);
+/// assert_eq!(kdtree.aggregate(), 10);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct KdTreeAggN<T: KdPoint, N: ArrayLength, M: Monoid<T>> {
+    kdtree: KdTreeN<T, N>,
+    /// The aggregate of each subtree, stored at the position of the median item of the subtree.
+    values: Vec<M::Value>,
+    /// The bounding box of each subtree, stored in the same way as `values`.
+    aabbs: Vec<Aabb<T::Scalar, N>>,
+}
+pub type KdTreeAgg<T, M> = KdTreeAggN<T, <T as KdPoint>::Dim, M>;
+
+impl<T: KdPoint<Dim = N>, N: ArrayLength, M: Monoid<T>> KdTreeAggN<T, N, M> {
+    pub fn new(kdtree: KdTreeN<T, N>) -> Self {
+        let values = per_subtree(&kdtree, |items| {
+            items.iter().fold(M::identity(), |value, item| {
+                M::combine(&value, &M::from_item(item))
+            })
+        });
+        let aabbs = per_subtree(&kdtree, SubtreeBound::<T>::from_items);
+        Self {
+            kdtree,
+            values,
+            aabbs,
+        }
+    }
+
+    pub fn kdtree(&self) -> &KdTreeN<T, N> {
+        &self.kdtree
+    }
+
+    pub fn into_kdtree(self) -> KdTreeN<T, N> {
+        self.kdtree
+    }
+
+    /// Returns the aggregate of all items.
+    pub fn aggregate(&self) -> M::Value {
+        match self.values.get(self.values.len() / 2) {
+            Some(value) => value.clone(),
+            None => M::identity(),
+        }
+    }
+
+    /// Returns the aggregate of the items within a rectangular region.
+    pub fn aggregate_within(
+        &self,
+        query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2],
+    ) -> M::Value {
+        self.aggregate_by(
+            |aabb| {
+                (0..N::to_usize())
+                    .all(|k| query[0].at(k) <= aabb.min[k] && aabb.max[k] <= query[1].at(k))
+            },
+            |aabb| {
+                (0..N::to_usize())
+                    .all(|k| query[0].at(k) <= aabb.max[k] && aabb.min[k] <= query[1].at(k))
+            },
+            |item| {
+                (0..N::to_usize())
+                    .all(|k| query[0].at(k) <= item.at(k) && item.at(k) <= query[1].at(k))
+            },
+        )
+    }
+
+    /// Returns the aggregate of the items within k-dimensional sphere.
+    pub fn aggregate_within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> M::Value {
+        let squared_radius = radius * radius;
+        self.aggregate_by(
+            |aabb| max_squared_distance(aabb, query) < squared_radius,
+            |aabb| SubtreeBound::<T>::min_squared_distance(aabb, query) < squared_radius,
+            |item| crate::squared_distance(item, query) < squared_radius,
+        )
+    }
+
+    /// Aggregates the items which satisfy `accept`, where `contains(aabb)` tells that all items in `aabb` are accepted,
+    /// and `intersects(aabb)` tells that some items in `aabb` may be accepted.
+    fn aggregate_by(
+        &self,
+        contains: impl Fn(&Aabb<T::Scalar, N>) -> bool + Copy,
+        intersects: impl Fn(&Aabb<T::Scalar, N>) -> bool + Copy,
+        accept: impl Fn(&T) -> bool + Copy,
+    ) -> M::Value {
+        fn recurse<T: KdPoint<Dim = N>, N: ArrayLength, M: Monoid<T>>(
+            value: &mut M::Value,
+            items: &[T],
+            values: &[M::Value],
+            aabbs: &[Aabb<T::Scalar, N>],
+            contains: impl Fn(&Aabb<T::Scalar, N>) -> bool + Copy,
+            intersects: impl Fn(&Aabb<T::Scalar, N>) -> bool + Copy,
+            accept: impl Fn(&T) -> bool + Copy,
+        ) {
+            if items.is_empty() {
+                return;
+            }
+            let mid = items.len() / 2;
+            if !intersects(&aabbs[mid]) {
+                return;
+            }
+            if contains(&aabbs[mid]) {
+                *value = M::combine(value, &values[mid]);
+                return;
+            }
+            if accept(&items[mid]) {
+                *value = M::combine(value, &M::from_item(&items[mid]));
+            }
+            recurse::<T, N, M>(
+                value,
+                &items[..mid],
+                &values[..mid],
+                &aabbs[..mid],
+                contains,
+                intersects,
+                accept,
+            );
+            recurse::<T, N, M>(
+                value,
+                &items[mid + 1..],
+                &values[mid + 1..],
+                &aabbs[mid + 1..],
+                contains,
+                intersects,
+                accept,
+            );
+        }
+        let mut value = M::identity();
+        recurse::<T, N, M>(
+            &mut value,
+            &self.kdtree,
+            &self.values,
+            &self.aabbs,
+            contains,
+            intersects,
+            accept,
+        );
+        value
+    }
+}
+
+/// Returns the squared distance from `query` to the farthest corner of `aabb`.
+fn max_squared_distance<S: crate::KdScalar, N: ArrayLength>(
+    aabb: &Aabb<S, N>,
+    query: &impl KdPoint<Scalar = S>,
+) -> S {
+    let mut squared_distance = S::zero();
+    for k in 0..aabb.min.len() {
+        let x = query.at(k);
+        let diff1 = x - aabb.min[k];
+        let diff2 = aabb.max[k] - x;
+        let diff = if diff1 > diff2 { diff1 } else { diff2 };
+        squared_distance += diff * diff;
+    }
+    squared_distance
+}
@@ -0,0 +1,201 @@
+#![cfg(feature = "arrow")]
+use crate::{KdIndexTreeN, KdPoint, KdTreeN};
+use arrow_array::cast::AsArray;
+use arrow_array::{Array, ArrowPrimitiveType, FixedSizeListArray, StructArray};
+use arrow_schema::ArrowError;
+use std::cmp::Ordering;
+use typenum::Unsigned;
+
+fn invalid_argument(message: String) -> ArrowError {
+    ArrowError::InvalidArgumentError(message)
+}
+
+fn check_no_nulls(array: &dyn Array, what: &str) -> Result<(), ArrowError> {
+    if array.null_count() == 0 {
+        Ok(())
+    } else {
+        Err(invalid_argument(format!("{} must not contain nulls", what)))
+    }
+}
+
+/// Returns the lists of `array` as points, which are the values of `array` without copying them.
+fn fixed_size_list_points<P: ArrowPrimitiveType, const D: usize>(
+    array: &FixedSizeListArray,
+) -> Result<&[[P::Native; D]], ArrowError> {
+    if array.value_length() as usize != D {
+        return Err(invalid_argument(format!(
+            "the length of the lists is {} (expected {})",
+            array.value_length(),
+            D
+        )));
+    }
+    check_no_nulls(array, "the list array")?;
+    let values = array.values().as_primitive_opt::<P>().ok_or_else(|| {
+        invalid_argument(format!(
+            "the type of the values is {} (expected {})",
+            array.value_type(),
+            P::DATA_TYPE
+        ))
+    })?;
+    check_no_nulls(values, "the values")?;
+    let values = &values.values()[..array.len() * D];
+    // SAFETY: `[P::Native; D]` has the same layout as `D` consecutive `P::Native`s,
+    // and `values` holds `array.len() * D` of them.
+    Ok(
+        unsafe {
+            std::slice::from_raw_parts(values.as_ptr().cast::<[P::Native; D]>(), array.len())
+        },
+    )
+}
+
+/// Returns the fields of `array` as the columns of the coordinates.
+fn struct_columns<P: ArrowPrimitiveType, N: Unsigned>(
+    array: &StructArray,
+) -> Result<Vec<&[P::Native]>, ArrowError> {
+    if array.num_columns() != N::to_usize() {
+        return Err(invalid_argument(format!(
+            "the number of the fields is {} (expected {})",
+            array.num_columns(),
+            N::to_usize()
+        )));
+    }
+    check_no_nulls(array, "the struct array")?;
+    array
+        .columns()
+        .iter()
+        .map(|column| {
+            let column = column.as_primitive_opt::<P>().ok_or_else(|| {
+                invalid_argument(format!(
+                    "the type of a field is {} (expected {})",
+                    column.data_type(),
+                    P::DATA_TYPE
+                ))
+            })?;
+            check_no_nulls(column, "the fields")?;
+            Ok(&column.values()[..])
+        })
+        .collect()
+}
+
+impl<'a, S: Copy, const D: usize, N: Unsigned> KdIndexTreeN<'a, [S; D], N>
+where
+    [S; D]: KdPoint<Scalar = S, Dim = N>,
+{
+    /// Builds a tree of the indices of the lists of `array`, each of which is a point,
+    /// referring to the values of `array` in place without copying them.
+    ///
+    /// `P` is the type of the values, e.g. `arrow_array::types::Float64Type`.
+    /// Returns an error if the lists are not of the length `D`, the values are not of the type `P`, or `array` contains nulls.
+    pub fn from_fixed_size_list_by<P, F>(
+        array: &'a FixedSizeListArray,
+        compare: F,
+    ) -> Result<Self, ArrowError>
+    where
+        P: ArrowPrimitiveType<Native = S>,
+        F: Fn(&[S; D], &[S; D], usize) -> Ordering + Copy,
+    {
+        Ok(Self::build_by(
+            fixed_size_list_points::<P, D>(array)?,
+            compare,
+        ))
+    }
+
+    pub fn from_fixed_size_list_by_key<P, Key, F>(
+        array: &'a FixedSizeListArray,
+        kd_key: F,
+    ) -> Result<Self, ArrowError>
+    where
+        P: ArrowPrimitiveType<Native = S>,
+        Key: Ord,
+        F: Fn(&[S; D], usize) -> Key + Copy,
+    {
+        Ok(Self::build_by_key(
+            fixed_size_list_points::<P, D>(array)?,
+            kd_key,
+        ))
+    }
+
+    /// # Example
+    /// ```
+    /// use arrow_array::{types::Float64Type, FixedSizeListArray};
+    /// let points = FixedSizeListArray::from_iter_primitive::<Float64Type, _, _>(
+    ///     vec![Some(vec![Some(1.0), Some(2.0)]), Some(vec![Some(3.0), Some(1.0)])],
+    ///     2,
+    /// );
+    /// let kdtree: kd_tree::KdIndexTree<[f64; 2]> =
+    ///     kd_tree::KdIndexTree::from_fixed_size_list_by_ordered_float::<Float64Type>(&points).unwrap();
+    /// assert_eq!(kdtree.nearest(&[3.1, 0.9]).unwrap().item, &1);
+    /// ```
+    pub fn from_fixed_size_list_by_ordered_float<P>(
+        array: &'a FixedSizeListArray,
+    ) -> Result<Self, ArrowError>
+    where
+        P: ArrowPrimitiveType<Native = S>,
+        S: ordered_float::FloatCore,
+    {
+        Ok(Self::build_by_ordered_float(
+            fixed_size_list_points::<P, D>(array)?,
+        ))
+    }
+
+    pub fn from_fixed_size_list<P>(array: &'a FixedSizeListArray) -> Result<Self, ArrowError>
+    where
+        P: ArrowPrimitiveType<Native = S>,
+        S: Ord,
+    {
+        Ok(Self::build(fixed_size_list_points::<P, D>(array)?))
+    }
+}
+
+impl<N: Unsigned> KdTreeN<usize, N> {
+    /// Builds a tree of the row indices of `array`, whose fields are the coordinates of the points,
+    /// reading the coordinates in place without copying them.
+    /// The tree is queried by the `*_by` methods, such as [`crate::KdSliceN::nearest_by`], with the coordinates of the rows.
+    ///
+    /// `P` is the type of the fields, e.g. `arrow_array::types::Float64Type`.
+    /// Returns an error if the number of the fields is not `N`, the fields are not of the type `P`, or `array` contains nulls.
+    pub fn from_struct_array_by_key<P, Key, F>(
+        array: &StructArray,
+        kd_key: F,
+    ) -> Result<Self, ArrowError>
+    where
+        P: ArrowPrimitiveType,
+        Key: Ord,
+        F: Fn(P::Native, usize) -> Key + Copy,
+    {
+        let columns = struct_columns::<P, N>(array)?;
+        Ok(Self::build_by_key((0..array.len()).collect(), |&i, k| {
+            kd_key(columns[k][i], k)
+        }))
+    }
+
+    /// # Example
+    /// ```
+    /// use arrow_array::{cast::AsArray, types::Float64Type, ArrayRef, Float64Array, StructArray};
+    /// use std::{convert::TryFrom, sync::Arc};
+    /// let points = StructArray::try_from(vec![
+    ///     ("x", Arc::new(Float64Array::from(vec![1.0, 3.0, 2.0])) as ArrayRef),
+    ///     ("y", Arc::new(Float64Array::from(vec![2.0, 1.0, 3.0])) as ArrayRef),
+    /// ])
+    /// .unwrap();
+    /// let kdtree: kd_tree::KdTree2<usize> =
+    ///     kd_tree::KdTree2::from_struct_array_by_ordered_float::<Float64Type>(&points).unwrap();
+    /// let coord = |&i: &usize, k: usize| points.column(k).as_primitive::<Float64Type>().value(i);
+    /// assert_eq!(kdtree.nearest_by(&[3.1, 0.9], coord).unwrap().item, &1);
+    /// ```
+    pub fn from_struct_array_by_ordered_float<P>(array: &StructArray) -> Result<Self, ArrowError>
+    where
+        P: ArrowPrimitiveType,
+        P::Native: ordered_float::FloatCore,
+    {
+        Self::from_struct_array_by_key::<P, _, _>(array, |x, _| ordered_float::OrderedFloat(x))
+    }
+
+    pub fn from_struct_array<P>(array: &StructArray) -> Result<Self, ArrowError>
+    where
+        P: ArrowPrimitiveType,
+        P::Native: Ord,
+    {
+        Self::from_struct_array_by_key::<P, _, _>(array, |x, _| x)
+    }
+}
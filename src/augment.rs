@@ -0,0 +1,361 @@
+use crate::{ItemAndDistance, KdPoint, KdScalar, KdSliceN, KdTreeN};
+use generic_array::sequence::GenericSequence;
+use generic_array::{ArrayLength, GenericArray};
+use num_traits::Float;
+use typenum::Unsigned;
+
+/// A bounding volume of the items in a subtree, used to prune subtrees in [`AugmentedKdTreeN`].
+pub trait SubtreeBound<T: KdPoint>: Sized {
+    /// Computes the bound of `items`, which is not empty.
+    fn from_items(items: &[T]) -> Self;
+
+    /// Returns a lower bound of the squared distances from `query` to the items in the bound.
+    fn min_squared_distance<Q>(&self, query: &Q) -> T::Scalar
+    where
+        Q: KdPoint<Scalar = T::Scalar, Dim = T::Dim>;
+}
+
+/// Axis-aligned bounding box in `N` dimensions, stored inline without heap allocations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aabb<Scalar, N: ArrayLength> {
+    pub min: GenericArray<Scalar, N>,
+    pub max: GenericArray<Scalar, N>,
+}
+
+impl<T: KdPoint> SubtreeBound<T> for Aabb<T::Scalar, T::Dim>
+where
+    T::Dim: ArrayLength,
+{
+    fn from_items(items: &[T]) -> Self {
+        let mut min = GenericArray::<T::Scalar, T::Dim>::generate(|k| items[0].at(k));
+        let mut max = min.clone();
+        for item in &items[1..] {
+            for k in 0..T::dim() {
+                let x = item.at(k);
+                if x < min[k] {
+                    min[k] = x;
+                }
+                if x > max[k] {
+                    max[k] = x;
+                }
+            }
+        }
+        Self { min, max }
+    }
+
+    fn min_squared_distance<Q>(&self, query: &Q) -> T::Scalar
+    where
+        Q: KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+    {
+        let mut squared_distance = T::Scalar::zero();
+        for k in 0..T::dim() {
+            let x = query.at(k);
+            let diff = if x < self.min[k] {
+                self.min[k] - x
+            } else if x > self.max[k] {
+                x - self.max[k]
+            } else {
+                continue;
+            };
+            squared_distance += diff * diff;
+        }
+        squared_distance
+    }
+}
+
+/// Bounding sphere, whose center is the center of the bounding box.
+/// It gives tighter pruning than [`Aabb`] for euclidean queries on blobby data, though each test computes a square root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundingSphere<Scalar, N: ArrayLength> {
+    pub center: GenericArray<Scalar, N>,
+    pub radius: Scalar,
+}
+
+impl<T: KdPoint> SubtreeBound<T> for BoundingSphere<T::Scalar, T::Dim>
+where
+    T::Scalar: Float,
+    T::Dim: ArrayLength,
+{
+    fn from_items(items: &[T]) -> Self {
+        let aabb: Aabb<T::Scalar, T::Dim> = SubtreeBound::<T>::from_items(items);
+        let two = T::Scalar::one() + T::Scalar::one();
+        let center =
+            GenericArray::<T::Scalar, T::Dim>::generate(|k| (aabb.min[k] + aabb.max[k]) / two);
+        let mut radius = T::Scalar::zero();
+        for item in items {
+            radius = radius.max(distance_from_center(&center, item));
+        }
+        Self { center, radius }
+    }
+
+    fn min_squared_distance<Q>(&self, query: &Q) -> T::Scalar
+    where
+        Q: KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+    {
+        let distance = distance_from_center(&self.center, query) - self.radius;
+        if distance > T::Scalar::zero() {
+            distance * distance
+        } else {
+            T::Scalar::zero()
+        }
+    }
+}
+
+fn distance_from_center<S: Float>(center: &[S], p: &impl KdPoint<Scalar = S>) -> S {
+    let mut squared_distance = S::zero();
+    for (k, &c) in center.iter().enumerate() {
+        let diff = p.at(k) - c;
+        squared_distance = squared_distance + diff * diff;
+    }
+    squared_distance.sqrt()
+}
+
+impl<T: KdPoint<Dim = N>, N: Unsigned> KdSliceN<T, N> {
+    /// Returns the axis-aligned bounding box of the items. Returns `None` if `self.is_empty()`.
+    ///
+    /// This visits all the items. Use [`AugmentedKdTreeN`] with [`Aabb`] to keep the bounding boxes of the subtrees.
+    pub fn bounding_box(&self) -> Option<Aabb<T::Scalar, N>>
+    where
+        N: ArrayLength,
+    {
+        if self.is_empty() {
+            None
+        } else {
+            Some(Aabb::from_items(self.items()))
+        }
+    }
+
+    /// Returns the squared distance from the input point to the bounding box of the items,
+    /// which is zero if the point is inside the box. Returns `None` if `self.is_empty()`.
+    ///
+    /// This is a lower bound of the squared distance to any item,
+    /// which tells whether this tree, e.g. a shard of a large dataset, can contain relevant neighbors before querying it.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[0, 0], [2, 1], [1, 3]]);
+    /// assert_eq!(kdtree.squared_distance_to_hull(&[1, 1]), Some(0));
+    /// assert_eq!(kdtree.squared_distance_to_hull(&[5, 5]), Some(9 + 4));
+    /// ```
+    pub fn squared_distance_to_hull(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<T::Scalar>
+    where
+        N: ArrayLength,
+    {
+        self.bounding_box()
+            .map(|aabb| SubtreeBound::<T>::min_squared_distance(&aabb, query))
+    }
+}
+
+/// Computes `f` for each subtree of `items`, and stores the value at the position of the median item of the subtree.
+pub(crate) fn per_subtree<T, V>(items: &[T], mut f: impl FnMut(&[T]) -> V) -> Vec<V> {
+    fn recurse<T, V>(
+        values: &mut Vec<Option<V>>,
+        items: &[T],
+        offset: usize,
+        f: &mut impl FnMut(&[T]) -> V,
+    ) {
+        if items.is_empty() {
+            return;
+        }
+        let mid = items.len() / 2;
+        values[offset + mid] = Some(f(items));
+        recurse(values, &items[..mid], offset, f);
+        recurse(values, &items[mid + 1..], offset + mid + 1, f);
+    }
+    let mut values: Vec<Option<V>> = items.iter().map(|_| None).collect();
+    recurse(&mut values, items, 0, &mut f);
+    values.into_iter().map(Option::unwrap).collect()
+}
+
+/// An owned kd-tree augmented with a bounding volume per subtree, such as [`Aabb`] or [`BoundingSphere`].
+///
+/// The bounds are used in addition to the splitting planes to prune subtrees in queries.
+/// # Example
+/// ```
+/// use kd_tree::{AugmentedKdTree, BoundingSphere, KdTree};
+/// let kdtree = KdTree::build_by_ordered_float(vec![[1.0, 2.0], [3.0, 1.0], [2.0, 3.0]]);
+/// let kdtree: AugmentedKdTree<_, BoundingSphere<f64, typenum::U2>> = AugmentedKdTree::new(kdtree);
+/// assert_eq!(kdtree.nearest(&[3.1, 0.9]).unwrap().item, &[3.0, 1.0]);
+/// assert_eq!(kdtree.within_radius(&[2.0, 2.0], 1.2).len(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AugmentedKdTreeN<T, N: Unsigned, B> {
+    kdtree: KdTreeN<T, N>,
+    /// The bound of each subtree, stored at the position of the median item of the subtree.
+    bounds: Vec<B>,
+}
+pub type AugmentedKdTree<T, B> = AugmentedKdTreeN<T, <T as KdPoint>::Dim, B>;
+
+impl<T, N: Unsigned, B> AugmentedKdTreeN<T, N, B> {
+    pub fn kdtree(&self) -> &KdSliceN<T, N> {
+        &self.kdtree
+    }
+
+    pub fn into_kdtree(self) -> KdTreeN<T, N> {
+        self.kdtree
+    }
+
+    /// Returns the bound of the whole tree. Returns `None` if the tree is empty.
+    pub fn bound(&self) -> Option<&B> {
+        self.bounds.get(self.bounds.len() / 2)
+    }
+}
+
+impl<T: KdPoint<Dim = N>, N: Unsigned, B: SubtreeBound<T>> AugmentedKdTreeN<T, N, B> {
+    pub fn new(kdtree: KdTreeN<T, N>) -> Self {
+        let bounds = per_subtree(&kdtree, B::from_items);
+        Self { kdtree, bounds }
+    }
+
+    /// Returns a lower bound of the squared distance from the input point to the items, given by the bound of the whole tree.
+    /// Unlike [`KdSliceN::squared_distance_to_hull`], this takes constant time. Returns `None` if the tree is empty.
+    /// # Example
+    /// ```
+    /// use kd_tree::{Aabb, AugmentedKdTree, KdTree};
+    /// let kdtree: AugmentedKdTree<_, Aabb<i32, typenum::U2>> = AugmentedKdTree::new(KdTree::build(vec![[0, 0], [2, 1], [1, 3]]));
+    /// assert_eq!(kdtree.squared_distance_to_hull(&[5, 5]), Some(9 + 4));
+    /// ```
+    pub fn squared_distance_to_hull(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<T::Scalar> {
+        self.bound().map(|bound| bound.min_squared_distance(query))
+    }
+
+    /// Returns the subtrees at `depth` below the root, each with its bound, from the lowest to the highest.
+    /// Subtrees of leaves shallower than `depth` are not included.
+    ///
+    /// The items of a subtree are returned as a plain slice, because a subtree is not split from the first axis.
+    pub fn subtrees_at_depth(&self, depth: usize) -> Vec<(&[T], &B)> {
+        fn recurse<'a, T, B>(
+            subtrees: &mut Vec<(&'a [T], &'a B)>,
+            items: &'a [T],
+            bounds: &'a [B],
+            depth: usize,
+        ) {
+            if items.is_empty() {
+                return;
+            }
+            let mid = items.len() / 2;
+            if depth == 0 {
+                subtrees.push((items, &bounds[mid]));
+            } else {
+                recurse(subtrees, &items[..mid], &bounds[..mid], depth - 1);
+                recurse(subtrees, &items[mid + 1..], &bounds[mid + 1..], depth - 1);
+            }
+        }
+        let mut subtrees = Vec::new();
+        recurse(&mut subtrees, &self.kdtree, &self.bounds, depth);
+        subtrees
+    }
+
+    /// Returns the nearest item from the input point. Returns `None` if `self.is_empty()`.
+    pub fn nearest(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<ItemAndDistance<'_, T, T::Scalar>> {
+        fn recurse<'a, T: KdPoint, B: SubtreeBound<T>>(
+            nearest: &mut ItemAndDistance<'a, T, T::Scalar>,
+            items: &'a [T],
+            bounds: &[B],
+            query: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+            axis: usize,
+        ) {
+            if items.is_empty() {
+                return;
+            }
+            let mid = items.len() / 2;
+            if bounds[mid].min_squared_distance(query) >= nearest.squared_distance {
+                return;
+            }
+            let item = &items[mid];
+            let squared_distance = crate::squared_distance(item, query);
+            if squared_distance < nearest.squared_distance {
+                nearest.item = item;
+                nearest.squared_distance = squared_distance;
+            }
+            let diff = query.at(axis) - item.at(axis);
+            let lower = (&items[..mid], &bounds[..mid]);
+            let upper = (&items[mid + 1..], &bounds[mid + 1..]);
+            let (near, far) = if diff < T::Scalar::zero() {
+                (lower, upper)
+            } else {
+                (upper, lower)
+            };
+            let axis = (axis + 1) % T::dim();
+            recurse(nearest, near.0, near.1, query, axis);
+            if diff * diff < nearest.squared_distance {
+                recurse(nearest, far.0, far.1, query, axis);
+            }
+        }
+        let first = self.kdtree.first()?;
+        let mut nearest = ItemAndDistance {
+            item: first,
+            squared_distance: crate::squared_distance(first, query),
+        };
+        recurse(&mut nearest, &self.kdtree, &self.bounds, query, 0);
+        Some(nearest)
+    }
+
+    /// search points within k-dimensional sphere
+    pub fn within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<&T> {
+        fn recurse<'a, T: KdPoint, B: SubtreeBound<T>>(
+            results: &mut Vec<&'a T>,
+            items: &'a [T],
+            bounds: &[B],
+            query: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+            squared_radius: T::Scalar,
+            axis: usize,
+        ) {
+            if items.is_empty() {
+                return;
+            }
+            let mid = items.len() / 2;
+            if bounds[mid].min_squared_distance(query) >= squared_radius {
+                return;
+            }
+            let item = &items[mid];
+            if crate::squared_distance(item, query) < squared_radius {
+                results.push(item);
+            }
+            let diff = query.at(axis) - item.at(axis);
+            let next = (axis + 1) % T::dim();
+            if diff < T::Scalar::zero() || diff * diff < squared_radius {
+                recurse(
+                    results,
+                    &items[..mid],
+                    &bounds[..mid],
+                    query,
+                    squared_radius,
+                    next,
+                );
+            }
+            if diff >= T::Scalar::zero() || diff * diff < squared_radius {
+                recurse(
+                    results,
+                    &items[mid + 1..],
+                    &bounds[mid + 1..],
+                    query,
+                    squared_radius,
+                    next,
+                );
+            }
+        }
+        let mut results = Vec::new();
+        recurse(
+            &mut results,
+            &self.kdtree,
+            &self.bounds,
+            query,
+            radius * radius,
+            0,
+        );
+        results
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{KdPoint, PermutedKdTreeN, QueryConfig};
+use std::cmp::Ordering;
+use typenum::Unsigned;
+
+/// The rule to choose the axis along which each level of a tree is split.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitRule {
+    /// Splits along the axes `0, 1, ..., N-1` in turn, as [`crate::KdTreeN`].
+    #[default]
+    Cyclic,
+    /// Splits along the axes in descending order of the spread of the items, as [`PermutedKdTreeN`],
+    /// which is efficient for anisotropic data.
+    SpreadOrder,
+}
+
+impl SplitRule {
+    /// Returns the order of the axes to build a [`PermutedKdTreeN`] of `points` by this rule.
+    /// # Example
+    /// ```
+    /// use kd_tree::SplitRule;
+    /// let points = vec![[0.0, 0.0, 0.0], [1.0, 10.0, 0.1], [2.0, 5.0, 0.2]];
+    /// assert_eq!(SplitRule::Cyclic.axis_order(&points), vec![0, 1, 2]);
+    /// assert_eq!(SplitRule::SpreadOrder.axis_order(&points), vec![1, 0, 2]);
+    /// ```
+    pub fn axis_order<T: KdPoint>(&self, points: &[T]) -> Vec<usize> {
+        let mut axis_order: Vec<usize> = (0..T::dim()).collect();
+        if *self == SplitRule::SpreadOrder && !points.is_empty() {
+            let spreads: Vec<_> = (0..T::dim())
+                .map(|k| {
+                    let (mut min, mut max) = (points[0].at(k), points[0].at(k));
+                    for p in points {
+                        if p.at(k) < min {
+                            min = p.at(k);
+                        }
+                        if p.at(k) > max {
+                            max = p.at(k);
+                        }
+                    }
+                    max - min
+                })
+                .collect();
+            axis_order.sort_by(|&a, &b| {
+                spreads[b]
+                    .partial_cmp(&spreads[a])
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+        axis_order
+    }
+}
+
+/// Tuning parameters to build a tree and to query it, which are chosen for the data by [`Self::autotune`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BuildConfig {
+    pub split_rule: SplitRule,
+    /// The parameters to pass to the `*_with_config` queries of the built tree.
+    pub query: QueryConfig,
+}
+
+/// The candidates of the parameters tried by [`BuildConfig::autotune`].
+const LEAF_SIZES: [usize; 5] = [1, 4, 8, 16, 32];
+/// The cost of entering a subtree relative to examining an item, in the cost model of [`BuildConfig::autotune`].
+const SUBTREE_COST: usize = 4;
+
+impl BuildConfig {
+    /// Tries the combinations of the split rule and the leaf size on `points`,
+    /// and returns the one which runs the kNN queries of `sample_queries` at the lowest cost.
+    ///
+    /// `points` may be a random sample of the data, and `sample_queries` should be representative of the workload.
+    /// The cost is not measured by the clock but counted by [`QueryConfig::instrument`],
+    /// as the number of the examined items plus a constant factor of the entered subtrees,
+    /// so the result is deterministic like the rest of this crate. Prefetching is left to the default.
+    /// The split rule of the result can be given to [`crate::KdTreeBuilder::build_config`].
+    /// # Example
+    /// ```
+    /// use kd_tree::{BuildConfig, PermutedKdTree};
+    /// let points: Vec<[f64; 3]> = (0..1000).map(|i| [(i % 10) as f64, (i / 10) as f64, 0.0]).collect();
+    /// let queries = vec![[1.5, 2.5, 0.0], [8.2, 50.0, 0.0]];
+    /// let config = BuildConfig::autotune_by_ordered_float(&points, &queries, 5);
+    /// let axis_order = config.split_rule.axis_order(&points);
+    /// let kdtree: PermutedKdTree<[f64; 3]> = PermutedKdTree::build_by_ordered_float(points, axis_order);
+    /// let found = kdtree.nearests_with_config(&queries[0], 5, &config.query);
+    /// assert_eq!(found.result.len(), 5);
+    /// ```
+    pub fn autotune_by<T, N, F>(
+        points: &[T],
+        sample_queries: &[impl KdPoint<Scalar = T::Scalar, Dim = N>],
+        num: usize,
+        compare: F,
+    ) -> Self
+    where
+        T: KdPoint<Dim = N> + Clone,
+        N: Unsigned,
+        F: Fn(&T, &T, usize) -> Ordering + Copy,
+    {
+        let mut best: Option<(usize, Self)> = None;
+        for split_rule in [SplitRule::Cyclic, SplitRule::SpreadOrder] {
+            let axis_order = split_rule.axis_order(points);
+            if split_rule != SplitRule::Cyclic && axis_order.iter().copied().eq(0..N::to_usize()) {
+                continue;
+            }
+            let kdtree = PermutedKdTreeN::<T, N>::build_by(points.to_vec(), axis_order, compare);
+            for leaf_size in LEAF_SIZES {
+                let config = Self {
+                    split_rule,
+                    query: QueryConfig {
+                        leaf_size,
+                        ..Default::default()
+                    },
+                };
+                let instrumented = QueryConfig {
+                    instrument: true,
+                    ..config.query
+                };
+                let cost = sample_queries
+                    .iter()
+                    .map(|query| {
+                        let stats = kdtree
+                            .nearests_with_config(query, num, &instrumented)
+                            .stats
+                            .unwrap_or_default();
+                        stats.visited_items + SUBTREE_COST * stats.visited_subtrees
+                    })
+                    .fold(0usize, usize::saturating_add);
+                // the earlier candidates win ties, so the default ones are preferred.
+                if best.as_ref().map_or(true, |(best, _)| cost < *best) {
+                    best = Some((cost, config));
+                }
+            }
+        }
+        best.map(|(_, config)| config).unwrap_or_default()
+    }
+
+    pub fn autotune_by_key<T, N, Key, F>(
+        points: &[T],
+        sample_queries: &[impl KdPoint<Scalar = T::Scalar, Dim = N>],
+        num: usize,
+        kd_key: F,
+    ) -> Self
+    where
+        T: KdPoint<Dim = N> + Clone,
+        N: Unsigned,
+        Key: Ord,
+        F: Fn(&T, usize) -> Key + Copy,
+    {
+        Self::autotune_by(points, sample_queries, num, |item1, item2, k| {
+            kd_key(item1, k).cmp(&kd_key(item2, k))
+        })
+    }
+
+    pub fn autotune_by_ordered_float<T, N>(
+        points: &[T],
+        sample_queries: &[impl KdPoint<Scalar = T::Scalar, Dim = N>],
+        num: usize,
+    ) -> Self
+    where
+        T: KdPoint<Dim = N> + Clone,
+        N: Unsigned,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        Self::autotune_by_key(points, sample_queries, num, |item, k| {
+            ordered_float::OrderedFloat(item.at(k))
+        })
+    }
+
+    pub fn autotune<T, N>(
+        points: &[T],
+        sample_queries: &[impl KdPoint<Scalar = T::Scalar, Dim = N>],
+        num: usize,
+    ) -> Self
+    where
+        T: KdPoint<Dim = N> + Clone,
+        N: Unsigned,
+        T::Scalar: Ord,
+    {
+        Self::autotune_by_key(points, sample_queries, num, |item, k| item.at(k))
+    }
+}
@@ -0,0 +1,182 @@
+use crate::nearests::kd_nearests_by_with_config;
+use crate::{ItemAndDistance, KdPoint, KdTreeN, PartialResult, QueryConfig};
+use std::cmp::Ordering;
+use typenum::Unsigned;
+
+/// An owned kd-tree which splits items along the axes in the given order, instead of `0, 1, ..., N-1`.
+///
+/// For strongly anisotropic data, such as flat terrain, splitting along the axis with the largest spread first
+/// makes the tree more efficient. The axis order is stored alongside the tree and respected in queries.
+/// # Example
+/// ```
+/// use kd_tree::PermutedKdTree;
+/// let kdtree: PermutedKdTree<[f64; 3]> = PermutedKdTree::build_by_ordered_float(
+///     vec![[1.0, 2.0, 0.0], [3.0, 1.0, 0.1], [2.0, 3.0, 0.0]],
+///     vec![1, 0, 2],
+/// );
+/// assert_eq!(kdtree.axis_order(), &[1, 0, 2]);
+/// assert_eq!(kdtree.nearest(&[3.1, 0.9, 0.0]).unwrap().item, &[3.0, 1.0, 0.1]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermutedKdTreeN<T, N: Unsigned> {
+    axis_order: Vec<usize>,
+    kdtree: KdTreeN<T, N>,
+}
+pub type PermutedKdTree<T> = PermutedKdTreeN<T, <T as KdPoint>::Dim>;
+
+/// A query point whose axes are permuted.
+struct Permuted<'a, P> {
+    point: &'a P,
+    axis_order: &'a [usize],
+}
+
+impl<'a, P: KdPoint> KdPoint for Permuted<'a, P> {
+    type Scalar = P::Scalar;
+    type Dim = P::Dim;
+    fn at(&self, k: usize) -> Self::Scalar {
+        self.point.at(self.axis_order[k])
+    }
+}
+
+impl<T, N: Unsigned> PermutedKdTreeN<T, N> {
+    /// # Panics
+    /// Panics if `axis_order` is not a permutation of `0..N`.
+    pub fn build_by<F>(items: Vec<T>, axis_order: Vec<usize>, compare: F) -> Self
+    where
+        F: Fn(&T, &T, usize) -> Ordering + Copy,
+    {
+        let mut sorted = axis_order.clone();
+        sorted.sort_unstable();
+        assert!(
+            sorted.iter().copied().eq(0..N::to_usize()),
+            "axis_order must be a permutation of 0..{}",
+            N::to_usize()
+        );
+        let kdtree = {
+            let axis_order = &axis_order;
+            KdTreeN::build_by(items, |item1, item2, k| {
+                compare(item1, item2, axis_order[k])
+            })
+        };
+        Self { axis_order, kdtree }
+    }
+
+    pub fn build_by_key<Key, F>(items: Vec<T>, axis_order: Vec<usize>, kd_key: F) -> Self
+    where
+        Key: Ord,
+        F: Fn(&T, usize) -> Key + Copy,
+    {
+        Self::build_by(items, axis_order, |item1, item2, k| {
+            kd_key(item1, k).cmp(&kd_key(item2, k))
+        })
+    }
+
+    pub fn build_by_ordered_float(points: Vec<T>, axis_order: Vec<usize>) -> Self
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        Self::build_by_key(points, axis_order, |item, k| {
+            ordered_float::OrderedFloat(item.at(k))
+        })
+    }
+
+    pub fn build(points: Vec<T>, axis_order: Vec<usize>) -> Self
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: Ord,
+    {
+        Self::build_by_key(points, axis_order, |item, k| item.at(k))
+    }
+
+    pub fn axis_order(&self) -> &[usize] {
+        &self.axis_order
+    }
+
+    pub fn items(&self) -> &[T] {
+        self.kdtree.items()
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.kdtree.into_vec()
+    }
+
+    fn permuted<'a, P>(&'a self, point: &'a P) -> Permuted<'a, P> {
+        Permuted {
+            point,
+            axis_order: &self.axis_order,
+        }
+    }
+
+    /// Returns the nearest item from the input point. Returns `None` if `self.is_empty()`.
+    pub fn nearest(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.kdtree
+            .nearest_by(&self.permuted(query), |item, k| item.at(self.axis_order[k]))
+    }
+
+    /// Returns kNN(k nearest neighbors) from the input point.
+    pub fn nearests(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.kdtree
+            .nearests_by(&self.permuted(query), num, |item, k| {
+                item.at(self.axis_order[k])
+            })
+    }
+
+    /// Same as [`Self::nearests`], but with tuning parameters and options given by `config`.
+    pub fn nearests_with_config(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+        config: &QueryConfig,
+    ) -> PartialResult<Vec<ItemAndDistance<'_, T, T::Scalar>>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        kd_nearests_by_with_config(
+            self.items(),
+            &self.permuted(query),
+            num,
+            config,
+            |item, k| item.at(self.axis_order[k]),
+        )
+    }
+
+    /// search points within a rectangular region
+    pub fn within(&self, query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2]) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.kdtree.within_by(
+            &[self.permuted(&query[0]), self.permuted(&query[1])],
+            |item, k| item.at(self.axis_order[k]),
+        )
+    }
+
+    /// search points within k-dimensional sphere
+    pub fn within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.kdtree
+            .within_radius_by(&self.permuted(query), radius, |item, k| {
+                item.at(self.axis_order[k])
+            })
+    }
+}
@@ -0,0 +1,278 @@
+use crate::nearests::kd_nearests_after_by;
+use crate::{ItemAndDistance, KdPoint, KdSliceN};
+use num_traits::ToPrimitive;
+use typenum::Unsigned;
+
+/// The order in which the batch queries, such as [`KdSliceN::nearests_batch`], process the query points.
+///
+/// The results are returned in the order of the query points in any case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum QueryOrder {
+    /// Processes the queries in the given order.
+    #[default]
+    Input,
+    /// Sorts the queries along the Morton (Z-order) curve before processing, so that spatially adjacent queries are processed together,
+    /// which keeps the visited subtrees hot in the cache and bounds each search by the result of the previous one.
+    /// This is much faster for clustered queries given in a random order.
+    Morton,
+}
+
+impl QueryOrder {
+    /// Returns the indices of `queries` in the order to process them.
+    fn indices<Q: KdPoint>(self, queries: &[Q]) -> Vec<usize>
+    where
+        Q::Scalar: ToPrimitive,
+    {
+        let mut indices: Vec<usize> = (0..queries.len()).collect();
+        if self == QueryOrder::Morton {
+            let codes = morton_codes(queries);
+            indices.sort_by_key(|&i| codes[i]);
+        }
+        indices
+    }
+}
+
+/// Returns the Morton codes of the points, quantized in the bounding box of them.
+fn morton_codes<Q: KdPoint>(points: &[Q]) -> Vec<u64>
+where
+    Q::Scalar: ToPrimitive,
+{
+    let dim = Q::dim();
+    let coord = |p: &Q, k: usize| p.at(k).to_f64().unwrap_or(0.0);
+    let mut min = vec![f64::INFINITY; dim];
+    let mut max = vec![f64::NEG_INFINITY; dim];
+    for p in points {
+        for k in 0..dim {
+            min[k] = min[k].min(coord(p, k));
+            max[k] = max[k].max(coord(p, k));
+        }
+    }
+    let bits = (64 / dim).min(32);
+    let scale = ((1u64 << bits) - 1) as f64;
+    points
+        .iter()
+        .map(|p| {
+            let cells: Vec<u64> = (0..dim)
+                .map(|k| {
+                    let extent = max[k] - min[k];
+                    if extent > 0.0 {
+                        ((coord(p, k) - min[k]) / extent * scale) as u64
+                    } else {
+                        0
+                    }
+                })
+                .collect();
+            let mut code = 0;
+            for bit in (0..bits).rev() {
+                for cell in &cells {
+                    code = (code << 1) | ((cell >> bit) & 1);
+                }
+            }
+            code
+        })
+        .collect()
+}
+
+impl<T: KdPoint<Dim = N>, N: Unsigned> KdSliceN<T, N> {
+    /// Returns kNN(k nearest neighbors) of each query point, in the order of `queries`.
+    /// # Example
+    /// ```
+    /// use kd_tree::{KdTree, QueryOrder};
+    /// let kdtree = KdTree::build(vec![[0, 0], [10, 0], [0, 10], [10, 10]]);
+    /// let queries = vec![[9, 9], [1, 1], [9, 1], [8, 8]];
+    /// let found = kdtree.nearests_batch(&queries, 1, QueryOrder::Morton);
+    /// assert_eq!(found.len(), 4);
+    /// assert_eq!(found[0][0].item, &[10, 10]);
+    /// assert_eq!(found[1][0].item, &[0, 0]);
+    /// assert_eq!(found, kdtree.nearests_batch(&queries, 1, QueryOrder::Input));
+    /// ```
+    pub fn nearests_batch<Q: KdPoint<Scalar = T::Scalar, Dim = N>>(
+        &self,
+        queries: &[Q],
+        num: usize,
+        order: QueryOrder,
+    ) -> Vec<Vec<ItemAndDistance<'_, T, T::Scalar>>>
+    where
+        T::Scalar: ToPrimitive,
+    {
+        let mut results: Vec<_> = std::iter::repeat_with(Vec::new)
+            .take(queries.len())
+            .collect();
+        nearests_sequence(
+            self.items(),
+            queries,
+            &order.indices(queries),
+            num,
+            |i, found| results[i] = found,
+        );
+        results
+    }
+
+    /// Same as [`Self::nearests_batch`], but returns a lazy iterator which processes `chunk_size` queries on each call of `next`,
+    /// and yields their results in the order of `queries`.
+    ///
+    /// This lets an async task run a huge batch without blocking the executor for long,
+    /// by yielding to it between the chunks, e.g. by `tokio::task::yield_now().await`.
+    /// # Example
+    /// ```
+    /// use kd_tree::{KdTree, QueryOrder};
+    /// let kdtree = KdTree::build(vec![[0, 0], [10, 0], [0, 10], [10, 10]]);
+    /// let queries = vec![[9, 9], [1, 1], [9, 1], [8, 8], [1, 9]];
+    /// let mut found = Vec::new();
+    /// for chunk in kdtree.nearests_batch_chunks(&queries, 1, QueryOrder::Morton, 2) {
+    ///     assert!(chunk.len() <= 2);
+    ///     found.extend(chunk);
+    ///     // yield to the executor here in an async context.
+    /// }
+    /// assert_eq!(found, kdtree.nearests_batch(&queries, 1, QueryOrder::Input));
+    /// ```
+    pub fn nearests_batch_chunks<'a, Q: KdPoint<Scalar = T::Scalar, Dim = N>>(
+        &'a self,
+        queries: &'a [Q],
+        num: usize,
+        order: QueryOrder,
+        chunk_size: usize,
+    ) -> impl Iterator<Item = Vec<Vec<ItemAndDistance<'a, T, T::Scalar>>>> + 'a
+    where
+        T::Scalar: ToPrimitive,
+    {
+        assert!(chunk_size > 0, "chunk_size must be positive");
+        queries
+            .chunks(chunk_size)
+            .map(move |chunk| self.nearests_batch(chunk, num, order))
+    }
+
+    /// Searches the items within `radius` from each query point, and returns them in the order of `queries`.
+    ///
+    /// All the queries are carried down in a single traversal of the tree, and each subtree is visited
+    /// only with the queries whose spheres may overlap it. This is much faster than calling [`Self::within_radius`]
+    /// for each query when there are many small, mostly disjoint spheres, e.g. the sensing ranges of many agents.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[0, 0], [10, 0], [0, 10], [10, 10], [1, 1]]);
+    /// let found = kdtree.within_radius_many(&[[0, 1], [10, 9], [5, 5]], 2);
+    /// assert_eq!(found.len(), 3);
+    /// assert_eq!(found[0].len(), 2);
+    /// assert_eq!(found[1], vec![&[10, 10]]);
+    /// assert!(found[2].is_empty());
+    /// ```
+    pub fn within_radius_many<Q: KdPoint<Scalar = T::Scalar, Dim = N>>(
+        &self,
+        queries: &[Q],
+        radius: T::Scalar,
+    ) -> Vec<Vec<&T>> {
+        struct Search<'a, 'q, T, Q: KdPoint> {
+            queries: &'q [Q],
+            radius: Q::Scalar,
+            results: Vec<Vec<&'a T>>,
+            /// The buffers of the indices of the active queries, reused between the subtrees.
+            pool: Vec<Vec<usize>>,
+        }
+        impl<'a, T: KdPoint, Q: KdPoint<Scalar = T::Scalar, Dim = T::Dim>> Search<'a, '_, T, Q> {
+            fn recurse(&mut self, kdtree: &'a [T], axis: usize, active: &[usize]) {
+                if kdtree.is_empty() || active.is_empty() {
+                    return;
+                }
+                let mid_idx = kdtree.len() / 2;
+                let item = &kdtree[mid_idx];
+                let squared_radius = self.radius * self.radius;
+                for &i in active {
+                    if crate::squared_distance(item, &self.queries[i]) < squared_radius {
+                        self.results[i].push(item);
+                    }
+                }
+                let mid_pos = item.at(axis);
+                let next_axis = (axis + 1) % T::dim();
+                let mut branch_active = self.pool.pop().unwrap_or_default();
+                branch_active.clear();
+                branch_active.extend(
+                    active
+                        .iter()
+                        .filter(|&&i| self.queries[i].at(axis) - self.radius < mid_pos),
+                );
+                self.recurse(&kdtree[..mid_idx], next_axis, &branch_active);
+                branch_active.clear();
+                branch_active.extend(
+                    active
+                        .iter()
+                        .filter(|&&i| mid_pos < self.queries[i].at(axis) + self.radius),
+                );
+                self.recurse(&kdtree[mid_idx + 1..], next_axis, &branch_active);
+                self.pool.push(branch_active);
+            }
+        }
+        let mut search = Search {
+            queries,
+            radius,
+            results: std::iter::repeat_with(Vec::new)
+                .take(queries.len())
+                .collect(),
+            pool: Vec::new(),
+        };
+        let active: Vec<usize> = (0..queries.len()).collect();
+        search.recurse(self.items(), 0, &active);
+        search.results
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: KdPoint<Dim = N> + Sync, N: Unsigned + Sync> KdSliceN<T, N> {
+    /// Same as [`Self::nearests_batch`], but using multiple threads.
+    ///
+    /// The queries are split into chunks in the processing order, and each chunk is processed sequentially.
+    pub fn par_nearests_batch<Q: KdPoint<Scalar = T::Scalar, Dim = N> + Sync>(
+        &self,
+        queries: &[Q],
+        num: usize,
+        order: QueryOrder,
+    ) -> Vec<Vec<ItemAndDistance<'_, T, T::Scalar>>>
+    where
+        T::Scalar: ToPrimitive + Send + Sync,
+    {
+        use rayon::prelude::*;
+        const CHUNK_SIZE: usize = 256;
+        let indices = order.indices(queries);
+        let mut results: Vec<_> = std::iter::repeat_with(Vec::new)
+            .take(queries.len())
+            .collect();
+        let chunks: Vec<Vec<_>> = indices
+            .par_chunks(CHUNK_SIZE)
+            .map(|chunk| {
+                let mut found = Vec::with_capacity(chunk.len());
+                nearests_sequence(self.items(), queries, chunk, num, |i, result| {
+                    found.push((i, result))
+                });
+                found
+            })
+            .collect();
+        for (i, found) in chunks.into_iter().flatten() {
+            results[i] = found;
+        }
+        results
+    }
+}
+
+/// Calls `f` with the index of each query in `indices` and its kNN, in the order of `indices`.
+fn nearests_sequence<'a, T: KdPoint, Q: KdPoint<Scalar = T::Scalar, Dim = T::Dim>>(
+    kdtree: &'a [T],
+    queries: &[Q],
+    indices: &[usize],
+    num: usize,
+    mut f: impl FnMut(usize, Vec<ItemAndDistance<'a, T, T::Scalar>>),
+) {
+    let mut previous = Vec::new();
+    for &i in indices {
+        let query = &queries[i];
+        let found = kd_nearests_after_by(
+            kdtree,
+            query,
+            num,
+            |item, k| item.at(k),
+            &previous,
+            |_| true,
+        );
+        previous.clear();
+        previous.extend(found.iter().map(|found| found.item));
+        f(i, found);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{KdPoint, KdSliceN, KdTreeN};
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::marker::PhantomData;
+use typenum::Unsigned;
+
+/// The magic bytes at the beginning of the binary format of [`KdSliceN::save_to`].
+const MAGIC: [u8; 8] = *b"KDTREE\0\0";
+/// The version of the binary format, which is incremented when the format or the layout of the tree changes.
+const VERSION: u32 = 1;
+
+/// A scalar type which can be written in the binary format of [`KdSliceN::save_to`].
+pub trait BinaryScalar: Copy {
+    /// The identifier of the type stored in the header, which is unique among the implementors.
+    const TYPE_ID: u8;
+    fn write_le(self, writer: &mut impl Write) -> Result<()>;
+    fn read_le(reader: &mut impl Read) -> Result<Self>;
+}
+
+macro_rules! impl_binary_scalar {
+    ($($t:ty => $id:literal),*) => {
+        $(
+            impl BinaryScalar for $t {
+                const TYPE_ID: u8 = $id;
+                fn write_le(self, writer: &mut impl Write) -> Result<()> {
+                    writer.write_all(&self.to_le_bytes())
+                }
+                fn read_le(reader: &mut impl Read) -> Result<Self> {
+                    let mut bytes = [0; std::mem::size_of::<$t>()];
+                    reader.read_exact(&mut bytes)?;
+                    Ok(Self::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+impl_binary_scalar!(
+    i8 => 1, i16 => 2, i32 => 3, i64 => 4, i128 => 5,
+    u8 => 6, u16 => 7, u32 => 8, u64 => 9, u128 => 10,
+    f32 => 11, f64 => 12
+);
+
+/// A point which can be read from its coordinates in the binary format of [`KdSliceN::save_to`].
+pub trait BinaryPoint: KdPoint {
+    fn from_coords(coord: impl FnMut(usize) -> Self::Scalar) -> Self;
+}
+
+impl<S, const D: usize> BinaryPoint for [S; D]
+where
+    [S; D]: KdPoint<Scalar = S>,
+{
+    fn from_coords(coord: impl FnMut(usize) -> S) -> Self {
+        std::array::from_fn(coord)
+    }
+}
+
+pub(crate) fn invalid_data(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Writes the header of a binary format: `magic`, `version`, the dimension `N`, the type of `S` and `len`.
+pub(crate) fn write_header<S: BinaryScalar, N: Unsigned>(
+    writer: &mut impl Write,
+    magic: &[u8; 8],
+    version: u32,
+    len: usize,
+) -> Result<()> {
+    writer.write_all(magic)?;
+    writer.write_all(&version.to_le_bytes())?;
+    writer.write_all(&N::to_u32().to_le_bytes())?;
+    writer.write_all(&[S::TYPE_ID])?;
+    writer.write_all(&(len as u64).to_le_bytes())
+}
+
+/// Reads a header written by [`write_header`], and returns the `len` in it.
+/// `what` names the format in the error of the magic bytes.
+pub(crate) fn read_header<S: BinaryScalar, N: Unsigned>(
+    reader: &mut impl Read,
+    magic: &[u8; 8],
+    version: u32,
+    what: &str,
+) -> Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    if &bytes != magic {
+        return Err(invalid_data(format!("not a {} file", what)));
+    }
+    let found = u32::read_le(reader)?;
+    if found != version {
+        return Err(invalid_data(format!(
+            "unsupported format version {} (expected {})",
+            found, version
+        )));
+    }
+    let dim = u32::read_le(reader)?;
+    if dim != N::to_u32() {
+        return Err(invalid_data(format!(
+            "dimension mismatch: {} (expected {})",
+            dim,
+            N::to_u32()
+        )));
+    }
+    let type_id = u8::read_le(reader)?;
+    if type_id != S::TYPE_ID {
+        return Err(invalid_data(format!(
+            "scalar type mismatch: {} (expected {})",
+            type_id,
+            S::TYPE_ID
+        )));
+    }
+    u64::read_le(reader)
+}
+
+impl<T: KdPoint<Dim = N>, N: Unsigned> KdSliceN<T, N>
+where
+    T::Scalar: BinaryScalar,
+{
+    /// Writes the tree in a binary format independent of `serde`, which is read back by [`KdTreeN::load_from`].
+    ///
+    /// The items are preceded by a header of the magic bytes, the format version, the dimension, the scalar type and the number of items,
+    /// so that a file of an incompatible tree or version is rejected on loading.
+    /// Only the coordinates of the items are written, in little endian.
+    /// Wrap `writer` in a [`std::io::BufWriter`] unless it is buffered.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[1, 2, 3], [3, 1, 2], [2, 3, 1]]);
+    /// let mut bytes = Vec::new();
+    /// kdtree.save_to(&mut bytes).unwrap();
+    /// let loaded = kd_tree::KdTree::<[i32; 3]>::load_from(&bytes[..]).unwrap();
+    /// assert_eq!(loaded, kdtree);
+    /// assert!(kd_tree::KdTree::<[i64; 3]>::load_from(&bytes[..]).is_err());
+    /// assert!(kd_tree::KdTree::<[i32; 2]>::load_from(&bytes[..]).is_err());
+    /// ```
+    pub fn save_to(&self, mut writer: impl Write) -> Result<()> {
+        write_header::<T::Scalar, N>(&mut writer, &MAGIC, VERSION, self.len())?;
+        for item in self.iter() {
+            for k in 0..N::to_usize() {
+                item.at(k).write_le(&mut writer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: BinaryPoint<Dim = N>, N: Unsigned> KdTreeN<T, N>
+where
+    T::Scalar: BinaryScalar,
+{
+    /// Reads a tree written by [`KdSliceN::save_to`].
+    /// Returns an error of kind `InvalidData` if the header does not match the version of this crate, `N` or `T::Scalar`.
+    ///
+    /// The kd-tree invariant of the items is not verified, so verify it by [`KdSliceN::check_order`] for untrusted data.
+    /// Wrap `reader` in a [`std::io::BufReader`] unless it is buffered.
+    pub fn load_from(mut reader: impl Read) -> Result<Self> {
+        let len = read_header::<T::Scalar, N>(&mut reader, &MAGIC, VERSION, "kd-tree")?;
+        let mut items = Vec::new();
+        for _ in 0..len {
+            let mut coords = Vec::with_capacity(N::to_usize());
+            for _ in 0..N::to_usize() {
+                coords.push(T::Scalar::read_le(&mut reader)?);
+            }
+            items.push(T::from_coords(|k| coords[k]));
+        }
+        Ok(Self(PhantomData, items))
+    }
+}
@@ -0,0 +1,105 @@
+//! Reference implementations of the queries by linear scan over a slice of points.
+//!
+//! These take `O(n)` time per query and need no tree, so they are useful to verify a custom [`KdPoint`] or [`Metric`]
+//! against the kd-tree queries, and to measure the speedup of the kd-tree on your own data.
+//! The results are the same as the corresponding queries of [`crate::KdSliceN`],
+//! except that items at the same distance may be returned in a different order.
+//! # Example
+//! ```
+//! use kd_tree::{brute_force, KdTree};
+//! let points = vec![[1.0, 2.0, 3.0], [3.0, 1.0, 2.0], [2.0, 3.0, 1.0]];
+//! let query = [3.1, 0.9, 2.1];
+//! let expected = brute_force::nearest(&points, &query).unwrap();
+//! let kdtree = KdTree::build_by_ordered_float(points.clone());
+//! assert_eq!(kdtree.nearest(&query).unwrap().item, expected.item);
+//! ```
+use crate::{squared_distance, ItemAndDistance, ItemAndMetricDistance, KdPoint, Metric};
+use std::cmp::Ordering;
+
+/// Compares distances, ordering NaN after all the others.
+fn compare_distances<S: PartialOrd>(a: &S, b: &S) -> Ordering {
+    a.partial_cmp(b).unwrap_or_else(|| {
+        #[allow(clippy::eq_op)]
+        let (a_nan, b_nan) = (a != a, b != b);
+        a_nan.cmp(&b_nan)
+    })
+}
+
+/// Returns the nearest item from the input point. Returns `None` if `items.is_empty()`.
+pub fn nearest<'a, T: KdPoint>(
+    items: &'a [T],
+    query: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+) -> Option<ItemAndDistance<'a, T, T::Scalar>> {
+    items
+        .iter()
+        .map(|item| ItemAndDistance {
+            item,
+            squared_distance: squared_distance(item, query),
+        })
+        .min_by(|a, b| compare_distances(&a.squared_distance, &b.squared_distance))
+}
+
+/// Returns kNN(k nearest neighbors) from the input point, in ascending order of the distance.
+pub fn nearests<'a, T: KdPoint>(
+    items: &'a [T],
+    query: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+    num: usize,
+) -> Vec<ItemAndDistance<'a, T, T::Scalar>> {
+    let mut found: Vec<_> = items
+        .iter()
+        .map(|item| ItemAndDistance {
+            item,
+            squared_distance: squared_distance(item, query),
+        })
+        .collect();
+    found.sort_by(|a, b| compare_distances(&a.squared_distance, &b.squared_distance));
+    found.truncate(num);
+    found
+}
+
+/// Returns kNN(k nearest neighbors) from the input point in the metric `M`, in ascending order of the distance.
+pub fn nearests_by_metric<'a, M: Metric<T::Scalar>, T: KdPoint>(
+    items: &'a [T],
+    query: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+    num: usize,
+) -> Vec<ItemAndMetricDistance<'a, T, T::Scalar>> {
+    let mut found: Vec<_> = items
+        .iter()
+        .map(|item| ItemAndMetricDistance {
+            item,
+            distance: M::distance(query, item, |item, k| item.at(k)),
+        })
+        .collect();
+    found.sort_by(|a, b| {
+        a.distance
+            .partial_cmp(&b.distance)
+            .unwrap_or(Ordering::Equal)
+    });
+    found.truncate(num);
+    found
+}
+
+/// search points within a rectangular region, in the order of `items`.
+pub fn within<'a, T: KdPoint>(
+    items: &'a [T],
+    query: &[impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>; 2],
+) -> Vec<&'a T> {
+    items
+        .iter()
+        .filter(|item| {
+            (0..T::dim()).all(|k| query[0].at(k) <= item.at(k) && item.at(k) <= query[1].at(k))
+        })
+        .collect()
+}
+
+/// search points within k-dimensional sphere, in the order of `items`.
+pub fn within_radius<'a, T: KdPoint>(
+    items: &'a [T],
+    query: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+    radius: T::Scalar,
+) -> Vec<&'a T> {
+    items
+        .iter()
+        .filter(|item| squared_distance(*item, query) < radius * radius)
+        .collect()
+}
@@ -0,0 +1,195 @@
+use crate::{
+    BuildConfig, BuildError, KdIndexTreeN, KdPoint, KdTreeN, NanPolicy, PermutedKdTreeN, SplitRule,
+};
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+use typenum::Unsigned;
+
+/// A builder of kd-trees, which collects the options of the construction in one place
+/// instead of choosing one of the `build_*` methods of [`KdTreeN`] and [`KdIndexTreeN`].
+///
+/// The order of the items is given by one of the constructors, [`Self::by`], [`Self::by_key`], [`Self::by_ordered_float`] or [`Self::new`],
+/// as the corresponding `build_*` methods.
+/// The same builder can build any number of trees, and the layout of a tree does not depend on whether it is built in parallel.
+/// The leaf size and the metric are not the options of the construction but of the queries (see [`crate::QueryConfig`] and [`crate::Metric`]).
+/// # Example
+/// ```
+/// use kd_tree::{KdTreeBuilder, NanPolicy};
+/// let builder = KdTreeBuilder::by_ordered_float().nan_policy(NanPolicy::FilterOut);
+/// let points = vec![[1.0, 2.0], [f64::NAN, 1.0], [3.0, 1.0]];
+/// let kdtree = builder.build(points.clone()).unwrap();
+/// assert_eq!(kdtree.len(), 2);
+/// let kdtree = builder.build_index(&points).unwrap();
+/// assert_eq!(kdtree.nearest(&[3.1, 0.9]).unwrap().item, &2);
+/// ```
+#[derive(Clone, Copy)]
+pub struct KdTreeBuilder<T, N: Unsigned, F> {
+    compare: F,
+    validation: Option<(NanPolicy, InvalidAxis<T>)>,
+    split_rule: SplitRule,
+    _dim: PhantomData<fn() -> N>,
+}
+
+/// A function to find the axis of an invalid coordinate of an item.
+type InvalidAxis<T> = fn(&T) -> Option<usize>;
+
+impl<T, N: Unsigned> KdTreeBuilder<T, N, ()> {
+    pub fn by<F>(compare: F) -> KdTreeBuilder<T, N, F>
+    where
+        F: Fn(&T, &T, usize) -> Ordering + Copy,
+    {
+        KdTreeBuilder {
+            compare,
+            validation: None,
+            split_rule: SplitRule::default(),
+            _dim: PhantomData,
+        }
+    }
+
+    pub fn by_key<Key, F>(
+        kd_key: F,
+    ) -> KdTreeBuilder<T, N, impl Fn(&T, &T, usize) -> Ordering + Copy>
+    where
+        Key: Ord,
+        F: Fn(&T, usize) -> Key + Copy,
+    {
+        Self::by(move |item1, item2, k| kd_key(item1, k).cmp(&kd_key(item2, k)))
+    }
+
+    /// Orders the items by the float coordinates as [`KdTreeN::build_by_ordered_float`].
+    /// [`Self::nan_policy`] is available with this constructor.
+    pub fn by_ordered_float() -> KdTreeBuilder<T, N, impl Fn(&T, &T, usize) -> Ordering + Copy>
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        Self::by_key(|item: &T, k| ordered_float::OrderedFloat(item.at(k)))
+    }
+
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> KdTreeBuilder<T, N, impl Fn(&T, &T, usize) -> Ordering + Copy>
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: Ord,
+    {
+        Self::by_key(|item: &T, k| item.at(k))
+    }
+}
+
+impl<T, N: Unsigned, F> KdTreeBuilder<T, N, F>
+where
+    F: Fn(&T, &T, usize) -> Ordering + Copy,
+{
+    /// Validates the coordinates of the items to be finite, and handles the invalid items by `policy`.
+    /// Without this option, the items are not validated.
+    pub fn nan_policy(self, policy: NanPolicy) -> Self
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        Self {
+            validation: Some((policy, crate::non_finite_axis::<T>)),
+            ..self
+        }
+    }
+
+    /// Splits the trees built by [`Self::build_permuted`] along the axes in the order given by `split_rule`.
+    /// # Example
+    /// ```
+    /// use kd_tree::{KdTreeBuilder, SplitRule};
+    /// let builder = KdTreeBuilder::new().split_rule(SplitRule::SpreadOrder);
+    /// let kdtree = builder.build_permuted(vec![[0, 0], [1, 10], [2, 5]]).unwrap();
+    /// assert_eq!(kdtree.axis_order(), &[1, 0]);
+    /// ```
+    pub fn split_rule(self, split_rule: SplitRule) -> Self {
+        Self { split_rule, ..self }
+    }
+
+    /// Takes the construction parameters of `config`, e.g. chosen by [`BuildConfig::autotune`],
+    /// which are used by [`Self::build_permuted`]. The query parameters are to be passed to the queries of the built tree.
+    /// # Example
+    /// ```
+    /// use kd_tree::{BuildConfig, KdTreeBuilder};
+    /// let points: Vec<[f64; 2]> = (0..100).map(|i| [i as f64, (i % 3) as f64]).collect();
+    /// let config = BuildConfig::autotune_by_ordered_float(&points, &[[50.2, 1.0]], 3);
+    /// let builder = KdTreeBuilder::by_ordered_float().build_config(&config);
+    /// let kdtree = builder.build_permuted(points).unwrap();
+    /// let found = kdtree.nearests_with_config(&[50.2, 1.0], 3, &config.query);
+    /// assert_eq!(found.result[0].item, &[50.0, 2.0]);
+    /// ```
+    pub fn build_config(self, config: &BuildConfig) -> Self {
+        self.split_rule(config.split_rule)
+    }
+
+    fn valid_items(&self, items: Vec<T>) -> Result<Vec<T>, BuildError> {
+        match self.validation {
+            Some((policy, invalid_axis)) => policy.valid_items(items, invalid_axis),
+            None => Ok(items),
+        }
+    }
+
+    fn valid_indices(&self, source: &[T]) -> Result<Vec<usize>, BuildError> {
+        let invalid = match self.validation {
+            Some((policy, invalid_axis)) => policy.invalid_items(source, invalid_axis)?,
+            None => Vec::new(),
+        };
+        Ok((0..source.len())
+            .filter(|i| invalid.binary_search(i).is_err())
+            .collect())
+    }
+
+    /// Builds an owned tree of `items`.
+    /// Returns an error only if the items are validated by [`NanPolicy::Reject`].
+    pub fn build(&self, items: Vec<T>) -> Result<KdTreeN<T, N>, BuildError> {
+        Ok(KdTreeN::build_by(self.valid_items(items)?, self.compare))
+    }
+
+    /// Builds a tree of `items` split along the axes in the order given by [`Self::split_rule`].
+    /// Returns an error only if the items are validated by [`NanPolicy::Reject`].
+    pub fn build_permuted(&self, items: Vec<T>) -> Result<PermutedKdTreeN<T, N>, BuildError>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let items = self.valid_items(items)?;
+        let axis_order = self.split_rule.axis_order(&items);
+        Ok(PermutedKdTreeN::build_by(items, axis_order, self.compare))
+    }
+
+    /// Builds a tree of the indices of `source`.
+    /// Returns an error only if the items are validated by [`NanPolicy::Reject`].
+    pub fn build_index<'a>(&self, source: &'a [T]) -> Result<KdIndexTreeN<'a, T, N>, BuildError> {
+        Ok(KdIndexTreeN::build_subset_by(
+            source,
+            self.valid_indices(source)?,
+            self.compare,
+        ))
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: Send + Sync, N: Unsigned, F> KdTreeBuilder<T, N, F>
+where
+    F: Fn(&T, &T, usize) -> Ordering + Copy + Send,
+{
+    /// Same as [`Self::build`], but using multiple threads.
+    pub fn par_build(&self, items: Vec<T>) -> Result<KdTreeN<T, N>, BuildError> {
+        Ok(KdTreeN::par_build_by(
+            self.valid_items(items)?,
+            self.compare,
+        ))
+    }
+
+    /// Same as [`Self::build_index`], but using multiple threads.
+    pub fn par_build_index<'a>(
+        &self,
+        source: &'a [T],
+    ) -> Result<KdIndexTreeN<'a, T, N>, BuildError> {
+        let compare = self.compare;
+        Ok(KdIndexTreeN {
+            source,
+            kdtree: KdTreeN::par_build_by(self.valid_indices(source)?, move |i1, i2, k| {
+                compare(&source[*i1], &source[*i2], k)
+            }),
+        })
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{ItemAndDistance, KdPoint, KdTreeN};
+use num_traits::ToPrimitive;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use typenum::Unsigned;
+
+/// A kd-tree which memoizes the results of the queries in a bounded LRU cache,
+/// for workloads issuing many identical or near-identical queries, such as tile rendering.
+///
+/// The queries are keyed by their coordinates quantized by `resolution` and their parameters,
+/// so the queries in the same cell of the grid share the result of the first one.
+/// Use a `resolution` small enough for the error to be acceptable, or `0.0` to share the results of identical queries only,
+/// in which case the coordinates are keyed by their exact values, including the integers beyond the precision of `f64`.
+/// The radius of [`Self::within_radius`] is always keyed by its exact value.
+/// The cache is cleared when the tree is modified by [`Self::update`].
+/// # Example
+/// ```
+/// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![[1.0, 2.0], [3.0, 1.0], [2.0, 3.0]]);
+/// let mut cached = kd_tree::CachedKdTree::new(kdtree, 100, 0.0);
+/// assert_eq!(cached.nearest(&[3.1, 0.9]).unwrap().item, &[3.0, 1.0]);
+/// assert_eq!(cached.nearest(&[3.1, 0.9]).unwrap().item, &[3.0, 1.0]);
+/// assert_eq!(cached.cache_len(), 1);
+/// cached.update(|kdtree| kdtree.insert_by_ordered_float([3.1, 1.0]));
+/// assert_eq!(cached.cache_len(), 0);
+/// assert_eq!(cached.nearest(&[3.1, 0.9]).unwrap().item, &[3.1, 1.0]);
+/// ```
+pub struct CachedKdTreeN<T, N: Unsigned> {
+    kdtree: KdTreeN<T, N>,
+    resolution: f64,
+    cache: Mutex<Lru>,
+}
+pub type CachedKdTree<T> = CachedKdTreeN<T, <T as KdPoint>::Dim>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum QueryKind {
+    Nearests(usize),
+    WithinRadius(Coord),
+}
+
+/// A coordinate of a query as a key of the cache.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Coord {
+    /// The index of the cell of the grid by `resolution`.
+    Cell(i64),
+    /// The exact value, as the integer part and the bits of the nearest `f64`, which distinguish
+    /// the integers beyond the precision of `f64` and the fractions respectively.
+    Exact(Option<u128>, u64),
+}
+
+impl Coord {
+    fn exact(x: impl ToPrimitive) -> Self {
+        let int = x.to_i128().map(|x| x as u128).or_else(|| x.to_u128());
+        Coord::Exact(int, x.to_f64().unwrap_or(f64::NAN).to_bits())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct QueryKey {
+    kind: QueryKind,
+    cell: Vec<Coord>,
+}
+
+/// A bounded map from queries to the offsets of the found items, which evicts the least recently used entry.
+struct Lru {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<QueryKey, (Vec<usize>, u64)>,
+    /// The keys of `entries` by the tick of their last use.
+    order: BTreeMap<u64, QueryKey>,
+}
+
+impl Lru {
+    fn get(&mut self, key: &QueryKey) -> Option<Vec<usize>> {
+        let (offsets, tick) = self.entries.get_mut(key)?;
+        let key = self.order.remove(tick).unwrap();
+        self.tick += 1;
+        *tick = self.tick;
+        self.order.insert(self.tick, key);
+        Some(offsets.clone())
+    }
+
+    fn insert(&mut self, key: QueryKey, offsets: Vec<usize>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            let (_, oldest) = self.order.pop_first().unwrap();
+            self.entries.remove(&oldest);
+        }
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (offsets, self.tick));
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+impl<T, N: Unsigned> CachedKdTreeN<T, N> {
+    /// Wraps `kdtree` with a cache of at most `capacity` query results.
+    pub fn new(kdtree: KdTreeN<T, N>, capacity: usize, resolution: f64) -> Self {
+        Self {
+            kdtree,
+            resolution,
+            cache: Mutex::new(Lru {
+                capacity,
+                tick: 0,
+                entries: HashMap::new(),
+                order: BTreeMap::new(),
+            }),
+        }
+    }
+
+    pub fn kdtree(&self) -> &KdTreeN<T, N> {
+        &self.kdtree
+    }
+
+    pub fn into_kdtree(self) -> KdTreeN<T, N> {
+        self.kdtree
+    }
+
+    /// Modifies the tree by `f`, e.g. by [`KdTreeN::insert`], and clears the cache.
+    pub fn update<R>(&mut self, f: impl FnOnce(&mut KdTreeN<T, N>) -> R) -> R {
+        self.clear_cache();
+        f(&mut self.kdtree)
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.cache.get_mut().unwrap().clear();
+    }
+
+    /// Returns the number of the cached query results.
+    pub fn cache_len(&self) -> usize {
+        self.cache.lock().unwrap().entries.len()
+    }
+
+    fn quantize(&self, x: impl ToPrimitive) -> Coord {
+        if self.resolution > 0.0 {
+            Coord::Cell((x.to_f64().unwrap_or(f64::NAN) / self.resolution).floor() as i64)
+        } else {
+            Coord::exact(x)
+        }
+    }
+
+    /// Returns the offsets of the found items of the query, computing them by `search` on a cache miss.
+    fn offsets_of(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar>,
+        kind: QueryKind,
+        search: impl FnOnce() -> Vec<usize>,
+    ) -> Vec<usize>
+    where
+        T: KdPoint,
+        T::Scalar: ToPrimitive,
+    {
+        let cell = (0..N::to_usize())
+            .map(|k| self.quantize(query.at(k)))
+            .collect();
+        let key = QueryKey { kind, cell };
+        if let Some(offsets) = self.cache.lock().unwrap().get(&key) {
+            return offsets;
+        }
+        let offsets = search();
+        self.cache.lock().unwrap().insert(key, offsets.clone());
+        offsets
+    }
+
+    /// Returns the nearest item from the input point, which may be cached. Returns `None` if the tree is empty.
+    pub fn nearest(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ToPrimitive,
+    {
+        self.nearests(query, 1).pop()
+    }
+
+    /// Returns kNN(k nearest neighbors) from the input point, which may be cached.
+    ///
+    /// If `resolution` is positive, the result is approximate: the items are the ones found for the first query in the same cell,
+    /// and their squared distances are computed from `query` and sorted again.
+    pub fn nearests(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ToPrimitive,
+    {
+        let offsets = self.offsets_of(query, QueryKind::Nearests(num), || {
+            self.kdtree
+                .nearests(query, num)
+                .into_iter()
+                .map(|found| self.kdtree.offset_of(found.item))
+                .collect()
+        });
+        let mut found: Vec<_> = offsets
+            .into_iter()
+            .map(|i| ItemAndDistance {
+                item: &self.kdtree[i],
+                squared_distance: crate::squared_distance(&self.kdtree[i], query),
+            })
+            .collect();
+        if self.resolution > 0.0 {
+            found.sort_by(|a, b| {
+                a.squared_distance
+                    .partial_cmp(&b.squared_distance)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        }
+        found
+    }
+
+    /// search points within k-dimensional sphere, which may be cached.
+    ///
+    /// If `resolution` is positive, the result is approximate: the items are the ones found for the first query in the same cell.
+    pub fn within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ToPrimitive,
+    {
+        // the radius is never quantized, or the queries of different radii share the result.
+        let kind = QueryKind::WithinRadius(Coord::exact(radius));
+        let offsets = self.offsets_of(query, kind, || {
+            self.kdtree
+                .within_radius(query, radius)
+                .into_iter()
+                .map(|item| self.kdtree.offset_of(item))
+                .collect()
+        });
+        offsets.into_iter().map(|i| &self.kdtree[i]).collect()
+    }
+}
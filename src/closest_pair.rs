@@ -0,0 +1,85 @@
+use crate::{squared_distance, KdPoint};
+use std::cmp::Ordering;
+
+/// Returns the pair of items with the minimum mutual distance, by divide-and-conquer over the kd structure.
+///
+/// The closest pair of a subtree is the closest one among the pairs within each branch
+/// and the pairs across the splitting plane, which are searched in the slab around the plane
+/// whose width is given by the closest pair within the branches.
+/// The items in the slab are swept along the axis of their largest extent.
+pub fn kd_closest_pair<T: KdPoint>(kdtree: &[T]) -> Option<(&T, &T, T::Scalar)> {
+    fn min<'a, T, S: PartialOrd>(
+        pair1: Option<(&'a T, &'a T, S)>,
+        pair2: Option<(&'a T, &'a T, S)>,
+    ) -> Option<(&'a T, &'a T, S)> {
+        match (pair1, pair2) {
+            (Some(pair1), Some(pair2)) => Some(if pair2.2 < pair1.2 { pair2 } else { pair1 }),
+            (pair1, pair2) => pair1.or(pair2),
+        }
+    }
+    fn recurse<T: KdPoint>(kdtree: &[T], axis: usize) -> Option<(&T, &T, T::Scalar)> {
+        if kdtree.len() < 2 {
+            return None;
+        }
+        let mid_idx = kdtree.len() / 2;
+        let next_axis = (axis + 1) % T::dim();
+        let mut closest = min(
+            recurse(&kdtree[..mid_idx], next_axis),
+            recurse(&kdtree[mid_idx + 1..], next_axis),
+        );
+        let mid_pos = kdtree[mid_idx].at(axis);
+        let is_in_slab = |item: &T, closest: &Option<(&T, &T, T::Scalar)>| {
+            let diff = item.at(axis) - mid_pos;
+            closest
+                .as_ref()
+                .map_or(true, |closest| diff * diff < closest.2)
+        };
+        let mut slab: Vec<&T> = kdtree
+            .iter()
+            .filter(|item| is_in_slab(item, &closest))
+            .collect();
+        if slab.len() < 2 {
+            return closest;
+        }
+        // the slab is thin along `axis`, so it is swept along the axis of the largest extent,
+        // which keeps the number of pairs compared small even if the items are aligned on the splitting plane.
+        let extent = |k: usize| {
+            let first = slab[0].at(k);
+            let (lo, hi) = slab[1..]
+                .iter()
+                .map(|item| item.at(k))
+                .fold((first, first), |(lo, hi), c| {
+                    (if c < lo { c } else { lo }, if hi < c { c } else { hi })
+                });
+            hi - lo
+        };
+        let (mut sweep_axis, mut max_extent) = (axis, extent(axis));
+        for k in 0..T::dim() {
+            let e = extent(k);
+            if max_extent < e {
+                sweep_axis = k;
+                max_extent = e;
+            }
+        }
+        slab.sort_by(|a, b| {
+            a.at(sweep_axis)
+                .partial_cmp(&b.at(sweep_axis))
+                .unwrap_or(Ordering::Equal)
+        });
+        for (i, a) in slab.iter().enumerate() {
+            for b in &slab[i + 1..] {
+                let diff = b.at(sweep_axis) - a.at(sweep_axis);
+                if closest
+                    .as_ref()
+                    .is_some_and(|closest| diff * diff >= closest.2)
+                {
+                    break;
+                }
+                let d = squared_distance(*a, *b);
+                closest = min(closest, Some((*a, *b, d)));
+            }
+        }
+        closest
+    }
+    recurse(kdtree, 0)
+}
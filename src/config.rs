@@ -0,0 +1,262 @@
+use crate::KdScalar;
+
+/// Tuning parameters and options of queries, accepted by `*_with_config` methods.
+///
+/// # Example
+/// ```
+/// use kd_tree::{KdTree, QueryConfig};
+/// let kdtree = KdTree::build(vec![[1, 2, 3], [3, 1, 2], [2, 3, 1]]);
+/// let config = QueryConfig {
+///     leaf_size: 16,
+///     instrument: true,
+///     ..Default::default()
+/// };
+/// let found = kdtree.nearest_with_config(&[3, 1, 2], &config).unwrap();
+/// assert!(found.is_exact);
+/// assert_eq!(found.result.item, &[3, 1, 2]);
+/// assert!(found.stats.unwrap().visited_items > 0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueryConfig {
+    /// Subtrees with at most this number of items are scanned linearly instead of being traversed recursively.
+    /// The optimal value depends on the size of items; larger items prefer smaller values.
+    pub leaf_size: usize,
+    /// The maximum depth of the traversal below the root.
+    /// If a query is cut off by this limit, the returned [`crate::PartialResult`] is not exact.
+    pub max_depth: usize,
+    /// Whether the nearest neighbor queries issue software prefetches for the subtrees to be visited next,
+    /// which hides memory latency on large trees. This is a no-op on architectures without a stable prefetch instruction.
+    pub prefetch: bool,
+    /// Trees with at most this number of items are scanned linearly as a whole, skipping the recursion entirely,
+    /// which is faster for small trees. It overrides `leaf_size` for such trees in all queries accepting the config.
+    /// Defaults to `0`, i.e. disabled, so that `leaf_size` alone determines the traversal.
+    pub small_tree_size: usize,
+    /// The relative error allowed to the distances of the nearest neighbor queries.
+    /// A subtree is pruned unless it can contain an item nearer than the current candidate divided by `1 + epsilon`,
+    /// so the found items are at most `1 + epsilon` times as far as the exact ones.
+    /// If a subtree is pruned only by this error, the returned [`crate::PartialResult`] is not exact.
+    ///
+    /// The factor is applied to the squared distances as a fraction with the denominator 256 reduced to the lowest terms,
+    /// so the squared distances of integer scalars must not overflow when multiplied by its numerator,
+    /// which is e.g. `4` for `epsilon = 1.0` but about `256 * (1 + epsilon)^2` in general. A zero `epsilon` multiplies nothing.
+    pub epsilon: f64,
+    /// The maximum number of the items examined by a query.
+    /// If a query is cut off by this limit, the returned [`crate::PartialResult`] is not exact.
+    pub node_budget: usize,
+    /// Whether the radius queries accept the items exactly on the sphere, i.e. `distance <= radius` instead of `distance < radius`.
+    pub inclusive: bool,
+    /// Which of the items at the same distance are returned by the nearest neighbor queries.
+    pub tie_break: DistanceTieBreak,
+    /// How the squared distances are accumulated over the axes.
+    pub accumulation: Accumulation,
+    /// Whether the query counts its work into [`crate::PartialResult::stats`].
+    pub instrument: bool,
+}
+
+impl Default for QueryConfig {
+    fn default() -> Self {
+        Self {
+            leaf_size: 1,
+            max_depth: usize::MAX,
+            prefetch: true,
+            small_tree_size: 0,
+            epsilon: 0.0,
+            node_budget: usize::MAX,
+            inclusive: false,
+            tie_break: DistanceTieBreak::default(),
+            accumulation: Accumulation::default(),
+            instrument: false,
+        }
+    }
+}
+
+/// The rule to choose among the items at the same distance from the query, used by [`QueryConfig::tie_break`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DistanceTieBreak {
+    /// Returns any of them, which is the fastest.
+    #[default]
+    Any,
+    /// Returns the ones which come first in the items of the tree, i.e. [`crate::KdSliceN::items`].
+    /// The result is independent of the traversal, e.g. of [`QueryConfig::leaf_size`].
+    FirstItem,
+}
+
+/// The way to sum up the squared differences along the axes into a squared distance, used by [`QueryConfig::accumulation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Accumulation {
+    /// Sums up them one by one.
+    #[default]
+    Plain,
+    /// Sums up them by the Kahan summation, which compensates the rounding errors of floating point numbers
+    /// in high dimensions. This is the same as `Plain` for integers.
+    Compensated,
+}
+
+impl Accumulation {
+    /// Returns the sum of the squares of `diff(k)` for `k` in `0..dim`.
+    pub(crate) fn squared_distance<S: KdScalar>(self, dim: usize, diff: impl Fn(usize) -> S) -> S {
+        let mut sum = S::zero();
+        match self {
+            Accumulation::Plain => {
+                for k in 0..dim {
+                    let diff = diff(k);
+                    sum += diff * diff;
+                }
+            }
+            Accumulation::Compensated => {
+                let mut compensation = S::zero();
+                for k in 0..dim {
+                    let diff = diff(k);
+                    let term = diff * diff - compensation;
+                    let next = sum + term;
+                    compensation = (next - sum) - term;
+                    sum = next;
+                }
+            }
+        }
+        sum
+    }
+}
+
+/// The work done by a query, reported if [`QueryConfig::instrument`] is enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QueryStats {
+    /// The number of the subtrees entered by the traversal.
+    pub visited_subtrees: usize,
+    /// The number of the items examined, which is limited by [`QueryConfig::node_budget`].
+    pub visited_items: usize,
+}
+
+/// The denominator of the factor of [`QueryConfig::epsilon`].
+const EPSILON_DENOMINATOR: u64 = 256;
+
+/// Returns `n` as a scalar, by doubling `one()`.
+fn scalar_of<S: KdScalar>(n: u64) -> S {
+    let mut result = S::zero();
+    for i in (0..64 - n.leading_zeros()).rev() {
+        result += result;
+        if n >> i & 1 == 1 {
+            result += S::one();
+        }
+    }
+    result
+}
+
+impl QueryConfig {
+    /// Returns the leaf size for a tree of `len` items, which is `len` itself for a small tree.
+    pub(crate) fn leaf_size_for(&self, len: usize) -> usize {
+        if len <= self.small_tree_size {
+            len
+        } else {
+            self.leaf_size
+        }
+    }
+
+    /// Returns `true` if the nearest neighbor queries can skip the options other than the tuning parameters,
+    /// i.e. the error, the budget, the tie-breaking, the accumulation and the instrumentation are the default ones.
+    /// Such a query is monomorphized into a separate fast path.
+    pub(crate) fn is_fast_path(&self) -> bool {
+        self.epsilon == 0.0
+            && !self.tracks_traversal()
+            && self.tie_break == DistanceTieBreak::Any
+            && self.accumulation == Accumulation::Plain
+    }
+
+    /// Returns `true` if the hooks of [`Traversal`] have anything to do,
+    /// i.e. the budget is limited or the traversal is instrumented.
+    pub(crate) fn tracks_traversal(&self) -> bool {
+        self.node_budget != usize::MAX || self.instrument
+    }
+
+    /// Returns the factor `(1 + epsilon)^2` as a fraction of scalars in the lowest terms, or `None` if `epsilon` is zero.
+    pub(crate) fn slack<S: KdScalar>(&self) -> Option<(S, S)> {
+        if self.epsilon == 0.0 {
+            return None;
+        }
+        let numerator = ((1.0 + self.epsilon).powi(2) * EPSILON_DENOMINATOR as f64).floor() as u64;
+        if numerator > EPSILON_DENOMINATOR {
+            let (mut a, mut b) = (numerator, EPSILON_DENOMINATOR);
+            while b != 0 {
+                (a, b) = (b, a % b);
+            }
+            Some((scalar_of(numerator / a), scalar_of(EPSILON_DENOMINATOR / a)))
+        } else {
+            None
+        }
+    }
+}
+
+/// The state of a traversal shared by the queries with [`QueryConfig`]: the limits, the exactness and the statistics.
+pub(crate) struct Traversal {
+    remaining_items: usize,
+    pub(crate) is_exact: bool,
+    stats: Option<QueryStats>,
+}
+
+impl Traversal {
+    pub(crate) fn new(config: &QueryConfig) -> Self {
+        Self {
+            remaining_items: config.node_budget,
+            is_exact: true,
+            stats: if config.instrument {
+                Some(QueryStats::default())
+            } else {
+                None
+            },
+        }
+    }
+
+    pub(crate) fn enter_subtree(&mut self) {
+        if let Some(stats) = &mut self.stats {
+            stats.visited_subtrees += 1;
+        }
+    }
+
+    /// Returns `false` if the budget is exhausted, in which case the result is not exact.
+    pub(crate) fn visit_item(&mut self) -> bool {
+        if self.remaining_items == 0 {
+            self.is_exact = false;
+            return false;
+        }
+        self.remaining_items -= 1;
+        if let Some(stats) = &mut self.stats {
+            stats.visited_items += 1;
+        }
+        true
+    }
+
+    pub(crate) fn is_exhausted(&self) -> bool {
+        self.remaining_items == 0
+    }
+
+    /// Returns `true` if a subtree at the squared distance `bound` may contain a neighbor nearer than `best`,
+    /// allowing the error given by `slack`, and marks the result inexact if it is pruned only by the error.
+    /// `ties` means that the subtree may also contain a neighbor at the same distance as `best`.
+    pub(crate) fn may_improve<S: KdScalar>(
+        &mut self,
+        bound: S,
+        best: S,
+        ties: bool,
+        slack: Option<(S, S)>,
+    ) -> bool {
+        let exact = bound < best || (ties && bound == best);
+        match slack {
+            Some((numerator, denominator)) if exact => {
+                let approximate = bound * numerator < best * denominator;
+                if !approximate {
+                    self.is_exact = false;
+                }
+                approximate
+            }
+            _ => exact,
+        }
+    }
+
+    pub(crate) fn finish<R>(self, result: R) -> crate::PartialResult<R> {
+        crate::PartialResult {
+            result,
+            is_exact: self.is_exact,
+            stats: self.stats,
+        }
+    }
+}
@@ -0,0 +1,54 @@
+/// A sparse matrix in CSR (compressed sparse row) format.
+///
+/// The column indices and the values of the `i`-th row are
+/// `indices[offsets[i]..offsets[i + 1]]` and `values[offsets[i]..offsets[i + 1]]` respectively.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CsrMatrix<V> {
+    pub offsets: Vec<usize>,
+    pub indices: Vec<usize>,
+    pub values: Vec<V>,
+}
+
+impl<V> CsrMatrix<V> {
+    /// Builds a matrix from rows of `(column index, value)` pairs.
+    pub fn from_rows(rows: impl IntoIterator<Item = Vec<(usize, V)>>) -> Self {
+        let mut matrix = Self {
+            offsets: vec![0],
+            indices: Vec::new(),
+            values: Vec::new(),
+        };
+        for mut row in rows {
+            row.sort_by_key(|&(j, _)| j);
+            for (j, value) in row {
+                matrix.indices.push(j);
+                matrix.values.push(value);
+            }
+            matrix.offsets.push(matrix.indices.len());
+        }
+        matrix
+    }
+
+    /// Returns the number of rows.
+    pub fn num_rows(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    /// Returns the number of stored (non-zero) entries.
+    pub fn num_entries(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Returns the column indices and the values of the `i`-th row.
+    pub fn row(&self, i: usize) -> (&[usize], &[V]) {
+        let range = self.offsets[i]..self.offsets[i + 1];
+        (&self.indices[range.clone()], &self.values[range])
+    }
+
+    /// Returns an iterator over `(row, column, value)` of all stored entries.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, &V)> + '_ {
+        (0..self.num_rows()).flat_map(move |i| {
+            let (indices, values) = self.row(i);
+            indices.iter().zip(values).map(move |(&j, v)| (i, j, v))
+        })
+    }
+}
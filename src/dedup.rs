@@ -0,0 +1,88 @@
+use crate::{ItemAndDistance, KdPoint, KdSliceN};
+use std::cmp::Ordering;
+use typenum::Unsigned;
+
+/// Which one of duplicate items is kept by the `*_dedup` queries of the trees made of multiple shards,
+/// such as [`crate::TiledKdTree`] and [`crate::DynamicKdTree`].
+///
+/// Shards are ordered as the tiles sorted by their keys, or as the buckets from the smallest one.
+/// Duplicates in the same shard are ordered as they are found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TieBreak {
+    /// Keeps the item in the first shard.
+    #[default]
+    FirstShard,
+    /// Keeps the item in the last shard.
+    LastShard,
+}
+
+/// Removes duplicates from `found`, which are pairs of a shard index and a result, keeping the order of `found`.
+pub(crate) fn dedup_shards<R>(
+    found: Vec<(usize, R)>,
+    compare: impl Fn(&R, &R) -> Ordering,
+    tie: TieBreak,
+) -> Vec<R> {
+    let mut order: Vec<usize> = (0..found.len()).collect();
+    order.sort_by(|&i, &j| compare(&found[i].1, &found[j].1).then(found[i].0.cmp(&found[j].0)));
+    let mut keep = vec![false; found.len()];
+    for run in order.chunk_by(|&i, &j| compare(&found[i].1, &found[j].1) == Ordering::Equal) {
+        let i = match tie {
+            TieBreak::FirstShard => run[0],
+            TieBreak::LastShard => run[run.len() - 1],
+        };
+        keep[i] = true;
+    }
+    found
+        .into_iter()
+        .zip(keep)
+        .filter_map(|((_, r), keep)| if keep { Some(r) } else { None })
+        .collect()
+}
+
+/// Compares the coordinates of two points lexicographically.
+pub(crate) fn compare_coords<T: KdPoint>(p1: &T, p2: &T) -> Ordering {
+    (0..T::dim())
+        .map(|k| p1.at(k).partial_cmp(&p2.at(k)).unwrap_or(Ordering::Equal))
+        .find(|ord| *ord != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Returns kNN over `shards` without duplicates.
+///
+/// Each shard is searched for `k` items, and `k` is doubled until no shard which returned `k` items
+/// may have an unseen item nearer than the `num`-th result.
+pub(crate) fn nearests_dedup<'a, T: KdPoint<Dim = N>, N: Unsigned>(
+    shards: &[&'a KdSliceN<T, N>],
+    query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    num: usize,
+    compare: impl Fn(&T, &T) -> Ordering,
+    tie: TieBreak,
+) -> Vec<ItemAndDistance<'a, T, T::Scalar>> {
+    let total: usize = shards.iter().map(|shard| shard.len()).sum();
+    let mut k = num;
+    loop {
+        let mut found = Vec::new();
+        let mut saturated = Vec::new();
+        for (i, shard) in shards.iter().enumerate() {
+            let nearests = shard.nearests(query, k);
+            if k > 0 && nearests.len() == k {
+                saturated.push(nearests[k - 1].squared_distance);
+            }
+            found.extend(nearests.into_iter().map(|nearest| (i, nearest)));
+        }
+        found.sort_by(|(_, a), (_, b)| {
+            a.squared_distance
+                .partial_cmp(&b.squared_distance)
+                .unwrap_or(Ordering::Equal)
+        });
+        let mut nearests = dedup_shards(found, |a, b| compare(a.item, b.item), tie);
+        nearests.truncate(num);
+        let is_complete = saturated
+            .iter()
+            .all(|d| nearests.len() == num && nearests[num - 1].squared_distance <= *d);
+        if is_complete || k >= total {
+            return nearests;
+        }
+        k *= 2;
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{KdIndexTreeN, KdSliceN, KdTreeN};
+use std::marker::PhantomData;
+use typenum::Unsigned;
+
+/// Difference of the indices of a [`KdIndexTreeN`] from those of a base snapshot.
+///
+/// When the source items change only slightly, most of the indices remain at the same positions,
+/// so shipping the delta is much smaller than shipping the whole indices.
+/// With `serde` feature, this type implements `Serialize` and `Deserialize`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndicesDelta {
+    /// The number of indices after the delta is applied.
+    pub len: usize,
+    /// Pairs of a position and the index stored at that position, which differ from the base snapshot.
+    pub changes: Vec<(usize, usize)>,
+}
+
+impl<'a, T, N: Unsigned> KdIndexTreeN<'a, T, N> {
+    /// Returns the difference of `self.indices()` from `base`.
+    /// # Example
+    /// ```
+    /// let mut items = vec![[1, 2], [3, 1], [2, 3], [5, 5]];
+    /// let base = kd_tree::KdIndexTree::build(&items).indices().to_owned();
+    /// items[3] = [5, 6];
+    /// let kdtree = kd_tree::KdIndexTree::build(&items);
+    /// let delta = kdtree.indices_delta(&base);
+    /// assert!(delta.changes.is_empty());
+    /// let restored = kd_tree::KdIndexTree::from_indices_delta(&items, &base, &delta).unwrap();
+    /// assert_eq!(restored, kdtree);
+    /// ```
+    pub fn indices_delta(&self, base: &KdSliceN<usize, N>) -> IndicesDelta {
+        IndicesDelta {
+            len: self.kdtree.len(),
+            changes: self
+                .kdtree
+                .iter()
+                .enumerate()
+                .filter(|&(pos, index)| base.get(pos) != Some(index))
+                .map(|(pos, &index)| (pos, index))
+                .collect(),
+        }
+    }
+
+    /// Restores an index tree over `source` by applying `delta` to `base`.
+    /// Returns `None` if `delta` is inconsistent with `base` or `source`.
+    ///
+    /// The kd-tree invariant of the restored indices is not verified,
+    /// so `delta` must be the one made by [`Self::indices_delta`] with the same `base`.
+    pub fn from_indices_delta(
+        source: &'a [T],
+        base: &KdSliceN<usize, N>,
+        delta: &IndicesDelta,
+    ) -> Option<Self> {
+        if delta.len != source.len() {
+            return None;
+        }
+        let mut indices: Vec<Option<usize>> =
+            (0..delta.len).map(|pos| base.get(pos).copied()).collect();
+        for &(pos, index) in &delta.changes {
+            *indices.get_mut(pos)? = Some(index);
+        }
+        let indices = indices
+            .into_iter()
+            .map(|index| index.filter(|&index| index < source.len()))
+            .collect::<Option<Vec<usize>>>()?;
+        Some(Self {
+            source,
+            kdtree: KdTreeN(PhantomData, indices),
+        })
+    }
+}
@@ -0,0 +1,101 @@
+use crate::KdPoint;
+use std::marker::PhantomData;
+use typenum::Unsigned;
+
+/// An adapter which implements [`KdPoint`] with the dimension `N` over a point of the same number of axes,
+/// made by [`KdPoint::as_dim`] or [`KdPoint::try_as_dim`].
+///
+/// This bridges a point whose `Dim` is not known to be `N` by the compiler,
+/// e.g. in a generic function which takes a tree and a query of different point types.
+/// # Example
+/// ```
+/// use kd_tree::{KdPoint, KdTree};
+/// fn nearest<'a, P, Q>(kdtree: &'a KdTree<P>, query: &Q) -> &'a P
+/// where
+///     P: KdPoint,
+///     Q: KdPoint<Scalar = P::Scalar>,
+/// {
+///     // `kdtree.nearest(query)` does not compile, since `Q::Dim` is not known to be `P::Dim`.
+///     kdtree.nearest(&query.as_dim::<P::Dim>()).unwrap().item
+/// }
+/// let kdtree = KdTree::build(vec![[1, 2, 3], [3, 1, 2], [2, 3, 1]]);
+/// assert_eq!(nearest(&kdtree, &[3, 1, 1]), &[3, 1, 2]);
+/// ```
+/// The dimensions are checked at compile time by [`KdPoint::as_dim`]:
+/// ```compile_fail
+/// use kd_tree::KdPoint;
+/// let kdtree = kd_tree::KdTree::build(vec![[1, 2, 3], [3, 1, 2], [2, 3, 1]]);
+/// kdtree.nearest(&[3, 1].as_dim::<typenum::U3>());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AsDim<P, N>(P, PhantomData<N>);
+
+impl<P, N> AsDim<P, N> {
+    pub fn point(&self) -> &P {
+        &self.0
+    }
+
+    pub fn into_point(self) -> P {
+        self.0
+    }
+}
+
+impl<P: KdPoint, N: Unsigned> KdPoint for AsDim<P, N> {
+    type Scalar = P::Scalar;
+    type Dim = N;
+    fn at(&self, k: usize) -> Self::Scalar {
+        self.0.at(k)
+    }
+}
+
+/// Fails the compilation of [`KdPoint::as_dim`] if the dimensions differ.
+struct DimCheck<P: ?Sized, N>(PhantomData<fn(&P) -> N>);
+
+impl<P: KdPoint + ?Sized, N: Unsigned> DimCheck<P, N> {
+    const OK: () = assert!(
+        <P::Dim as Unsigned>::USIZE == N::USIZE,
+        "the dimension of the point differs from the one required by `as_dim`"
+    );
+}
+
+/// Makes the adapter after the dimensions are checked at compile time.
+pub(crate) fn as_dim<P: KdPoint + ?Sized, N: Unsigned>(point: &P) -> AsDim<&P, N> {
+    #[allow(clippy::let_unit_value)]
+    let () = DimCheck::<P, N>::OK;
+    AsDim(point, PhantomData)
+}
+
+/// Makes the adapter if the dimensions are the same.
+pub(crate) fn try_as_dim<P: KdPoint + ?Sized, N: Unsigned>(
+    point: &P,
+) -> Result<AsDim<&P, N>, DimMismatch> {
+    if P::dim() == N::to_usize() {
+        Ok(AsDim(point, PhantomData))
+    } else {
+        Err(DimMismatch {
+            expected: N::to_usize(),
+            actual: P::dim(),
+        })
+    }
+}
+
+/// The error returned by [`KdPoint::try_as_dim`] when the dimensions of the points differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DimMismatch {
+    /// The dimension required.
+    pub expected: usize,
+    /// The dimension of the point.
+    pub actual: usize,
+}
+
+impl std::fmt::Display for DimMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the point has {} axes, but {} axes are required",
+            self.actual, self.expected
+        )
+    }
+}
+
+impl std::error::Error for DimMismatch {}
@@ -0,0 +1,254 @@
+use crate::dedup::{compare_coords, dedup_shards, nearests_dedup};
+use crate::{ItemAndDistance, KdPoint, KdTreeN, TieBreak};
+use std::cmp::Ordering;
+use typenum::Unsigned;
+
+/// A kd-tree which supports insertion, by the logarithmic method.
+///
+/// Items are stored in static [`KdTreeN`]s whose sizes are distinct powers of two.
+/// An insertion merges the buckets smaller than the first empty one into it,
+/// so that an insertion takes amortized `O(log^2 n)` time, and a query visits `O(log n)` buckets.
+/// # Example
+/// ```
+/// let mut kdtree = kd_tree::DynamicKdTree::new();
+/// for p in [[1.0, 2.0, 3.0], [3.0, 1.0, 2.0], [2.0, 3.0, 1.0]] {
+///     kdtree.insert_by_ordered_float(p);
+/// }
+/// assert_eq!(kdtree.len(), 3);
+/// assert_eq!(kdtree.buckets().count(), 2);
+/// assert_eq!(kdtree.nearest(&[3.1, 0.9, 2.1]).unwrap().item, &[3.0, 1.0, 2.0]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynamicKdTreeN<T, N: Unsigned> {
+    buckets: Vec<Option<KdTreeN<T, N>>>,
+}
+pub type DynamicKdTree<T> = DynamicKdTreeN<T, <T as KdPoint>::Dim>;
+
+impl<T, N: Unsigned> Default for DynamicKdTreeN<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, N: Unsigned> DynamicKdTreeN<T, N> {
+    pub fn new() -> Self {
+        Self {
+            buckets: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets().map(|kdtree| kdtree.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets().next().is_none()
+    }
+
+    /// Returns the non-empty buckets, from the smallest one.
+    pub fn buckets(&self) -> impl Iterator<Item = &KdTreeN<T, N>> {
+        self.buckets.iter().flatten()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.buckets().flat_map(|kdtree| kdtree.iter())
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.buckets
+            .into_iter()
+            .flatten()
+            .flat_map(|kdtree| kdtree.into_vec())
+            .collect()
+    }
+
+    /// Inserts an item, merging the smaller buckets into a new one.
+    /// `compare` is used to build the merged bucket, as in [`KdTreeN::build_by`].
+    pub fn insert_by<F>(&mut self, item: T, compare: F)
+    where
+        F: Fn(&T, &T, usize) -> Ordering + Copy,
+    {
+        let mut items = vec![item];
+        for bucket in self.buckets.iter_mut() {
+            match bucket.take() {
+                Some(kdtree) => items.extend(kdtree.into_vec()),
+                None => {
+                    *bucket = Some(KdTreeN::build_by(items, compare));
+                    return;
+                }
+            }
+        }
+        self.buckets.push(Some(KdTreeN::build_by(items, compare)));
+    }
+
+    /// Inserts an item. `kd_key` is used to build the merged bucket, as in [`KdTreeN::build_by_key`].
+    pub fn insert_by_key<Key, F>(&mut self, item: T, kd_key: F)
+    where
+        Key: Ord,
+        F: Fn(&T, usize) -> Key + Copy,
+    {
+        self.insert_by(item, |item1, item2, k| {
+            kd_key(item1, k).cmp(&kd_key(item2, k))
+        })
+    }
+
+    pub fn insert_by_ordered_float(&mut self, point: T)
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        self.insert_by_key(point, |item, k| ordered_float::OrderedFloat(item.at(k)))
+    }
+
+    pub fn insert(&mut self, point: T)
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: Ord,
+    {
+        self.insert_by_key(point, |item, k| item.at(k))
+    }
+
+    /// Returns the nearest item from the input point over all buckets. Returns `None` if `self.is_empty()`.
+    pub fn nearest(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.buckets()
+            .filter_map(|kdtree| kdtree.nearest(query))
+            .min_by(|a, b| {
+                a.squared_distance
+                    .partial_cmp(&b.squared_distance)
+                    .unwrap_or(Ordering::Equal)
+            })
+    }
+
+    /// Returns kNN(k nearest neighbors) from the input point over all buckets.
+    pub fn nearests(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let mut nearests: Vec<_> = self
+            .buckets()
+            .flat_map(|kdtree| kdtree.nearests(query, num))
+            .collect();
+        nearests.sort_by(|a, b| {
+            a.squared_distance
+                .partial_cmp(&b.squared_distance)
+                .unwrap_or(Ordering::Equal)
+        });
+        nearests.truncate(num);
+        nearests
+    }
+
+    /// search points within a rectangular region over all buckets.
+    pub fn within(&self, query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2]) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.buckets()
+            .flat_map(|kdtree| kdtree.within(query))
+            .collect()
+    }
+
+    /// search points within a sphere over all buckets.
+    pub fn within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.buckets()
+            .flat_map(|kdtree| kdtree.within_radius(query, radius))
+            .collect()
+    }
+
+    /// Same as [`Self::nearests`], but the items which are equal by `compare` are regarded as duplicates,
+    /// and only one of them chosen by `tie` is returned.
+    /// # Example
+    /// ```
+    /// use kd_tree::{DynamicKdTree, TieBreak};
+    /// let mut kdtree = DynamicKdTree::new();
+    /// for (p, id) in [([1, 1], 0), ([2, 2], 1), ([1, 1], 2)] {
+    ///     kdtree.insert_by_key((p, id), |item, k| item.0[k]);
+    /// }
+    /// let compare = |a: &([i32; 2], usize), b: &([i32; 2], usize)| a.0.cmp(&b.0);
+    /// // the buckets are `[([1, 1], 2)]` and `[([1, 1], 0), ([2, 2], 1)]`.
+    /// let found = kdtree.nearests_dedup_by(&[0, 0], 2, compare, TieBreak::FirstShard);
+    /// assert_eq!(found.iter().map(|f| f.item.1).collect::<Vec<_>>(), vec![2, 1]);
+    /// let found = kdtree.nearests_dedup_by(&[0, 0], 2, compare, TieBreak::LastShard);
+    /// assert_eq!(found.iter().map(|f| f.item.1).collect::<Vec<_>>(), vec![0, 1]);
+    /// ```
+    pub fn nearests_dedup_by(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+        compare: impl Fn(&T, &T) -> Ordering,
+        tie: TieBreak,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let shards: Vec<_> = self.buckets().map(|kdtree| &**kdtree).collect();
+        nearests_dedup(&shards, query, num, compare, tie)
+    }
+
+    /// Same as [`Self::nearests`], but the points at the same position are returned only once.
+    pub fn nearests_dedup(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+        tie: TieBreak,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.nearests_dedup_by(query, num, compare_coords, tie)
+    }
+
+    /// Same as [`Self::within_radius`], but the items which are equal by `compare` are regarded as duplicates,
+    /// and only one of them chosen by `tie` is returned.
+    pub fn within_radius_dedup_by(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+        compare: impl Fn(&T, &T) -> Ordering,
+        tie: TieBreak,
+    ) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let found = self
+            .buckets()
+            .enumerate()
+            .flat_map(|(i, kdtree)| {
+                kdtree
+                    .within_radius(query, radius)
+                    .into_iter()
+                    .map(move |item| (i, item))
+            })
+            .collect();
+        dedup_shards(found, |a, b| compare(a, b), tie)
+    }
+
+    /// Same as [`Self::within_radius`], but the points at the same position are returned only once.
+    pub fn within_radius_dedup(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+        tie: TieBreak,
+    ) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.within_radius_dedup_by(query, radius, compare_coords, tie)
+    }
+}
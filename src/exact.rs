@@ -0,0 +1,142 @@
+use crate::KdScalar;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
+
+/// A 256-bit signed integer scalar, which computes squared distances exactly for 64-bit coordinates.
+///
+/// The standard queries compute squared distances in the scalar type itself,
+/// which overflows for large 64-bit coordinates such as fixed-point geographic encodings.
+/// Querying by `Exact` coordinates with the `*_by` methods computes them in 256 bits instead.
+/// A squared difference of 64-bit coordinates is less than `2^128`, so the squared distances never overflow
+/// in any dimension, and the nearest items are found correctly over the full range of `i64` and `u64`.
+/// # Example
+/// ```
+/// use kd_tree::Exact;
+/// let kdtree = kd_tree::KdTree::build(vec![[i64::MIN, 0], [i64::MAX, 0]]);
+/// let query = [Exact::from(i64::MAX - 1), Exact::from(0)];
+/// let found = kdtree.nearest_by(&query, |item, k| Exact::from(item[k])).unwrap();
+/// assert_eq!(found.item, &[i64::MAX, 0]);
+/// assert_eq!(found.squared_distance, Exact::from(1));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Exact {
+    // the value is `hi * 2^128 + lo` in two's complement, so the derived order of the fields is the order of the values.
+    hi: i128,
+    lo: u128,
+}
+
+impl Exact {
+    /// The upper 128 bits of the value, which is `hi * 2^128 + lo`.
+    pub fn hi(self) -> i128 {
+        self.hi
+    }
+
+    /// The lower 128 bits of the value, which is `hi * 2^128 + lo`.
+    pub fn lo(self) -> u128 {
+        self.lo
+    }
+
+    fn to_limbs(self) -> [u64; 4] {
+        let hi = self.hi as u128;
+        [
+            self.lo as u64,
+            (self.lo >> 64) as u64,
+            hi as u64,
+            (hi >> 64) as u64,
+        ]
+    }
+
+    fn from_limbs(limbs: [u64; 4]) -> Self {
+        Self {
+            hi: (u128::from(limbs[2]) | u128::from(limbs[3]) << 64) as i128,
+            lo: u128::from(limbs[0]) | u128::from(limbs[1]) << 64,
+        }
+    }
+}
+
+macro_rules! impl_from_int {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Exact {
+                fn from(x: $t) -> Self {
+                    let x = x as i128;
+                    Self { hi: if x < 0 { -1 } else { 0 }, lo: x as u128 }
+                }
+            }
+        )*
+    };
+}
+impl_from_int!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, usize);
+
+impl From<u128> for Exact {
+    fn from(x: u128) -> Self {
+        Self { hi: 0, lo: x }
+    }
+}
+
+// the operations wrap around at 256 bits, which is never reached by the squared distances of 64-bit coordinates.
+impl Add for Exact {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        let (lo, carry) = self.lo.overflowing_add(other.lo);
+        Self {
+            hi: self.hi.wrapping_add(other.hi).wrapping_add(carry as i128),
+            lo,
+        }
+    }
+}
+
+impl Sub for Exact {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        let (lo, borrow) = self.lo.overflowing_sub(other.lo);
+        Self {
+            hi: self.hi.wrapping_sub(other.hi).wrapping_sub(borrow as i128),
+            lo,
+        }
+    }
+}
+
+impl Mul for Exact {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        // the product modulo `2^256` is the same for the unsigned and the two's complement values.
+        let (a, b) = (self.to_limbs(), other.to_limbs());
+        let mut limbs = [0u64; 4];
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 - i {
+                let t = u128::from(a[i]) * u128::from(b[j]) + u128::from(limbs[i + j]) + carry;
+                limbs[i + j] = t as u64;
+                carry = t >> 64;
+            }
+        }
+        Self::from_limbs(limbs)
+    }
+}
+
+impl AddAssign for Exact {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Exact {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign for Exact {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+impl KdScalar for Exact {
+    fn zero() -> Self {
+        Self::from(0)
+    }
+    fn one() -> Self {
+        Self::from(1)
+    }
+}
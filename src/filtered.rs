@@ -0,0 +1,150 @@
+use crate::augment::per_subtree;
+use crate::nearests::kd_nearests_by_pruned;
+use crate::{AxisRanges, ItemAndDistance, KdPoint, KdSliceN, QueryConfig};
+use typenum::Unsigned;
+
+/// A view of a kd-tree which only shows the items satisfying a predicate, e.g. "active items only",
+/// without copying them into another tree.
+///
+/// Every query skips the items rejected by the predicate.
+/// With [`Self::with_count_hints`], the number of accepted items is precomputed for each subtree,
+/// so that nearest queries prune the subtrees without accepted items at once,
+/// which pays off when the predicate rejects most of the items in some regions.
+/// # Example
+/// ```
+/// let kdtree = kd_tree::KdTree::build(vec![[1, 2], [3, 1], [2, 3], [5, 5]]);
+/// let odd = kdtree.filtered(|p| p[0] % 2 == 1).with_count_hints();
+/// assert_eq!(odd.len(), 3);
+/// assert_eq!(odd.nearest(&[2, 3]).unwrap().item, &[1, 2]);
+/// assert_eq!(odd.within_radius(&[4, 3], 3).len(), 2);
+/// ```
+#[derive(Clone)]
+pub struct FilteredKdSlice<'a, T, N: Unsigned, F> {
+    kdtree: &'a KdSliceN<T, N>,
+    filter: F,
+    /// The number of accepted items in each subtree, stored at the position of the median item of the subtree.
+    counts: Option<Vec<usize>>,
+}
+
+impl<'a, T, N: Unsigned, F: Fn(&T) -> bool> FilteredKdSlice<'a, T, N, F> {
+    pub fn new(kdtree: &'a KdSliceN<T, N>, filter: F) -> Self {
+        Self {
+            kdtree,
+            filter,
+            counts: None,
+        }
+    }
+
+    /// Precomputes the number of accepted items of each subtree, which takes `O(n log n)` time.
+    ///
+    /// The predicate must not change its result for any item afterwards.
+    pub fn with_count_hints(mut self) -> Self {
+        let filter = &self.filter;
+        let counts = per_subtree(self.kdtree.items(), |subtree| {
+            subtree.iter().filter(|item| filter(item)).count()
+        });
+        self.counts = Some(counts);
+        self
+    }
+
+    /// Returns the underlying kd-tree, which contains rejected items as well.
+    pub fn kdtree(&self) -> &'a KdSliceN<T, N> {
+        self.kdtree
+    }
+
+    /// Returns `true` if the item is shown in this view.
+    pub fn accepts(&self, item: &T) -> bool {
+        (self.filter)(item)
+    }
+
+    /// Returns the accepted items.
+    pub fn iter(&self) -> impl Iterator<Item = &'a T> + '_ {
+        self.kdtree.iter().filter(move |item| self.accepts(item))
+    }
+
+    /// Returns the number of accepted items, which takes `O(1)` time with count hints and `O(n)` time otherwise.
+    pub fn len(&self) -> usize {
+        match &self.counts {
+            Some(counts) => counts.get(counts.len() / 2).copied().unwrap_or(0),
+            None => self.iter().count(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the subtree is known to have no accepted items.
+    fn is_empty_subtree(&self, subtree: &[T]) -> bool {
+        match &self.counts {
+            Some(counts) => counts[self.kdtree.offset_of(&subtree[subtree.len() / 2])] == 0,
+            None => false,
+        }
+    }
+
+    /// Returns the nearest accepted item from the input point. Returns `None` if `self.is_empty()`.
+    pub fn nearest(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<ItemAndDistance<'a, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.nearests(query, 1).pop()
+    }
+
+    /// Returns kNN(k nearest neighbors) of accepted items from the input point.
+    pub fn nearests(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+    ) -> Vec<ItemAndDistance<'a, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        kd_nearests_by_pruned(
+            self.kdtree.items(),
+            query,
+            num,
+            &QueryConfig::default(),
+            |item, k| item.at(k),
+            None,
+            &self.filter,
+            &AxisRanges::new(),
+            |subtree| self.is_empty_subtree(subtree),
+        )
+        .result
+    }
+
+    /// search accepted points within a rectangular region.
+    pub fn within(&self, query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2]) -> Vec<&'a T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let mut results = self.kdtree.within(query);
+        results.retain(|item| self.accepts(item));
+        results
+    }
+
+    /// search accepted points within a sphere.
+    pub fn within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<&'a T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let mut results = self.kdtree.within_radius(query, radius);
+        results.retain(|item| self.accepts(item));
+        results
+    }
+}
+
+impl<T, N: Unsigned> KdSliceN<T, N> {
+    /// Returns a view of this tree which only shows the items satisfying `filter`.
+    /// See [`FilteredKdSlice`].
+    pub fn filtered<F: Fn(&T) -> bool>(&self, filter: F) -> FilteredKdSlice<'_, T, N, F> {
+        FilteredKdSlice::new(self, filter)
+    }
+}
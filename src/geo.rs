@@ -0,0 +1,64 @@
+#![cfg(feature = "geo")]
+use crate::{KdPoint, KdScalar, KdTreeN};
+use geo_types::{Coord, CoordNum, MultiPoint, Point};
+use std::cmp::Ordering;
+use typenum::U2;
+
+impl<T: CoordNum + KdScalar> KdPoint for Coord<T> {
+    type Scalar = T;
+    type Dim = U2;
+    fn at(&self, k: usize) -> T {
+        match k {
+            0 => self.x,
+            1 => self.y,
+            _ => panic!("index out of range: {}", k),
+        }
+    }
+}
+
+impl<T: CoordNum + KdScalar> KdPoint for Point<T> {
+    type Scalar = T;
+    type Dim = U2;
+    fn at(&self, k: usize) -> T {
+        self.0.at(k)
+    }
+}
+
+impl<T: CoordNum + KdScalar> KdTreeN<Point<T>, U2> {
+    /// Builds a tree of the points of `multi_point`.
+    pub fn from_multi_point_by<F>(multi_point: MultiPoint<T>, compare: F) -> Self
+    where
+        F: Fn(&Point<T>, &Point<T>, usize) -> Ordering + Copy,
+    {
+        Self::build_by(multi_point.0, compare)
+    }
+
+    pub fn from_multi_point_by_key<Key, F>(multi_point: MultiPoint<T>, kd_key: F) -> Self
+    where
+        Key: Ord,
+        F: Fn(&Point<T>, usize) -> Key + Copy,
+    {
+        Self::build_by_key(multi_point.0, kd_key)
+    }
+
+    /// # Example
+    /// ```
+    /// use geo_types::{point, MultiPoint};
+    /// let points = MultiPoint::new(vec![point!(x: 1.0, y: 2.0), point!(x: 3.0, y: 1.0), point!(x: 2.0, y: 3.0)]);
+    /// let kdtree = kd_tree::KdTree2::from_multi_point_by_ordered_float(points);
+    /// assert_eq!(kdtree.nearest(&point!(x: 3.1, y: 0.9)).unwrap().item, &point!(x: 3.0, y: 1.0));
+    /// ```
+    pub fn from_multi_point_by_ordered_float(multi_point: MultiPoint<T>) -> Self
+    where
+        T: ordered_float::FloatCore,
+    {
+        Self::build_by_ordered_float(multi_point.0)
+    }
+
+    pub fn from_multi_point(multi_point: MultiPoint<T>) -> Self
+    where
+        T: Ord,
+    {
+        Self::build(multi_point.0)
+    }
+}
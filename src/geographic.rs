@@ -0,0 +1,395 @@
+//! Queries on geographic points, which are 2-D points of latitude and longitude in degrees.
+//!
+//! Points must implement `KdPoint<Scalar = f64, Dim = U2>` with `at(0)` as the latitude in `[-90, 90]`
+//! and `at(1)` as the longitude in `[-180, 180]`.
+//! Distances are great-circle distances in meters on a sphere of radius [`EARTH_RADIUS`].
+use crate::{ItemAndMetricDistance, KdPoint, KdSliceN};
+use std::cmp::Ordering;
+use typenum::U2;
+
+/// The mean radius of the earth in meters.
+pub const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Returns the great-circle distance in meters between two points given as `[latitude, longitude]` in degrees.
+/// # Example
+/// ```
+/// let tokyo = [35.6812, 139.7671];
+/// let osaka = [34.7025, 135.4959];
+/// let distance = kd_tree::geographic::haversine_distance(&tokyo, &osaka);
+/// assert!((distance - 403_000.0).abs() < 1_000.0);
+/// ```
+pub fn haversine_distance(p1: &impl KdPoint<Scalar = f64>, p2: &impl KdPoint<Scalar = f64>) -> f64 {
+    let (lat1, lat2) = (p1.at(0).to_radians(), p2.at(0).to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (p2.at(1) - p1.at(1)).to_radians();
+    let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin()
+}
+
+/// Returns the great-circle distance in meters from `p` to the great-circle segment between `a` and `b`.
+/// All points are given as `[latitude, longitude]` in degrees.
+/// # Example
+/// ```
+/// use kd_tree::geographic::{haversine_distance, haversine_segment_distance};
+/// let d = haversine_segment_distance(&[1.0, 0.5], &[0.0, 0.0], &[0.0, 1.0]);
+/// assert!((d - haversine_distance(&[1.0, 0.5], &[0.0, 0.5])).abs() < 1.0e-6);
+/// let d = haversine_segment_distance(&[0.0, 2.0], &[0.0, 0.0], &[0.0, 1.0]);
+/// assert!((d - haversine_distance(&[0.0, 2.0], &[0.0, 1.0])).abs() < 1.0e-6);
+/// ```
+pub fn haversine_segment_distance(
+    p: &impl KdPoint<Scalar = f64>,
+    a: &impl KdPoint<Scalar = f64>,
+    b: &impl KdPoint<Scalar = f64>,
+) -> f64 {
+    let endpoints = || haversine_distance(p, a).min(haversine_distance(p, b));
+    let (vp, va, vb) = (to_unit_vector(p), to_unit_vector(a), to_unit_vector(b));
+    let n = cross(&va, &vb);
+    let norm = dot(&n, &n).sqrt();
+    if norm < 1.0e-12 {
+        return endpoints();
+    }
+    let n = n.map(|x| x / norm);
+    // `p` is projected onto the great circle between the planes through `a` and `b` perpendicular to it.
+    if dot(&cross(&va, &vp), &n) >= 0.0 && dot(&cross(&vp, &vb), &n) >= 0.0 {
+        dot(&vp, &n).abs().min(1.0).asin() * EARTH_RADIUS
+    } else {
+        endpoints()
+    }
+}
+
+fn to_unit_vector(p: &impl KdPoint<Scalar = f64>) -> [f64; 3] {
+    let (lat, lon) = (p.at(0).to_radians(), p.at(1).to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+fn from_unit_vector(v: &[f64; 3]) -> [f64; 2] {
+    [
+        v[2].atan2(v[0].hypot(v[1])).to_degrees(),
+        v[1].atan2(v[0]).to_degrees(),
+    ]
+}
+
+fn dot(v1: &[f64; 3], v2: &[f64; 3]) -> f64 {
+    v1[0] * v2[0] + v1[1] * v2[1] + v1[2] * v2[2]
+}
+
+fn cross(v1: &[f64; 3], v2: &[f64; 3]) -> [f64; 3] {
+    [
+        v1[1] * v2[2] - v1[2] * v2[1],
+        v1[2] * v2[0] - v1[0] * v2[2],
+        v1[0] * v2[1] - v1[1] * v2[0],
+    ]
+}
+
+/// Returns the bounding boxes, as `[[lat_min, lon_min], [lat_max, lon_max]]`,
+/// which cover all points within `radius` meters from `center`.
+///
+/// The longitude half width depends on the latitude of `center`, and the whole longitude range is covered
+/// if the circle contains a pole. A box crossing the antimeridian is split into two boxes.
+pub fn bounding_boxes_of_circle(
+    center: &impl KdPoint<Scalar = f64>,
+    radius: f64,
+) -> Vec<[[f64; 2]; 2]> {
+    let (lat, lon) = (center.at(0), center.at(1));
+    let delta = radius / EARTH_RADIUS;
+    if delta >= std::f64::consts::PI {
+        return vec![[[-90.0, -180.0], [90.0, 180.0]]];
+    }
+    let lat_min = lat - delta.to_degrees();
+    let lat_max = lat + delta.to_degrees();
+    if lat_min <= -90.0 || lat_max >= 90.0 {
+        return vec![[[lat_min.max(-90.0), -180.0], [lat_max.min(90.0), 180.0]]];
+    }
+    let sin_dlon = delta.sin() / lat.to_radians().cos();
+    if sin_dlon >= 1.0 {
+        return vec![[[lat_min, -180.0], [lat_max, 180.0]]];
+    }
+    let dlon = sin_dlon.asin().to_degrees();
+    split_at_antimeridian([lat_min, lat_max], [lon - dlon, lon + dlon])
+}
+
+/// Splits a box whose longitude interval `[lon[0], lon[1]]` may exceed `[-180, 180]` at the antimeridian.
+fn split_at_antimeridian(lat: [f64; 2], lon: [f64; 2]) -> Vec<[[f64; 2]; 2]> {
+    if lon[1] - lon[0] >= 360.0 {
+        vec![[[lat[0], -180.0], [lat[1], 180.0]]]
+    } else if lon[0] < -180.0 {
+        vec![
+            [[lat[0], -180.0], [lat[1], lon[1]]],
+            [[lat[0], lon[0] + 360.0], [lat[1], 180.0]],
+        ]
+    } else if lon[1] > 180.0 {
+        vec![
+            [[lat[0], lon[0]], [lat[1], 180.0]],
+            [[lat[0], -180.0], [lat[1], lon[1] - 360.0]],
+        ]
+    } else {
+        vec![[[lat[0], lon[0]], [lat[1], lon[1]]]]
+    }
+}
+
+/// Returns a lower bound of the distance in meters from `query` to the points in the region
+/// `[lat[0], lat[1]] x [lon[0], lon[1]]`, where `lon[0] <= lon[1]`.
+///
+/// The nearest point of the region is on the meridian through `query` (or the opposite one across a pole),
+/// or on one of the meridian edges of the region.
+fn min_distance_to_region(query: &[f64; 2], lat: [f64; 2], lon: [f64; 2]) -> f64 {
+    if lon[0] <= query[1] && query[1] <= lon[1] {
+        let dlat = query[0] - query[0].clamp(lat[0], lat[1]);
+        return dlat.abs().to_radians() * EARTH_RADIUS;
+    }
+    let mut min = min_distance_to_meridian(query, lon[0], lat)
+        .min(min_distance_to_meridian(query, lon[1], lat));
+    let opposite = if query[1] > 0.0 {
+        query[1] - 180.0
+    } else {
+        query[1] + 180.0
+    };
+    if lon[0] <= opposite && opposite <= lon[1] {
+        for lat in lat {
+            min = min.min(haversine_distance(query, &[lat, opposite]));
+        }
+    }
+    min
+}
+
+/// Returns the distance in meters from `query` to the meridian segment `[lat[0], lat[1]]` at `lon`.
+fn min_distance_to_meridian(query: &[f64; 2], lon: f64, lat: [f64; 2]) -> f64 {
+    let phi = query[0].to_radians();
+    let dlon = (lon - query[1]).to_radians();
+    // the latitude of the foot of the perpendicular to the great circle of the meridian
+    let foot = phi.sin().atan2(phi.cos() * dlon.cos()).to_degrees();
+    let mut min =
+        haversine_distance(query, &[lat[0], lon]).min(haversine_distance(query, &[lat[1], lon]));
+    if lat[0] <= foot && foot <= lat[1] {
+        min = min.min(haversine_distance(query, &[foot, lon]));
+    }
+    min
+}
+
+fn kd_nearests_geo<'a, T: KdPoint<Scalar = f64, Dim = U2>>(
+    kdtree: &'a [T],
+    query: &[f64; 2],
+    num: usize,
+) -> Vec<ItemAndMetricDistance<'a, T, f64>> {
+    fn recurse<'a, T: KdPoint<Scalar = f64, Dim = U2>>(
+        nearests: &mut Vec<ItemAndMetricDistance<'a, T, f64>>,
+        num: usize,
+        kdtree: &'a [T],
+        query: &[f64; 2],
+        region: [[f64; 2]; 2],
+        axis: usize,
+    ) {
+        let is_candidate = |nearests: &Vec<ItemAndMetricDistance<'a, T, f64>>, distance| {
+            nearests.len() < num || distance < nearests.last().unwrap().distance
+        };
+        let mid_idx = kdtree.len() / 2;
+        let item = &kdtree[mid_idx];
+        let distance = haversine_distance(item, query);
+        if is_candidate(nearests, distance) {
+            if nearests.len() == num {
+                nearests.pop();
+            }
+            let i = nearests
+                .binary_search_by(|item| {
+                    item.distance
+                        .partial_cmp(&distance)
+                        .unwrap_or(Ordering::Equal)
+                })
+                .unwrap_or_else(|i| i);
+            nearests.insert(i, ItemAndMetricDistance { item, distance });
+        }
+        let mid_pos = item.at(axis);
+        let mut lower = region;
+        lower[axis][1] = mid_pos;
+        let mut upper = region;
+        upper[axis][0] = mid_pos;
+        let mut branches = [
+            (
+                &kdtree[..mid_idx],
+                lower,
+                min_distance_to_region(query, lower[0], lower[1]),
+            ),
+            (
+                &kdtree[mid_idx + 1..],
+                upper,
+                min_distance_to_region(query, upper[0], upper[1]),
+            ),
+        ];
+        if branches[1].2 < branches[0].2 {
+            branches.swap(0, 1);
+        }
+        for (branch, region, bound) in branches {
+            if !branch.is_empty() && is_candidate(nearests, bound) {
+                recurse(nearests, num, branch, query, region, (axis + 1) % 2);
+            }
+        }
+    }
+    let mut nearests = Vec::with_capacity(num);
+    if num != 0 && !kdtree.is_empty() {
+        let region = [[-90.0, 90.0], [-180.0, 180.0]];
+        recurse(&mut nearests, num, kdtree, query, region, 0);
+    }
+    nearests
+}
+
+impl<T: KdPoint<Scalar = f64, Dim = U2>> KdSliceN<T, U2> {
+    /// search geographic points within `radius` meters of great-circle distance from `query`.
+    /// See [`crate::geographic`] for the convention of points.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![
+    ///     [89.9, 0.0],
+    ///     [89.9, 180.0],
+    ///     [0.0, 179.9],
+    ///     [0.0, -179.9],
+    /// ]);
+    /// // points across the pole
+    /// assert_eq!(kdtree.within_radius_geo(&[89.9, 90.0], 30_000.0).len(), 2);
+    /// // points across the antimeridian
+    /// assert_eq!(kdtree.within_radius_geo(&[0.0, 180.0], 30_000.0).len(), 2);
+    /// ```
+    pub fn within_radius_geo(
+        &self,
+        query: &impl KdPoint<Scalar = f64, Dim = U2>,
+        radius: f64,
+    ) -> Vec<&T> {
+        let mut results = Vec::new();
+        for bounds in bounding_boxes_of_circle(query, radius) {
+            results.extend(
+                self.within(&bounds)
+                    .into_iter()
+                    .filter(|item| haversine_distance(*item, query) < radius),
+            );
+        }
+        results
+    }
+
+    /// search geographic points within a rectangular region given as `[[lat_min, lon_min], [lat_max, lon_max]]`.
+    /// If `lon_min > lon_max`, the region is regarded as crossing the antimeridian,
+    /// i.e. it covers `[lon_min, 180]` and `[-180, lon_max]`.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![
+    ///     [10.0, 179.0],
+    ///     [10.0, -179.0],
+    ///     [10.0, 0.0],
+    /// ]);
+    /// let found = kdtree.within_geo(&[[0.0, 170.0], [20.0, -170.0]]);
+    /// assert_eq!(found.len(), 2);
+    /// assert!(!found.contains(&&[10.0, 0.0]));
+    /// ```
+    pub fn within_geo(&self, query: &[impl KdPoint<Scalar = f64, Dim = U2>; 2]) -> Vec<&T> {
+        let lat = [query[0].at(0), query[1].at(0)];
+        let (lon_min, lon_max) = (query[0].at(1), query[1].at(1));
+        let lon = if lon_min <= lon_max {
+            [lon_min, lon_max]
+        } else {
+            [lon_min, lon_max + 360.0]
+        };
+        let mut results = Vec::new();
+        for bounds in split_at_antimeridian(lat, lon) {
+            results.extend(self.within(&bounds));
+        }
+        results
+    }
+
+    /// Returns the nearest geographic point from `query` in great-circle distance, which is in meters.
+    /// Returns `None` if `self.is_empty()`.
+    /// See [`crate::geographic`] for the convention of points.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![
+    ///     [0.0, 179.0],
+    ///     [0.0, -170.0],
+    ///     [85.0, 0.0],
+    ///     [85.0, 179.0],
+    /// ]);
+    /// // across the antimeridian
+    /// assert_eq!(kdtree.nearest_geo(&[0.0, -179.5]).unwrap().item, &[0.0, 179.0]);
+    /// // across the pole
+    /// assert_eq!(kdtree.nearest_geo(&[87.0, 175.0]).unwrap().item, &[85.0, 179.0]);
+    /// assert_eq!(kdtree.nearest_geo(&[89.0, 10.0]).unwrap().item, &[85.0, 0.0]);
+    /// ```
+    pub fn nearest_geo(
+        &self,
+        query: &impl KdPoint<Scalar = f64, Dim = U2>,
+    ) -> Option<ItemAndMetricDistance<'_, T, f64>> {
+        kd_nearests_geo(self.items(), &[query.at(0), query.at(1)], 1).pop()
+    }
+
+    /// Returns kNN(k nearest neighbors) from `query` in great-circle distance, which is in meters.
+    pub fn nearests_geo(
+        &self,
+        query: &impl KdPoint<Scalar = f64, Dim = U2>,
+        num: usize,
+    ) -> Vec<ItemAndMetricDistance<'_, T, f64>> {
+        kd_nearests_geo(self.items(), &[query.at(0), query.at(1)], num)
+    }
+
+    /// search geographic points within `distance` meters of great-circle distance from a track,
+    /// which is a polyline of great-circle segments between consecutive points of `track`.
+    ///
+    /// Each segment is covered by circles of radius `1.5 * distance` at intervals of `distance`,
+    /// so the number of sub-queries is proportional to the length of the track divided by `distance`.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![
+    ///     [0.001, 0.5],
+    ///     [0.5, 1.001],
+    ///     [0.5, 0.5],
+    ///     [1.001, 1.001],
+    /// ]);
+    /// let track = [[0.0, 0.0], [0.0, 1.0], [1.0, 1.0]];
+    /// let found = kdtree.within_corridor_geo(&track, 1_000.0);
+    /// assert_eq!(found.len(), 3);
+    /// assert!(!found.contains(&&[0.5, 0.5]));
+    /// ```
+    pub fn within_corridor_geo(
+        &self,
+        track: &[impl KdPoint<Scalar = f64, Dim = U2>],
+        distance: f64,
+    ) -> Vec<&T> {
+        let mut visited = vec![false; self.len()];
+        let mut results = Vec::new();
+        let mut visit = |query: &[f64; 2], accept: &dyn Fn(&T) -> bool| {
+            for item in self.within_radius_geo(query, 1.5 * distance) {
+                let i = self.offset_of(item);
+                if !visited[i] && accept(item) {
+                    visited[i] = true;
+                    results.push(item);
+                }
+            }
+        };
+        if distance <= 0.0 {
+            return results;
+        }
+        if let [p] = track {
+            visit(&[p.at(0), p.at(1)], &|item| {
+                haversine_distance(item, p) < distance
+            });
+        }
+        for segment in track.windows(2) {
+            let (a, b) = (&segment[0], &segment[1]);
+            let accept = |item: &T| haversine_segment_distance(item, a, b) < distance;
+            let (va, vb) = (to_unit_vector(a), to_unit_vector(b));
+            let n = cross(&va, &vb);
+            let angle = dot(&n, &n).sqrt().atan2(dot(&va, &vb));
+            let count = (angle * EARTH_RADIUS / distance).ceil().max(1.0) as usize;
+            for i in 0..=count {
+                // spherical linear interpolation between `a` and `b`
+                let t = i as f64 / count as f64;
+                let (wa, wb) = if angle < 1.0e-12 {
+                    (1.0 - t, t)
+                } else {
+                    (
+                        ((1.0 - t) * angle).sin() / angle.sin(),
+                        (t * angle).sin() / angle.sin(),
+                    )
+                };
+                let v = [0, 1, 2].map(|k| wa * va[k] + wb * vb[k]);
+                visit(&from_unit_vector(&v), &accept);
+            }
+        }
+        results
+    }
+}
@@ -0,0 +1,45 @@
+use crate::ItemAndDistance;
+use num_traits::Float;
+
+impl<T, S: Float> ItemAndDistance<'_, T, S> {
+    /// Returns the distance from the query, i.e. the square root of `squared_distance`.
+    pub fn distance(&self) -> S {
+        self.squared_distance.sqrt()
+    }
+}
+
+/// Helpers for the trees of float arrays, e.g. to steer towards the found point.
+impl<S: Float, const D: usize> ItemAndDistance<'_, [S; D], S> {
+    /// Returns the coordinates of the found point.
+    pub fn point(&self) -> [S; D] {
+        *self.item
+    }
+
+    /// Returns the vector from `query` to the found point.
+    pub fn offset_from(&self, query: &[S; D]) -> [S; D] {
+        std::array::from_fn(|k| self.item[k] - query[k])
+    }
+
+    /// Returns the unit vector from `query` towards the found point, or `None` if they coincide.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![[1.0, 2.0], [3.0, 1.0], [2.0, 3.0]]);
+    /// let query = [3.0, 4.0];
+    /// let found = kdtree.nearest(&query).unwrap();
+    /// assert_eq!(found.point(), [2.0, 3.0]);
+    /// assert_eq!(found.offset_from(&query), [-1.0, -1.0]);
+    /// assert_eq!(found.distance(), 2f64.sqrt());
+    /// let direction = found.direction_from(&query).unwrap();
+    /// assert!((direction[0] + 0.5f64.sqrt()).abs() < 1e-12);
+    /// assert!(found.direction_from(&[2.0, 3.0]).is_none());
+    /// ```
+    pub fn direction_from(&self, query: &[S; D]) -> Option<[S; D]> {
+        let offset = self.offset_from(query);
+        let distance = offset.iter().fold(S::zero(), |sum, &x| sum + x * x).sqrt();
+        if distance > S::zero() {
+            Some(offset.map(|x| x / distance))
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "glam")]
+use super::KdPoint;
+
+macro_rules! impl_kdpoint_for_glam_vector {
+    ($($vector:ident: $scalar:ty, $dim:literal;)*) => {
+        $(
+            paste::paste! {
+                impl KdPoint for glam::$vector {
+                    type Scalar = $scalar;
+                    type Dim = typenum::[<U $dim>];
+                    fn at(&self, k: usize) -> $scalar {
+                        self[k]
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_kdpoint_for_glam_vector! {
+    Vec2: f32, 2;
+    Vec3: f32, 3;
+    Vec3A: f32, 3;
+    Vec4: f32, 4;
+    DVec2: f64, 2;
+    DVec3: f64, 3;
+    DVec4: f64, 4;
+    IVec2: i32, 2;
+    IVec3: i32, 3;
+    IVec4: i32, 4;
+    UVec2: u32, 2;
+    UVec3: u32, 3;
+    UVec4: u32, 4;
+    I64Vec2: i64, 2;
+    I64Vec3: i64, 3;
+    I64Vec4: i64, 4;
+    U64Vec2: u64, 2;
+    U64Vec3: u64, 3;
+    U64Vec4: u64, 4;
+}
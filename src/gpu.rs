@@ -0,0 +1,131 @@
+use crate::{KdOrderError, KdPoint, KdSliceN, KdTreeN};
+use typenum::Unsigned;
+
+/// A node of a [`GpuLayout`], in a `#[repr(C)]` layout of 4-byte fields for `f32`, `i32` and `u32` scalars.
+///
+/// An inner node splits the items `item_begin..item_end` of its subtree at the item `item_begin + (item_end - item_begin) / 2`,
+/// whose coordinate on `axis` is `split_value`; the items before and after it are in the subtrees `left` and `right`.
+/// A leaf, whose `axis` is [`GpuNode::LEAF`], has no children and its items are scanned linearly.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GpuNode<S> {
+    /// The coordinate of the split item on `axis`, or zero for a leaf.
+    pub split_value: S,
+    /// The split axis, or [`GpuNode::LEAF`].
+    pub axis: u32,
+    /// The index of the lower child node, or [`GpuNode::NONE`] if the subtree is empty.
+    pub left: u32,
+    /// The index of the upper child node, or [`GpuNode::NONE`] if the subtree is empty.
+    pub right: u32,
+    /// The first item of the subtree.
+    pub item_begin: u32,
+    /// The end of the items of the subtree.
+    pub item_end: u32,
+}
+
+impl<S> GpuNode<S> {
+    /// The `axis` of a leaf.
+    pub const LEAF: u32 = u32::MAX;
+    /// The `left` or `right` of a node without the child.
+    pub const NONE: u32 = u32::MAX;
+
+    /// Returns `true` if the node is a leaf.
+    pub fn is_leaf(&self) -> bool {
+        self.axis == Self::LEAF
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+mod impl_bytemuck {
+    use super::GpuNode;
+    unsafe impl bytemuck::Zeroable for GpuNode<f32> {}
+    unsafe impl bytemuck::Pod for GpuNode<f32> {}
+    unsafe impl bytemuck::Zeroable for GpuNode<i32> {}
+    unsafe impl bytemuck::Pod for GpuNode<i32> {}
+    unsafe impl bytemuck::Zeroable for GpuNode<u32> {}
+    unsafe impl bytemuck::Pod for GpuNode<u32> {}
+}
+
+/// A flat, index-based representation of a kd-tree made by [`KdSliceN::to_gpu_layout`],
+/// e.g. to upload it to a GPU or to share it with query kernels not written in Rust.
+/// The root is `nodes[0]` unless the tree is empty, and the nodes are in the depth-first pre-order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuLayout<T: KdPoint> {
+    /// The nodes, whose item ranges refer to `items`.
+    pub nodes: Vec<GpuNode<T::Scalar>>,
+    /// The items in the order of the tree, i.e. [`KdSliceN::items`].
+    pub items: Vec<T>,
+}
+
+fn push_nodes<T: KdPoint>(
+    nodes: &mut Vec<GpuNode<T::Scalar>>,
+    items: &[T],
+    begin: usize,
+    end: usize,
+    axis: usize,
+    leaf_size: usize,
+) -> u32 {
+    if begin == end {
+        return GpuNode::<T::Scalar>::NONE;
+    }
+    let index = nodes.len();
+    nodes.push(GpuNode {
+        split_value: <T::Scalar as crate::KdScalar>::zero(),
+        axis: GpuNode::<T::Scalar>::LEAF,
+        left: GpuNode::<T::Scalar>::NONE,
+        right: GpuNode::<T::Scalar>::NONE,
+        item_begin: begin as u32,
+        item_end: end as u32,
+    });
+    if end - begin > leaf_size {
+        let mid = begin + (end - begin) / 2;
+        let next = (axis + 1) % T::dim();
+        let left = push_nodes(nodes, items, begin, mid, next, leaf_size);
+        let right = push_nodes(nodes, items, mid + 1, end, next, leaf_size);
+        let node = &mut nodes[index];
+        node.split_value = items[mid].at(axis);
+        node.axis = axis as u32;
+        node.left = left;
+        node.right = right;
+    }
+    index as u32
+}
+
+impl<T: KdPoint<Dim = N> + Clone, N: Unsigned> KdSliceN<T, N> {
+    /// Exports the tree as a flat array of nodes and the items, where the subtrees with at most `leaf_size` items are leaves.
+    /// The tree is reconstructed from it by [`KdTreeN::from_gpu_layout`].
+    ///
+    /// # Panics
+    /// Panics if `leaf_size` is zero or the tree has `u32::MAX` or more items.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![[1.0f32, 2.0], [3.0, 1.0], [2.0, 3.0]]);
+    /// let layout = kdtree.to_gpu_layout(1);
+    /// let root = &layout.nodes[0];
+    /// assert_eq!((root.axis, root.split_value), (0, 2.0));
+    /// assert!(layout.nodes[root.left as usize].is_leaf());
+    /// let restored = kd_tree::KdTree::from_gpu_layout(layout).unwrap();
+    /// assert_eq!(restored, kdtree);
+    /// ```
+    pub fn to_gpu_layout(&self, leaf_size: usize) -> GpuLayout<T> {
+        assert!(leaf_size > 0, "leaf_size must be positive");
+        assert!(
+            self.len() < u32::MAX as usize,
+            "too many items for the GPU layout"
+        );
+        let mut nodes = Vec::new();
+        push_nodes(&mut nodes, self.items(), 0, self.len(), 0, leaf_size);
+        GpuLayout {
+            nodes,
+            items: self.items().to_vec(),
+        }
+    }
+}
+
+impl<T: KdPoint<Dim = N>, N: Unsigned> KdTreeN<T, N> {
+    /// Reconstructs a tree from the items of a [`GpuLayout`], returning an error if they are out of the kd-tree order.
+    /// The nodes are not read, since they are derived from the items. See [`Self::try_from_sorted`].
+    pub fn from_gpu_layout(layout: GpuLayout<T>) -> Result<Self, KdOrderError> {
+        Self::try_from_sorted(layout.items)
+    }
+}
@@ -0,0 +1,323 @@
+use crate::within::kd_for_each_within_by_predicates;
+use crate::{ItemAndDistance, KdPoint, KdScalar, KdSliceN};
+use num_traits::Float;
+use std::cmp::Ordering;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
+use typenum::Unsigned;
+
+/// A closed interval `[lo, hi]` of floats, which contains the exact value of a computation.
+///
+/// The arithmetic operators round the bounds outwards by about one ulp,
+/// so the result contains the exact result of the operation on any values in the operands.
+/// A lower bound overflowing to infinity is rounded down to the largest finite value, and vice versa.
+/// The comparison is the certain one: `a < b` is `true` only if every value in `a` is less than every value in `b`,
+/// and two intervals are incomparable if they overlap, unless both of them are the same single value.
+/// # Example
+/// ```
+/// use kd_tree::Interval;
+/// let tenth = Interval::point(0.1) + Interval::point(0.2);
+/// assert!(tenth.contains(0.3));
+/// assert!(tenth.partial_cmp(&Interval::point(0.3)).is_none());
+/// assert!(tenth < Interval::point(0.31));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Interval<S> {
+    pub lo: S,
+    pub hi: S,
+}
+
+impl<S: Float> Interval<S> {
+    pub fn new(lo: S, hi: S) -> Self {
+        debug_assert!(lo <= hi);
+        Self { lo, hi }
+    }
+
+    /// Returns the interval which contains the single value `x`.
+    pub fn point(x: S) -> Self {
+        Self { lo: x, hi: x }
+    }
+
+    pub fn contains(&self, x: S) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    pub fn width(&self) -> S {
+        self.hi - self.lo
+    }
+
+    /// Returns the square, which is tighter than `self * self` if `self` contains zero.
+    pub fn square(self) -> Self {
+        let (lo, hi) = (self.lo.abs(), self.hi.abs());
+        let (min, max) = if lo < hi { (lo, hi) } else { (hi, lo) };
+        if self.contains(S::zero()) {
+            Self::new(S::zero(), round_up(max * max))
+        } else {
+            Self::new(round_down(min * min), round_up(max * max))
+        }
+    }
+}
+
+/// Returns a value not greater than the exact result rounded to `x`.
+fn round_down<S: Float>(x: S) -> S {
+    if x == S::infinity() {
+        // the exact result overflowed, so it is greater than the largest finite value.
+        S::max_value()
+    } else if x == S::neg_infinity() {
+        x
+    } else if x == S::zero() {
+        x - S::min_positive_value()
+    } else {
+        x - (x.abs() * S::epsilon() + S::min_positive_value())
+    }
+}
+
+/// Returns a value not less than the exact result rounded to `x`.
+fn round_up<S: Float>(x: S) -> S {
+    -round_down(-x)
+}
+
+/// Compares floats in a total order, where NaN is greater than any other value.
+fn total_cmp<S: Float>(a: S, b: S) -> Ordering {
+    a.partial_cmp(&b)
+        .unwrap_or_else(|| a.is_nan().cmp(&b.is_nan()))
+}
+
+impl<S: Float> PartialEq for Interval<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl<S: Float> PartialOrd for Interval<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.hi < other.lo {
+            Some(Ordering::Less)
+        } else if self.lo > other.hi {
+            Some(Ordering::Greater)
+        } else if self.lo == self.hi && other.lo == other.hi && self.lo == other.lo {
+            Some(Ordering::Equal)
+        } else {
+            None
+        }
+    }
+}
+
+impl<S: Float> Add for Interval<S> {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self::new(round_down(self.lo + other.lo), round_up(self.hi + other.hi))
+    }
+}
+
+impl<S: Float> Sub for Interval<S> {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Self::new(round_down(self.lo - other.hi), round_up(self.hi - other.lo))
+    }
+}
+
+impl<S: Float> Mul for Interval<S> {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        let products = [
+            self.lo * other.lo,
+            self.lo * other.hi,
+            self.hi * other.lo,
+            self.hi * other.hi,
+        ];
+        let min = products.iter().fold(S::infinity(), |a, &b| a.min(b));
+        let max = products.iter().fold(S::neg_infinity(), |a, &b| a.max(b));
+        Self::new(round_down(min), round_up(max))
+    }
+}
+
+impl<S: Float> AddAssign for Interval<S> {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl<S: Float> SubAssign for Interval<S> {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+impl<S: Float> MulAssign for Interval<S> {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+impl<S: Float> KdScalar for Interval<S> {
+    fn zero() -> Self {
+        Self::point(S::zero())
+    }
+    fn one() -> Self {
+        Self::point(S::one())
+    }
+}
+
+/// The result of a verified query, which brackets the exact result under floating point errors:
+/// `certain` is a subset of the exact result, and `possible` is a superset of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verified<R> {
+    pub certain: R,
+    pub possible: R,
+}
+
+fn interval_squared_distance<T: KdPoint>(
+    item: &T,
+    query: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+) -> Interval<T::Scalar>
+where
+    T::Scalar: Float,
+{
+    let mut squared_distance = Interval::zero();
+    for k in 0..T::dim() {
+        squared_distance += (Interval::point(item.at(k)) - Interval::point(query.at(k))).square();
+    }
+    squared_distance
+}
+
+/// Returns `Ordering::Less` if the items not greater than `item` along `axis` are certainly farther than `bound` from `query`,
+/// `Ordering::Greater` if so are the items not less than `item`, and `Ordering::Equal` otherwise.
+fn prune_beyond<T: KdPoint>(
+    item: &T,
+    axis: usize,
+    query: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+    squared_bound: T::Scalar,
+) -> Ordering
+where
+    T::Scalar: Float,
+{
+    let diff = Interval::point(item.at(axis)) - Interval::point(query.at(axis));
+    let zero = <T::Scalar as num_traits::Zero>::zero();
+    if diff.hi < zero && Interval::point(diff.hi).square().lo > squared_bound {
+        Ordering::Less
+    } else if diff.lo > zero && Interval::point(diff.lo).square().lo > squared_bound {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    }
+}
+
+impl<T, N: Unsigned> KdSliceN<T, N>
+where
+    T: KdPoint<Dim = N>,
+    T::Scalar: Float,
+{
+    /// Same as [`Self::within_radius`], but robust against floating point errors:
+    /// `certain` are the items whose exact distance is certainly less than `radius`,
+    /// and `possible` are the items whose exact distance may be less than `radius`.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![[0.1, 0.2], [0.3, 0.0], [2.0, 2.0]]);
+    /// let found = kdtree.within_radius_verified(&[0.0, 0.0], 0.3);
+    /// assert_eq!(found.certain, vec![&[0.1, 0.2]]);
+    /// // the exact distance of [0.3, 0.0] is 0.3 in `f64`, which is not certainly less than 0.3.
+    /// assert_eq!(found.possible.len(), 2);
+    /// ```
+    pub fn within_radius_verified(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Verified<Vec<&T>> {
+        let squared_radius = Interval::point(radius).square();
+        let mut certain = Vec::new();
+        let mut possible = Vec::new();
+        kd_for_each_within_by_predicates(
+            self.items(),
+            N::to_usize(),
+            |item, k| prune_beyond(item, k, query, squared_radius.hi),
+            |_| true,
+            |item| {
+                let squared_distance = interval_squared_distance(item, query);
+                if squared_distance.lo < squared_radius.hi {
+                    possible.push(item);
+                    if squared_distance.hi < squared_radius.lo {
+                        certain.push(item);
+                    }
+                }
+            },
+        );
+        Verified { certain, possible }
+    }
+
+    /// Same as [`Self::nearests`], but robust against floating point errors, with the squared distances as intervals:
+    /// `certain` are the items which are certainly in the exact `num` nearest neighbors,
+    /// and `possible` are the items which may be in them, both in ascending order of the lower bound of the distance.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![[0.1, 0.2], [0.2, 0.1], [1.0, 0.0], [3.0, 3.0]]);
+    /// let found = kdtree.nearests_verified(&[0.0, 0.0], 2);
+    /// // the tie is exact in real numbers, and both of them are certainly in the 2 nearest neighbors.
+    /// assert_eq!(found.certain.len(), 2);
+    /// assert_eq!(found.possible.len(), 2);
+    /// let found = kdtree.nearests_verified(&[0.0, 0.0], 1);
+    /// assert!(found.certain.is_empty());
+    /// assert_eq!(found.possible.len(), 2);
+    /// ```
+    pub fn nearests_verified(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+    ) -> Verified<Vec<ItemAndDistance<'_, T, Interval<T::Scalar>>>> {
+        // `num` items are certainly within `bound`, so the items farther than it cannot be in the nearest neighbors.
+        let bound = self
+            .nearests(query, num)
+            .iter()
+            .map(|found| interval_squared_distance(found.item, query).hi)
+            .fold(None, |max: Option<T::Scalar>, hi| {
+                Some(max.map_or(hi, |max| max.max(hi)))
+            });
+        let bound = match bound {
+            Some(bound) => bound,
+            None => {
+                return Verified {
+                    certain: Vec::new(),
+                    possible: Vec::new(),
+                }
+            }
+        };
+        let mut candidates = Vec::new();
+        kd_for_each_within_by_predicates(
+            self.items(),
+            N::to_usize(),
+            |item, k| prune_beyond(item, k, query, bound),
+            |_| true,
+            |item| {
+                let squared_distance = interval_squared_distance(item, query);
+                if squared_distance.lo <= bound {
+                    candidates.push(ItemAndDistance {
+                        item,
+                        squared_distance,
+                    });
+                }
+            },
+        );
+        candidates.sort_by(|a, b| total_cmp(a.squared_distance.lo, b.squared_distance.lo));
+        let mut his: Vec<_> = candidates.iter().map(|c| c.squared_distance.hi).collect();
+        his.sort_by(|&a, &b| total_cmp(a, b));
+        let mut certain = Vec::new();
+        let mut possible = Vec::new();
+        for candidate in &candidates {
+            let Interval { lo, hi } = candidate.squared_distance;
+            // the number of the items certainly nearer than the candidate.
+            let nearer = his.partition_point(|&other| other < lo);
+            // the number of the other items which may be as near as the candidate.
+            let rivals = candidates.partition_point(|other| other.squared_distance.lo <= hi) - 1;
+            let copy = || ItemAndDistance {
+                item: candidate.item,
+                squared_distance: candidate.squared_distance,
+            };
+            if nearer < num {
+                possible.push(copy());
+                if rivals < num {
+                    certain.push(copy());
+                }
+            }
+        }
+        Verified { certain, possible }
+    }
+}
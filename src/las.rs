@@ -0,0 +1,149 @@
+#![cfg(feature = "las")]
+use crate::binary::invalid_data;
+use crate::{KdMap, KdTree};
+use std::io::{Read, Result};
+
+/// A point record of a LAS file, given to the `attributes` function of [`KdMap::from_las_reader_with`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LasPoint {
+    /// The coordinates, with the scale factors and the offsets of the header applied.
+    pub position: [f64; 3],
+    /// The pulse return magnitude.
+    pub intensity: u16,
+    /// The return number, starting from 1.
+    pub return_number: u8,
+    /// The ASPRS classification, e.g. 2 for ground.
+    pub classification: u8,
+    /// The red, green and blue channels, if the point data record format has them.
+    pub color: Option<[u16; 3]>,
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    let mut b = [0; 4];
+    b.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(b)
+}
+
+fn f64_at(bytes: &[u8], offset: usize) -> f64 {
+    let mut b = [0; 8];
+    b.copy_from_slice(&bytes[offset..offset + 8]);
+    f64::from_le_bytes(b)
+}
+
+/// Reads the point records of a LAS file, calling `f` with each of them.
+fn read_points(mut reader: impl Read, mut f: impl FnMut(&LasPoint)) -> Result<()> {
+    // the public header block of LAS 1.0 to 1.3; LAS 1.4 appends the 64-bit point count and more.
+    let mut header = vec![0; 227];
+    reader.read_exact(&mut header)?;
+    if &header[0..4] != b"LASF" {
+        return Err(invalid_data("not a LAS file".to_string()));
+    }
+    let (major, minor) = (header[24], header[25]);
+    let header_size = u16_at(&header, 94) as usize;
+    let point_offset = u32_at(&header, 96) as usize;
+    if header_size < header.len() || point_offset < header_size {
+        return Err(invalid_data(format!(
+            "invalid LAS header size {}",
+            header_size
+        )));
+    }
+    header.resize(header_size, 0);
+    reader.read_exact(&mut header[227..])?;
+    let format = header[104];
+    if format & 0xc0 != 0 {
+        return Err(invalid_data(
+            "compressed LAS (LAZ) is not supported".to_string(),
+        ));
+    }
+    if format > 10 {
+        return Err(invalid_data(format!(
+            "unknown LAS point data record format {}",
+            format
+        )));
+    }
+    let record_length = u16_at(&header, 105) as usize;
+    let mut count = u32_at(&header, 107) as u64;
+    if count == 0 && (major, minor) >= (1, 4) && header_size >= 255 {
+        let mut b = [0; 8];
+        b.copy_from_slice(&header[247..255]);
+        count = u64::from_le_bytes(b);
+    }
+    let scale = [
+        f64_at(&header, 131),
+        f64_at(&header, 139),
+        f64_at(&header, 147),
+    ];
+    let offset = [
+        f64_at(&header, 155),
+        f64_at(&header, 163),
+        f64_at(&header, 171),
+    ];
+    // the offset of the color in the record of each format.
+    let color_offset = match format {
+        2 => Some(20),
+        3 | 5 => Some(28),
+        7 | 8 | 10 => Some(30),
+        _ => None,
+    };
+    let minimum_length = color_offset.map_or(if format < 6 { 20 } else { 30 }, |c| c + 6);
+    if record_length < minimum_length {
+        return Err(invalid_data(format!(
+            "too short LAS point data record length {}",
+            record_length
+        )));
+    }
+    // skips the variable length records.
+    std::io::copy(
+        &mut (&mut reader).take((point_offset - header_size) as u64),
+        &mut std::io::sink(),
+    )?;
+    let mut record = vec![0; record_length];
+    for _ in 0..count {
+        reader.read_exact(&mut record)?;
+        let position =
+            [0, 1, 2].map(|k| u32_at(&record, 4 * k) as i32 as f64 * scale[k] + offset[k]);
+        let (return_number, classification) = if format < 6 {
+            (record[14] & 0x07, record[15] & 0x1f)
+        } else {
+            (record[14] & 0x0f, record[16])
+        };
+        f(&LasPoint {
+            position,
+            intensity: u16_at(&record, 12),
+            return_number,
+            classification,
+            color: color_offset.map(|c| [0, 1, 2].map(|k| u16_at(&record, c + 2 * k))),
+        });
+    }
+    Ok(())
+}
+
+impl KdTree<[f64; 3]> {
+    /// Builds a tree of the points of a LAS file, of the version 1.0 to 1.4 and the point data record format 0 to 10.
+    ///
+    /// The coordinates are scaled by the factors and the offsets of the header. Compressed LAZ files are not supported.
+    pub fn from_las_reader(reader: impl Read) -> Result<Self> {
+        let mut points = Vec::new();
+        read_points(reader, |point| points.push(point.position))?;
+        Ok(Self::build_by_ordered_float(points))
+    }
+}
+
+impl<V> KdMap<[f64; 3], V> {
+    /// Same as [`KdTree::from_las_reader`], but each point is associated with the value returned by `attributes`,
+    /// e.g. [`LasPoint::classification`].
+    pub fn from_las_reader_with(
+        reader: impl Read,
+        mut attributes: impl FnMut(&LasPoint) -> V,
+    ) -> Result<Self> {
+        let mut entries = Vec::new();
+        read_points(reader, |point| {
+            entries.push((point.position, attributes(point)))
+        })?;
+        Ok(Self::build_by_ordered_float(entries))
+    }
+}
@@ -24,20 +24,132 @@
 //! assert!(found.iter().any(|&&p| p == [1.0, 2.0, 3.0]));
 //! assert!(found.iter().any(|&&p| p == [3.0, 1.0, 2.0]));
 //! ```
+//!
+//! # Determinism
+//! No part of this crate uses randomness: building a tree and every query are deterministic functions of their inputs,
+//! and the parallel builders with the `rayon` feature produce the same layout as the sequential ones.
+//! So a query result can be reproduced exactly from the same items in the same order, without any seed.
+//! ```
+//! let points = vec![[3, 1], [1, 2], [2, 3], [1, 2], [5, 0]];
+//! let kdtree1 = kd_tree::KdTree::build(points.clone());
+//! let kdtree2 = kd_tree::KdTree::build(points);
+//! assert_eq!(kdtree1, kdtree2);
+//! assert_eq!(kdtree1.nearests(&[1, 2], 3), kdtree2.nearests(&[1, 2], 3));
+//! ```
+mod aggregate;
+mod arrow;
+mod augment;
+mod autotune;
+mod axis_order;
+mod batch;
+mod binary;
+pub mod brute_force;
+mod builder;
+mod cache;
+mod closest_pair;
+mod config;
+mod csr;
+mod dedup;
+mod delta;
+mod dim;
+mod dynamic;
+mod exact;
+mod filtered;
+mod geo;
+pub mod geographic;
+mod geometry;
+mod glam;
+mod gpu;
+mod interval;
+mod las;
+pub mod low_level;
+mod metadata;
+mod metric;
+mod moving;
+mod multimap;
 mod nalgebra;
+mod ndarray;
 mod nearest;
+mod nearest_iter;
 mod nearests;
+mod par_iter;
+mod partition;
+mod ply;
+mod pod;
+mod prefetch;
+mod project;
+mod quantize;
+mod ranges;
+mod replay;
+mod reverse;
+mod scalar;
+mod self_join;
+pub mod simple;
+mod skyline;
 mod sort;
+mod stable;
+mod subset;
 mod tests;
+mod tile;
+mod tombstone;
+mod tracker;
+mod transform;
 mod within;
+use closest_pair::kd_closest_pair;
 use nearest::*;
 use nearests::*;
+use self_join::kd_neighbor_pairs;
 use sort::*;
 use std::cmp::Ordering;
 use std::marker::PhantomData;
 use typenum::Unsigned;
 use within::*;
 
+pub use aggregate::{Count, KdTreeAgg, KdTreeAggN, Monoid, WeightSum};
+pub use augment::{Aabb, AugmentedKdTree, AugmentedKdTreeN, BoundingSphere, SubtreeBound};
+pub use autotune::{BuildConfig, SplitRule};
+pub use axis_order::{PermutedKdTree, PermutedKdTreeN};
+pub use batch::QueryOrder;
+pub use binary::{BinaryPoint, BinaryScalar};
+pub use builder::KdTreeBuilder;
+pub use cache::{CachedKdTree, CachedKdTreeN};
+pub use config::{Accumulation, DistanceTieBreak, QueryConfig, QueryStats};
+pub use csr::CsrMatrix;
+pub use dedup::TieBreak;
+pub use delta::IndicesDelta;
+pub use dim::{AsDim, DimMismatch};
+pub use dynamic::{DynamicKdTree, DynamicKdTreeN};
+pub use exact::Exact;
+pub use filtered::FilteredKdSlice;
+pub use gpu::{GpuLayout, GpuNode};
+#[cfg(feature = "rkyv")]
+pub use impl_rkyv::{ArchivedKdPoint, ArchivedKdTree};
+#[cfg(feature = "serde")]
+pub use impl_serde::{KeysOnly, ValuesOnly};
+pub use interval::{Interval, Verified};
+#[cfg(feature = "las")]
+pub use las::LasPoint;
+pub use metadata::{KdTreeMeta, KdTreeMetaN};
+pub use metric::{Chebyshev, ItemAndMetricDistance, Manhattan, Metric, SquaredEuclidean};
+pub use moving::MovingNearest;
+pub use multimap::Colocated;
+pub use nearest_iter::NearestIter;
+#[cfg(feature = "ply")]
+pub use ply::PlyVertex;
+#[cfg(feature = "mmap")]
+pub use pod::KdTreeFile;
+pub use project::{Axes, Axes1, Axes2, Axes3, Axes4, Project};
+pub use quantize::QuantizedKdTree;
+pub use ranges::AxisRanges;
+pub use replay::{QueryLog, QueryRecorder, RecordedQuery, ReplayReport};
+pub use reverse::ReverseNearest;
+pub use scalar::KdScalar;
+pub use stable::{StableKdTree, StableKdTreeN};
+pub use tile::{TiledKdTree, TiledKdTreeN};
+pub use tombstone::{TombstoneKdTree, TombstoneKdTreeN};
+pub use tracker::NearestTracker;
+pub use transform::{TransformedKdTree, TransformedKdTreeN};
+
 /// A trait to represent k-dimensional point.
 ///
 /// # Example
@@ -59,12 +171,52 @@ use within::*;
 /// assert_eq!(kdtree.nearest(&[3.1, 0.1, 2.2]).unwrap().item.id, 222);
 /// ```
 pub trait KdPoint {
-    type Scalar: num_traits::NumAssign + Copy + PartialOrd;
+    type Scalar: KdScalar;
     type Dim: Unsigned;
     fn dim() -> usize {
         <Self::Dim as Unsigned>::to_usize()
     }
     fn at(&self, i: usize) -> Self::Scalar;
+
+    /// Returns an adapter of this point whose `Dim` is `N`, which must have the same number of axes.
+    /// The dimensions are checked at compile time, when the function is instantiated. See [`AsDim`].
+    fn as_dim<N: Unsigned>(&self) -> AsDim<&Self, N> {
+        dim::as_dim(self)
+    }
+
+    /// Same as [`Self::as_dim`], but the dimensions are checked at run time.
+    /// # Example
+    /// ```
+    /// use kd_tree::KdPoint;
+    /// assert!([1.0, 2.0, 3.0].try_as_dim::<typenum::U3>().is_ok());
+    /// assert_eq!([1.0, 2.0].try_as_dim::<typenum::U3>().unwrap_err().actual, 2);
+    /// ```
+    fn try_as_dim<N: Unsigned>(&self) -> Result<AsDim<&Self, N>, DimMismatch> {
+        dim::try_as_dim(self)
+    }
+}
+
+/// A trait to represent k-dimensional point with a weight, such as mass or intensity.
+///
+/// This trait is implemented for `(P, P::Scalar)`,
+/// so a [`KdMap`] whose values are the weights can be used as a weighted point set.
+/// # Example
+/// ```
+/// let kdmap: kd_tree::KdMap<[f64; 2], f64> = kd_tree::KdMap::build_by_ordered_float(vec![
+///     ([1.0, 1.0], 0.5),
+///     ([2.0, 2.0], 1.5),
+///     ([9.0, 9.0], 4.0),
+/// ]);
+/// assert_eq!(kdmap.weight_within_radius(&[1.5, 1.5], 1.0), 2.0);
+/// ```
+pub trait WeightedKdPoint: KdPoint {
+    fn weight(&self) -> Self::Scalar;
+}
+
+impl<P: KdPoint> WeightedKdPoint for (P, P::Scalar) {
+    fn weight(&self) -> P::Scalar {
+        self.1
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -73,11 +225,128 @@ pub struct ItemAndDistance<'a, T, Scalar> {
     pub squared_distance: Scalar,
 }
 
+impl<T: Clone, Scalar: Copy> ItemAndDistance<'_, T, Scalar> {
+    /// Returns the result with a clone of the item, which does not borrow the tree.
+    pub fn cloned(&self) -> OwnedItemAndDistance<T, Scalar> {
+        OwnedItemAndDistance {
+            item: self.item.clone(),
+            squared_distance: self.squared_distance,
+        }
+    }
+}
+
+/// Same as [`ItemAndDistance`], but owns the item, e.g. to send the result to another thread.
+/// Returned by the `*_cloned` queries such as [`KdSliceN::nearest_cloned`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OwnedItemAndDistance<T, Scalar> {
+    pub item: T,
+    pub squared_distance: Scalar,
+}
+
+/// The result of a query whose traversal may have been cut off before completion.
+/// `is_exact` is `false` if some subtrees which might contain better results were left unvisited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialResult<R> {
+    pub result: R,
+    pub is_exact: bool,
+    /// The work done by the query, which is `Some` only if [`QueryConfig::instrument`] is enabled.
+    pub stats: Option<QueryStats>,
+}
+
+impl<R> PartialResult<Option<R>> {
+    /// Converts a `PartialResult<Option<R>>` into an `Option<PartialResult<R>>`.
+    pub fn transpose(self) -> Option<PartialResult<R>> {
+        let (is_exact, stats) = (self.is_exact, self.stats);
+        self.result.map(|result| PartialResult {
+            result,
+            is_exact,
+            stats,
+        })
+    }
+}
+
+/// The error returned when items are not in the order of a kd-tree,
+/// e.g. by [`KdTreeN::try_from_sorted`] for corrupted data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdOrderError {
+    /// The offset of an item out of order.
+    pub offset: usize,
+}
+
+impl std::fmt::Display for KdOrderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the item at offset {} is out of the kd-tree order",
+            self.offset
+        )
+    }
+}
+
+impl std::error::Error for KdOrderError {}
+
+/// The error returned by a fallible build, such as [`KdTreeN::try_build_by_float`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BuildError {
+    /// The coordinate on `axis` of the item at `index` in the input is NaN or infinite.
+    NonFinite { index: usize, axis: usize },
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::NonFinite { index, axis } => write!(
+                f,
+                "the coordinate on axis {} of the item at index {} is not finite",
+                axis, index
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// How a float build such as [`KdTreeN::build_by_float_with_policy`] handles points with NaN or infinite coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum NanPolicy {
+    /// Returns [`BuildError::NonFinite`] for the first such point.
+    #[default]
+    Reject,
+    /// Drops such points from the tree.
+    FilterOut,
+    /// Panics on such points in debug builds, and skips the check in release builds for the best performance.
+    PanicDebugOnly,
+}
+
+/// The result of an approximate count, such as [`KdSliceN::estimate_within_radius_count`].
+/// The exact count is guaranteed to be in `lower..=upper`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountEstimate {
+    pub lower: usize,
+    pub upper: usize,
+}
+
+impl CountEstimate {
+    /// Returns the midpoint of `lower` and `upper`, whose error is at most a half of `upper - lower`, rounded up.
+    pub fn estimate(&self) -> usize {
+        self.lower + (self.upper - self.lower) / 2
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.lower == self.upper
+    }
+}
+
 /// A slice of kd-tree.
 /// This type implements [`std::ops::Deref`] to `[T]`.
 /// This is an unsized type, meaning that it must always be used as a reference.
 /// For an owned version of this type, see [`KdTree`].
+///
+/// The methods locating the found items by their offsets in the slice, e.g. [`Self::nearest_indexed`],
+/// panic for zero-sized items, which share the same address.
 #[derive(Debug, PartialEq, Eq)]
+#[repr(transparent)]
 pub struct KdSliceN<T, N: Unsigned>(PhantomData<N>, [T]);
 pub type KdSlice<T> = KdSliceN<T, <T as KdPoint>::Dim>;
 impl<T, N: Unsigned> std::ops::Deref for KdSliceN<T, N> {
@@ -97,10 +366,25 @@ impl<T, N: Unsigned> KdSliceN<T, N> {
         &self.1
     }
 
+    /// # Safety
+    /// `items` should be sorted as a kd-tree, e.g. by [`kd_sort_by`], or the queries return wrong results.
     unsafe fn new_unchecked(items: &[T]) -> &Self {
+        // SAFETY: `Self` is `repr(transparent)` over `[T]`, so the pointer cast keeps the layout and the length.
         &*(items as *const _ as *const Self)
     }
 
+    /// Returns the offset of `item`, which must be in this slice.
+    /// # Panics
+    /// Panics if `T` is zero-sized, since all the items have the same address.
+    fn offset_of(&self, item: &T) -> usize {
+        let size = std::mem::size_of::<T>();
+        assert_ne!(
+            size, 0,
+            "the offsets of zero-sized items are indistinguishable"
+        );
+        (item as *const T as usize - self.1.as_ptr() as usize) / size
+    }
+
     /// # Example
     /// ```
     /// struct Item {
@@ -120,6 +404,7 @@ impl<T, N: Unsigned> KdSliceN<T, N> {
         F: Fn(&T, &T, usize) -> Ordering + Copy,
     {
         kd_sort_by(items, N::to_usize(), compare);
+        // SAFETY: `items` has just been sorted as a kd-tree.
         unsafe { Self::new_unchecked(items) }
     }
 
@@ -177,6 +462,43 @@ impl<T, N: Unsigned> KdSliceN<T, N> {
         Self::sort_by_key(points, |item, k| item.at(k))
     }
 
+    /// Verifies that the items are in the order made by [`Self::sort_by`] with `compare`,
+    /// e.g. for a tree loaded from untrusted data. This takes `O(n log n)` time.
+    pub fn check_order_by<F>(&self, compare: F) -> Result<(), KdOrderError>
+    where
+        F: Fn(&T, &T, usize) -> Ordering + Copy,
+    {
+        match kd_find_unsorted_by(self.items(), N::to_usize(), compare) {
+            Some(offset) => Err(KdOrderError { offset }),
+            None => Ok(()),
+        }
+    }
+
+    /// Verifies that the items are in the order of a kd-tree by their coordinates, e.g. for a tree loaded from untrusted data.
+    /// NaN is regarded as greater than any other value, as in [`KdTreeN::build_by_ordered_float`].
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[1, 2], [3, 1], [2, 3], [5, 5]]);
+    /// assert!(kdtree.check_order().is_ok());
+    /// let edited = vec![[1, 2], [2, 3], [3, 1], [0, 5]];
+    /// assert_eq!(kd_tree::KdTree::try_from_sorted(edited).unwrap_err().offset, 3);
+    /// ```
+    pub fn check_order(&self) -> Result<(), KdOrderError>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.check_order_by(|item1, item2, k| {
+            let (a, b) = (item1.at(k), item2.at(k));
+            #[allow(clippy::eq_op)]
+            match (a == a, b == b) {
+                (true, true) => a.partial_cmp(&b).unwrap(),
+                (false, false) => Ordering::Equal,
+                (false, true) => Ordering::Greater,
+                (true, false) => Ordering::Less,
+            }
+        })
+    }
+
     /// Returns the nearest item from the input point. Returns `None` if `self.is_empty()`.
     /// # Example
     /// ```
@@ -197,12 +519,8 @@ impl<T, N: Unsigned> KdSliceN<T, N> {
         &self,
         query: &Q,
         coord: impl Fn(&T, usize) -> Q::Scalar + Copy,
-    ) -> Option<ItemAndDistance<T, Q::Scalar>> {
-        if self.is_empty() {
-            None
-        } else {
-            Some(kd_nearest_by(self.items(), query, coord))
-        }
+    ) -> Option<ItemAndDistance<'_, T, Q::Scalar>> {
+        kd_nearest_by(self.items(), query, coord)
     }
 
     /// Returns the nearest item from the input point. Returns `None` if `self.is_empty()`.
@@ -215,34 +533,162 @@ impl<T, N: Unsigned> KdSliceN<T, N> {
     pub fn nearest(
         &self,
         query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
-    ) -> Option<ItemAndDistance<T, T::Scalar>>
+    ) -> Option<ItemAndDistance<'_, T, T::Scalar>>
     where
         T: KdPoint<Dim = N>,
     {
-        if self.is_empty() {
-            None
-        } else {
-            Some(kd_nearest(self.items(), query))
-        }
+        kd_nearest(self.items(), query)
+    }
+
+    /// Same as [`Self::nearest`], but returns a clone of the item, which does not borrow the tree.
+    /// # Example
+    /// ```
+    /// let found = {
+    ///     let kdtree = kd_tree::KdTree::build(vec![[1, 2, 3], [3, 1, 2], [2, 3, 1]]);
+    ///     kdtree.nearest_cloned(&[3, 1, 1])
+    /// };
+    /// let handle = std::thread::spawn(move || found.unwrap().item);
+    /// assert_eq!(handle.join().unwrap(), [3, 1, 2]);
+    /// ```
+    pub fn nearest_cloned(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<OwnedItemAndDistance<T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N> + Clone,
+    {
+        self.nearest(query).map(|found| found.cloned())
+    }
+
+    /// Same as [`Self::nearests`], but returns clones of the items, which do not borrow the tree.
+    pub fn nearests_cloned(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+    ) -> Vec<OwnedItemAndDistance<T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N> + Clone,
+    {
+        self.nearests(query, num)
+            .iter()
+            .map(ItemAndDistance::cloned)
+            .collect()
+    }
+
+    /// Same as [`Self::nearest`], but the offset of the item in this slice is returned together,
+    /// so that it can be used as a key of auxiliary arrays aligned with the items.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[1, 2, 3], [3, 1, 2], [2, 3, 1]]);
+    /// let (i, found) = kdtree.nearest_indexed(&[3, 1, 2]).unwrap();
+    /// assert_eq!(&kdtree[i], found.item);
+    /// ```
+    pub fn nearest_indexed(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<(usize, ItemAndDistance<'_, T, T::Scalar>)>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.nearest(query)
+            .map(|found| (self.offset_of(found.item), found))
+    }
+
+    /// Same as [`Self::nearests`], but the offsets of the items in this slice are returned together.
+    pub fn nearests_indexed(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+    ) -> Vec<(usize, ItemAndDistance<'_, T, T::Scalar>)>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.nearests(query, num)
+            .into_iter()
+            .map(|found| (self.offset_of(found.item), found))
+            .collect()
+    }
+
+    /// Same as [`Self::within_radius`], but the offsets of the items in this slice are returned together.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[1, 2], [3, 1], [2, 3], [9, 9]]);
+    /// let labels: Vec<char> = kdtree.iter().map(|p| if p[0] > 5 { 'b' } else { 'a' }).collect();
+    /// let found = kdtree.within_radius_indexed(&[3, 2], 2);
+    /// assert_eq!(found.len(), 2);
+    /// assert!(found.iter().all(|&(i, item)| &kdtree[i] == item && labels[i] == 'a'));
+    /// ```
+    pub fn within_radius_indexed(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<(usize, &T)>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.within_radius(query, radius)
+            .into_iter()
+            .map(|item| (self.offset_of(item), item))
+            .collect()
     }
 
-    /*
+    /// Returns the nearest item from a query given implicitly by `kd_difference(item, k)`,
+    /// which is the difference `query[k] - item[k]` on the `k`-th axis.
+    /// Returns `None` if `self.is_empty()`.
+    ///
+    /// This is useful when the query can't be a [`KdPoint`], e.g. when the key type of the items is not a point type.
     /// # Example
     /// ```
     /// let kdtree = kd_tree::KdTree3::build(vec![[1, 2, 3], [3, 1, 2], [2, 3, 1]]);
     /// let key = [3, 1, 2];
-    /// assert_eq!(kdtree.nearest_with(|p, k| key[k] - p[k]).item, &[3, 1, 2]);
+    /// assert_eq!(kdtree.nearest_with(|p, k| key[k] - p[k]).unwrap().item, &[3, 1, 2]);
     /// ```
     pub fn nearest_with<Scalar>(
         &self,
         kd_difference: impl Fn(&T, usize) -> Scalar + Copy,
-    ) -> ItemAndDistance<T, Scalar>
+    ) -> Option<ItemAndDistance<'_, T, Scalar>>
     where
-        Scalar: num_traits::NumAssign + Copy + PartialOrd,
+        Scalar: KdScalar,
     {
         kd_nearest_with(self.items(), N::to_usize(), kd_difference)
     }
-    */
+
+    /// Returns kNN(k nearest neighbors) from a query given implicitly by `kd_difference`, as in [`Self::nearest_with`].
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree3::build(vec![[1, 2, 3], [3, 1, 2], [2, 3, 1]]);
+    /// let key = [3, 1, 1];
+    /// let found = kdtree.nearests_with(2, |p, k| key[k] - p[k]);
+    /// assert_eq!(found[0].item, &[3, 1, 2]);
+    /// assert_eq!(found[1].item, &[2, 3, 1]);
+    /// ```
+    pub fn nearests_with<Scalar>(
+        &self,
+        num: usize,
+        kd_difference: impl Fn(&T, usize) -> Scalar + Copy,
+    ) -> Vec<ItemAndDistance<'_, T, Scalar>>
+    where
+        Scalar: KdScalar,
+    {
+        kd_nearests_by(self.items(), &Origin::<Scalar, N>::new(), num, |item, k| {
+            Scalar::zero() - kd_difference(item, k)
+        })
+    }
+
+    /// search points within k-dimensional sphere around a query given implicitly by `kd_difference`,
+    /// as in [`Self::nearest_with`].
+    pub fn within_radius_with<Scalar>(
+        &self,
+        radius: Scalar,
+        kd_difference: impl Fn(&T, usize) -> Scalar + Copy,
+    ) -> Vec<&T>
+    where
+        Scalar: KdScalar,
+    {
+        self.within_radius_by(&Origin::<Scalar, N>::new(), radius, |item, k| {
+            Scalar::zero() - kd_difference(item, k)
+        })
+    }
 
     /// Returns the nearest item from the input point. Returns `None` if `self.is_empty()`.
     /// # Example
@@ -268,7 +714,7 @@ impl<T, N: Unsigned> KdSliceN<T, N> {
         query: &Q,
         num: usize,
         coord: impl Fn(&T, usize) -> Q::Scalar + Copy,
-    ) -> Vec<ItemAndDistance<T, Q::Scalar>> {
+    ) -> Vec<ItemAndDistance<'_, T, Q::Scalar>> {
         kd_nearests_by(self.items(), query, num, coord)
     }
 
@@ -286,115 +732,1087 @@ impl<T, N: Unsigned> KdSliceN<T, N> {
         &self,
         query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
         num: usize,
-    ) -> Vec<ItemAndDistance<T, T::Scalar>>
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>>
     where
         T: KdPoint<Dim = N>,
     {
         kd_nearests(self.items(), query, num)
     }
 
-    pub fn within_by_cmp(&self, compare: impl Fn(&T, usize) -> Ordering + Copy) -> Vec<&T> {
-        kd_within_by_cmp(self, N::to_usize(), compare)
-    }
-
-    pub fn within_by<Q: KdPoint<Dim = N>>(
+    /// Same as [`Self::nearest`], but the traversal doesn't descend deeper than `max_depth` levels below the root.
+    /// The work per call is bounded by `max_depth`, and the returned flag tells whether the result is guaranteed to be the exact nearest.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build((0..100).map(|i| [i, i]).collect());
+    /// let found = kdtree.nearest_with_max_depth(&[3, 3], 2).unwrap();
+    /// assert!(!found.is_exact);
+    /// let found = kdtree.nearest_with_max_depth(&[3, 3], 10).unwrap();
+    /// assert!(found.is_exact);
+    /// assert_eq!(found.result.item, &[3, 3]);
+    /// ```
+    pub fn nearest_with_max_depth(
         &self,
-        query: &[Q; 2],
-        coord: impl Fn(&T, usize) -> Q::Scalar + Copy,
-    ) -> Vec<&T> {
-        assert!((0..Q::dim()).all(|k| query[0].at(k) <= query[1].at(k)));
-        self.within_by_cmp(|item, k| {
-            let a = coord(item, k);
-            if a < query[0].at(k) {
-                Ordering::Less
-            } else if a > query[1].at(k) {
-                Ordering::Greater
-            } else {
-                Ordering::Equal
-            }
-        })
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        max_depth: usize,
+    ) -> Option<PartialResult<ItemAndDistance<'_, T, T::Scalar>>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.nearest_with_config(
+            query,
+            &QueryConfig {
+                max_depth,
+                ..Default::default()
+            },
+        )
     }
 
-    /// search points within a rectangular region
+    /// Same as [`Self::nearest`], but only the items whose squared distance is less than `max_squared_distance` are searched.
+    /// A bound known in advance, e.g. from the previous frame, prunes subtrees from the very first node.
+    /// Returns `None` if there is no item within the bound.
     /// # Example
     /// ```
-    /// let mut items: Vec<[i32; 2]> = vec![[0, 0], [1, 0], [0, 1], [1, 1]];
-    /// let kdtree = kd_tree::KdSlice::sort(&mut items);
-    /// let within = kdtree.within(&[[1, 0], [2, 1]]);
-    /// assert_eq!(within.len(), 2);
-    /// assert!(within.contains(&&[1, 0]));
-    /// assert!(within.contains(&&[1, 1]));
+    /// let kdtree = kd_tree::KdTree::build(vec![[1, 2], [3, 1], [2, 3]]);
+    /// assert_eq!(kdtree.nearest_with_bound(&[3, 2], 2).unwrap().item, &[3, 1]);
+    /// assert!(kdtree.nearest_with_bound(&[3, 2], 1).is_none());
     /// ```
-    pub fn within(&self, query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2]) -> Vec<&T>
+    pub fn nearest_with_bound(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        max_squared_distance: T::Scalar,
+    ) -> Option<ItemAndDistance<'_, T, T::Scalar>>
     where
         T: KdPoint<Dim = N>,
     {
-        self.within_by(query, |item, k| item.at(k))
+        kd_nearest_by_seeded(
+            self.items(),
+            query,
+            &QueryConfig::default(),
+            |item, k| item.at(k),
+            None,
+            Some(max_squared_distance),
+            |_| {},
+        )
+        .result
     }
 
-    pub fn within_radius_by<Q: KdPoint<Dim = N>>(
+    /// Same as [`Self::nearests`], but only the items whose squared distance is less than `max_squared_distance` are searched.
+    /// The result may contain less than `num` items.
+    pub fn nearests_with_bound(
         &self,
-        query: &Q,
-        radius: Q::Scalar,
-        coord: impl Fn(&T, usize) -> Q::Scalar + Copy,
-    ) -> Vec<&T> {
-        let mut results = self.within_by_cmp(|item, k| {
-            let coord = coord(item, k);
-            if coord < query.at(k) - radius {
-                Ordering::Less
-            } else if coord > query.at(k) + radius {
-                Ordering::Greater
-            } else {
-                Ordering::Equal
-            }
-        });
-        results.retain(|item| {
-            let mut distance = <Q::Scalar as num_traits::Zero>::zero();
-            for k in 0..N::to_usize() {
-                let diff = coord(item, k) - query.at(k);
-                distance += diff * diff;
-            }
-            distance < radius * radius
-        });
-        results
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+        max_squared_distance: T::Scalar,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        kd_nearests_by_filtered(
+            self.items(),
+            query,
+            num,
+            &QueryConfig::default(),
+            |item, k| item.at(k),
+            Some(max_squared_distance),
+            |_| true,
+        )
+        .result
     }
 
-    /// search points within k-dimensional sphere
-    pub fn within_radius(
+    /// Returns at most `num` nearest items whose distance from the input point is less than `radius`.
+    /// The pruning bound is `radius * radius` from the start, which is faster than filtering the results of [`Self::nearests`].
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![[1.0, 2.0], [3.0, 1.0], [2.0, 3.0], [9.0, 9.0]]);
+    /// let found = kdtree.nearests_within(&[3.0, 2.0], 3, 1.5);
+    /// assert_eq!(found.len(), 2);
+    /// assert_eq!(found[0].item, &[3.0, 1.0]);
+    /// assert_eq!(found[1].item, &[2.0, 3.0]);
+    /// ```
+    pub fn nearests_within(
         &self,
         query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
         radius: T::Scalar,
-    ) -> Vec<&T>
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>>
     where
         T: KdPoint<Dim = N>,
     {
-        self.within_radius_by(query, radius, |item, k| item.at(k))
+        self.nearests_with_bound(query, num, radius * radius)
     }
-}
-#[cfg(feature = "rayon")]
-impl<T: Send, N: Unsigned> KdSliceN<T, N> {
-    /// Same as [`Self::sort_by`], but using multiple threads.
-    pub fn par_sort_by<F>(items: &mut [T], compare: F) -> &Self
+
+    /// Same as [`Self::nearests`] with `K` as the number of neighbors, but faster for small `K`,
+    /// since the candidates are kept in a fixed-size sorted array without allocation.
+    /// The result is padded with `None` if the tree has less than `K` items.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[1, 2, 3], [3, 1, 2], [2, 3, 1], [3, 2, 2]]);
+    /// let [first, second] = kdtree.nearests_const::<2>(&[3, 1, 2]);
+    /// assert_eq!(first.unwrap().item, &[3, 1, 2]);
+    /// assert_eq!(second.unwrap().item, &[3, 2, 2]);
+    /// ```
+    pub fn nearests_const<const K: usize>(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> [Option<ItemAndDistance<'_, T, T::Scalar>>; K]
     where
-        F: Fn(&T, &T, usize) -> Ordering + Copy + Send,
+        T: KdPoint<Dim = N>,
     {
-        kd_par_sort_by(items, N::to_usize(), compare);
-        unsafe { Self::new_unchecked(items) }
+        kd_nearests_const_by::<K, _, _>(self.items(), query, |item, k| item.at(k))
     }
 
-    /// Same as [`Self::sort_by_key`], but using multiple threads.
-    pub fn par_sort_by_key<Key: Ord, F>(items: &mut [T], kd_key: F) -> &Self
+    /// Returns the nearest item from `query` except the items regarded as the query itself by `is_same`.
+    /// # Example
+    /// ```
+    /// struct Item {
+    ///     point: [i32; 2],
+    ///     id: usize,
+    /// }
+    /// impl kd_tree::KdPoint for Item {
+    ///     type Scalar = i32;
+    ///     type Dim = typenum::U2;
+    ///     fn at(&self, k: usize) -> i32 { self.point[k] }
+    /// }
+    /// let kdtree = kd_tree::KdTree::build(vec![
+    ///     Item { point: [1, 2], id: 1 },
+    ///     Item { point: [3, 1], id: 2 },
+    ///     Item { point: [2, 3], id: 3 },
+    /// ]);
+    /// let query = Item { point: [1, 2], id: 1 };
+    /// let found = kdtree.nearest_excluding_by(&query, |a, b| a.id == b.id).unwrap();
+    /// assert_eq!(found.item.id, 3);
+    /// ```
+    pub fn nearest_excluding_by(
+        &self,
+        query: &T,
+        is_same: impl Fn(&T, &T) -> bool,
+    ) -> Option<ItemAndDistance<'_, T, T::Scalar>>
     where
-        F: Fn(&T, usize) -> Key + Copy + Send,
+        T: KdPoint<Dim = N>,
     {
-        Self::par_sort_by(items, move |item1, item2, k| {
-            kd_key(item1, k).cmp(&kd_key(item2, k))
-        })
+        self.nearests_excluding_by(query, 1, is_same).pop()
     }
 
-    /// Same as [`Self::sort_by_ordered_float`], but using multiple threads.
-    pub fn par_sort_by_ordered_float(points: &mut [T]) -> &Self
-    where
+    /// Returns kNN(k nearest neighbors) from `query` except the items regarded as the query itself by `is_same`.
+    pub fn nearests_excluding_by(
+        &self,
+        query: &T,
+        num: usize,
+        is_same: impl Fn(&T, &T) -> bool,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        kd_nearests_by_filtered(
+            self.items(),
+            query,
+            num,
+            &QueryConfig::default(),
+            |item, k| item.at(k),
+            None,
+            |item| !is_same(item, query),
+        )
+        .result
+    }
+
+    /// Returns the nearest items from the input point on both sides of the plane `x[axis] = position`, in a single traversal.
+    /// The first is the nearest item with `x[axis] < position`, and the second is the nearest item with `x[axis] >= position`,
+    /// each of which is `None` if there is no item on the side.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[0, 3], [1, 1], [2, -1], [5, -2], [0, -6]]);
+    /// let [below, above] = kdtree.nearest_on_each_side(&[0, 0], 1, 0);
+    /// assert_eq!(below.unwrap().item, &[2, -1]);
+    /// assert_eq!(above.unwrap().item, &[1, 1]);
+    /// let [below, above] = kdtree.nearest_on_each_side(&[0, 0], 1, 5);
+    /// assert_eq!(below.unwrap().item, &[1, 1]);
+    /// assert!(above.is_none());
+    /// ```
+    pub fn nearest_on_each_side(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        axis: usize,
+        position: T::Scalar,
+    ) -> [Option<ItemAndDistance<'_, T, T::Scalar>>; 2]
+    where
+        T: KdPoint<Dim = N>,
+    {
+        assert!(axis < N::to_usize(), "axis is out of range");
+        kd_nearest_on_each_side(self.items(), query, axis, position)
+    }
+
+    /// Returns the nearest item from `query`, which is an item of this tree, except `query` itself.
+    /// Other items at the same position as `query` can be returned.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[1, 2], [3, 1], [2, 3], [3, 1]]);
+    /// for item in kdtree.iter() {
+    ///     let found = kdtree.nearest_excluding(item).unwrap();
+    ///     assert!(!std::ptr::eq(found.item, item));
+    /// }
+    /// let found = kdtree.nearest_excluding(&kdtree[0]).unwrap();
+    /// assert_eq!((found.item, found.squared_distance), (&[2, 3], 2));
+    /// ```
+    pub fn nearest_excluding(&self, query: &T) -> Option<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.nearests_excluding(query, 1).pop()
+    }
+
+    /// Returns kNN(k nearest neighbors) from `query`, which is an item of this tree, except `query` itself.
+    /// Items are compared by their addresses, so other items at the same position as `query` can be returned.
+    pub fn nearests_excluding(
+        &self,
+        query: &T,
+        num: usize,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.nearests_excluding_by(query, num, |a, b| std::ptr::eq(a, b))
+    }
+
+    /// Returns the nearest item from the input point among the items whose coordinates are in `ranges`.
+    /// Returns `None` if there is no such item.
+    ///
+    /// The subtrees lying outside `ranges` are pruned, which is not possible with a predicate on items.
+    /// See [`AxisRanges`] for an example.
+    pub fn nearest_in_ranges(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        ranges: &AxisRanges<T::Scalar, N>,
+    ) -> Option<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.nearests_in_ranges(query, 1, ranges).pop()
+    }
+
+    /// Returns kNN(k nearest neighbors) from the input point among the items whose coordinates are in `ranges`.
+    pub fn nearests_in_ranges(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+        ranges: &AxisRanges<T::Scalar, N>,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        kd_nearests_by_filtered_in_ranges(
+            self.items(),
+            query,
+            num,
+            &QueryConfig::default(),
+            |item, k| item.at(k),
+            None,
+            |_| true,
+            ranges,
+        )
+        .result
+    }
+
+    /// Returns the pairs of each item of this tree and its nearest item in `other`, in the order of this tree.
+    /// Returns an empty `Vec` if `other` is empty.
+    ///
+    /// The items of this tree are visited in the order of the tree, so that consecutive items are close to each other,
+    /// and the search in `other` starts from the answer for the previous item, as in [`MovingNearest`].
+    /// Thus most of the subtrees of `other` are pruned from the very first node.
+    ///
+    /// This is a nearest neighbor search per item rather than a dual-tree traversal,
+    /// so it takes `O(n log m)` time for `n` items of this tree and `m` items of `other` distributed evenly,
+    /// and `O(n m)` in the worst case, e.g. if the items of this tree are far from all of `other`.
+    /// # Example
+    /// ```
+    /// let a = kd_tree::KdTree::build(vec![[0, 0], [5, 5], [9, 0]]);
+    /// let b = kd_tree::KdTree::build(vec![[1, 1], [8, 1], [4, 6]]);
+    /// let pairs = a.nearest_pairs(&b);
+    /// assert_eq!(pairs.len(), 3);
+    /// for (item, found) in pairs {
+    ///     assert_eq!(found, b.nearest(item).unwrap());
+    /// }
+    /// ```
+    pub fn nearest_pairs<'b, U: KdPoint<Scalar = T::Scalar, Dim = N>>(
+        &self,
+        other: &'b KdSliceN<U, N>,
+    ) -> Vec<(&T, ItemAndDistance<'b, U, T::Scalar>)>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let mut moving = MovingNearest::new(other);
+        self.iter()
+            .filter_map(|item| moving.nearest(item).map(|found| (item, found)))
+            .collect()
+    }
+
+    /// Returns the two items with the minimum mutual distance, and their squared distance.
+    /// Returns `None` if the tree has less than two items.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[0, 0], [5, 5], [9, 0], [4, 7]]);
+    /// let (a, b, squared_distance) = kdtree.closest_pair().unwrap();
+    /// let mut pair = [a, b];
+    /// pair.sort();
+    /// assert_eq!(pair, [&[4, 7], &[5, 5]]);
+    /// assert_eq!(squared_distance, 5);
+    /// ```
+    pub fn closest_pair(&self) -> Option<(&T, &T, T::Scalar)>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        kd_closest_pair(self.items())
+    }
+
+    /// Returns kNN(k nearest neighbors) of every item, except the item itself.
+    /// The `i`-th result is for `self[i]`.
+    ///
+    /// The items are queried one by one in the order of the tree, as [`Self::knn_graph`] does,
+    /// so that each query is bounded by the result of the previous one, which is spatially close.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[0, 0], [1, 0], [3, 0], [7, 0]]);
+    /// let all = kdtree.all_nearests(1);
+    /// for (item, nearests) in kdtree.iter().zip(&all) {
+    ///     assert_eq!(nearests[0].item, kdtree.nearest_excluding(item).unwrap().item);
+    /// }
+    /// ```
+    pub fn all_nearests(&self, num: usize) -> Vec<Vec<ItemAndDistance<'_, T, T::Scalar>>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let mut all = Vec::with_capacity(self.len());
+        kd_knn_graph_by(
+            self.items(),
+            num,
+            0..self.len(),
+            |item| item,
+            |item, k| item.at(k),
+            |_, found| all.push(found),
+        );
+        all
+    }
+
+    /// Returns an iterator over the items in ascending order of the distance from the input point.
+    /// This is useful when the number of neighbors needed is not known in advance.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build((0..100).map(|i| [i, 0]).collect());
+    /// // the nearest point whose x coordinate is a multiple of 7, except 0.
+    /// let found = kdtree
+    ///     .nearest_iter(&[30, 0])
+    ///     .find(|found| found.item[0] % 7 == 0 && found.item[0] != 0)
+    ///     .unwrap();
+    /// assert_eq!(found.item, &[28, 0]);
+    /// ```
+    pub fn nearest_iter<'a, 'q, Q: KdPoint<Scalar = T::Scalar, Dim = N>>(
+        &'a self,
+        query: &'q Q,
+    ) -> NearestIter<'a, 'q, T, Q>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        NearestIter::new(self.items(), query)
+    }
+
+    /// Same as [`Self::nearests`], but the traversal doesn't descend deeper than `max_depth` levels below the root.
+    pub fn nearests_with_max_depth(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+        max_depth: usize,
+    ) -> PartialResult<Vec<ItemAndDistance<'_, T, T::Scalar>>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.nearests_with_config(
+            query,
+            num,
+            &QueryConfig {
+                max_depth,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Same as [`Self::nearest_with_config`], but `on_improve` is called with the initial candidate
+    /// and whenever the nearest candidate improves during the search.
+    /// This is useful to show progressively better answers during a long search.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build((0..100).map(|i| [i, i]).collect());
+    /// let mut candidates = Vec::new();
+    /// let found = kdtree.nearest_with_progress(&[10, 10], &Default::default(), |nearest| {
+    ///     candidates.push(nearest.squared_distance)
+    /// });
+    /// assert_eq!(found.unwrap().result.item, &[10, 10]);
+    /// assert_eq!(candidates.last(), Some(&0));
+    /// assert!(candidates.windows(2).all(|w| w[0] > w[1]));
+    /// ```
+    pub fn nearest_with_progress<'a>(
+        &'a self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        config: &QueryConfig,
+        on_improve: impl FnMut(&ItemAndDistance<'a, T, T::Scalar>),
+    ) -> Option<PartialResult<ItemAndDistance<'a, T, T::Scalar>>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        kd_nearest_by_with_progress(
+            self.items(),
+            query,
+            config,
+            |item, k| item.at(k),
+            on_improve,
+        )
+        .transpose()
+    }
+
+    /// Same as [`Self::nearest`], but with tuning parameters and options given by `config`.
+    pub fn nearest_with_config(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        config: &QueryConfig,
+    ) -> Option<PartialResult<ItemAndDistance<'_, T, T::Scalar>>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        kd_nearest_by_with_config(self.items(), query, config, |item, k| item.at(k)).transpose()
+    }
+
+    /// Same as [`Self::nearests`], but with tuning parameters and options given by `config`.
+    pub fn nearests_with_config(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+        config: &QueryConfig,
+    ) -> PartialResult<Vec<ItemAndDistance<'_, T, T::Scalar>>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        kd_nearests_by_with_config(self.items(), query, num, config, |item, k| item.at(k))
+    }
+
+    pub fn within_by_cmp(&self, compare: impl Fn(&T, usize) -> Ordering + Copy) -> Vec<&T> {
+        kd_within_by_cmp(self, N::to_usize(), compare)
+    }
+
+    /// Searches items in a custom region, given by a per-axis pruning predicate and an acceptance predicate.
+    ///
+    /// Unlike [`Self::within_by_cmp`], in which an item is accepted when `compare` returns `Ordering::Equal` on every axis,
+    /// the two roles are separated:
+    /// `prune(item, axis)` returns `Ordering::Less` if the region lies entirely above `item` along `axis`,
+    /// `Ordering::Greater` if the region lies entirely below `item` along `axis`, and `Ordering::Equal` otherwise,
+    /// and `accept(item)` decides whether `item` is in the region.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[1, 1], [2, 3], [3, 1], [4, 4], [0, 5]]);
+    /// // the triangle `x >= 0, y >= 0, x + y <= 4`.
+    /// let mut found = kdtree.within_by_predicates(
+    ///     |p, k| if p[k] < 0 { std::cmp::Ordering::Less } else if p[k] > 4 { std::cmp::Ordering::Greater } else { std::cmp::Ordering::Equal },
+    ///     |p| p[0] >= 0 && p[1] >= 0 && p[0] + p[1] <= 4,
+    /// );
+    /// found.sort();
+    /// assert_eq!(found, vec![&[1, 1], &[3, 1]]);
+    /// ```
+    pub fn within_by_predicates(
+        &self,
+        prune: impl Fn(&T, usize) -> Ordering + Copy,
+        accept: impl Fn(&T) -> bool + Copy,
+    ) -> Vec<&T> {
+        let mut results = Vec::new();
+        kd_for_each_within_by_predicates(self.items(), N::to_usize(), prune, accept, |item| {
+            results.push(item)
+        });
+        results
+    }
+
+    pub fn within_by<Q: KdPoint<Dim = N>>(
+        &self,
+        query: &[Q; 2],
+        coord: impl Fn(&T, usize) -> Q::Scalar + Copy,
+    ) -> Vec<&T> {
+        assert!((0..Q::dim()).all(|k| query[0].at(k) <= query[1].at(k)));
+        self.within_by_cmp(|item, k| {
+            let a = coord(item, k);
+            if a < query[0].at(k) {
+                Ordering::Less
+            } else if a > query[1].at(k) {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        })
+    }
+
+    /// search points within a rectangular region
+    /// # Example
+    /// ```
+    /// let mut items: Vec<[i32; 2]> = vec![[0, 0], [1, 0], [0, 1], [1, 1]];
+    /// let kdtree = kd_tree::KdSlice::sort(&mut items);
+    /// let within = kdtree.within(&[[1, 0], [2, 1]]);
+    /// assert_eq!(within.len(), 2);
+    /// assert!(within.contains(&&[1, 0]));
+    /// assert!(within.contains(&&[1, 1]));
+    /// ```
+    pub fn within(&self, query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2]) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.within_by(query, |item, k| item.at(k))
+    }
+
+    pub fn within_radius_by<Q: KdPoint<Dim = N>>(
+        &self,
+        query: &Q,
+        radius: Q::Scalar,
+        coord: impl Fn(&T, usize) -> Q::Scalar + Copy,
+    ) -> Vec<&T> {
+        let mut results = self.within_by_cmp(|item, k| {
+            let coord = coord(item, k);
+            if coord < query.at(k) - radius {
+                Ordering::Less
+            } else if coord > query.at(k) + radius {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        });
+        results.retain(|item| {
+            let mut distance = <Q::Scalar as KdScalar>::zero();
+            for k in 0..N::to_usize() {
+                let diff = coord(item, k) - query.at(k);
+                distance += diff * diff;
+            }
+            distance < radius * radius
+        });
+        results
+    }
+
+    /// search points within k-dimensional sphere
+    pub fn within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.within_radius_by(query, radius, |item, k| item.at(k))
+    }
+
+    /// Same as [`Self::within_radius`], but returns clones of the items with their squared distances,
+    /// which do not borrow the tree.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[1, 2], [3, 1], [2, 3], [5, 5]]);
+    /// let mut found = kdtree.within_radius_cloned(&[2, 2], 2);
+    /// found.sort_by_key(|found| found.item);
+    /// assert_eq!(found[0].item, [1, 2]);
+    /// assert_eq!(found[0].squared_distance, 1);
+    /// assert_eq!(found.len(), 3);
+    /// ```
+    pub fn within_radius_cloned(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<OwnedItemAndDistance<T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N> + Clone,
+    {
+        self.within_radius(query, radius)
+            .into_iter()
+            .map(|item| OwnedItemAndDistance {
+                item: item.clone(),
+                squared_distance: squared_distance(item, query),
+            })
+            .collect()
+    }
+
+    /// search points whose distance from the segment between `a` and `b` is less than `radius`,
+    /// i.e. within the capsule swept by a sphere moving from `a` to `b`.
+    ///
+    /// The subtrees farther than `radius` from the segment are pruned, not only the ones out of the bounding box of the capsule.
+    /// The distances are compared without division by multiplying two squared distances,
+    /// so the result is exact for integer scalars as long as `dim^2 * size^4` doesn't overflow,
+    /// where `size` is the extent of the bounding box of the capsule along the longest axis,
+    /// e.g. the size must be less than about 150 for 2-dimensional `i32` points, and 38000 for `i64` points.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[0, 1], [5, -1], [10, 2], [-2, 0], [12, 0]]);
+    /// let mut found = kdtree.within_capsule(&[0, 0], &[10, 0], 2);
+    /// found.sort();
+    /// assert_eq!(found, vec![&[0, 1], &[5, -1]]);
+    /// ```
+    pub fn within_capsule(
+        &self,
+        a: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        b: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        kd_within_capsule(self.items(), a, b, radius)
+    }
+
+    /// Calls `f` for each point within a rectangular region, without allocating the results.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[0, 0], [1, 0], [0, 1], [1, 1]]);
+    /// let mut sum = 0;
+    /// kdtree.for_each_within(&[[1, 0], [2, 1]], |p| sum += p[1]);
+    /// assert_eq!(sum, 1);
+    /// ```
+    pub fn for_each_within<'a>(
+        &'a self,
+        query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2],
+        f: impl FnMut(&'a T),
+    ) where
+        T: KdPoint<Dim = N>,
+    {
+        assert!((0..N::to_usize()).all(|k| query[0].at(k) <= query[1].at(k)));
+        kd_for_each_within_by_cmp(
+            self.items(),
+            N::to_usize(),
+            |item, k| {
+                let a = item.at(k);
+                if a < query[0].at(k) {
+                    Ordering::Less
+                } else if a > query[1].at(k) {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            },
+            f,
+        );
+    }
+
+    /// Calls `f` with each point within k-dimensional sphere and its squared distance, without allocating the results.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[1, 2], [3, 1], [2, 3], [9, 9]]);
+    /// let mut sum = 0;
+    /// kdtree.for_each_within_radius(&[3, 2], 2, |_, squared_distance| sum += squared_distance);
+    /// assert_eq!(sum, 3);
+    /// ```
+    pub fn for_each_within_radius<'a>(
+        &'a self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+        mut f: impl FnMut(&'a T, T::Scalar),
+    ) where
+        T: KdPoint<Dim = N>,
+    {
+        kd_for_each_within_by_cmp(
+            self.items(),
+            N::to_usize(),
+            |item, k| {
+                let coord = item.at(k);
+                if coord < query.at(k) - radius {
+                    Ordering::Less
+                } else if coord > query.at(k) + radius {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            },
+            |item| {
+                let squared_distance = squared_distance(item, query);
+                if squared_distance < radius * radius {
+                    f(item, squared_distance);
+                }
+            },
+        );
+    }
+
+    /// Returns the number of points within a rectangular region, without allocating the results.
+    /// A subtree whose region is entirely inside the query is counted by its size without visiting its items.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[0, 0], [1, 0], [0, 1], [1, 1]]);
+    /// assert_eq!(kdtree.count_within(&[[1, 0], [2, 1]]), 2);
+    /// ```
+    pub fn count_within(&self, query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2]) -> usize
+    where
+        T: KdPoint<Dim = N>,
+    {
+        assert!((0..N::to_usize()).all(|k| query[0].at(k) <= query[1].at(k)));
+        kd_count_within(self.items(), query)
+    }
+
+    /// Returns the number of points within k-dimensional sphere, without allocating the results.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[1, 2], [3, 1], [2, 3], [9, 9]]);
+    /// assert_eq!(kdtree.count_within_radius(&[3, 2], 2), 2);
+    /// ```
+    pub fn count_within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> usize
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let mut count = 0;
+        self.for_each_within_radius(query, radius, |_, _| count += 1);
+        count
+    }
+
+    /// Counts the points in each bin of the distances from the input point, in a single traversal.
+    /// The `i`-th count is of the points whose distances are in `[bin_edges[i], bin_edges[i + 1])`,
+    /// so the result has `bin_edges.len() - 1` counts.
+    ///
+    /// A subtree whose region falls in a single bin is counted by its size without visiting its items.
+    ///
+    /// # Panics
+    /// Panics if `bin_edges` are negative or not sorted in ascending order.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[0, 0], [1, 0], [0, 2], [3, 0], [5, 5]]);
+    /// assert_eq!(kdtree.distance_histogram(&[0, 0], &[0, 1, 2, 4]), vec![1, 1, 2]);
+    /// ```
+    pub fn distance_histogram(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        bin_edges: &[T::Scalar],
+    ) -> Vec<usize>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let zero = <T::Scalar as KdScalar>::zero();
+        assert!(
+            bin_edges.iter().all(|&edge| edge >= zero)
+                && bin_edges.windows(2).all(|edges| edges[0] <= edges[1]),
+            "bin_edges must be non-negative and sorted in ascending order"
+        );
+        let squared_edges: Vec<_> = bin_edges.iter().map(|&edge| edge * edge).collect();
+        kd_distance_histogram(self.items(), query, &squared_edges)
+    }
+
+    /// Estimates the number of points within k-dimensional sphere,
+    /// much faster than [`Self::count_within_radius`] for a large result.
+    ///
+    /// A subtree entirely inside or outside the sphere is counted by its size without visiting its items,
+    /// and the subtrees partially overlapping the sphere are refined from the largest one,
+    /// until the items in them are few enough for the error bound.
+    /// The error of [`CountEstimate::estimate`] from the exact count `n` is at most `max_rel_error * n`.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build((0..100).flat_map(|x| (0..100).map(move |y| [x, y])).collect());
+    /// let count = kdtree.count_within_radius(&[50, 50], 30);
+    /// let found = kdtree.estimate_within_radius_count(&[50, 50], 30, 0.05);
+    /// assert!(found.lower <= count && count <= found.upper);
+    /// assert!(found.estimate().abs_diff(count) as f64 <= 0.05 * count as f64);
+    /// assert!(kdtree.estimate_within_radius_count(&[50, 50], 30, 0.0).is_exact());
+    /// ```
+    pub fn estimate_within_radius_count(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+        max_rel_error: f64,
+    ) -> CountEstimate
+    where
+        T: KdPoint<Dim = N>,
+    {
+        kd_estimate_within_radius_count(self.items(), query, radius, max_rel_error)
+    }
+
+    /// Same as [`Self::within_radius`], but the results carry their squared distances and are sorted in ascending order of them.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[1, 2], [3, 1], [2, 3], [9, 9]]);
+    /// let found = kdtree.within_radius_sorted(&[3, 2], 2);
+    /// assert_eq!(found.len(), 2);
+    /// assert_eq!((found[0].item, found[0].squared_distance), (&[3, 1], 1));
+    /// assert_eq!((found[1].item, found[1].squared_distance), (&[2, 3], 2));
+    /// ```
+    pub fn within_radius_sorted(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let mut results: Vec<_> = self
+            .within_radius(query, radius)
+            .into_iter()
+            .map(|item| ItemAndDistance {
+                item,
+                squared_distance: squared_distance(item, query),
+            })
+            .collect();
+        results.sort_by(|a, b| {
+            a.squared_distance
+                .partial_cmp(&b.squared_distance)
+                .unwrap_or(Ordering::Equal)
+        });
+        results
+    }
+
+    /// Same as [`Self::within`], but with tuning parameters and options given by `config`.
+    pub fn within_with_config(
+        &self,
+        query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2],
+        config: &QueryConfig,
+    ) -> PartialResult<Vec<&T>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        assert!((0..N::to_usize()).all(|k| query[0].at(k) <= query[1].at(k)));
+        kd_within_by_cmp_with_config(
+            self,
+            N::to_usize(),
+            |item, k| {
+                let a = item.at(k);
+                if a < query[0].at(k) {
+                    Ordering::Less
+                } else if a > query[1].at(k) {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            },
+            config,
+        )
+    }
+
+    /// Same as [`Self::within_radius`], but with tuning parameters and options given by `config`.
+    pub fn within_radius_with_config(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+        config: &QueryConfig,
+    ) -> PartialResult<Vec<&T>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let mut found = kd_within_by_cmp_with_config(
+            self,
+            N::to_usize(),
+            |item, k| {
+                let coord = item.at(k);
+                if coord < query.at(k) - radius {
+                    Ordering::Less
+                } else if coord > query.at(k) + radius {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            },
+            config,
+        );
+        let squared_radius = radius * radius;
+        found.result.retain(|item| {
+            let squared_distance = config
+                .accumulation
+                .squared_distance(N::to_usize(), |k| item.at(k) - query.at(k));
+            squared_distance < squared_radius
+                || (config.inclusive && squared_distance == squared_radius)
+        });
+        found
+    }
+
+    /// Same as [`Self::within_radius_stratified`], but with a custom coordinate accessor.
+    pub fn within_radius_stratified_by<Q: KdPoint<Dim = N>>(
+        &self,
+        query: &Q,
+        radius: Q::Scalar,
+        cell_size: usize,
+        max_per_cell: usize,
+        coord: impl Fn(&T, usize) -> Q::Scalar + Copy,
+    ) -> Vec<&T> {
+        kd_within_stratified_by(
+            self,
+            N::to_usize(),
+            |item, k| {
+                let coord = coord(item, k);
+                if coord < query.at(k) - radius {
+                    Ordering::Less
+                } else if coord > query.at(k) + radius {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            },
+            |item| {
+                let mut distance = <Q::Scalar as KdScalar>::zero();
+                for k in 0..N::to_usize() {
+                    let diff = coord(item, k) - query.at(k);
+                    distance += diff * diff;
+                }
+                distance < radius * radius
+            },
+            cell_size,
+            max_per_cell,
+        )
+    }
+
+    /// search points within k-dimensional sphere, returning a spatially spread subset of them.
+    ///
+    /// The tree is divided into cells, the subtrees with at most `cell_size` items,
+    /// and at most `max_per_cell` items are returned from each cell.
+    /// Each splitting item above the cells forms a cell by itself.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build((0..1000).map(|i| [i, 0]).collect());
+    /// assert_eq!(kdtree.within_radius(&[500, 0], 1000).len(), 1000);
+    /// let found = kdtree.within_radius_stratified(&[500, 0], 1000, 100, 1);
+    /// assert!(found.len() < 100);
+    /// ```
+    pub fn within_radius_stratified(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+        cell_size: usize,
+        max_per_cell: usize,
+    ) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.within_radius_stratified_by(query, radius, cell_size, max_per_cell, |item, k| {
+            item.at(k)
+        })
+    }
+
+    /// Returns the sum of weights of the items within a rectangular region.
+    pub fn weight_within(&self, query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2]) -> T::Scalar
+    where
+        T: WeightedKdPoint<Dim = N>,
+    {
+        sum_weights(self.within(query))
+    }
+
+    /// Returns the sum of weights of the items within k-dimensional sphere.
+    pub fn weight_within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> T::Scalar
+    where
+        T: WeightedKdPoint<Dim = N>,
+    {
+        sum_weights(self.within_radius(query, radius))
+    }
+
+    /// Returns the sparse matrix of squared distances between all pairs of items closer than `radius`.
+    /// Rows and columns are the positions of the items in `self.items()`, and the diagonal is not stored.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[0, 0], [1, 0], [5, 5]]);
+    /// let matrix = kdtree.distance_matrix_within_radius(2);
+    /// assert_eq!(matrix.num_rows(), 3);
+    /// assert_eq!(matrix.num_entries(), 2);
+    /// ```
+    pub fn distance_matrix_within_radius(&self, radius: T::Scalar) -> CsrMatrix<T::Scalar>
+    where
+        T: KdPoint<Dim = N>,
+        N: generic_array::ArrayLength,
+    {
+        let mut rows: Vec<Vec<_>> = self.iter().map(|_| Vec::new()).collect();
+        for (i, j) in self.neighbor_pairs(radius) {
+            let squared_distance = squared_distance(&self[i], &self[j]);
+            rows[i].push((j, squared_distance));
+            rows[j].push((i, squared_distance));
+        }
+        CsrMatrix::from_rows(rows)
+    }
+
+    /// Returns all pairs of the positions `(i, j)` in `self.items()` with `i < j` of the items closer than `radius`,
+    /// in ascending order.
+    ///
+    /// The pairs are found by a single traversal joining the tree with itself,
+    /// which is much faster than querying [`Self::within_radius`] of each item.
+    /// See [`Self::distance_matrix_within_radius`] for the same pairs as a symmetric sparse matrix.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[0, 0], [1, 0], [5, 5], [5, 6]]);
+    /// let mut pairs: Vec<_> = kdtree
+    ///     .neighbor_pairs(2)
+    ///     .into_iter()
+    ///     .map(|(i, j)| {
+    ///         let mut pair = [kdtree[i], kdtree[j]];
+    ///         pair.sort();
+    ///         pair
+    ///     })
+    ///     .collect();
+    /// pairs.sort();
+    /// assert_eq!(pairs, vec![[[0, 0], [1, 0]], [[5, 5], [5, 6]]]);
+    /// ```
+    pub fn neighbor_pairs(&self, radius: T::Scalar) -> Vec<(usize, usize)>
+    where
+        T: KdPoint<Dim = N>,
+        N: generic_array::ArrayLength,
+    {
+        kd_neighbor_pairs(self.items(), radius)
+    }
+
+    /// Returns the kNN graph, whose `i`-th row holds the positions of the `k` nearest other items of the `i`-th item
+    /// in `self.items()` and their squared distances.
+    ///
+    /// The items are queried in the order of the tree, so that each query is bounded by the result of the previous one.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[0, 0], [1, 0], [3, 0], [7, 0]]);
+    /// let graph = kdtree.knn_graph(2);
+    /// assert_eq!(graph.num_rows(), 4);
+    /// assert_eq!(graph.num_entries(), 8);
+    /// let i = kdtree.iter().position(|p| p == &[7, 0]).unwrap();
+    /// let (_, squared_distances) = graph.row(i);
+    /// let mut squared_distances = squared_distances.to_vec();
+    /// squared_distances.sort();
+    /// assert_eq!(squared_distances, vec![16, 36]);
+    /// ```
+    pub fn knn_graph(&self, k: usize) -> CsrMatrix<T::Scalar>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let mut rows = Vec::with_capacity(self.len());
+        kd_knn_graph_by(
+            self.items(),
+            k,
+            0..self.len(),
+            |item| item,
+            |item, k| item.at(k),
+            |_, found| {
+                rows.push(
+                    found
+                        .into_iter()
+                        .map(|found| (self.offset_of(found.item), found.squared_distance))
+                        .collect(),
+                )
+            },
+        );
+        CsrMatrix::from_rows(rows)
+    }
+}
+#[cfg(feature = "rayon")]
+impl<T: Send, N: Unsigned> KdSliceN<T, N> {
+    /// Same as [`Self::sort_by`], but using multiple threads.
+    pub fn par_sort_by<F>(items: &mut [T], compare: F) -> &Self
+    where
+        F: Fn(&T, &T, usize) -> Ordering + Copy + Send,
+    {
+        kd_par_sort_by(items, N::to_usize(), compare);
+        // SAFETY: `items` has just been sorted as a kd-tree.
+        unsafe { Self::new_unchecked(items) }
+    }
+
+    /// Same as [`Self::sort_by_key`], but using multiple threads.
+    pub fn par_sort_by_key<Key: Ord, F>(items: &mut [T], kd_key: F) -> &Self
+    where
+        F: Fn(&T, usize) -> Key + Copy + Send,
+    {
+        Self::par_sort_by(items, move |item1, item2, k| {
+            kd_key(item1, k).cmp(&kd_key(item2, k))
+        })
+    }
+
+    /// Same as [`Self::sort_by_ordered_float`], but using multiple threads.
+    pub fn par_sort_by_ordered_float(points: &mut [T]) -> &Self
+    where
         T: KdPoint<Dim = N>,
         T::Scalar: ordered_float::FloatCore,
     {
@@ -410,15 +1828,107 @@ impl<T: Send, N: Unsigned> KdSliceN<T, N> {
         Self::par_sort_by_key(points, |item, k| item.at(k))
     }
 }
+#[cfg(feature = "rayon")]
+impl<T: Sync, N: Unsigned + Sync> KdSliceN<T, N> {
+    /// Same as [`Self::all_nearests`], but using multiple threads.
+    pub fn par_all_nearests(&self, num: usize) -> Vec<Vec<ItemAndDistance<'_, T, T::Scalar>>>
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: Send,
+    {
+        use rayon::prelude::*;
+        // each thread takes a contiguous range of the tree to keep the queries bounded by their predecessors.
+        const ROWS_PER_TASK: usize = 1024;
+        (0..self.len())
+            .step_by(ROWS_PER_TASK)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .flat_map_iter(|first_row| {
+                let mut all = Vec::new();
+                kd_knn_graph_by(
+                    self.items(),
+                    num,
+                    first_row..(first_row + ROWS_PER_TASK).min(self.len()),
+                    |item| item,
+                    |item, k| item.at(k),
+                    |_, found| all.push(found),
+                );
+                all
+            })
+            .collect()
+    }
+}
+
+/// Returns the axis of the first coordinate of `point` which is not finite.
+fn non_finite_axis<T: KdPoint>(point: &T) -> Option<usize>
+where
+    T::Scalar: ordered_float::FloatCore,
+{
+    (0..T::dim()).find(|&k| !ordered_float::FloatCore::is_finite(point.at(k)))
+}
+
+impl NanPolicy {
+    /// Returns the offsets of the items to be dropped by this policy, where `invalid_axis` finds an invalid coordinate of an item.
+    pub(crate) fn invalid_items<T>(
+        self,
+        items: &[T],
+        invalid_axis: impl Fn(&T) -> Option<usize>,
+    ) -> Result<Vec<usize>, BuildError> {
+        let first_error = || {
+            items.iter().enumerate().find_map(|(index, item)| {
+                invalid_axis(item).map(|axis| BuildError::NonFinite { index, axis })
+            })
+        };
+        match self {
+            NanPolicy::Reject => match first_error() {
+                Some(error) => Err(error),
+                None => Ok(Vec::new()),
+            },
+            NanPolicy::FilterOut => Ok((0..items.len())
+                .filter(|&i| invalid_axis(&items[i]).is_some())
+                .collect()),
+            NanPolicy::PanicDebugOnly => {
+                if cfg!(debug_assertions) {
+                    if let Some(error) = first_error() {
+                        panic!("{}", error);
+                    }
+                }
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Drops the items by this policy, see [`Self::invalid_items`].
+    pub(crate) fn valid_items<T>(
+        self,
+        mut items: Vec<T>,
+        invalid_axis: impl Fn(&T) -> Option<usize>,
+    ) -> Result<Vec<T>, BuildError> {
+        let invalid = self.invalid_items(&items, invalid_axis)?;
+        if !invalid.is_empty() {
+            let mut offset = 0;
+            items.retain(|_| {
+                offset += 1;
+                invalid.binary_search(&(offset - 1)).is_err()
+            });
+        }
+        Ok(items)
+    }
+}
 
 /// An owned kd-tree.
 /// This type implements [`std::ops::Deref`] to [`KdSlice`].
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct KdTreeN<T, N: Unsigned>(PhantomData<N>, Vec<T>);
 pub type KdTree<T> = KdTreeN<T, <T as KdPoint>::Dim>;
 impl<T, N: Unsigned> std::ops::Deref for KdTreeN<T, N> {
     type Target = KdSliceN<T, N>;
     fn deref(&self) -> &Self::Target {
+        // SAFETY: the items of `KdTreeN` are always sorted as a kd-tree.
         unsafe { KdSliceN::new_unchecked(&self.1) }
     }
 }
@@ -442,89 +1952,413 @@ impl<T, N: Unsigned> KdTreeN<T, N> {
         self.1
     }
 
+    /// Makes a tree of the items in the order made by [`Self::build_by`] with `compare` without sorting them,
+    /// or returns an error if they are out of order. This takes `O(n log n)` time.
+    pub fn try_from_sorted_by<F>(items: Vec<T>, compare: F) -> Result<Self, KdOrderError>
+    where
+        F: Fn(&T, &T, usize) -> Ordering + Copy,
+    {
+        let kdtree = Self(PhantomData, items);
+        kdtree.check_order_by(compare)?;
+        Ok(kdtree)
+    }
+
+    /// Makes a tree of the items in the order of a kd-tree without sorting them, or returns an error if they are out of order.
+    /// See [`KdSliceN::check_order`].
+    /// # Example
+    /// ```
+    /// let items = kd_tree::KdTree::build(vec![[1, 2], [3, 1], [2, 3]]).into_vec();
+    /// assert!(kd_tree::KdTree::try_from_sorted(items.clone()).is_ok());
+    /// assert!(kd_tree::KdTree::try_from_sorted(items.into_iter().rev().collect()).is_err());
+    /// ```
+    pub fn try_from_sorted(items: Vec<T>) -> Result<Self, KdOrderError>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let kdtree = Self(PhantomData, items);
+        kdtree.check_order()?;
+        Ok(kdtree)
+    }
+
+    /// # Example
+    /// ```
+    /// struct Item {
+    ///     point: [i32; 3],
+    ///     id: usize,
+    /// }
+    /// let kdtree = kd_tree::KdTree3::build_by(
+    ///     vec![
+    ///         Item { point: [1, 2, 3], id: 111 },
+    ///         Item { point: [3, 1, 2], id: 222 },
+    ///         Item { point: [2, 3, 1], id: 333 },
+    ///     ],
+    ///     |item1, item2, k| item1.point[k].cmp(&item2.point[k])
+    /// );
+    /// assert_eq!(kdtree.nearest_by(&[3, 1, 2], |item, k| item.point[k]).unwrap().item.id, 222);
+    /// ```
+    pub fn build_by<F>(mut items: Vec<T>, compare: F) -> Self
+    where
+        F: Fn(&T, &T, usize) -> Ordering + Copy,
+    {
+        kd_sort_by(&mut items, N::to_usize(), compare);
+        Self(PhantomData, items)
+    }
+
+    /// # Example
+    /// ```
+    /// struct Item {
+    ///     point: [f64; 3],
+    ///     id: usize,
+    /// }
+    /// let kdtree = kd_tree::KdTree3::build_by_key(
+    ///     vec![
+    ///         Item { point: [1.0, 2.0, 3.0], id: 111 },
+    ///         Item { point: [3.0, 1.0, 2.0], id: 222 },
+    ///         Item { point: [2.0, 3.0, 1.0], id: 333 },
+    ///     ],
+    ///     |item, k| ordered_float::OrderedFloat(item.point[k])
+    /// );
+    /// assert_eq!(kdtree.nearest_by(&[3.1, 0.9, 2.1], |item, k| item.point[k]).unwrap().item.id, 222);
+    /// ```
+    pub fn build_by_key<Key, F>(items: Vec<T>, kd_key: F) -> Self
+    where
+        Key: Ord,
+        F: Fn(&T, usize) -> Key + Copy,
+    {
+        Self::build_by(items, |item1, item2, k| {
+            kd_key(item1, k).cmp(&kd_key(item2, k))
+        })
+    }
+
+    /// # Example
+    /// ```
+    /// use kd_tree::KdTree;
+    /// let kdtree: KdTree<[f64; 3]> = KdTree::build_by_ordered_float(vec![
+    ///     [1.0, 2.0, 3.0], [3.0, 1.0, 2.0], [2.0, 3.0, 1.0]
+    /// ]);
+    /// assert_eq!(kdtree.nearest(&[3.1, 0.9, 2.1]).unwrap().item, &[3.0, 1.0, 2.0]);
+    /// ```
+    pub fn build_by_ordered_float(points: Vec<T>) -> Self
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        Self::build_by_key(points, |item, k| ordered_float::OrderedFloat(item.at(k)))
+    }
+
+    /// Same as [`Self::build_by_ordered_float`], but returns an error if any coordinate is NaN or infinite,
+    /// with which the order of the tree, and hence the query results, would be meaningless.
+    /// # Example
+    /// ```
+    /// use kd_tree::{BuildError, KdTree};
+    /// let kdtree = KdTree::try_build_by_float(vec![[1.0, 2.0], [3.0, 1.0]]).unwrap();
+    /// assert_eq!(kdtree.nearest(&[3.1, 0.9]).unwrap().item, &[3.0, 1.0]);
+    /// let result = KdTree::try_build_by_float(vec![[1.0, 2.0], [3.0, f64::NAN]]);
+    /// assert_eq!(result.unwrap_err(), BuildError::NonFinite { index: 1, axis: 1 });
+    /// ```
+    pub fn try_build_by_float(points: Vec<T>) -> Result<Self, BuildError>
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        Self::build_by_float_with_policy(points, NanPolicy::Reject)
+    }
+
+    /// Same as [`Self::build_by_ordered_float`], but handles the points with NaN or infinite coordinates by `policy`.
+    /// Returns an error only with [`NanPolicy::Reject`].
+    /// # Example
+    /// ```
+    /// use kd_tree::{KdTree, NanPolicy};
+    /// let points = vec![[1.0, 2.0], [f64::NAN, 1.0], [2.0, 3.0]];
+    /// let kdtree = KdTree::build_by_float_with_policy(points.clone(), NanPolicy::FilterOut).unwrap();
+    /// assert_eq!(kdtree.len(), 2);
+    /// assert!(KdTree::build_by_float_with_policy(points, NanPolicy::Reject).is_err());
+    /// ```
+    pub fn build_by_float_with_policy(points: Vec<T>, policy: NanPolicy) -> Result<Self, BuildError>
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        let points = policy.valid_items(points, non_finite_axis)?;
+        Ok(Self::build_by_ordered_float(points))
+    }
+
+    /// # Example
+    /// ```
+    /// use kd_tree::KdTree;
+    /// let kdtree: KdTree<[i32; 3]> = KdTree::build(vec![[1, 2, 3], [3, 1, 2], [2, 3, 1]]);
+    /// assert_eq!(kdtree.nearest(&[3, 1, 2]).unwrap().item, &[3, 1, 2]);
+    /// ```
+    pub fn build(points: Vec<T>) -> Self
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: Ord,
+    {
+        Self::build_by_key(points, |item, k| item.at(k))
+    }
+
+    /// Inserts an item, keeping the kd-tree valid.
+    /// `compare` must be the same as the one given to `build_by`.
+    ///
+    /// The items after the inserted position are shifted, and the subtrees whose median position moves by the insertion
+    /// are repaired by moving their medians by one item along the axis, without being rebuilt (see [`low_level::kd_insert_by`]).
+    /// It still takes `O(n)` time to shift the items, so use [`DynamicKdTree`] to insert many items one by one.
+    /// # Example
+    /// ```
+    /// let mut kdtree = kd_tree::KdTree::build_by(vec![[1, 2], [3, 1]], |p1, p2, k| p1[k].cmp(&p2[k]));
+    /// kdtree.insert_by([2, 3], |p1, p2, k| p1[k].cmp(&p2[k]));
+    /// assert_eq!(kdtree.len(), 3);
+    /// assert_eq!(kdtree.nearest(&[2, 4]).unwrap().item, &[2, 3]);
+    /// ```
+    pub fn insert_by<F>(&mut self, item: T, compare: F)
+    where
+        F: Fn(&T, &T, usize) -> Ordering + Copy,
+    {
+        kd_insert_by(&mut self.1, item, N::to_usize(), compare);
+    }
+
+    /// Inserts an item, keeping the kd-tree valid.
+    /// `kd_key` must be the same as the one given to `build_by_key`.
+    pub fn insert_by_key<Key, F>(&mut self, item: T, kd_key: F)
+    where
+        Key: Ord,
+        F: Fn(&T, usize) -> Key + Copy,
+    {
+        self.insert_by(item, |item1, item2, k| {
+            kd_key(item1, k).cmp(&kd_key(item2, k))
+        })
+    }
+
+    /// Inserts a point into a kd-tree built by `build_by_ordered_float`.
     /// # Example
     /// ```
-    /// struct Item {
-    ///     point: [i32; 3],
-    ///     id: usize,
-    /// }
-    /// let kdtree = kd_tree::KdTree3::build_by(
-    ///     vec![
-    ///         Item { point: [1, 2, 3], id: 111 },
-    ///         Item { point: [3, 1, 2], id: 222 },
-    ///         Item { point: [2, 3, 1], id: 333 },
-    ///     ],
-    ///     |item1, item2, k| item1.point[k].cmp(&item2.point[k])
-    /// );
-    /// assert_eq!(kdtree.nearest_by(&[3, 1, 2], |item, k| item.point[k]).unwrap().item.id, 222);
+    /// let mut kdtree = kd_tree::KdTree::build_by_ordered_float(vec![[1.0, 2.0], [3.0, 1.0]]);
+    /// kdtree.insert_by_ordered_float([2.0, 3.0]);
+    /// assert_eq!(kdtree.nearest(&[2.1, 2.9]).unwrap().item, &[2.0, 3.0]);
     /// ```
-    pub fn build_by<F>(mut items: Vec<T>, compare: F) -> Self
+    pub fn insert_by_ordered_float(&mut self, point: T)
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        self.insert_by_key(point, |item, k| ordered_float::OrderedFloat(item.at(k)))
+    }
+
+    /// Inserts a point into a kd-tree built by `build`.
+    pub fn insert(&mut self, point: T)
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: Ord,
+    {
+        self.insert_by_key(point, |item, k| item.at(k))
+    }
+
+    /// Removes the items which satisfy `pred`, keeping the kd-tree valid, and returns the removed items.
+    /// `compare` must be the same as the one given to `build_by`.
+    ///
+    /// The subtrees whose structure is kept after the removal are not rebuilt.
+    pub fn remove_if_by<F>(&mut self, mut pred: impl FnMut(&T) -> bool, compare: F) -> Vec<T>
     where
         F: Fn(&T, &T, usize) -> Ordering + Copy,
     {
-        kd_sort_by(&mut items, N::to_usize(), compare);
-        Self(PhantomData, items)
+        let removed: Vec<bool> = self.1.iter().map(&mut pred).collect();
+        kd_remove_by(&mut self.1, &removed, N::to_usize(), compare)
     }
 
-    /// # Example
-    /// ```
-    /// struct Item {
-    ///     point: [f64; 3],
-    ///     id: usize,
-    /// }
-    /// let kdtree = kd_tree::KdTree3::build_by_key(
-    ///     vec![
-    ///         Item { point: [1.0, 2.0, 3.0], id: 111 },
-    ///         Item { point: [3.0, 1.0, 2.0], id: 222 },
-    ///         Item { point: [2.0, 3.0, 1.0], id: 333 },
-    ///     ],
-    ///     |item, k| ordered_float::OrderedFloat(item.point[k])
-    /// );
-    /// assert_eq!(kdtree.nearest_by(&[3.1, 0.9, 2.1], |item, k| item.point[k]).unwrap().item.id, 222);
-    /// ```
-    pub fn build_by_key<Key, F>(items: Vec<T>, kd_key: F) -> Self
+    /// Removes the items which satisfy `pred`, and returns the removed items.
+    /// `kd_key` must be the same as the one given to `build_by_key`.
+    pub fn remove_if_by_key<Key, F>(&mut self, pred: impl FnMut(&T) -> bool, kd_key: F) -> Vec<T>
     where
         Key: Ord,
         F: Fn(&T, usize) -> Key + Copy,
     {
-        Self::build_by(items, |item1, item2, k| {
+        self.remove_if_by(pred, |item1, item2, k| {
             kd_key(item1, k).cmp(&kd_key(item2, k))
         })
     }
 
+    /// Removes the points which satisfy `pred` from a kd-tree built by `build_by_ordered_float`.
     /// # Example
     /// ```
-    /// use kd_tree::KdTree;
-    /// let kdtree: KdTree<[f64; 3]> = KdTree::build_by_ordered_float(vec![
-    ///     [1.0, 2.0, 3.0], [3.0, 1.0, 2.0], [2.0, 3.0, 1.0]
-    /// ]);
-    /// assert_eq!(kdtree.nearest(&[3.1, 0.9, 2.1]).unwrap().item, &[3.0, 1.0, 2.0]);
+    /// let mut kdtree = kd_tree::KdTree::build_by_ordered_float(vec![[1.0, 2.0], [3.0, 1.0], [2.0, 3.0]]);
+    /// let removed = kdtree.remove_if_by_ordered_float(|p| p[0] > 2.5);
+    /// assert_eq!(removed, vec![[3.0, 1.0]]);
+    /// assert_eq!(kdtree.nearest(&[3.0, 1.0]).unwrap().item, &[1.0, 2.0]);
     /// ```
-    pub fn build_by_ordered_float(points: Vec<T>) -> Self
+    pub fn remove_if_by_ordered_float(&mut self, pred: impl FnMut(&T) -> bool) -> Vec<T>
     where
         T: KdPoint<Dim = N>,
         T::Scalar: ordered_float::FloatCore,
     {
-        Self::build_by_key(points, |item, k| ordered_float::OrderedFloat(item.at(k)))
+        self.remove_if_by_key(pred, |item, k| ordered_float::OrderedFloat(item.at(k)))
+    }
+
+    /// Removes the points which satisfy `pred` from a kd-tree built by `build`.
+    pub fn remove_if(&mut self, pred: impl FnMut(&T) -> bool) -> Vec<T>
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: Ord,
+    {
+        self.remove_if_by_key(pred, |item, k| item.at(k))
+    }
+
+    /// Removes a point at the same position as `query` from a kd-tree built by `build_by_ordered_float`.
+    /// Returns `None` if there is no such point.
+    pub fn remove_by_ordered_float(
+        &mut self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<T>
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        // NaN is compared as in the build, so a NaN coordinate matches NaN only.
+        let index = self.position_of(query, |a, b| {
+            ordered_float::OrderedFloat(*a).cmp(&ordered_float::OrderedFloat(*b))
+        })?;
+        let mut i = 0;
+        self.remove_if_by_ordered_float(|_| {
+            i += 1;
+            i - 1 == index
+        })
+        .pop()
     }
 
+    /// Removes a point at the same position as `query` from a kd-tree built by `build`.
+    /// Returns `None` if there is no such point.
     /// # Example
     /// ```
-    /// use kd_tree::KdTree;
-    /// let kdtree: KdTree<[i32; 3]> = KdTree::build(vec![[1, 2, 3], [3, 1, 2], [2, 3, 1]]);
-    /// assert_eq!(kdtree.nearest(&[3, 1, 2]).unwrap().item, &[3, 1, 2]);
+    /// let mut kdtree = kd_tree::KdTree::build(vec![[1, 2], [3, 1], [2, 3]]);
+    /// assert_eq!(kdtree.remove(&[3, 1]), Some([3, 1]));
+    /// assert_eq!(kdtree.remove(&[3, 1]), None);
+    /// assert_eq!(kdtree.len(), 2);
     /// ```
-    pub fn build(points: Vec<T>) -> Self
+    pub fn remove(&mut self, query: &impl KdPoint<Scalar = T::Scalar, Dim = N>) -> Option<T>
     where
         T: KdPoint<Dim = N>,
         T::Scalar: Ord,
     {
-        Self::build_by_key(points, |item, k| item.at(k))
+        let index = self.position_of(query, Ord::cmp)?;
+        let mut i = 0;
+        self.remove_if(|_| {
+            i += 1;
+            i - 1 == index
+        })
+        .pop()
+    }
+
+    fn position_of(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        compare: impl Fn(&T::Scalar, &T::Scalar) -> Ordering + Copy,
+    ) -> Option<usize>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let found = self.within_by_cmp(|item, k| compare(&item.at(k), &query.at(k)));
+        found.first().map(|item| self.offset_of(item))
+    }
+}
+#[cfg(feature = "rkyv")]
+mod impl_rkyv {
+    use super::{ArchivedKdTreeN, ItemAndDistance, KdPoint, KdSliceN, Unsigned};
+    use rkyv::rancor::{Infallible, Strategy};
+    use rkyv::{Archive, Deserialize};
+
+    /// The archived form of [`super::KdTree`].
+    pub type ArchivedKdTree<T> = ArchivedKdTreeN<T, <T as KdPoint>::Dim>;
+
+    impl<T: Archive, N: Unsigned> ArchivedKdTreeN<T, N> {
+        /// Returns the archived items, in the same order as the original tree.
+        pub fn items(&self) -> &[T::Archived] {
+            self.1.as_slice()
+        }
+
+        /// Returns the archived items as a kd-tree without copying them,
+        /// which is queried by the `*_by` methods with a function to read the coordinates of the archived items.
+        /// # Example
+        /// ```
+        /// let kdtree = kd_tree::KdTree::build(vec![[1, 2], [3, 1], [2, 3]]);
+        /// let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&kdtree).unwrap();
+        /// let archived =
+        ///     rkyv::access::<kd_tree::ArchivedKdTree<[i32; 2]>, rkyv::rancor::Error>(&bytes).unwrap();
+        /// let found = archived.kdslice().nearest_by(&[3, 0], |item, k| item[k].to_native());
+        /// assert_eq!(found.unwrap().item, &[3, 1]);
+        /// ```
+        pub fn kdslice(&self) -> &KdSliceN<T::Archived, N> {
+            // SAFETY: the items are archived in the same order as the original tree, which is sorted as a kd-tree.
+            unsafe { KdSliceN::new_unchecked(self.items()) }
+        }
+    }
+
+    /// A point whose coordinates are read from its archived form directly, without deserializing the whole point.
+    pub trait ArchivedKdPoint: KdPoint + Archive {
+        fn archived_at(item: &Self::Archived, k: usize) -> Self::Scalar;
+    }
+
+    impl<S, const D: usize> ArchivedKdPoint for [S; D]
+    where
+        [S; D]: KdPoint<Scalar = S>,
+        S: Archive,
+        S::Archived: Deserialize<S, Strategy<(), Infallible>>,
+    {
+        fn archived_at(item: &[S::Archived; D], k: usize) -> S {
+            item[k]
+                .deserialize(Strategy::<(), Infallible>::wrap(&mut ()))
+                .unwrap_or_else(|e| match e {})
+        }
+    }
+
+    /// The queries of the archived points, whose coordinates are read by [`ArchivedKdPoint::archived_at`].
+    impl<T: ArchivedKdPoint<Dim = N>, N: Unsigned> ArchivedKdTreeN<T, N> {
+        /// Returns the nearest item from the input point. Returns `None` if the tree is empty.
+        /// # Example
+        /// ```
+        /// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![[1.0, 2.0], [3.0, 1.0], [2.0, 3.0]]);
+        /// let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&kdtree).unwrap();
+        /// let archived =
+        ///     rkyv::access::<kd_tree::ArchivedKdTree<[f64; 2]>, rkyv::rancor::Error>(&bytes).unwrap();
+        /// assert_eq!(archived.nearest(&[3.1, 0.9]).unwrap().item, &[3.0, 1.0]);
+        /// assert_eq!(archived.within_radius(&[2.0, 2.0], 1.2).len(), 2);
+        /// ```
+        pub fn nearest(
+            &self,
+            query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        ) -> Option<ItemAndDistance<'_, T::Archived, T::Scalar>> {
+            self.kdslice().nearest_by(query, T::archived_at)
+        }
+
+        /// Returns kNN(k nearest neighbors) from the input point.
+        pub fn nearests(
+            &self,
+            query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+            num: usize,
+        ) -> Vec<ItemAndDistance<'_, T::Archived, T::Scalar>> {
+            self.kdslice().nearests_by(query, num, T::archived_at)
+        }
+
+        /// search points within a rectangular region.
+        pub fn within(
+            &self,
+            query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2],
+        ) -> Vec<&T::Archived> {
+            self.kdslice().within_by(query, T::archived_at)
+        }
+
+        /// search points within a sphere.
+        pub fn within_radius(
+            &self,
+            query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+            radius: T::Scalar,
+        ) -> Vec<&T::Archived> {
+            self.kdslice()
+                .within_radius_by(query, radius, T::archived_at)
+        }
     }
 }
 #[cfg(feature = "serde")]
 mod impl_serde {
-    use super::{KdTreeN, PhantomData, Unsigned};
+    use super::{KdPoint, KdSliceN, KdTreeN, PhantomData, Unsigned};
     impl<T: serde::Serialize, N: Unsigned> serde::Serialize for KdTreeN<T, N> {
         fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
             self.1.serialize(serializer)
@@ -535,6 +2369,58 @@ mod impl_serde {
             Vec::<T>::deserialize(deserializer).map(|items| Self(PhantomData, items))
         }
     }
+
+    /// Serializes only the keys of a [`crate::KdMap`], as a sequence in the order of the entries.
+    /// Returned by [`KdSliceN::keys_only`].
+    pub struct KeysOnly<'a, P, T>(&'a [(P, T)]);
+    impl<P: serde::Serialize, T> serde::Serialize for KeysOnly<'_, P, T> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.0.iter().map(|(key, _)| key))
+        }
+    }
+
+    /// Serializes only the values of a [`crate::KdMap`], as a sequence in the order of the entries.
+    /// Returned by [`KdSliceN::values_only`].
+    pub struct ValuesOnly<'a, P, T>(&'a [(P, T)]);
+    impl<P, T: serde::Serialize> serde::Serialize for ValuesOnly<'_, P, T> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.0.iter().map(|(_, value)| value))
+        }
+    }
+
+    impl<P: KdPoint, T> KdSliceN<(P, T), P::Dim> {
+        /// Returns a serializable view of the keys only, for the values which can be rebuilt from the keys.
+        /// Deserialize it as `Vec<P>`, and re-associate the values by [`KdTreeN::from_keys_with`].
+        /// # Example
+        /// ```
+        /// let kdmap = kd_tree::KdMap::build(vec![([1, 2], "foo"), ([3, 4], "bar")]);
+        /// let json = serde_json::to_string(&kdmap.keys_only()).unwrap();
+        /// let keys: Vec<[i32; 2]> = serde_json::from_str(&json).unwrap();
+        /// let rebuilt = kd_tree::KdMap::from_keys_with(keys, |key| if key[0] == 1 { "foo" } else { "bar" }).unwrap();
+        /// assert_eq!(rebuilt, kdmap);
+        /// ```
+        pub fn keys_only(&self) -> KeysOnly<'_, P, T> {
+            KeysOnly(self.items())
+        }
+
+        /// Returns a serializable view of the values only, for the keys which are stored elsewhere.
+        /// Deserialize it as `Vec<T>`, and re-associate it with the keys by [`KdTreeN::from_keys_and_values`].
+        /// # Example
+        /// ```
+        /// let kdmap = kd_tree::KdMap::build(vec![([1, 2], "foo"), ([3, 4], "bar")]);
+        /// let keys = serde_json::to_string(&kdmap.keys_only()).unwrap();
+        /// let values = serde_json::to_string(&kdmap.values_only()).unwrap();
+        /// let rebuilt = kd_tree::KdMap::<[i32; 2], String>::from_keys_and_values(
+        ///     serde_json::from_str(&keys).unwrap(),
+        ///     serde_json::from_str(&values).unwrap(),
+        /// )
+        /// .unwrap();
+        /// assert_eq!(rebuilt.nearest(&[3, 3]).unwrap().item.1, "bar");
+        /// ```
+        pub fn values_only(&self) -> ValuesOnly<'_, P, T> {
+            ValuesOnly(self.items())
+        }
+    }
 }
 #[cfg(feature = "rayon")]
 impl<T: Send, N: Unsigned> KdTreeN<T, N> {
@@ -645,7 +2531,7 @@ impl<'a, T, N: Unsigned> KdIndexTreeN<'a, T, N> {
         &self,
         query: &Q,
         coord: impl Fn(&T, usize) -> Q::Scalar + Copy,
-    ) -> Option<ItemAndDistance<usize, Q::Scalar>> {
+    ) -> Option<ItemAndDistance<'_, usize, Q::Scalar>> {
         self.kdtree
             .nearest_by(query, |&index, k| coord(&self.source[index], k))
     }
@@ -659,7 +2545,7 @@ impl<'a, T, N: Unsigned> KdIndexTreeN<'a, T, N> {
     pub fn nearest(
         &self,
         query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
-    ) -> Option<ItemAndDistance<usize, T::Scalar>>
+    ) -> Option<ItemAndDistance<'_, usize, T::Scalar>>
     where
         T: KdPoint<Dim = N>,
     {
@@ -671,7 +2557,7 @@ impl<'a, T, N: Unsigned> KdIndexTreeN<'a, T, N> {
         query: &Q,
         num: usize,
         coord: impl Fn(&T, usize) -> Q::Scalar + Copy,
-    ) -> Vec<ItemAndDistance<usize, Q::Scalar>> {
+    ) -> Vec<ItemAndDistance<'_, usize, Q::Scalar>> {
         self.kdtree
             .nearests_by(query, num, |&index, k| coord(&self.source[index], k))
     }
@@ -690,13 +2576,86 @@ impl<'a, T, N: Unsigned> KdIndexTreeN<'a, T, N> {
         &self,
         query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
         num: usize,
-    ) -> Vec<ItemAndDistance<usize, T::Scalar>>
+    ) -> Vec<ItemAndDistance<'_, usize, T::Scalar>>
     where
         T: KdPoint<Dim = N>,
     {
         self.nearests_by(query, num, |item, k| item.at(k))
     }
 
+    /// Same as [`KdSliceN::nearest_with`], i.e. `kd_difference(item, k)` is the difference `query[k] - item[k]`.
+    /// # Example
+    /// ```
+    /// let items: Vec<[i32; 3]> = vec![[1, 2, 3], [3, 1, 2], [2, 3, 1]];
+    /// let kdtree = kd_tree::KdIndexTree3::build(&items);
+    /// let key = [3, 1, 2];
+    /// assert_eq!(kdtree.nearest_with(|p, k| key[k] - p[k]).unwrap().item, &1);
+    /// ```
+    pub fn nearest_with<Scalar>(
+        &self,
+        kd_difference: impl Fn(&T, usize) -> Scalar + Copy,
+    ) -> Option<ItemAndDistance<'_, usize, Scalar>>
+    where
+        Scalar: KdScalar,
+    {
+        self.kdtree
+            .nearest_with(|&index, k| kd_difference(&self.source[index], k))
+    }
+
+    /// Same as [`KdSliceN::nearests_with`].
+    pub fn nearests_with<Scalar>(
+        &self,
+        num: usize,
+        kd_difference: impl Fn(&T, usize) -> Scalar + Copy,
+    ) -> Vec<ItemAndDistance<'_, usize, Scalar>>
+    where
+        Scalar: KdScalar,
+    {
+        self.kdtree
+            .nearests_with(num, |&index, k| kd_difference(&self.source[index], k))
+    }
+
+    /// Same as [`KdSliceN::within_radius_with`].
+    pub fn within_radius_with<Scalar>(
+        &self,
+        radius: Scalar,
+        kd_difference: impl Fn(&T, usize) -> Scalar + Copy,
+    ) -> Vec<&usize>
+    where
+        Scalar: KdScalar,
+    {
+        self.kdtree
+            .within_radius_with(radius, |&index, k| kd_difference(&self.source[index], k))
+    }
+
+    /// Same as [`Self::nearests`], but with the tuning parameters of [`QueryConfig`].
+    /// # Example
+    /// ```
+    /// use kd_tree::{KdIndexTree, QueryConfig};
+    /// let items: Vec<[i32; 3]> = vec![[1, 2, 3], [3, 1, 2], [2, 3, 1], [3, 2, 2]];
+    /// let kdtree = KdIndexTree::build(&items);
+    /// let config = QueryConfig {
+    ///     leaf_size: 8,
+    ///     ..Default::default()
+    /// };
+    /// let nearests = kdtree.nearests_with_config(&[3, 1, 2], 2, &config).result;
+    /// assert_eq!(nearests[0].item, &1);
+    /// assert_eq!(nearests[1].item, &3);
+    /// ```
+    pub fn nearests_with_config(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+        config: &QueryConfig,
+    ) -> PartialResult<Vec<ItemAndDistance<'_, usize, T::Scalar>>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        kd_nearests_by_with_config(self.kdtree.items(), query, num, config, |&index, k| {
+            self.source[index].at(k)
+        })
+    }
+
     pub fn within_by_cmp(&self, compare: impl Fn(&T, usize) -> Ordering + Copy) -> Vec<&usize> {
         self.kdtree
             .within_by_cmp(|&index, k| compare(&self.source[index], k))
@@ -738,6 +2697,71 @@ impl<'a, T, N: Unsigned> KdIndexTreeN<'a, T, N> {
     {
         self.within_radius_by(query, radius, |item, k| item.at(k))
     }
+
+    /// Returns the sum of weights of the items within a rectangular region.
+    pub fn weight_within(&self, query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2]) -> T::Scalar
+    where
+        T: WeightedKdPoint<Dim = N>,
+    {
+        sum_weights(self.within(query).into_iter().map(|&i| self.item(i)))
+    }
+
+    /// Returns the sum of weights of the items within k-dimensional sphere.
+    pub fn weight_within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> T::Scalar
+    where
+        T: WeightedKdPoint<Dim = N>,
+    {
+        sum_weights(
+            self.within_radius(query, radius)
+                .into_iter()
+                .map(|&i| self.item(i)),
+        )
+    }
+
+    /// Returns the sparse matrix of squared distances between all pairs of items closer than `radius`.
+    /// Rows and columns are the indices of the source items, and the diagonal is not stored.
+    pub fn distance_matrix_within_radius(&self, radius: T::Scalar) -> CsrMatrix<T::Scalar>
+    where
+        T: KdPoint<Dim = N>,
+        N: generic_array::ArrayLength,
+    {
+        CsrMatrix::from_rows(self.source.iter().enumerate().map(|(i, item)| {
+            self.within_radius(item, radius)
+                .into_iter()
+                .filter(|&&j| j != i)
+                .map(|&j| (j, squared_distance(item, &self.source[j])))
+                .collect()
+        }))
+    }
+
+    /// Returns the kNN graph, whose `i`-th row holds the indices of the `k` nearest other source items of the `i`-th source item
+    /// and their squared distances.
+    ///
+    /// The items are queried in the order of the tree, so that each query is bounded by the result of the previous one.
+    pub fn knn_graph(&self, k: usize) -> CsrMatrix<T::Scalar>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let mut rows = vec![Vec::new(); self.source.len()];
+        kd_knn_graph_by(
+            self.kdtree.items(),
+            k,
+            0..self.kdtree.len(),
+            |&i| &self.source[i],
+            |&i, k| self.source[i].at(k),
+            |i, found| {
+                rows[self.kdtree[i]] = found
+                    .into_iter()
+                    .map(|found| (*found.item, found.squared_distance))
+                    .collect()
+            },
+        );
+        CsrMatrix::from_rows(rows)
+    }
 }
 #[cfg(feature = "rayon")]
 impl<'a, T: Sync, N: Unsigned> KdIndexTreeN<'a, T, N> {
@@ -778,6 +2802,98 @@ impl<'a, T: Sync, N: Unsigned> KdIndexTreeN<'a, T, N> {
     {
         Self::par_build_by_key(points, |item, k| item.at(k))
     }
+
+    /// Computes the kNN graph of the source items in parallel, without materializing the whole graph.
+    ///
+    /// The rows are computed in chunks whose entries fit in about `memory_budget` bytes,
+    /// and `f` is called for each chunk in order, with the index of the first row and the chunk as a [`CsrMatrix`].
+    /// The `i`-th row of the graph holds the indices of the `k` nearest other items of the `i`-th source item and their squared distances.
+    /// # Example
+    /// ```
+    /// let points = vec![[0, 0], [1, 0], [3, 0], [7, 0]];
+    /// let kdtree = kd_tree::KdIndexTree::build(&points);
+    /// let mut rows = Vec::new();
+    /// kdtree.par_knn_graph_chunked(1, 32, |first_row, chunk| {
+    ///     assert_eq!(first_row, rows.len());
+    ///     rows.extend((0..chunk.num_rows()).map(|i| chunk.row(i).0.to_vec()));
+    /// });
+    /// assert_eq!(rows, vec![vec![1], vec![0], vec![1], vec![2]]);
+    /// ```
+    pub fn par_knn_graph_chunked(
+        &self,
+        k: usize,
+        memory_budget: usize,
+        mut f: impl FnMut(usize, CsrMatrix<T::Scalar>),
+    ) where
+        T: KdPoint<Dim = N>,
+        T::Scalar: Send,
+        N: Sync,
+    {
+        use rayon::prelude::*;
+        let row_size = k.max(1) * (std::mem::size_of::<usize>() + std::mem::size_of::<T::Scalar>());
+        let rows_per_chunk = (memory_budget / row_size).max(1);
+        for first_row in (0..self.source.len()).step_by(rows_per_chunk) {
+            let last_row = (first_row + rows_per_chunk).min(self.source.len());
+            let rows: Vec<Vec<_>> = (first_row..last_row)
+                .into_par_iter()
+                .map(|i| {
+                    // the item itself is excluded by the filter, as in `knn_graph`.
+                    kd_nearests_by_filtered(
+                        self.kdtree.items(),
+                        &self.source[i],
+                        k,
+                        &QueryConfig::default(),
+                        |&j, k| self.source[j].at(k),
+                        None,
+                        |&j| j != i,
+                    )
+                    .result
+                    .into_iter()
+                    .map(|found| (*found.item, found.squared_distance))
+                    .collect()
+                })
+                .collect();
+            f(first_row, CsrMatrix::from_rows(rows));
+        }
+    }
+}
+
+/// The origin, used as the query point of the `*_with` queries,
+/// in which the coordinates of an item are given as the negated differences from the query.
+struct Origin<Scalar, N>(PhantomData<(Scalar, N)>);
+
+impl<Scalar, N> Origin<Scalar, N> {
+    fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<Scalar: KdScalar, N: Unsigned> KdPoint for Origin<Scalar, N> {
+    type Scalar = Scalar;
+    type Dim = N;
+    fn at(&self, _: usize) -> Scalar {
+        Scalar::zero()
+    }
+}
+
+fn squared_distance<T: KdPoint>(
+    p1: &T,
+    p2: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+) -> T::Scalar {
+    let mut squared_distance = <T::Scalar as KdScalar>::zero();
+    for k in 0..T::dim() {
+        let diff = p1.at(k) - p2.at(k);
+        squared_distance += diff * diff;
+    }
+    squared_distance
+}
+
+fn sum_weights<'a, T: WeightedKdPoint + 'a>(items: impl IntoIterator<Item = &'a T>) -> T::Scalar {
+    let mut sum = <T::Scalar as KdScalar>::zero();
+    for item in items {
+        sum += item.weight();
+    }
+    sum
 }
 
 macro_rules! define_kdtree_aliases {
@@ -797,7 +2913,7 @@ macro_rules! impl_kd_points {
     ($($len:literal),*) => {
         $(
             paste::paste!{
-                impl<T: num_traits::NumAssign + Copy + PartialOrd> KdPoint for [T; $len] {
+                impl<T: KdScalar> KdPoint for [T; $len] {
                     type Scalar = T;
                     type Dim = typenum::[<U $len>];
                     fn at(&self, i: usize) -> T { self[i] }
@@ -808,6 +2924,14 @@ macro_rules! impl_kd_points {
 }
 impl_kd_points!(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16);
 
+impl<P: KdPoint + ?Sized> KdPoint for &P {
+    type Scalar = P::Scalar;
+    type Dim = P::Dim;
+    fn at(&self, k: usize) -> Self::Scalar {
+        (**self).at(k)
+    }
+}
+
 impl<P: KdPoint, T> KdPoint for (P, T) {
     type Scalar = P::Scalar;
     type Dim = P::Dim;
@@ -838,3 +2962,191 @@ pub type KdMap<P, T> = KdTree<(P, T)>;
 /// assert_eq!(kdmap.nearest(&[3, 1, 2]).unwrap().item.1, "buzz");
 /// ```
 pub type KdMapSlice<P, T> = KdSlice<(P, T)>;
+impl<P: KdPoint, T> KdTreeN<(P, T), P::Dim> {
+    /// Builds a [`KdMap`] in which entries with identical keys are merged into one entry.
+    /// `merge` is called with the values of two entries sharing the same key and returns the combined value.
+    /// # Example
+    /// ```
+    /// let kdmap: kd_tree::KdMap<[i32; 2], usize> = kd_tree::KdMap::build_merging(
+    ///     vec![([1, 2], 1), ([3, 4], 10), ([1, 2], 100)],
+    ///     |v1, v2| v1 + v2,
+    /// );
+    /// assert_eq!(kdmap.len(), 2);
+    /// assert_eq!(kdmap.nearest(&[1, 2]).unwrap().item.1, 101);
+    /// ```
+    pub fn build_merging(items: Vec<(P, T)>, merge: impl FnMut(T, T) -> T) -> Self
+    where
+        P::Scalar: Ord,
+    {
+        Self::build_merging_by_key(items, |key, k| key.at(k), merge)
+    }
+
+    /// Same as [`Self::build_merging`], but for floating point keys.
+    /// # Example
+    /// ```
+    /// let kdmap: kd_tree::KdMap<[f64; 2], Vec<&str>> = kd_tree::KdMap::build_merging_by_ordered_float(
+    ///     vec![([1.0, 2.0], vec!["foo"]), ([3.0, 4.0], vec!["bar"]), ([1.0, 2.0], vec!["buzz"])],
+    ///     |mut v1, v2| { v1.extend(v2); v1 },
+    /// );
+    /// assert_eq!(kdmap.len(), 2);
+    /// assert_eq!(kdmap.nearest(&[1.1, 2.1]).unwrap().item.1, vec!["foo", "buzz"]);
+    /// ```
+    pub fn build_merging_by_ordered_float(items: Vec<(P, T)>, merge: impl FnMut(T, T) -> T) -> Self
+    where
+        P::Scalar: ordered_float::FloatCore,
+    {
+        Self::build_merging_by_key(
+            items,
+            |key, k| ordered_float::OrderedFloat(key.at(k)),
+            merge,
+        )
+    }
+
+    /// Same as [`Self::build_merging`], but two keys are regarded as identical when `kd_key` returns equal values on every axis.
+    pub fn build_merging_by_key<Key, F>(
+        mut items: Vec<(P, T)>,
+        kd_key: F,
+        mut merge: impl FnMut(T, T) -> T,
+    ) -> Self
+    where
+        Key: Ord,
+        F: Fn(&P, usize) -> Key + Copy,
+    {
+        let compare = |p1: &P, p2: &P| {
+            (0..P::dim())
+                .map(|k| kd_key(p1, k).cmp(&kd_key(p2, k)))
+                .find(|ord| ord.is_ne())
+                .unwrap_or(Ordering::Equal)
+        };
+        items.sort_by(|item1, item2| compare(&item1.0, &item2.0));
+        let mut merged: Vec<(P, T)> = Vec::with_capacity(items.len());
+        for (key, value) in items {
+            match merged.last() {
+                Some(last) if compare(&last.0, &key).is_eq() => {
+                    let (last_key, last_value) = merged.pop().unwrap();
+                    merged.push((last_key, merge(last_value, value)));
+                }
+                _ => merged.push((key, value)),
+            }
+        }
+        Self::build_by_key(merged, |item, k| kd_key(&item.0, k))
+    }
+
+    /// Same as [`Self::build_merging_by_ordered_float`], but entries whose keys are within `tolerance` are merged as well.
+    ///
+    /// The tolerance is inclusive, so a `tolerance` of zero merges the entries with identical keys only.
+    /// The entries are merged greedily in the order of `items`: each key which is not merged yet keeps its entry,
+    /// and the values of the following entries within `tolerance` from the key are merged into it in that order.
+    /// So every merged key is within `tolerance` from the kept key, but two merged keys may be farther apart than that,
+    /// and the result depends on the order of `items`.
+    /// The keys are ordered by [`ordered_float::OrderedFloat`], so a key with NaN is never merged.
+    /// # Example
+    /// ```
+    /// let kdmap: kd_tree::KdMap<[f64; 2], usize> = kd_tree::KdMap::build_merging_within(
+    ///     vec![([1.0, 2.0], 1), ([3.0, 4.0], 10), ([1.05, 2.0], 100)],
+    ///     0.1,
+    ///     |v1, v2| v1 + v2,
+    /// );
+    /// assert_eq!(kdmap.len(), 2);
+    /// assert_eq!(kdmap.nearest(&[1.0, 2.0]).unwrap().item, &([1.0, 2.0], 101));
+    /// ```
+    pub fn build_merging_within(
+        items: Vec<(P, T)>,
+        tolerance: P::Scalar,
+        mut merge: impl FnMut(T, T) -> T,
+    ) -> Self
+    where
+        P::Scalar: ordered_float::FloatCore,
+    {
+        use ordered_float::OrderedFloat;
+        // the offset of the kept entry which each entry is merged into.
+        let mut kept = vec![usize::MAX; items.len()];
+        {
+            let kdtree = KdIndexTreeN::<(P, T), P::Dim>::build_by_key(&items, |item, k| {
+                OrderedFloat(item.0.at(k))
+            });
+            let squared_tolerance = tolerance * tolerance;
+            for i in 0..items.len() {
+                if kept[i] != usize::MAX {
+                    continue;
+                }
+                let key = &items[i].0;
+                let found = kdtree.within_by_cmp(|item, k| {
+                    let x = OrderedFloat(item.0.at(k));
+                    if x < OrderedFloat(key.at(k) - tolerance) {
+                        Ordering::Less
+                    } else if x > OrderedFloat(key.at(k) + tolerance) {
+                        Ordering::Greater
+                    } else {
+                        Ordering::Equal
+                    }
+                });
+                for &j in found {
+                    if kept[j] == usize::MAX
+                        && squared_distance(&items[j].0, key) <= squared_tolerance
+                    {
+                        kept[j] = i;
+                    }
+                }
+                kept[i] = i;
+            }
+        }
+        // an entry is always merged into a preceding one, which is visited first.
+        let mut merged: Vec<Option<(P, T)>> = Vec::with_capacity(items.len());
+        for (j, (key, value)) in items.into_iter().enumerate() {
+            let i = kept[j];
+            if i == j {
+                merged.push(Some((key, value)));
+            } else {
+                let (key, last) = merged[i].take().unwrap();
+                merged[i] = Some((key, merge(last, value)));
+                merged.push(None);
+            }
+        }
+        Self::build_by_key(merged.into_iter().flatten().collect(), |item, k| {
+            OrderedFloat(item.0.at(k))
+        })
+    }
+
+    /// Re-associates values with `keys`, which are in the order of the keys of a [`KdMap`],
+    /// e.g. deserialized from [`KdSliceN::keys_only`]. The keys are not sorted again,
+    /// but verified by [`KdSliceN::check_order`], and an error is returned if they are out of order.
+    /// # Example
+    /// ```
+    /// let kdmap = kd_tree::KdMap::build(vec![([1, 2], 1), ([3, 4], 2)]);
+    /// let keys: Vec<[i32; 2]> = kdmap.iter().map(|(key, _)| *key).collect();
+    /// let rebuilt = kd_tree::KdMap::from_keys_with(keys, |key| key[0] + key[1]).unwrap();
+    /// assert_eq!(rebuilt.nearest(&[3, 3]).unwrap().item, &([3, 4], 7));
+    /// assert!(kd_tree::KdMap::from_keys_with(vec![[3, 4], [1, 2], [0, 0]], |_| 0).is_err());
+    /// ```
+    pub fn from_keys_with(
+        keys: Vec<P>,
+        mut value: impl FnMut(&P) -> T,
+    ) -> Result<Self, KdOrderError> {
+        let keys = KdTreeN::<P, P::Dim>::try_from_sorted(keys)?.into_vec();
+        let items = keys
+            .into_iter()
+            .map(|key| {
+                let value = value(&key);
+                (key, value)
+            })
+            .collect();
+        Ok(Self(PhantomData, items))
+    }
+
+    /// Re-associates `values` with `keys`, both of which are in the order of the entries of a [`KdMap`],
+    /// e.g. deserialized from [`KdSliceN::keys_only`] and [`KdSliceN::values_only`]. The keys are not sorted again,
+    /// but verified by [`KdSliceN::check_order`], and an error is returned if they are out of order.
+    ///
+    /// # Panics
+    /// Panics if `keys` and `values` have different lengths.
+    pub fn from_keys_and_values(keys: Vec<P>, values: Vec<T>) -> Result<Self, KdOrderError> {
+        assert_eq!(
+            keys.len(),
+            values.len(),
+            "keys and values must have the same length"
+        );
+        let keys = KdTreeN::<P, P::Dim>::try_from_sorted(keys)?.into_vec();
+        Ok(Self(PhantomData, keys.into_iter().zip(values).collect()))
+    }
+}
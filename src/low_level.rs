@@ -0,0 +1,33 @@
+//! Low-level functions operating on a plain slice sorted as a kd-tree.
+//!
+//! [`crate::KdSliceN`] and [`crate::KdTreeN`] are thin wrappers around these functions.
+//! They are useful when the items live in a buffer you don't want to wrap, e.g. a field of your own struct,
+//! or when the coordinates are accessed through a closure rather than [`KdPoint`](crate::KdPoint).
+//!
+//! The slice passed to the queries must be sorted by [`kd_sort_by`] with the same dimension and coordinates,
+//! otherwise the results are unspecified (but never undefined behavior).
+//! None of these functions panic on an empty slice: the nearest neighbor queries return `None`,
+//! and the others return an empty `Vec`.
+//! # Example
+//! ```
+//! use kd_tree::low_level::{kd_nearest, kd_nearests, kd_sort_by};
+//! let mut points = vec![[1, 2, 3], [3, 1, 2], [2, 3, 1]];
+//! kd_sort_by(&mut points, 3, |a, b, k| a[k].cmp(&b[k]));
+//! assert_eq!(kd_nearest(&points, &[3, 1, 2]).unwrap().item, &[3, 1, 2]);
+//! assert_eq!(kd_nearests(&points, &[3, 1, 2], 2).len(), 2);
+//! assert!(kd_nearest(&points[..0], &[3, 1, 2]).is_none());
+//! ```
+#[cfg(feature = "rayon")]
+pub use crate::sort::kd_par_sort_by;
+pub use crate::{
+    nearest::{
+        kd_nearest, kd_nearest_by, kd_nearest_by_with_config, kd_nearest_by_with_progress,
+        kd_nearest_with,
+    },
+    nearests::{kd_nearests, kd_nearests_by, kd_nearests_by_filtered, kd_nearests_by_with_config},
+    sort::{kd_find_unsorted_by, kd_insert_by, kd_remove_by, kd_sort_by},
+    within::{
+        kd_for_each_within_by_cmp, kd_for_each_within_by_predicates, kd_within_by_cmp,
+        kd_within_by_cmp_with_config,
+    },
+};
@@ -0,0 +1,188 @@
+use crate::augment::per_subtree;
+use crate::nearests::kd_nearests_by_pruned;
+use crate::{AxisRanges, ItemAndDistance, KdPoint, KdScalar, KdSliceN, KdTreeN, QueryConfig};
+use typenum::Unsigned;
+
+/// An owned kd-tree with user-defined metadata per subtree, such as the maximum intensity or a histogram of classes,
+/// which is computed at build time and used to prune subtrees in the traversal.
+/// # Example
+/// ```
+/// use kd_tree::{KdMap, KdTreeMeta};
+/// // points with classes, and the set of the classes in each subtree as a bit mask.
+/// let kdmap: KdMap<[i32; 2], u8> = KdMap::build(vec![([0, 0], 1), ([1, 0], 2), ([0, 1], 1), ([5, 5], 3)]);
+/// let kdtree: KdTreeMeta<_, u32> = KdTreeMeta::from_fold(kdmap, 0, |mask, (_, class)| mask | 1 << class);
+/// assert_eq!(kdtree.metadata(), Some(&0b1110));
+/// // the nearest point of class 3, skipping the subtrees without it.
+/// let found = kdtree.nearest_by_metadata(&[0, 0], |mask| mask & 1 << 3 == 0, |(_, class)| *class == 3);
+/// assert_eq!(found.unwrap().item, &([5, 5], 3));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct KdTreeMetaN<T, N: Unsigned, M> {
+    kdtree: KdTreeN<T, N>,
+    /// The metadata of each subtree, stored at the position of the median item of the subtree.
+    metadata: Vec<M>,
+}
+pub type KdTreeMeta<T, M> = KdTreeMetaN<T, <T as KdPoint>::Dim, M>;
+
+impl<T, N: Unsigned, M> KdTreeMetaN<T, N, M> {
+    /// Computes the metadata of each subtree by `f` with its items, which are `O(n log n)` items in total.
+    pub fn new(kdtree: KdTreeN<T, N>, f: impl FnMut(&[T]) -> M) -> Self {
+        let metadata = per_subtree(&kdtree, f);
+        Self { kdtree, metadata }
+    }
+
+    /// Computes the metadata of each subtree by folding its items with `f`, starting from `init`.
+    pub fn from_fold(kdtree: KdTreeN<T, N>, init: M, mut f: impl FnMut(M, &T) -> M) -> Self
+    where
+        M: Clone,
+    {
+        Self::new(kdtree, |items| items.iter().fold(init.clone(), &mut f))
+    }
+
+    pub fn kdtree(&self) -> &KdSliceN<T, N> {
+        &self.kdtree
+    }
+
+    pub fn into_kdtree(self) -> KdTreeN<T, N> {
+        self.kdtree
+    }
+
+    /// Returns the metadata of the whole tree. Returns `None` if the tree is empty.
+    pub fn metadata(&self) -> Option<&M> {
+        self.metadata.get(self.metadata.len() / 2)
+    }
+
+    /// Visits the subtrees in pre-order with their items, split axes and metadata,
+    /// descending into the children of a subtree only if `visitor` returns `true`.
+    ///
+    /// The split item of a subtree is `items[items.len() / 2]`,
+    /// and the items before and after it are the lower and the upper subtrees respectively.
+    pub fn visit(&self, mut visitor: impl FnMut(&[T], usize, &M) -> bool) {
+        fn recurse<T, M>(
+            items: &[T],
+            metadata: &[M],
+            axis: usize,
+            dim: usize,
+            visitor: &mut impl FnMut(&[T], usize, &M) -> bool,
+        ) {
+            if items.is_empty() {
+                return;
+            }
+            let mid = items.len() / 2;
+            if visitor(items, axis, &metadata[mid]) {
+                let next = (axis + 1) % dim;
+                recurse(&items[..mid], &metadata[..mid], next, dim, visitor);
+                recurse(&items[mid + 1..], &metadata[mid + 1..], next, dim, visitor);
+            }
+        }
+        recurse(&self.kdtree, &self.metadata, 0, N::to_usize(), &mut visitor);
+    }
+}
+
+impl<T: KdPoint<Dim = N>, N: Unsigned, M> KdTreeMetaN<T, N, M> {
+    /// Returns the nearest item accepted by `filter` from the input point, skipping the subtrees whose metadata satisfies `skip`.
+    /// `skip` must return `true` only for the subtrees without any items accepted by `filter`.
+    pub fn nearest_by_metadata(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        skip: impl Fn(&M) -> bool,
+        filter: impl Fn(&T) -> bool,
+    ) -> Option<ItemAndDistance<'_, T, T::Scalar>> {
+        self.nearests_by_metadata(query, 1, skip, filter).pop()
+    }
+
+    /// Returns kNN(k nearest neighbors) of the items accepted by `filter`, skipping the subtrees whose metadata satisfies `skip`.
+    /// See [`Self::nearest_by_metadata`].
+    pub fn nearests_by_metadata(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+        skip: impl Fn(&M) -> bool,
+        filter: impl Fn(&T) -> bool,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>> {
+        kd_nearests_by_pruned(
+            &self.kdtree,
+            query,
+            num,
+            &QueryConfig::default(),
+            |item, k| item.at(k),
+            None,
+            filter,
+            &AxisRanges::new(),
+            |subtree| skip(&self.metadata[self.kdtree.offset_of(&subtree[subtree.len() / 2])]),
+        )
+        .result
+    }
+
+    /// Searches the items accepted by `filter` within a sphere, skipping the subtrees whose metadata satisfies `skip`.
+    /// See [`Self::nearest_by_metadata`].
+    pub fn within_radius_by_metadata(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+        skip: impl Fn(&M) -> bool,
+        filter: impl Fn(&T) -> bool,
+    ) -> Vec<&T> {
+        #[allow(clippy::too_many_arguments)]
+        fn recurse<'a, T: KdPoint, M>(
+            results: &mut Vec<&'a T>,
+            items: &'a [T],
+            metadata: &[M],
+            query: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+            squared_radius: T::Scalar,
+            axis: usize,
+            skip: &impl Fn(&M) -> bool,
+            filter: &impl Fn(&T) -> bool,
+        ) {
+            if items.is_empty() {
+                return;
+            }
+            let mid = items.len() / 2;
+            if skip(&metadata[mid]) {
+                return;
+            }
+            let item = &items[mid];
+            if crate::squared_distance(item, query) < squared_radius && filter(item) {
+                results.push(item);
+            }
+            let diff = query.at(axis) - item.at(axis);
+            let next = (axis + 1) % T::dim();
+            if diff < T::Scalar::zero() || diff * diff < squared_radius {
+                recurse(
+                    results,
+                    &items[..mid],
+                    &metadata[..mid],
+                    query,
+                    squared_radius,
+                    next,
+                    skip,
+                    filter,
+                );
+            }
+            if diff >= T::Scalar::zero() || diff * diff < squared_radius {
+                recurse(
+                    results,
+                    &items[mid + 1..],
+                    &metadata[mid + 1..],
+                    query,
+                    squared_radius,
+                    next,
+                    skip,
+                    filter,
+                );
+            }
+        }
+        let mut results = Vec::new();
+        recurse(
+            &mut results,
+            &self.kdtree,
+            &self.metadata,
+            query,
+            radius * radius,
+            0,
+            &skip,
+            &filter,
+        );
+        results
+    }
+}
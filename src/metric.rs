@@ -0,0 +1,286 @@
+use crate::{KdPoint, KdSliceN};
+use std::cmp::Ordering;
+use typenum::Unsigned;
+
+/// A distance function which is determined by per-axis differences, used by `*_with_metric` queries.
+///
+/// The distance between two points is `accumulate` of `axis_distance` of the differences on all axes, starting from zero.
+/// `accumulate` must be monotonic and not less than each of `axis_distance`,
+/// because `axis_distance` of the difference from a splitting plane is used to prune subtrees.
+pub trait Metric<Scalar> {
+    fn axis_distance(diff: Scalar) -> Scalar;
+    fn accumulate(distance: Scalar, axis_distance: Scalar) -> Scalar;
+
+    fn distance<P: KdPoint<Scalar = Scalar>, T>(
+        query: &P,
+        item: &T,
+        get: impl Fn(&T, usize) -> Scalar,
+    ) -> Scalar
+    where
+        Scalar: crate::KdScalar,
+    {
+        let mut distance = Scalar::zero();
+        for k in 0..P::dim() {
+            distance = Self::accumulate(distance, Self::axis_distance(query.at(k) - get(item, k)));
+        }
+        distance
+    }
+}
+
+fn abs<Scalar: crate::KdScalar>(x: Scalar) -> Scalar {
+    if x < Scalar::zero() {
+        Scalar::zero() - x
+    } else {
+        x
+    }
+}
+
+/// The squared euclidean distance, which is used by the queries without `_with_metric`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SquaredEuclidean;
+
+impl<Scalar: crate::KdScalar> Metric<Scalar> for SquaredEuclidean {
+    fn axis_distance(diff: Scalar) -> Scalar {
+        diff * diff
+    }
+    fn accumulate(distance: Scalar, axis_distance: Scalar) -> Scalar {
+        distance + axis_distance
+    }
+}
+
+/// The manhattan (L1) distance, `|dx| + |dy| + ...`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Manhattan;
+
+impl<Scalar: crate::KdScalar> Metric<Scalar> for Manhattan {
+    fn axis_distance(diff: Scalar) -> Scalar {
+        abs(diff)
+    }
+    fn accumulate(distance: Scalar, axis_distance: Scalar) -> Scalar {
+        distance + axis_distance
+    }
+}
+
+/// The chebyshev (L∞) distance, `max(|dx|, |dy|, ...)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Chebyshev;
+
+impl<Scalar: crate::KdScalar> Metric<Scalar> for Chebyshev {
+    fn axis_distance(diff: Scalar) -> Scalar {
+        abs(diff)
+    }
+    fn accumulate(distance: Scalar, axis_distance: Scalar) -> Scalar {
+        if axis_distance > distance {
+            axis_distance
+        } else {
+            distance
+        }
+    }
+}
+
+/// An item and its distance from the query, in the metric given to the query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ItemAndMetricDistance<'a, T, Scalar> {
+    pub item: &'a T,
+    pub distance: Scalar,
+}
+
+pub fn kd_nearests_by_metric<'a, M, T, P>(
+    kdtree: &'a [T],
+    query: &P,
+    num: usize,
+    get: impl Fn(&T, usize) -> P::Scalar + Copy,
+) -> Vec<ItemAndMetricDistance<'a, T, P::Scalar>>
+where
+    M: Metric<P::Scalar>,
+    P: KdPoint,
+{
+    fn recurse<'a, M, T, P>(
+        nearests: &mut Vec<ItemAndMetricDistance<'a, T, P::Scalar>>,
+        num: usize,
+        kdtree: &'a [T],
+        get: impl Fn(&T, usize) -> P::Scalar + Copy,
+        query: &P,
+        axis: usize,
+    ) where
+        M: Metric<P::Scalar>,
+        P: KdPoint,
+    {
+        let is_candidate = |nearests: &Vec<ItemAndMetricDistance<'a, T, P::Scalar>>, distance| {
+            nearests.len() < num || distance < nearests.last().unwrap().distance
+        };
+        let mid_idx = kdtree.len() / 2;
+        let item = &kdtree[mid_idx];
+        let distance = M::distance(query, item, get);
+        if is_candidate(nearests, distance) {
+            if nearests.len() == num {
+                nearests.pop();
+            }
+            let i = nearests
+                .binary_search_by(|item| {
+                    item.distance
+                        .partial_cmp(&distance)
+                        .unwrap_or(Ordering::Equal)
+                })
+                .unwrap_or_else(|i| i);
+            nearests.insert(i, ItemAndMetricDistance { item, distance });
+        }
+        let mid_pos = get(item, axis);
+        let [branch1, branch2] = if query.at(axis) < mid_pos {
+            [&kdtree[..mid_idx], &kdtree[mid_idx + 1..]]
+        } else {
+            [&kdtree[mid_idx + 1..], &kdtree[..mid_idx]]
+        };
+        if !branch1.is_empty() {
+            recurse::<M, _, _>(nearests, num, branch1, get, query, (axis + 1) % P::dim());
+        }
+        if !branch2.is_empty() && is_candidate(nearests, M::axis_distance(query.at(axis) - mid_pos))
+        {
+            recurse::<M, _, _>(nearests, num, branch2, get, query, (axis + 1) % P::dim());
+        }
+    }
+    let mut nearests = Vec::with_capacity(num);
+    if num != 0 && !kdtree.is_empty() {
+        recurse::<M, _, _>(&mut nearests, num, kdtree, get, query, 0);
+    }
+    nearests
+}
+
+impl<T, N: Unsigned> KdSliceN<T, N> {
+    /// Returns the nearest item from the input point in the metric `M`. Returns `None` if `self.is_empty()`.
+    /// # Example
+    /// ```
+    /// use kd_tree::{KdTree, Manhattan};
+    /// let kdtree = KdTree::build(vec![[3, 3], [0, 5]]);
+    /// // [3, 3] is the nearest in euclidean distance, but [0, 5] is the nearest in manhattan distance.
+    /// assert_eq!(kdtree.nearest(&[0, 0]).unwrap().item, &[3, 3]);
+    /// assert_eq!(kdtree.nearest_with_metric::<Manhattan>(&[0, 0]).unwrap().item, &[0, 5]);
+    /// ```
+    pub fn nearest_with_metric<M: Metric<T::Scalar>>(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<ItemAndMetricDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        kd_nearests_by_metric::<M, _, _>(self.items(), query, 1, |item, k| item.at(k)).pop()
+    }
+
+    /// Returns kNN(k nearest neighbors) from the input point in the metric `M`.
+    pub fn nearests_with_metric<M: Metric<T::Scalar>>(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+    ) -> Vec<ItemAndMetricDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        kd_nearests_by_metric::<M, _, _>(self.items(), query, num, |item, k| item.at(k))
+    }
+
+    /// search points whose distance from the input point in the metric `M` is less than `radius`.
+    pub fn within_radius_with_metric<M: Metric<T::Scalar>>(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let mut results = self.within_by_cmp(|item, k| {
+            let diff = item.at(k) - query.at(k);
+            if M::axis_distance(diff) < radius {
+                Ordering::Equal
+            } else if diff < <T::Scalar as crate::KdScalar>::zero() {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        });
+        results.retain(|item| M::distance(query, *item, |item, k| item.at(k)) < radius);
+        results
+    }
+
+    /// Returns the nearest item from the input point in manhattan (L1) distance.
+    pub fn nearest_manhattan(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<ItemAndMetricDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.nearest_with_metric::<Manhattan>(query)
+    }
+
+    /// Returns kNN(k nearest neighbors) from the input point in manhattan (L1) distance.
+    pub fn nearests_manhattan(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+    ) -> Vec<ItemAndMetricDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.nearests_with_metric::<Manhattan>(query, num)
+    }
+
+    /// search points whose manhattan (L1) distance from the input point is less than `radius`.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[0, 0], [1, 1], [2, 0], [2, 2]]);
+    /// let found = kdtree.within_l1(&[0, 0], 3);
+    /// assert_eq!(found.len(), 3);
+    /// assert!(!found.contains(&&[2, 2]));
+    /// ```
+    pub fn within_l1(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.within_radius_with_metric::<Manhattan>(query, radius)
+    }
+
+    /// Returns the nearest item from the input point in chebyshev (L∞) distance.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[2, 2], [0, 3]]);
+    /// assert_eq!(kdtree.nearest_chebyshev(&[0, 0]).unwrap().item, &[2, 2]);
+    /// ```
+    pub fn nearest_chebyshev(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<ItemAndMetricDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.nearest_with_metric::<Chebyshev>(query)
+    }
+
+    /// Returns kNN(k nearest neighbors) from the input point in chebyshev (L∞) distance.
+    pub fn nearests_chebyshev(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+    ) -> Vec<ItemAndMetricDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.nearests_with_metric::<Chebyshev>(query, num)
+    }
+
+    /// search points whose chebyshev (L∞) distance from the input point is less than `radius`,
+    /// i.e. points within an open cube centered at the input point.
+    pub fn within_linf(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.within_radius_with_metric::<Chebyshev>(query, radius)
+    }
+}
@@ -0,0 +1,62 @@
+use crate::nearest::kd_nearest_by_seeded;
+use crate::{ItemAndDistance, KdPoint, KdSliceN, QueryConfig};
+use typenum::Unsigned;
+
+/// A nearest neighbor query for a slowly moving point, which exploits temporal coherence.
+///
+/// The previous answer is used as the initial candidate of the next search,
+/// so that most of the subtrees are pruned from the very first node.
+/// # Example
+/// ```
+/// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]]);
+/// let mut moving = kd_tree::MovingNearest::new(&kdtree);
+/// for (i, expected) in [0.1, 0.4, 0.7, 1.0, 1.3, 1.6].iter().zip([0.0, 0.0, 1.0, 1.0, 1.0, 2.0]) {
+///     assert_eq!(moving.nearest(&[*i, 0.1]).unwrap().item, &[expected, 0.0]);
+/// }
+/// assert_eq!(moving.previous(), Some(&[2.0, 0.0]));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MovingNearest<'a, T, N: Unsigned> {
+    kdtree: &'a KdSliceN<T, N>,
+    previous: Option<&'a T>,
+}
+
+impl<'a, T: KdPoint<Dim = N>, N: Unsigned> MovingNearest<'a, T, N> {
+    pub fn new(kdtree: &'a KdSliceN<T, N>) -> Self {
+        Self {
+            kdtree,
+            previous: None,
+        }
+    }
+
+    /// Returns the previous answer, which will be the initial candidate of the next search.
+    pub fn previous(&self) -> Option<&'a T> {
+        self.previous
+    }
+
+    /// Forgets the previous answer, e.g. when the query point jumps.
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+
+    /// Returns the nearest item from the current position of the query point.
+    /// Returns `None` if the kd-tree is empty.
+    pub fn nearest(
+        &mut self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<ItemAndDistance<'a, T, T::Scalar>> {
+        let seed = self.previous.or_else(|| self.kdtree.first());
+        let found = kd_nearest_by_seeded(
+            self.kdtree.items(),
+            query,
+            &QueryConfig::default(),
+            |item, k| item.at(k),
+            seed,
+            None,
+            |_| {},
+        )
+        .result;
+        self.previous = found.as_ref().map(|found| found.item);
+        found
+    }
+}
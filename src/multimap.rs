@@ -0,0 +1,122 @@
+use crate::dedup::compare_coords;
+use crate::{squared_distance, KdPoint, KdSliceN};
+use std::cmp::Ordering;
+
+/// The values of a [`crate::KdMap`] which share the same key, returned by the `*_grouped` queries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Colocated<'a, P, T, Scalar> {
+    pub key: &'a P,
+    pub values: Vec<&'a T>,
+    pub squared_distance: Scalar,
+}
+
+/// Multimap semantics of a [`crate::KdMap`], in which several entries may have identical keys.
+impl<P: KdPoint, T> KdSliceN<(P, T), P::Dim> {
+    /// Returns all the values whose keys are at the same position as `key`.
+    /// # Example
+    /// ```
+    /// let kdmap = kd_tree::KdMap::build(vec![([1, 2], "foo"), ([3, 4], "bar"), ([1, 2], "buzz")]);
+    /// let mut values = kdmap.get_all(&[1, 2]);
+    /// values.sort();
+    /// assert_eq!(values, vec![&"buzz", &"foo"]);
+    /// assert!(kdmap.get_all(&[2, 1]).is_empty());
+    /// ```
+    pub fn get_all(&self, key: &impl KdPoint<Scalar = P::Scalar, Dim = P::Dim>) -> Vec<&T> {
+        self.within_by_cmp(|item, k| {
+            item.0
+                .at(k)
+                .partial_cmp(&key.at(k))
+                .unwrap_or(Ordering::Equal)
+        })
+        .into_iter()
+        .map(|item| &item.1)
+        .collect()
+    }
+
+    /// Returns all the values at the nearest key from the input point. Returns `None` if `self.is_empty()`.
+    /// # Example
+    /// ```
+    /// let kdmap = kd_tree::KdMap::build(vec![([1, 2], "foo"), ([3, 4], "bar"), ([1, 2], "buzz")]);
+    /// let found = kdmap.nearest_grouped(&[1, 1]).unwrap();
+    /// assert_eq!(found.key, &[1, 2]);
+    /// assert_eq!(found.values.len(), 2);
+    /// assert_eq!(found.squared_distance, 1);
+    /// ```
+    pub fn nearest_grouped(
+        &self,
+        query: &impl KdPoint<Scalar = P::Scalar, Dim = P::Dim>,
+    ) -> Option<Colocated<'_, P, T, P::Scalar>> {
+        self.nearests_grouped(query, 1).pop()
+    }
+
+    /// Returns the `num` nearest distinct keys from the input point, each with all the values at the key,
+    /// in ascending order of the distance.
+    pub fn nearests_grouped(
+        &self,
+        query: &impl KdPoint<Scalar = P::Scalar, Dim = P::Dim>,
+        num: usize,
+    ) -> Vec<Colocated<'_, P, T, P::Scalar>> {
+        let mut groups: Vec<Colocated<'_, P, T, P::Scalar>> = Vec::with_capacity(num);
+        if num == 0 {
+            return groups;
+        }
+        for found in self.nearest_iter(query) {
+            if groups.len() == num && found.squared_distance > groups[num - 1].squared_distance {
+                break;
+            }
+            let (key, value) = found.item;
+            let same = groups.iter().rposition(|group| {
+                group.squared_distance == found.squared_distance
+                    && compare_coords(group.key, key) == Ordering::Equal
+            });
+            match same {
+                Some(i) => groups[i].values.push(value),
+                None if groups.len() < num => groups.push(Colocated {
+                    key,
+                    values: vec![value],
+                    squared_distance: found.squared_distance,
+                }),
+                None => {}
+            }
+        }
+        groups
+    }
+
+    /// Returns the distinct keys within a sphere, each with all the values at the key,
+    /// in ascending order of the distance.
+    /// # Example
+    /// ```
+    /// let kdmap = kd_tree::KdMap::build(vec![([0, 0], 'a'), ([1, 0], 'b'), ([0, 0], 'c'), ([5, 5], 'd')]);
+    /// let found = kdmap.within_radius_grouped(&[0, 0], 2);
+    /// assert_eq!(found.len(), 2);
+    /// assert_eq!(found[0].key, &[0, 0]);
+    /// assert_eq!(found[0].values.len(), 2);
+    /// assert_eq!(found[1].values, vec![&'b']);
+    /// ```
+    pub fn within_radius_grouped(
+        &self,
+        query: &impl KdPoint<Scalar = P::Scalar, Dim = P::Dim>,
+        radius: P::Scalar,
+    ) -> Vec<Colocated<'_, P, T, P::Scalar>> {
+        let mut found: Vec<_> = self
+            .within_radius(query, radius)
+            .into_iter()
+            .map(|item| (squared_distance::<P>(&item.0, query), item))
+            .collect();
+        found.sort_by(|(d1, item1), (d2, item2)| {
+            d1.partial_cmp(d2)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| compare_coords(&item1.0, &item2.0))
+        });
+        found
+            .chunk_by(|(_, item1), (_, item2)| {
+                compare_coords(&item1.0, &item2.0) == Ordering::Equal
+            })
+            .map(|run| Colocated {
+                key: &run[0].1 .0,
+                values: run.iter().map(|(_, item)| &item.1).collect(),
+                squared_distance: run[0].0,
+            })
+            .collect()
+    }
+}
@@ -7,7 +7,7 @@ macro_rules! impl_kdpoint_for_nalgebra_point {
             paste::paste! {
                 impl<Scalar> KdPoint for nalgebra::Point<Scalar, $dim>
                 where
-                    Scalar: num_traits::NumAssign + Copy + PartialOrd + nalgebra::Scalar,
+                    Scalar: crate::KdScalar + nalgebra::Scalar,
                 {
                     type Scalar = Scalar;
                     type Dim = typenum::[<U $dim>];
@@ -26,7 +26,7 @@ macro_rules! impl_kdpoint_for_nalgebra_vector {
             paste::paste! {
                 impl<Scalar, Storage> KdPoint for nalgebra::Vector<Scalar, nalgebra::Const<$dim>, Storage>
                 where
-                    Scalar: num_traits::NumAssign + Copy + PartialOrd + nalgebra::Scalar,
+                    Scalar: crate::KdScalar + nalgebra::Scalar,
                     Storage: nalgebra::StorageMut<Scalar, nalgebra::Const<$dim>>
                 {
                     type Scalar = Scalar;
@@ -42,3 +42,91 @@ macro_rules! impl_kdpoint_for_nalgebra_vector {
 
 impl_kdpoint_for_nalgebra_point!(1, 2, 3, 4, 5, 6, 7, 8);
 impl_kdpoint_for_nalgebra_vector!(1, 2, 3, 4, 5, 6, 7, 8);
+
+macro_rules! impl_within_oriented_box {
+    ($($dim:literal),*) => {
+        $(
+            paste::paste! {
+                impl<T, Scalar> crate::KdSliceN<T, typenum::[<U $dim>]>
+                where
+                    T: KdPoint<Scalar = Scalar, Dim = typenum::[<U $dim>]>,
+                    Scalar: crate::KdScalar + nalgebra::RealField,
+                {
+                    /// search points within an oriented box, which is the box `[-half_extents, half_extents]`
+                    /// rotated by `rotation` and then translated to `center`.
+                    ///
+                    /// Subtrees are pruned by the axis-aligned bounding box of the oriented box,
+                    /// and the candidates are tested for exact containment.
+                    /// # Example
+                    /// ```
+                    /// use nalgebra as na;
+                    /// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![[1.0, 1.0], [1.0, -1.0], [-1.5, -1.5], [0.0, 0.0]]);
+                    /// // a thin box along the diagonal `y = x`.
+                    /// let found = kdtree.within_oriented_box(
+                    ///     &na::Point2::new(0.0, 0.0),
+                    ///     &na::Vector2::new(3.0, 0.1),
+                    ///     &na::Rotation2::new(std::f64::consts::FRAC_PI_4),
+                    /// );
+                    /// assert_eq!(found.len(), 3);
+                    /// assert!(!found.contains(&&[1.0, -1.0]));
+                    /// ```
+                    pub fn within_oriented_box(
+                        &self,
+                        center: &nalgebra::Point<Scalar, $dim>,
+                        half_extents: &nalgebra::SVector<Scalar, $dim>,
+                        rotation: &nalgebra::Rotation<Scalar, $dim>,
+                    ) -> Vec<&T> {
+                        let matrix = rotation.matrix();
+                        let aabb_half_extents: nalgebra::SVector<Scalar, $dim> = matrix.abs() * half_extents;
+                        self.within_by_predicates(
+                            |item, k| {
+                                if item.at(k) < center[k] - aabb_half_extents[k] {
+                                    std::cmp::Ordering::Less
+                                } else if item.at(k) > center[k] + aabb_half_extents[k] {
+                                    std::cmp::Ordering::Greater
+                                } else {
+                                    std::cmp::Ordering::Equal
+                                }
+                            },
+                            |item| {
+                                let offset = nalgebra::SVector::<Scalar, $dim>::from_fn(|k, _| item.at(k) - center[k]);
+                                let local = matrix.tr_mul(&offset);
+                                (0..$dim).all(|k| local[k].abs() <= half_extents[k])
+                            },
+                        )
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_within_oriented_box!(2, 3);
+
+/// Helpers for the trees of nalgebra points, e.g. to steer towards the found point.
+impl<Scalar: nalgebra::RealField + Copy, const D: usize>
+    crate::ItemAndDistance<'_, nalgebra::Point<Scalar, D>, Scalar>
+{
+    /// Returns the vector from `query` to the found point.
+    pub fn offset_from(&self, query: &nalgebra::Point<Scalar, D>) -> nalgebra::SVector<Scalar, D> {
+        self.item - query
+    }
+
+    /// Returns the unit vector from `query` towards the found point, or `None` if they coincide.
+    /// # Example
+    /// ```
+    /// use nalgebra as na;
+    /// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![na::Point3::new(1.0, 2.0, 3.0), na::Point3::new(3.0, 1.0, 2.0)]);
+    /// let query = na::Point3::new(3.0, 1.0, 0.0);
+    /// let found = kdtree.nearest(&query).unwrap();
+    /// assert_eq!(found.offset_from(&query), na::Vector3::new(0.0, 0.0, 2.0));
+    /// assert_eq!(found.direction_from(&query).unwrap().into_inner(), na::Vector3::z());
+    /// assert_eq!(found.distance(), 2.0);
+    /// ```
+    pub fn direction_from(
+        &self,
+        query: &nalgebra::Point<Scalar, D>,
+    ) -> Option<nalgebra::Unit<nalgebra::SVector<Scalar, D>>> {
+        nalgebra::Unit::try_new(self.offset_from(query), Scalar::zero())
+    }
+}
@@ -0,0 +1,207 @@
+#![cfg(feature = "ndarray")]
+use crate::{KdIndexTreeN, KdPoint, KdScalar, KdSliceN, KdTreeN};
+use ndarray::{Array2, ArrayView1, ArrayView2};
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+use typenum::Unsigned;
+
+/// A row of an array, as a query point.
+struct Row<'a, S, N>(ArrayView1<'a, S>, PhantomData<N>);
+
+impl<S: KdScalar, N: Unsigned> KdPoint for Row<'_, S, N> {
+    type Scalar = S;
+    type Dim = N;
+    fn at(&self, k: usize) -> S {
+        self.0[k]
+    }
+}
+
+/// Returns the rows of `array` as points.
+/// # Panics
+/// Panics if the number of the columns is not `N`.
+fn rows<'a, S, N: Unsigned>(array: &'a ArrayView2<'_, S>) -> impl Iterator<Item = Row<'a, S, N>> {
+    assert_eq!(
+        array.ncols(),
+        N::to_usize(),
+        "the number of the columns must be equal to the dimension"
+    );
+    array.rows().into_iter().map(|row| Row(row, PhantomData))
+}
+
+/// Returns the rows of `array` as points without copying them.
+/// # Panics
+/// Panics if the number of the columns is not `D`, or `array` is not in the standard layout.
+fn row_points<'a, S, const D: usize>(array: ArrayView2<'a, S>) -> &'a [[S; D]] {
+    assert_eq!(
+        array.ncols(),
+        D,
+        "the number of the columns must be equal to the dimension"
+    );
+    let nrows = array.nrows();
+    let values = array
+        .to_slice()
+        .expect("the array must be in the standard layout");
+    // SAFETY: `[S; D]` has the same layout as `D` consecutive `S`s,
+    // and `values` holds `nrows * D` of them in the row-major order.
+    unsafe { std::slice::from_raw_parts(values.as_ptr().cast::<[S; D]>(), nrows) }
+}
+
+impl<'a, S: KdScalar, const D: usize, N: Unsigned> KdIndexTreeN<'a, [S; D], N>
+where
+    [S; D]: KdPoint<Scalar = S, Dim = N>,
+{
+    /// Builds a tree of the indices of the rows of `array`, each of which is a point, without copying the rows.
+    /// # Panics
+    /// Panics if the number of the columns is not `N`, or `array` is not in the standard layout, e.g. a transposed view.
+    /// Use [`KdTreeN::from_array2_by`] for such an array, which copies the rows.
+    pub fn from_array2_by<F>(array: ArrayView2<'a, S>, compare: F) -> Self
+    where
+        F: Fn(&[S; D], &[S; D], usize) -> Ordering + Copy,
+    {
+        Self::build_by(row_points(array), compare)
+    }
+
+    pub fn from_array2_by_key<Key, F>(array: ArrayView2<'a, S>, kd_key: F) -> Self
+    where
+        Key: Ord,
+        F: Fn(&[S; D], usize) -> Key + Copy,
+    {
+        Self::build_by_key(row_points(array), kd_key)
+    }
+
+    /// # Example
+    /// ```
+    /// use ndarray::array;
+    /// let points = array![[1.0, 2.0, 3.0], [3.0, 1.0, 2.0], [2.0, 3.0, 1.0]];
+    /// let kdtree: kd_tree::KdIndexTree<[f64; 3]> = kd_tree::KdIndexTree::from_array2_by_ordered_float(points.view());
+    /// assert_eq!(kdtree.nearest(&[3.1, 0.9, 2.1]).unwrap().item, &1);
+    /// ```
+    pub fn from_array2_by_ordered_float(array: ArrayView2<'a, S>) -> Self
+    where
+        S: ordered_float::FloatCore,
+    {
+        Self::build_by_ordered_float(row_points(array))
+    }
+
+    pub fn from_array2(array: ArrayView2<'a, S>) -> Self
+    where
+        S: Ord,
+    {
+        Self::build(row_points(array))
+    }
+}
+
+impl<S: KdScalar, const D: usize, N: Unsigned> KdTreeN<[S; D], N>
+where
+    [S; D]: KdPoint<Scalar = S, Dim = N>,
+{
+    /// Builds a tree of the rows of `array`, each of which is a point, copying the rows into the tree.
+    /// See [`KdIndexTreeN::from_array2_by`] to build a tree without copying.
+    /// # Panics
+    /// Panics if the number of the columns is not `N`.
+    pub fn from_array2_by<F>(array: ArrayView2<'_, S>, compare: F) -> Self
+    where
+        F: Fn(&[S; D], &[S; D], usize) -> Ordering + Copy,
+    {
+        let points = rows::<S, N>(&array)
+            .map(|row| std::array::from_fn(|k| row.at(k)))
+            .collect();
+        Self::build_by(points, compare)
+    }
+
+    pub fn from_array2_by_key<Key, F>(array: ArrayView2<'_, S>, kd_key: F) -> Self
+    where
+        Key: Ord,
+        F: Fn(&[S; D], usize) -> Key + Copy,
+    {
+        Self::from_array2_by(array, |item1, item2, k| {
+            kd_key(item1, k).cmp(&kd_key(item2, k))
+        })
+    }
+
+    /// # Example
+    /// ```
+    /// use ndarray::array;
+    /// let points = array![[1.0, 2.0, 3.0], [3.0, 1.0, 2.0], [2.0, 3.0, 1.0]];
+    /// let kdtree: kd_tree::KdTree<[f64; 3]> = kd_tree::KdTree::from_array2_by_ordered_float(points.view());
+    /// assert_eq!(kdtree.nearest(&[3.1, 0.9, 2.1]).unwrap().item, &[3.0, 1.0, 2.0]);
+    /// ```
+    pub fn from_array2_by_ordered_float(array: ArrayView2<'_, S>) -> Self
+    where
+        S: ordered_float::FloatCore,
+    {
+        Self::from_array2_by_key(array, |item, k| ordered_float::OrderedFloat(item[k]))
+    }
+
+    pub fn from_array2(array: ArrayView2<'_, S>) -> Self
+    where
+        S: Ord,
+    {
+        Self::from_array2_by_key(array, |item, k| item[k])
+    }
+}
+
+impl<S: KdScalar, const D: usize, N: Unsigned> KdSliceN<[S; D], N>
+where
+    [S; D]: KdPoint<Scalar = S, Dim = N>,
+{
+    /// Returns the items as the rows of an array, in the order of the tree.
+    pub fn to_array2(&self) -> Array2<S> {
+        Array2::from_shape_fn((self.len(), D), |(i, k)| self[i][k])
+    }
+}
+
+impl<T: KdPoint<Dim = N>, N: Unsigned> KdSliceN<T, N> {
+    /// Searches kNN of each row of `queries`, and returns the offsets of the found items in [`Self::items`]
+    /// and their squared distances, in arrays of the shape `(queries.nrows(), num.min(self.len()))`.
+    /// # Panics
+    /// Panics if the number of the columns of `queries` is not `N`.
+    /// # Example
+    /// ```
+    /// use ndarray::array;
+    /// let kdtree: kd_tree::KdTree<[i32; 2]> = kd_tree::KdTree::from_array2(array![[0, 0], [5, 5], [9, 0]].view());
+    /// let (offsets, squared_distances) = kdtree.nearests_array2(array![[1, 1], [8, 1]].view(), 2);
+    /// assert_eq!(offsets.shape(), &[2, 2]);
+    /// assert_eq!(kdtree[offsets[[0, 0]]], [0, 0]);
+    /// assert_eq!(kdtree[offsets[[1, 0]]], [9, 0]);
+    /// assert_eq!(squared_distances, array![[2, 32], [2, 25]]);
+    /// ```
+    pub fn nearests_array2(
+        &self,
+        queries: ArrayView2<'_, T::Scalar>,
+        num: usize,
+    ) -> (Array2<usize>, Array2<T::Scalar>) {
+        let num = num.min(self.len());
+        let mut offsets = Vec::with_capacity(queries.nrows() * num);
+        let mut squared_distances = Vec::with_capacity(queries.nrows() * num);
+        for query in rows::<T::Scalar, N>(&queries) {
+            for found in self.nearests(&query, num) {
+                offsets.push(self.offset_of(found.item));
+                squared_distances.push(found.squared_distance);
+            }
+        }
+        let shape = (queries.nrows(), num);
+        (
+            Array2::from_shape_vec(shape, offsets).unwrap(),
+            Array2::from_shape_vec(shape, squared_distances).unwrap(),
+        )
+    }
+
+    /// Searches the items within `radius` from each row of `queries`, and returns the offsets of them in [`Self::items`].
+    /// # Panics
+    /// Panics if the number of the columns of `queries` is not `N`.
+    pub fn within_radius_array2(
+        &self,
+        queries: ArrayView2<'_, T::Scalar>,
+        radius: T::Scalar,
+    ) -> Vec<Vec<usize>> {
+        rows::<T::Scalar, N>(&queries)
+            .map(|query| {
+                self.within_radius(&query, radius)
+                    .into_iter()
+                    .map(|item| self.offset_of(item))
+                    .collect()
+            })
+            .collect()
+    }
+}
@@ -1,82 +1,256 @@
-use crate::{ItemAndDistance, KdPoint};
+use crate::config::Traversal;
+use crate::prefetch::prefetch_midpoint;
+use crate::{Accumulation, DistanceTieBreak, ItemAndDistance, KdPoint, PartialResult, QueryConfig};
 
+/// Searches the nearest item of `query` in `kdtree` sorted by [`kd_sort_by`](crate::low_level::kd_sort_by).
+/// Returns `None` if `kdtree` is empty.
 pub fn kd_nearest<'a, T: KdPoint>(
     kdtree: &'a [T],
     query: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
-) -> ItemAndDistance<'a, T, T::Scalar> {
+) -> Option<ItemAndDistance<'a, T, T::Scalar>> {
     kd_nearest_by(kdtree, query, |item, k| item.at(k))
 }
 
+/// Searches the nearest item of `query` in `kdtree` sorted by [`kd_sort_by`](crate::low_level::kd_sort_by),
+/// where `get(item, k)` returns the `k`-th coordinate of `item`.
+/// Returns `None` if `kdtree` is empty.
 pub fn kd_nearest_by<'a, T, P: KdPoint>(
     kdtree: &'a [T],
     query: &P,
     get: impl Fn(&T, usize) -> P::Scalar + Copy,
-) -> ItemAndDistance<'a, T, P::Scalar> {
-    fn distance_squared<P: KdPoint, T>(
-        p1: &P,
-        p2: &T,
-        get: impl Fn(&T, usize) -> P::Scalar,
-    ) -> P::Scalar {
-        let mut squared_distance = <P::Scalar as num_traits::Zero>::zero();
-        for i in 0..P::dim() {
-            let diff = p1.at(i) - get(p2, i);
-            squared_distance += diff * diff;
-        }
-        squared_distance
+) -> Option<ItemAndDistance<'a, T, P::Scalar>> {
+    kd_nearest_by_with_config(kdtree, query, &QueryConfig::default(), get).result
+}
+
+/// Same as [`kd_nearest_by`], but with tuning parameters and options given by `config`.
+pub fn kd_nearest_by_with_config<'a, T, P: KdPoint>(
+    kdtree: &'a [T],
+    query: &P,
+    config: &QueryConfig,
+    get: impl Fn(&T, usize) -> P::Scalar + Copy,
+) -> PartialResult<Option<ItemAndDistance<'a, T, P::Scalar>>> {
+    kd_nearest_by_with_progress(kdtree, query, config, get, |_| {})
+}
+
+/// `on_improve` is called with the initial candidate and whenever the nearest candidate improves.
+pub fn kd_nearest_by_with_progress<'a, T, P: KdPoint>(
+    kdtree: &'a [T],
+    query: &P,
+    config: &QueryConfig,
+    get: impl Fn(&T, usize) -> P::Scalar + Copy,
+    on_improve: impl FnMut(&ItemAndDistance<'a, T, P::Scalar>),
+) -> PartialResult<Option<ItemAndDistance<'a, T, P::Scalar>>> {
+    kd_nearest_by_seeded(kdtree, query, config, get, kdtree.first(), None, on_improve)
+}
+
+/// Searches the nearest item, starting from `seed` as the initial candidate
+/// and ignoring the items whose squared distance is not less than `max_squared_distance`.
+/// Returns `None` if no item is found within the bound.
+pub fn kd_nearest_by_seeded<'a, T, P: KdPoint>(
+    kdtree: &'a [T],
+    query: &P,
+    config: &QueryConfig,
+    get: impl Fn(&T, usize) -> P::Scalar + Copy,
+    seed: Option<&'a T>,
+    max_squared_distance: Option<P::Scalar>,
+    on_improve: impl FnMut(&ItemAndDistance<'a, T, P::Scalar>),
+) -> PartialResult<Option<ItemAndDistance<'a, T, P::Scalar>>> {
+    struct Nearest<'a, T, Scalar> {
+        item: Option<&'a T>,
+        /// `None` means that there is no bound yet.
+        squared_distance: Option<Scalar>,
     }
-    fn recurse<'a, T, Q: KdPoint>(
-        nearest: &mut ItemAndDistance<'a, T, Q::Scalar>,
-        kdtree: &'a [T],
-        get: impl Fn(&T, usize) -> Q::Scalar + Copy,
-        query: &Q,
-        axis: usize,
-    ) {
-        let mid_idx = kdtree.len() / 2;
-        let item = &kdtree[mid_idx];
-        let squared_distance = distance_squared(query, item, get);
-        if squared_distance < nearest.squared_distance {
-            nearest.item = item;
-            nearest.squared_distance = squared_distance;
-            use num_traits::Zero;
-            if nearest.squared_distance.is_zero() {
-                return;
+    /// `FAST` is the search with the default options of `QueryConfig`,
+    /// which skips the traversal hooks, the slack and the tie-breaking at compile time.
+    /// `FIRST_ITEM` is [`DistanceTieBreak::FirstItem`], which is resolved at compile time as well.
+    struct Search<'q, Q: KdPoint, G, C, const FAST: bool, const FIRST_ITEM: bool> {
+        query: &'q Q,
+        get: G,
+        on_improve: C,
+        leaf_size: usize,
+        prefetch: bool,
+        accumulation: Accumulation,
+        slack: Option<(Q::Scalar, Q::Scalar)>,
+        traversal: Traversal,
+    }
+    impl<'q, Q: KdPoint, G, C, const FAST: bool, const FIRST_ITEM: bool>
+        Search<'q, Q, G, C, FAST, FIRST_ITEM>
+    {
+        fn distance_squared<T>(&self, item: &T) -> Q::Scalar
+        where
+            G: Fn(&T, usize) -> Q::Scalar,
+        {
+            let accumulation = if FAST {
+                Accumulation::Plain
+            } else {
+                self.accumulation
+            };
+            accumulation.squared_distance(Q::dim(), |k| self.query.at(k) - (self.get)(item, k))
+        }
+
+        /// Returns `true` if an item at `squared_distance` replaces the nearest candidate.
+        fn is_candidate<T>(
+            &self,
+            nearest: &Nearest<'_, T, Q::Scalar>,
+            item: &T,
+            squared_distance: Q::Scalar,
+        ) -> bool {
+            match (nearest.item, nearest.squared_distance) {
+                (_, None) => true,
+                (Some(best), Some(bound)) if FIRST_ITEM && squared_distance == bound => {
+                    std::ptr::from_ref(item) < std::ptr::from_ref(best)
+                }
+                (_, Some(bound)) => squared_distance < bound,
             }
         }
-        let mid_pos = get(item, axis);
-        let [branch1, branch2] = if query.at(axis) < mid_pos {
-            [&kdtree[..mid_idx], &kdtree[mid_idx + 1..]]
-        } else {
-            [&kdtree[mid_idx + 1..], &kdtree[..mid_idx]]
-        };
-        if !branch1.is_empty() {
-            recurse(nearest, branch1, get, query, (axis + 1) % Q::dim());
+
+        /// Returns `true` if a subtree at `squared_distance` may contain a better candidate.
+        fn may_improve<T>(
+            &mut self,
+            nearest: &Nearest<'_, T, Q::Scalar>,
+            squared_distance: Q::Scalar,
+        ) -> bool {
+            match nearest.squared_distance {
+                None => true,
+                Some(bound) if FAST => squared_distance < bound,
+                Some(bound) => self.traversal.may_improve(
+                    squared_distance,
+                    bound,
+                    FIRST_ITEM && nearest.item.is_some(),
+                    self.slack,
+                ),
+            }
         }
-        if !branch2.is_empty() {
-            let diff = query.at(axis) - mid_pos;
-            if diff * diff < nearest.squared_distance {
-                recurse(nearest, branch2, get, query, (axis + 1) % Q::dim());
+
+        /// Returns `true` if the search can be finished,
+        /// i.e. an item at distance zero is found or the budget is exhausted.
+        fn visit<'a, T>(&mut self, nearest: &mut Nearest<'a, T, Q::Scalar>, item: &'a T) -> bool
+        where
+            G: Fn(&T, usize) -> Q::Scalar,
+            C: FnMut(&ItemAndDistance<'a, T, Q::Scalar>),
+        {
+            if !FAST && !self.traversal.visit_item() {
+                return true;
+            }
+            let squared_distance = self.distance_squared(item);
+            if self.is_candidate(nearest, item, squared_distance) {
+                nearest.item = Some(item);
+                nearest.squared_distance = Some(squared_distance);
+                (self.on_improve)(&ItemAndDistance {
+                    item,
+                    squared_distance,
+                });
+                use crate::KdScalar;
+                return squared_distance.is_zero() && !FIRST_ITEM;
+            }
+            false
+        }
+
+        /// Returns `true` if the search can be finished.
+        fn recurse<'a, T>(
+            &mut self,
+            nearest: &mut Nearest<'a, T, Q::Scalar>,
+            kdtree: &'a [T],
+            axis: usize,
+            depth: usize,
+        ) -> bool
+        where
+            G: Fn(&T, usize) -> Q::Scalar,
+            C: FnMut(&ItemAndDistance<'a, T, Q::Scalar>),
+        {
+            if !FAST {
+                self.traversal.enter_subtree();
+            }
+            if kdtree.len() <= self.leaf_size {
+                return kdtree.iter().any(|item| self.visit(nearest, item));
+            }
+            let mid_idx = kdtree.len() / 2;
+            let item = &kdtree[mid_idx];
+            if self.visit(nearest, item) {
+                return true;
+            }
+            let mid_pos = (self.get)(item, axis);
+            let [branch1, branch2] = if self.query.at(axis) < mid_pos {
+                [&kdtree[..mid_idx], &kdtree[mid_idx + 1..]]
+            } else {
+                [&kdtree[mid_idx + 1..], &kdtree[..mid_idx]]
+            };
+            if self.prefetch {
+                prefetch_midpoint(branch1);
+                prefetch_midpoint(branch2);
+            }
+            let next_axis = (axis + 1) % Q::dim();
+            if !branch1.is_empty() {
+                if depth == 0 {
+                    self.traversal.is_exact = false;
+                } else if self.recurse(nearest, branch1, next_axis, depth - 1) {
+                    return true;
+                }
+            }
+            if !branch2.is_empty() {
+                let diff = self.query.at(axis) - mid_pos;
+                if self.may_improve(nearest, diff * diff) {
+                    if depth == 0 {
+                        self.traversal.is_exact = false;
+                    } else if self.recurse(nearest, branch2, next_axis, depth - 1) {
+                        return true;
+                    }
+                }
             }
+            false
         }
     }
-    assert!(!kdtree.is_empty());
-    let mut nearest = ItemAndDistance {
-        item: &kdtree[0],
-        squared_distance: distance_squared(query, &kdtree[0], get),
-    };
-    recurse(&mut nearest, kdtree, get, query, 0);
-    nearest
+    macro_rules! search {
+        ($fast:literal, $first_item:literal) => {{
+            let mut search = Search::<_, _, _, $fast, $first_item> {
+                query,
+                get,
+                on_improve,
+                leaf_size: config.leaf_size_for(kdtree.len()),
+                prefetch: config.prefetch,
+                accumulation: config.accumulation,
+                slack: if $fast { None } else { config.slack() },
+                traversal: Traversal::new(config),
+            };
+            let mut nearest = Nearest {
+                item: None,
+                squared_distance: max_squared_distance,
+            };
+            let finished = match seed {
+                Some(seed) => search.visit(&mut nearest, seed),
+                None => false,
+            };
+            if !finished && !kdtree.is_empty() {
+                search.recurse(&mut nearest, kdtree, 0, config.max_depth);
+            }
+            search
+                .traversal
+                .finish(nearest.item.map(|item| ItemAndDistance {
+                    item,
+                    squared_distance: nearest.squared_distance.unwrap(),
+                }))
+        }};
+    }
+    if config.is_fast_path() {
+        search!(true, false)
+    } else if config.tie_break == DistanceTieBreak::FirstItem {
+        search!(false, true)
+    } else {
+        search!(false, false)
+    }
 }
 
-#[allow(dead_code)]
+/// Searches the nearest item of a query given implicitly by `kd_difference`.
+/// Returns `None` if `kdtree` is empty.
 pub fn kd_nearest_with<T, Scalar>(
     kdtree: &[T],
     dim: usize,
     kd_difference: impl Fn(&T, usize) -> Scalar + Copy,
-) -> ItemAndDistance<T, Scalar>
+) -> Option<ItemAndDistance<'_, T, Scalar>>
 where
-    Scalar: num_traits::NumAssign + Copy + PartialOrd,
+    Scalar: crate::KdScalar,
 {
-    fn squared_distance<T, Scalar: num_traits::NumAssign + Copy>(
+    fn squared_distance<T, Scalar: crate::KdScalar>(
         item: &T,
         dim: usize,
         kd_difference: impl Fn(&T, usize) -> Scalar + Copy,
@@ -95,7 +269,7 @@ where
         dim: usize,
         kd_difference: impl Fn(&T, usize) -> Scalar + Copy,
     ) where
-        Scalar: num_traits::NumAssign + Copy + PartialOrd,
+        Scalar: crate::KdScalar,
     {
         let mid_idx = kdtree.len() / 2;
         let mid = &kdtree[mid_idx];
@@ -124,11 +298,128 @@ where
             }
         }
     }
-    assert!(!kdtree.is_empty());
+    let first = kdtree.first()?;
     let mut nearest = ItemAndDistance {
-        item: &kdtree[0],
-        squared_distance: squared_distance(&kdtree[0], dim, kd_difference),
+        item: first,
+        squared_distance: squared_distance(first, dim, kd_difference),
     };
     recurse(&mut nearest, kdtree, 0, dim, kd_difference);
-    nearest
+    Some(nearest)
+}
+
+/// Searches the nearest items on both sides of the plane `x[plane_axis] = position` in a single traversal,
+/// returning `[below, above]`, where `below` is the nearest item with `x[plane_axis] < position`
+/// and `above` is the nearest item with `x[plane_axis] >= position`.
+///
+/// A subtree is visited if it can improve the candidate on either side.
+/// The range of each subtree along `plane_axis` is tracked by the splitting planes above it,
+/// so a subtree entirely on one side is pruned by the candidate on that side only.
+pub fn kd_nearest_on_each_side<'a, T: KdPoint>(
+    kdtree: &'a [T],
+    query: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+    plane_axis: usize,
+    position: T::Scalar,
+) -> [Option<ItemAndDistance<'a, T, T::Scalar>>; 2] {
+    struct Search<'a, 'q, T: KdPoint, Q> {
+        query: &'q Q,
+        plane_axis: usize,
+        position: T::Scalar,
+        /// The squared distances from the query to the sides below and above the plane.
+        gaps: [T::Scalar; 2],
+        nearests: [Option<ItemAndDistance<'a, T, T::Scalar>>; 2],
+    }
+    impl<'a, T: KdPoint, Q: KdPoint<Scalar = T::Scalar, Dim = T::Dim>> Search<'a, '_, T, Q> {
+        /// Returns `true` if a subtree whose squared distance from the query is at least `squared_distance`
+        /// and whose range along the plane axis is `[lower, upper]` can improve either candidate.
+        fn is_candidate(
+            &self,
+            squared_distance: T::Scalar,
+            lower: Option<T::Scalar>,
+            upper: Option<T::Scalar>,
+        ) -> bool {
+            let has_side = [
+                lower.map_or(true, |lower| lower < self.position),
+                upper.map_or(true, |upper| upper >= self.position),
+            ];
+            (0..2).any(|side| {
+                let bound = if self.gaps[side] > squared_distance {
+                    self.gaps[side]
+                } else {
+                    squared_distance
+                };
+                has_side[side]
+                    && self.nearests[side]
+                        .as_ref()
+                        .map_or(true, |nearest| bound < nearest.squared_distance)
+            })
+        }
+
+        fn recurse(
+            &mut self,
+            kdtree: &'a [T],
+            axis: usize,
+            squared_distance: T::Scalar,
+            lower: Option<T::Scalar>,
+            upper: Option<T::Scalar>,
+        ) {
+            if kdtree.is_empty() || !self.is_candidate(squared_distance, lower, upper) {
+                return;
+            }
+            let mid_idx = kdtree.len() / 2;
+            let item = &kdtree[mid_idx];
+            let item_distance = crate::squared_distance(item, self.query);
+            let side = (item.at(self.plane_axis) >= self.position) as usize;
+            if self.nearests[side]
+                .as_ref()
+                .map_or(true, |nearest| item_distance < nearest.squared_distance)
+            {
+                self.nearests[side] = Some(ItemAndDistance {
+                    item,
+                    squared_distance: item_distance,
+                });
+            }
+            let mid_pos = item.at(axis);
+            let is_plane_axis = axis == self.plane_axis;
+            let lower_branch = (
+                &kdtree[..mid_idx],
+                lower,
+                if is_plane_axis { Some(mid_pos) } else { upper },
+            );
+            let upper_branch = (
+                &kdtree[mid_idx + 1..],
+                if is_plane_axis { Some(mid_pos) } else { lower },
+                upper,
+            );
+            let diff = self.query.at(axis) - mid_pos;
+            let (near, far) = if diff < T::Scalar::zero() {
+                (lower_branch, upper_branch)
+            } else {
+                (upper_branch, lower_branch)
+            };
+            let far_distance = if diff * diff > squared_distance {
+                diff * diff
+            } else {
+                squared_distance
+            };
+            let next_axis = (axis + 1) % T::dim();
+            self.recurse(near.0, next_axis, squared_distance, near.1, near.2);
+            self.recurse(far.0, next_axis, far_distance, far.1, far.2);
+        }
+    }
+    use crate::KdScalar;
+    let diff = query.at(plane_axis) - position;
+    let gap = diff * diff;
+    let mut search = Search {
+        query,
+        plane_axis,
+        position,
+        gaps: if diff < T::Scalar::zero() {
+            [T::Scalar::zero(), gap]
+        } else {
+            [gap, T::Scalar::zero()]
+        },
+        nearests: [None, None],
+    };
+    search.recurse(kdtree, 0, T::Scalar::zero(), None, None);
+    search.nearests
 }
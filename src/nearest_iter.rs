@@ -0,0 +1,121 @@
+use crate::{squared_distance, ItemAndDistance, KdPoint};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+enum Entry<'a, T> {
+    Subtree { kdtree: &'a [T], axis: usize },
+    Item(&'a T),
+}
+
+/// An entry of the priority queue, ordered so that the smallest `squared_distance` is popped first.
+struct Candidate<'a, T: KdPoint> {
+    squared_distance: T::Scalar,
+    entry: Entry<'a, T>,
+}
+
+impl<T: KdPoint> PartialEq for Candidate<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: KdPoint> Eq for Candidate<'_, T> {}
+
+impl<T: KdPoint> PartialOrd for Candidate<'_, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: KdPoint> Ord for Candidate<'_, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let ord = other
+            .squared_distance
+            .partial_cmp(&self.squared_distance)
+            .unwrap_or(Ordering::Equal);
+        // items come before subtrees at the same distance, so that they are yielded as early as possible.
+        ord.then_with(|| match (&self.entry, &other.entry) {
+            (Entry::Item(_), Entry::Subtree { .. }) => Ordering::Greater,
+            (Entry::Subtree { .. }, Entry::Item(_)) => Ordering::Less,
+            _ => Ordering::Equal,
+        })
+    }
+}
+
+/// An iterator over the items in ascending order of the distance from a query point,
+/// returned by [`crate::KdSliceN::nearest_iter`].
+///
+/// Subtrees are visited in best-first order using a priority queue,
+/// so that taking `k` items costs about as much as `nearests` with `k`.
+/// The yielded items borrow only the tree `'a`, not the query `'q`.
+pub struct NearestIter<'a, 'q, T: KdPoint, Q> {
+    query: &'q Q,
+    heap: BinaryHeap<Candidate<'a, T>>,
+}
+
+impl<'a, 'q, T: KdPoint, Q: KdPoint<Scalar = T::Scalar, Dim = T::Dim>> NearestIter<'a, 'q, T, Q> {
+    pub(crate) fn new(kdtree: &'a [T], query: &'q Q) -> Self {
+        let mut heap = BinaryHeap::new();
+        if !kdtree.is_empty() {
+            heap.push(Candidate {
+                squared_distance: <T::Scalar as crate::KdScalar>::zero(),
+                entry: Entry::Subtree { kdtree, axis: 0 },
+            });
+        }
+        Self { query, heap }
+    }
+}
+
+impl<'a, T: KdPoint, Q: KdPoint<Scalar = T::Scalar, Dim = T::Dim>> Iterator
+    for NearestIter<'a, '_, T, Q>
+{
+    type Item = ItemAndDistance<'a, T, T::Scalar>;
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(Candidate {
+            squared_distance: bound,
+            entry,
+        }) = self.heap.pop()
+        {
+            match entry {
+                Entry::Item(item) => {
+                    return Some(ItemAndDistance {
+                        item,
+                        squared_distance: bound,
+                    })
+                }
+                Entry::Subtree { kdtree, axis } => {
+                    let mid_idx = kdtree.len() / 2;
+                    let item = &kdtree[mid_idx];
+                    self.heap.push(Candidate {
+                        squared_distance: squared_distance::<T>(item, self.query),
+                        entry: Entry::Item(item),
+                    });
+                    let diff = self.query.at(axis) - item.at(axis);
+                    let [near, far] = if diff < <T::Scalar as crate::KdScalar>::zero() {
+                        [&kdtree[..mid_idx], &kdtree[mid_idx + 1..]]
+                    } else {
+                        [&kdtree[mid_idx + 1..], &kdtree[..mid_idx]]
+                    };
+                    let next_axis = (axis + 1) % T::dim();
+                    let far_bound = if diff * diff > bound {
+                        diff * diff
+                    } else {
+                        bound
+                    };
+                    for (kdtree, squared_distance) in [(near, bound), (far, far_bound)] {
+                        if !kdtree.is_empty() {
+                            self.heap.push(Candidate {
+                                squared_distance,
+                                entry: Entry::Subtree {
+                                    kdtree,
+                                    axis: next_axis,
+                                },
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
@@ -1,5 +1,12 @@
-use crate::{ItemAndDistance, KdPoint};
+use crate::config::Traversal;
+use crate::prefetch::prefetch_midpoint;
+use crate::{
+    Accumulation, AxisRanges, DistanceTieBreak, ItemAndDistance, KdPoint, PartialResult,
+    QueryConfig,
+};
 
+/// Searches the `num` nearest items of `query` in `kdtree` sorted by [`kd_sort_by`](crate::low_level::kd_sort_by),
+/// in ascending order of distance. Returns an empty `Vec` if `kdtree` is empty or `num` is zero.
 pub fn kd_nearests<'a, T: KdPoint>(
     kdtree: &'a [T],
     query: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
@@ -8,75 +15,487 @@ pub fn kd_nearests<'a, T: KdPoint>(
     kd_nearests_by(kdtree, query, num, |item, k| item.at(k))
 }
 
+/// Same as [`kd_nearests`], but `get(item, k)` returns the `k`-th coordinate of `item`.
 pub fn kd_nearests_by<'a, T, P: KdPoint>(
     kdtree: &'a [T],
     query: &P,
     num: usize,
     get: impl Fn(&T, usize) -> P::Scalar + Copy,
 ) -> Vec<ItemAndDistance<'a, T, P::Scalar>> {
-    fn distance_squared<P: KdPoint, T>(
-        p1: &P,
-        p2: &T,
-        get: impl Fn(&T, usize) -> P::Scalar,
-    ) -> P::Scalar {
-        let mut squared_distance = <P::Scalar as num_traits::Zero>::zero();
-        for i in 0..P::dim() {
-            let diff = p1.at(i) - get(p2, i);
-            squared_distance += diff * diff;
-        }
-        squared_distance
+    kd_nearests_by_with_config(kdtree, query, num, &QueryConfig::default(), get).result
+}
+
+/// Same as [`kd_nearests_by`], but with tuning parameters and options given by `config`.
+pub fn kd_nearests_by_with_config<'a, T, P: KdPoint>(
+    kdtree: &'a [T],
+    query: &P,
+    num: usize,
+    config: &QueryConfig,
+    get: impl Fn(&T, usize) -> P::Scalar + Copy,
+) -> PartialResult<Vec<ItemAndDistance<'a, T, P::Scalar>>> {
+    kd_nearests_by_filtered(kdtree, query, num, config, get, None, |_| true)
+}
+
+/// Same as `kd_nearests_by_with_config`, but the items for which `filter` returns `false` are skipped,
+/// and so are the items whose squared distance is not less than `max_squared_distance`.
+pub fn kd_nearests_by_filtered<'a, T, P: KdPoint>(
+    kdtree: &'a [T],
+    query: &P,
+    num: usize,
+    config: &QueryConfig,
+    get: impl Fn(&T, usize) -> P::Scalar + Copy,
+    max_squared_distance: Option<P::Scalar>,
+    filter: impl Fn(&T) -> bool,
+) -> PartialResult<Vec<ItemAndDistance<'a, T, P::Scalar>>> {
+    kd_nearests_by_filtered_in_ranges(
+        kdtree,
+        query,
+        num,
+        config,
+        get,
+        max_squared_distance,
+        filter,
+        &AxisRanges::new(),
+    )
+}
+
+/// Same as `kd_nearests_by_filtered`, but the items out of `ranges` are skipped as well,
+/// and the subtrees lying outside `ranges` are pruned.
+#[allow(clippy::too_many_arguments)]
+pub fn kd_nearests_by_filtered_in_ranges<'a, T, P: KdPoint>(
+    kdtree: &'a [T],
+    query: &P,
+    num: usize,
+    config: &QueryConfig,
+    get: impl Fn(&T, usize) -> P::Scalar + Copy,
+    max_squared_distance: Option<P::Scalar>,
+    filter: impl Fn(&T) -> bool,
+    ranges: &AxisRanges<P::Scalar, P::Dim>,
+) -> PartialResult<Vec<ItemAndDistance<'a, T, P::Scalar>>> {
+    kd_nearests_by_pruned(
+        kdtree,
+        query,
+        num,
+        config,
+        get,
+        max_squared_distance,
+        filter,
+        ranges,
+        |_| false,
+    )
+}
+
+/// Same as `kd_nearests_by_filtered_in_ranges`, but the subtrees for which `skip_subtree` returns `true` are pruned,
+/// which must contain no items accepted by `filter`.
+#[allow(clippy::too_many_arguments)]
+pub fn kd_nearests_by_pruned<'a, T, P: KdPoint>(
+    kdtree: &'a [T],
+    query: &P,
+    num: usize,
+    config: &QueryConfig,
+    get: impl Fn(&T, usize) -> P::Scalar + Copy,
+    max_squared_distance: Option<P::Scalar>,
+    filter: impl Fn(&T) -> bool,
+    ranges: &AxisRanges<P::Scalar, P::Dim>,
+    skip_subtree: impl Fn(&[T]) -> bool,
+) -> PartialResult<Vec<ItemAndDistance<'a, T, P::Scalar>>> {
+    /// `FAST` is the search with the default options of `QueryConfig`,
+    /// which skips the traversal hooks, the slack and the tie-breaking at compile time.
+    /// `FIRST_ITEM` is [`DistanceTieBreak::FirstItem`], which is resolved at compile time as well.
+    struct Search<'q, Q: KdPoint, G, F, S, const FAST: bool, const FIRST_ITEM: bool> {
+        query: &'q Q,
+        get: G,
+        filter: F,
+        skip_subtree: S,
+        ranges: &'q AxisRanges<Q::Scalar, Q::Dim>,
+        max_squared_distance: Option<Q::Scalar>,
+        num: usize,
+        leaf_size: usize,
+        prefetch: bool,
+        accumulation: Accumulation,
+        slack: Option<(Q::Scalar, Q::Scalar)>,
+        traversal: Traversal,
     }
-    fn recurse<'a, T, Q: KdPoint>(
-        nearests: &mut Vec<ItemAndDistance<'a, T, Q::Scalar>>,
-        kdtree: &'a [T],
-        get: impl Fn(&T, usize) -> Q::Scalar + Copy,
-        query: &Q,
-        axis: usize,
-    ) {
-        let mid_idx = kdtree.len() / 2;
-        let item = &kdtree[mid_idx];
-        let squared_distance = distance_squared(query, item, get);
-        if nearests.len() < nearests.capacity()
-            || squared_distance < nearests.last().unwrap().squared_distance
+    impl<'q, Q: KdPoint, G, F, S, const FAST: bool, const FIRST_ITEM: bool>
+        Search<'q, Q, G, F, S, FAST, FIRST_ITEM>
+    {
+        fn distance_squared<T>(&self, item: &T) -> Q::Scalar
+        where
+            G: Fn(&T, usize) -> Q::Scalar,
+        {
+            let accumulation = if FAST {
+                Accumulation::Plain
+            } else {
+                self.accumulation
+            };
+            accumulation.squared_distance(Q::dim(), |k| self.query.at(k) - (self.get)(item, k))
+        }
+
+        /// Returns `true` if a subtree whose squared distance from the query is `squared_distance` can contain neighbors.
+        fn may_improve<T>(
+            &mut self,
+            nearests: &[ItemAndDistance<T, Q::Scalar>],
+            squared_distance: Q::Scalar,
+        ) -> bool {
+            if nearests.len() < self.num {
+                self.max_squared_distance
+                    .map_or(true, |bound| squared_distance < bound)
+            } else {
+                let bound = nearests.last().unwrap().squared_distance;
+                if FAST {
+                    squared_distance < bound
+                } else {
+                    self.traversal
+                        .may_improve(squared_distance, bound, FIRST_ITEM, self.slack)
+                }
+            }
+        }
+
+        /// Returns `true` if `item` at `squared_distance` is one of the neighbors found so far.
+        fn is_candidate<T>(
+            &self,
+            nearests: &[ItemAndDistance<T, Q::Scalar>],
+            item: &T,
+            squared_distance: Q::Scalar,
+        ) -> bool {
+            if nearests.len() < self.num {
+                self.max_squared_distance
+                    .map_or(true, |bound| squared_distance < bound)
+            } else {
+                let last = nearests.last().unwrap();
+                squared_distance < last.squared_distance
+                    || (FIRST_ITEM
+                        && squared_distance == last.squared_distance
+                        && std::ptr::from_ref(item) < std::ptr::from_ref(last.item))
+            }
+        }
+
+        fn accepts<T>(&self, item: &T) -> bool
+        where
+            G: Fn(&T, usize) -> Q::Scalar,
+            F: Fn(&T) -> bool,
+        {
+            (self.ranges.is_empty() || self.ranges.contains_by(item, &self.get))
+                && (self.filter)(item)
+        }
+
+        fn visit<'a, T>(
+            &mut self,
+            nearests: &mut Vec<ItemAndDistance<'a, T, Q::Scalar>>,
+            item: &'a T,
+        ) where
+            G: Fn(&T, usize) -> Q::Scalar,
+            F: Fn(&T) -> bool,
+        {
+            if (FAST || self.traversal.visit_item()) && self.accepts(item) {
+                self.insert(nearests, item, self.distance_squared(item));
+            }
+        }
+
+        /// Visits the items of a leaf, evaluating the filter only for the candidates.
+        fn visit_leaf<'a, T>(
+            &mut self,
+            nearests: &mut Vec<ItemAndDistance<'a, T, Q::Scalar>>,
+            leaf: &'a [T],
+        ) where
+            G: Fn(&T, usize) -> Q::Scalar,
+            F: Fn(&T) -> bool,
+        {
+            for item in leaf {
+                if !FAST && !self.traversal.visit_item() {
+                    return;
+                }
+                let squared_distance = self.distance_squared(item);
+                if self.is_candidate(nearests, item, squared_distance) && self.accepts(item) {
+                    self.insert(nearests, item, squared_distance);
+                }
+            }
+        }
+
+        fn insert<'a, T>(
+            &self,
+            nearests: &mut Vec<ItemAndDistance<'a, T, Q::Scalar>>,
+            item: &'a T,
+            squared_distance: Q::Scalar,
+        ) {
+            if self.is_candidate(nearests, item, squared_distance) {
+                if nearests.len() == self.num {
+                    nearests.pop();
+                }
+                let i = nearests
+                    .binary_search_by(|other| {
+                        let ordering = other
+                            .squared_distance
+                            .partial_cmp(&squared_distance)
+                            .unwrap_or(std::cmp::Ordering::Equal);
+                        if FIRST_ITEM {
+                            ordering
+                                .then(std::ptr::from_ref(other.item).cmp(&std::ptr::from_ref(item)))
+                        } else {
+                            ordering
+                        }
+                    })
+                    .unwrap_or_else(|i| i);
+                nearests.insert(
+                    i,
+                    ItemAndDistance {
+                        item,
+                        squared_distance,
+                    },
+                );
+            }
+        }
+
+        fn recurse<'a, T>(
+            &mut self,
+            nearests: &mut Vec<ItemAndDistance<'a, T, Q::Scalar>>,
+            kdtree: &'a [T],
+            axis: usize,
+            depth: usize,
+        ) where
+            G: Fn(&T, usize) -> Q::Scalar,
+            F: Fn(&T) -> bool,
+            S: Fn(&[T]) -> bool,
         {
-            if nearests.len() == nearests.capacity() {
-                nearests.pop();
-            }
-            let i = nearests
-                .binary_search_by(|item| {
-                    item.squared_distance
-                        .partial_cmp(&squared_distance)
-                        .unwrap_or(std::cmp::Ordering::Equal)
-                })
-                .unwrap_or_else(|i| i);
-            nearests.insert(
-                i,
-                ItemAndDistance {
-                    item,
-                    squared_distance,
-                },
-            );
+            if (self.skip_subtree)(kdtree) {
+                return;
+            }
+            if !FAST {
+                if self.traversal.is_exhausted() {
+                    self.traversal.is_exact = false;
+                    return;
+                }
+                self.traversal.enter_subtree();
+            }
+            if kdtree.len() <= self.leaf_size {
+                if kdtree.len() == 1 {
+                    self.visit(nearests, &kdtree[0]);
+                } else {
+                    self.visit_leaf(nearests, kdtree);
+                }
+                return;
+            }
+            let mid_idx = kdtree.len() / 2;
+            let item = &kdtree[mid_idx];
+            self.visit(nearests, item);
+            let mid_pos = (self.get)(item, axis);
+            let mut lower = &kdtree[..mid_idx];
+            let mut upper = &kdtree[mid_idx + 1..];
+            if self.ranges.is_below(axis, mid_pos) {
+                lower = &[];
+            }
+            if self.ranges.is_above(axis, mid_pos) {
+                upper = &[];
+            }
+            let [branch1, branch2] = if self.query.at(axis) < mid_pos {
+                [lower, upper]
+            } else {
+                [upper, lower]
+            };
+            if self.prefetch {
+                prefetch_midpoint(branch1);
+                prefetch_midpoint(branch2);
+            }
+            let next_axis = (axis + 1) % Q::dim();
+            if !branch1.is_empty() {
+                if depth == 0 {
+                    self.traversal.is_exact = false;
+                } else {
+                    self.recurse(nearests, branch1, next_axis, depth - 1);
+                }
+            }
+            if !branch2.is_empty() {
+                let diff = self.query.at(axis) - mid_pos;
+                if self.may_improve(nearests, diff * diff) {
+                    if depth == 0 {
+                        self.traversal.is_exact = false;
+                    } else {
+                        self.recurse(nearests, branch2, next_axis, depth - 1);
+                    }
+                }
+            }
+        }
+    }
+    macro_rules! search {
+        ($fast:literal, $first_item:literal) => {{
+            let mut search = Search::<_, _, _, _, $fast, $first_item> {
+                query,
+                get,
+                filter,
+                skip_subtree,
+                ranges,
+                max_squared_distance,
+                num,
+                leaf_size: config.leaf_size_for(kdtree.len()),
+                prefetch: config.prefetch,
+                accumulation: config.accumulation,
+                slack: if $fast { None } else { config.slack() },
+                traversal: Traversal::new(config),
+            };
+            let mut nearests = Vec::with_capacity(num);
+            if num != 0 && !kdtree.is_empty() {
+                search.recurse(&mut nearests, kdtree, 0, config.max_depth);
+            }
+            search.traversal.finish(nearests)
+        }};
+    }
+    if config.is_fast_path() {
+        search!(true, false)
+    } else if config.tie_break == DistanceTieBreak::FirstItem {
+        search!(false, true)
+    } else {
+        search!(false, false)
+    }
+}
+
+/// Same as `kd_nearests_by`, but the nearest candidates are kept in a fixed-size sorted array of length `K`
+/// instead of a `Vec`, which is faster for small `K`.
+pub fn kd_nearests_const_by<'a, const K: usize, T, P: KdPoint>(
+    kdtree: &'a [T],
+    query: &P,
+    get: impl Fn(&T, usize) -> P::Scalar + Copy,
+) -> [Option<ItemAndDistance<'a, T, P::Scalar>>; K] {
+    struct Search<'a, 'q, const K: usize, T, Q: KdPoint, G> {
+        query: &'q Q,
+        get: G,
+        nearests: [Option<(&'a T, Q::Scalar)>; K],
+        len: usize,
+    }
+    impl<'a, const K: usize, T, Q: KdPoint, G: Fn(&T, usize) -> Q::Scalar> Search<'a, '_, K, T, Q, G> {
+        fn is_candidate(&self, squared_distance: Q::Scalar) -> bool {
+            self.len < K || squared_distance < self.nearests[K - 1].unwrap().1
         }
-        let mid_pos = get(item, axis);
-        let [branch1, branch2] = if query.at(axis) < mid_pos {
-            [&kdtree[..mid_idx], &kdtree[mid_idx + 1..]]
-        } else {
-            [&kdtree[mid_idx + 1..], &kdtree[..mid_idx]]
-        };
-        if !branch1.is_empty() {
-            recurse(nearests, branch1, get, query, (axis + 1) % Q::dim());
+
+        fn visit(&mut self, item: &'a T) {
+            let mut squared_distance = <Q::Scalar as crate::KdScalar>::zero();
+            for k in 0..Q::dim() {
+                let diff = self.query.at(k) - (self.get)(item, k);
+                squared_distance += diff * diff;
+            }
+            if !self.is_candidate(squared_distance) {
+                return;
+            }
+            // the last one is dropped if the array is full.
+            let mut i = self.len.min(K - 1);
+            while i > 0 && self.nearests[i - 1].unwrap().1 > squared_distance {
+                self.nearests[i] = self.nearests[i - 1];
+                i -= 1;
+            }
+            self.nearests[i] = Some((item, squared_distance));
+            self.len = (self.len + 1).min(K);
         }
-        if !branch2.is_empty() {
-            let diff = query.at(axis) - mid_pos;
-            if diff * diff < nearests.last().unwrap().squared_distance {
-                recurse(nearests, branch2, get, query, (axis + 1) % Q::dim());
+
+        fn recurse(&mut self, kdtree: &'a [T], axis: usize) {
+            let mid_idx = kdtree.len() / 2;
+            let item = &kdtree[mid_idx];
+            self.visit(item);
+            let mid_pos = (self.get)(item, axis);
+            let [branch1, branch2] = if self.query.at(axis) < mid_pos {
+                [&kdtree[..mid_idx], &kdtree[mid_idx + 1..]]
+            } else {
+                [&kdtree[mid_idx + 1..], &kdtree[..mid_idx]]
+            };
+            let next_axis = (axis + 1) % Q::dim();
+            if !branch1.is_empty() {
+                self.recurse(branch1, next_axis);
             }
+            if !branch2.is_empty() {
+                let diff = self.query.at(axis) - mid_pos;
+                if self.is_candidate(diff * diff) {
+                    self.recurse(branch2, next_axis);
+                }
+            }
+        }
+    }
+    let mut search = Search {
+        query,
+        get,
+        nearests: [None; K],
+        len: 0,
+    };
+    if K != 0 && !kdtree.is_empty() {
+        search.recurse(kdtree, 0);
+    }
+    search.nearests.map(|nearest| {
+        nearest.map(|(item, squared_distance)| ItemAndDistance {
+            item,
+            squared_distance,
+        })
+    })
+}
+
+/// Same as `kd_nearests_by_filtered` without `max_squared_distance`, but the search is bounded by the result of a previous query,
+/// given as the items found by it.
+///
+/// If `num` of the previous items pass `filter`, the `num` neighbors are not farther than the `num`-th nearest of them,
+/// so consecutive queries close to each other are pruned tightly from the beginning.
+/// The bound is one of the squared distances from `query` to the items, which are computed by the search anyway,
+/// so it overflows no more than the search itself.
+pub fn kd_nearests_after_by<'a, T, P: KdPoint>(
+    kdtree: &'a [T],
+    query: &P,
+    num: usize,
+    get: impl Fn(&T, usize) -> P::Scalar + Copy,
+    previous: &[&'a T],
+    filter: impl Fn(&T) -> bool + Copy,
+) -> Vec<ItemAndDistance<'a, T, P::Scalar>> {
+    let config = QueryConfig::default();
+    let mut previous: Vec<_> = previous
+        .iter()
+        .filter(|item| filter(item))
+        .map(|&item| ItemAndDistance {
+            item,
+            squared_distance: config
+                .accumulation
+                .squared_distance(P::dim(), |k| query.at(k) - get(item, k)),
+        })
+        .collect();
+    if num == 0 || previous.len() < num {
+        return kd_nearests_by_filtered(kdtree, query, num, &config, get, None, filter).result;
+    }
+    previous.sort_by(|a, b| {
+        a.squared_distance
+            .partial_cmp(&b.squared_distance)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let bound = previous[num - 1].squared_distance;
+    let mut found =
+        kd_nearests_by_filtered(kdtree, query, num, &config, get, Some(bound), filter).result;
+    // the items exactly at the bound are not found by the search, but the previous ones fill the rest.
+    for item in previous {
+        if found.len() == num {
+            break;
+        }
+        if item.squared_distance == bound {
+            found.push(item);
         }
     }
-    if num == 0 || kdtree.is_empty() {
-        return Vec::new();
+    found
+}
+
+/// Calls `f` with the offset of each item in `rows` and its `k` nearest other items, in the order of the items.
+///
+/// Consecutive items are spatially close in a kd-tree, so each search is bounded by the result of the previous one
+/// by `kd_nearests_after_by`.
+pub fn kd_knn_graph_by<'a, T, Q: KdPoint + 'a>(
+    kdtree: &'a [T],
+    k: usize,
+    rows: std::ops::Range<usize>,
+    query_of: impl Fn(&'a T) -> &'a Q,
+    get: impl Fn(&T, usize) -> Q::Scalar + Copy,
+    mut f: impl FnMut(usize, Vec<ItemAndDistance<'a, T, Q::Scalar>>),
+) {
+    let mut previous = Vec::new();
+    for i in rows {
+        let item = &kdtree[i];
+        let query = query_of(item);
+        let found = kd_nearests_after_by(kdtree, query, k, get, &previous, |other| {
+            !std::ptr::eq(other, item)
+        });
+        // the item itself is one of the candidates of the next item, which is likely to be close to it.
+        previous.clear();
+        previous.extend(found.iter().map(|found| found.item));
+        previous.push(item);
+        f(i, found);
     }
-    let mut nearests = Vec::with_capacity(num);
-    recurse(&mut nearests, kdtree, get, query, 0);
-    nearests
 }
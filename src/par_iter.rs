@@ -0,0 +1,202 @@
+#![cfg(feature = "rayon")]
+use crate::{KdPoint, KdSliceN, KdTreeN};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use typenum::Unsigned;
+
+impl<'a, T: Sync, N: Unsigned> IntoParallelIterator for &'a KdSliceN<T, N> {
+    type Iter = rayon::slice::Iter<'a, T>;
+    type Item = &'a T;
+    fn into_par_iter(self) -> Self::Iter {
+        self.items().into_par_iter()
+    }
+}
+
+impl<'a, T: Sync, N: Unsigned> IntoParallelIterator for &'a KdTreeN<T, N> {
+    type Iter = rayon::slice::Iter<'a, T>;
+    type Item = &'a T;
+    fn into_par_iter(self) -> Self::Iter {
+        self.items().into_par_iter()
+    }
+}
+
+/// Subtrees with at most this number of items are not split into parallel tasks.
+const MIN_SPLIT_LEN: usize = 1024;
+
+/// The query region of the parallel iterators.
+#[derive(Clone)]
+enum Region<S> {
+    Box(Vec<S>, Vec<S>),
+    Sphere(Vec<S>, S),
+}
+
+impl<S: crate::KdScalar> Region<S> {
+    /// Returns whether the lower and the upper subtrees split at `item` on `axis` may intersect the region.
+    fn children<T: KdPoint<Scalar = S>>(&self, item: &T, axis: usize) -> (bool, bool) {
+        let coord = item.at(axis);
+        match self {
+            Region::Box(min, max) => (min[axis] <= coord, coord <= max[axis]),
+            Region::Sphere(center, squared_radius) => {
+                let diff = center[axis] - coord;
+                let crosses = diff * diff < *squared_radius;
+                (diff < S::zero() || crosses, diff >= S::zero() || crosses)
+            }
+        }
+    }
+
+    fn contains<T: KdPoint<Scalar = S>>(&self, item: &T) -> bool {
+        match self {
+            Region::Box(min, max) => {
+                (0..T::dim()).all(|k| min[k] <= item.at(k) && item.at(k) <= max[k])
+            }
+            Region::Sphere(center, squared_radius) => {
+                let mut squared_distance = S::zero();
+                for (k, &c) in center.iter().enumerate() {
+                    let diff = item.at(k) - c;
+                    squared_distance += diff * diff;
+                }
+                squared_distance < *squared_radius
+            }
+        }
+    }
+}
+
+/// The subtrees yet to be searched, with their split axes.
+struct Pending<'a, T: KdPoint> {
+    subtrees: Vec<(&'a [T], usize)>,
+    region: Region<T::Scalar>,
+}
+
+impl<'a, T: KdPoint> Pending<'a, T> {
+    /// Replaces a subtree with its split item and its children intersecting the region.
+    fn expand(&mut self, (items, axis): (&'a [T], usize)) {
+        let mid = items.len() / 2;
+        let (lower, upper) = self.region.children(&items[mid], axis);
+        let next = (axis + 1) % T::dim();
+        // a subtree of a single item is the item itself, regardless of the axis.
+        self.subtrees.push((&items[mid..=mid], 0));
+        if lower {
+            self.subtrees.push((&items[..mid], next));
+        }
+        if upper {
+            self.subtrees.push((&items[mid + 1..], next));
+        }
+    }
+
+    fn split(mut self) -> (Self, Option<Self>) {
+        while self.subtrees.len() == 1 && self.subtrees[0].0.len() > MIN_SPLIT_LEN {
+            let subtree = self.subtrees.pop().unwrap();
+            self.expand(subtree);
+        }
+        if self.subtrees.len() < 2 {
+            return (self, None);
+        }
+        let other = Self {
+            subtrees: self.subtrees.split_off(self.subtrees.len() / 2),
+            region: self.region.clone(),
+        };
+        (self, Some(other))
+    }
+}
+
+impl<'a, T: KdPoint> Iterator for Pending<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        while let Some((items, axis)) = self.subtrees.pop() {
+            if items.is_empty() {
+                continue;
+            }
+            let mid = items.len() / 2;
+            let (lower, upper) = self.region.children(&items[mid], axis);
+            let next = (axis + 1) % T::dim();
+            if upper {
+                self.subtrees.push((&items[mid + 1..], next));
+            }
+            if lower {
+                self.subtrees.push((&items[..mid], next));
+            }
+            if self.region.contains(&items[mid]) {
+                return Some(&items[mid]);
+            }
+        }
+        None
+    }
+}
+
+fn par_iter_region<'a, T>(
+    items: &'a [T],
+    region: Region<T::Scalar>,
+) -> impl ParallelIterator<Item = &'a T> + 'a
+where
+    T: KdPoint + Sync,
+    T::Scalar: Send + Sync,
+{
+    let pending = Pending {
+        subtrees: vec![(items, 0)],
+        region,
+    };
+    rayon::iter::split(pending, Pending::split).flat_map_iter(|pending| pending)
+}
+
+impl<T: KdPoint<Dim = N> + Sync, N: Unsigned> KdSliceN<T, N>
+where
+    T::Scalar: Send + Sync,
+{
+    /// Returns a parallel iterator over the points within a rectangular region, in no particular order.
+    ///
+    /// Independent subtrees are searched by different threads, and each point is passed downstream
+    /// as soon as it is found, so the per-item work is parallelized without collecting the results into a `Vec` first.
+    /// # Example
+    /// ```
+    /// use rayon::prelude::*;
+    /// let kdtree = kd_tree::KdTree::build((0..100).map(|i| [i, i % 10]).collect());
+    /// let sum: i32 = kdtree.par_iter_within(&[[10, 0], [19, 4]]).map(|p| p[0]).sum();
+    /// assert_eq!(sum, 10 + 11 + 12 + 13 + 14);
+    /// ```
+    pub fn par_iter_within(
+        &self,
+        query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2],
+    ) -> impl ParallelIterator<Item = &T> + '_ {
+        let min = (0..N::to_usize()).map(|k| query[0].at(k)).collect();
+        let max = (0..N::to_usize()).map(|k| query[1].at(k)).collect();
+        par_iter_region(self.items(), Region::Box(min, max))
+    }
+
+    /// Returns a parallel iterator over the points within k-dimensional sphere, in no particular order.
+    /// See [`Self::par_iter_within`].
+    /// # Example
+    /// ```
+    /// use rayon::prelude::*;
+    /// let kdtree = kd_tree::KdTree::build(vec![[1, 2], [3, 1], [2, 3], [9, 9]]);
+    /// assert_eq!(kdtree.par_iter_within_radius(&[2, 2], 2).count(), 3);
+    /// ```
+    pub fn par_iter_within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> impl ParallelIterator<Item = &T> + '_ {
+        let center = (0..N::to_usize()).map(|k| query.at(k)).collect();
+        par_iter_region(self.items(), Region::Sphere(center, radius * radius))
+    }
+
+    /// Same as [`Self::within`], but independent subtrees are searched by multiple threads,
+    /// each of which collects the points into its own buffer, and the buffers are concatenated at the end.
+    /// This pays off for queries returning a large number of points. The points are returned in no particular order.
+    pub fn par_within(&self, query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2]) -> Vec<&T> {
+        self.par_iter_within(query).collect()
+    }
+
+    /// Same as [`Self::within_radius`], but using multiple threads. See [`Self::par_within`].
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build((0..10000).map(|i| [i % 100, i / 100]).collect());
+    /// let found = kdtree.par_within_radius(&[50, 50], 30);
+    /// assert_eq!(found.len(), kdtree.within_radius(&[50, 50], 30).len());
+    /// ```
+    pub fn par_within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<&T> {
+        self.par_iter_within_radius(query, radius).collect()
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{KdIndexTreeN, KdPoint, KdTreeN};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::Hash;
+use typenum::Unsigned;
+
+impl<'a, T, N: Unsigned> KdIndexTreeN<'a, T, N> {
+    /// Builds an index tree per category over the shared `source`, where `category` returns the category of each item.
+    ///
+    /// The items are grouped by their categories in a single pass, and each tree holds the indices of its category only.
+    pub fn build_partitioned_by<K, F>(
+        source: &'a [T],
+        category: impl Fn(&T) -> K,
+        compare: F,
+    ) -> HashMap<K, Self>
+    where
+        K: Eq + Hash,
+        F: Fn(&T, &T, usize) -> Ordering + Copy,
+    {
+        let mut groups: HashMap<K, Vec<usize>> = HashMap::new();
+        for (i, item) in source.iter().enumerate() {
+            groups.entry(category(item)).or_default().push(i);
+        }
+        groups
+            .into_iter()
+            .map(|(key, indices)| {
+                let kdtree =
+                    KdTreeN::build_by(indices, |i1, i2, k| compare(&source[*i1], &source[*i2], k));
+                (key, Self { source, kdtree })
+            })
+            .collect()
+    }
+
+    pub fn build_partitioned_by_key<K, Key, F>(
+        source: &'a [T],
+        category: impl Fn(&T) -> K,
+        kd_key: F,
+    ) -> HashMap<K, Self>
+    where
+        K: Eq + Hash,
+        Key: Ord,
+        F: Fn(&T, usize) -> Key + Copy,
+    {
+        Self::build_partitioned_by(source, category, |item1, item2, k| {
+            kd_key(item1, k).cmp(&kd_key(item2, k))
+        })
+    }
+
+    pub fn build_partitioned_by_ordered_float<K: Eq + Hash>(
+        points: &'a [T],
+        category: impl Fn(&T) -> K,
+    ) -> HashMap<K, Self>
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        Self::build_partitioned_by_key(points, category, |item, k| {
+            ordered_float::OrderedFloat(item.at(k))
+        })
+    }
+
+    /// # Example
+    /// ```
+    /// struct Detection {
+    ///     point: [i32; 2],
+    ///     class: &'static str,
+    /// }
+    /// impl kd_tree::KdPoint for Detection {
+    ///     type Scalar = i32;
+    ///     type Dim = typenum::U2;
+    ///     fn at(&self, k: usize) -> i32 { self.point[k] }
+    /// }
+    /// let detections = vec![
+    ///     Detection { point: [1, 2], class: "car" },
+    ///     Detection { point: [3, 1], class: "person" },
+    ///     Detection { point: [2, 3], class: "car" },
+    ///     Detection { point: [5, 5], class: "person" },
+    /// ];
+    /// let trees = kd_tree::KdIndexTree::build_partitioned(&detections, |item| item.class);
+    /// assert_eq!(trees.len(), 2);
+    /// assert_eq!(trees["car"].nearest(&[1, 1]).unwrap().item, &0);
+    /// assert_eq!(trees["person"].nearest(&[1, 2]).unwrap().item, &1);
+    /// ```
+    pub fn build_partitioned<K: Eq + Hash>(
+        points: &'a [T],
+        category: impl Fn(&T) -> K,
+    ) -> HashMap<K, Self>
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: Ord,
+    {
+        Self::build_partitioned_by_key(points, category, |item, k| item.at(k))
+    }
+}
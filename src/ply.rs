@@ -0,0 +1,226 @@
+#![cfg(feature = "ply")]
+use crate::binary::invalid_data;
+use crate::{KdMap, KdTree};
+use std::io::{BufRead, Read, Result};
+
+/// The encoding of the body of a PLY file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+/// The type of a scalar property of a PLY file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PropertyType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl PropertyType {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return Err(invalid_data(format!("unknown property type {}", name))),
+        })
+    }
+
+    fn read(self, reader: &mut impl Read, format: Format) -> Result<f64> {
+        macro_rules! read {
+            ($t:ty) => {{
+                let mut bytes = [0; std::mem::size_of::<$t>()];
+                reader.read_exact(&mut bytes)?;
+                if format == Format::BigEndian {
+                    <$t>::from_be_bytes(bytes) as f64
+                } else {
+                    <$t>::from_le_bytes(bytes) as f64
+                }
+            }};
+        }
+        Ok(match self {
+            Self::I8 => read!(i8),
+            Self::U8 => read!(u8),
+            Self::I16 => read!(i16),
+            Self::U16 => read!(u16),
+            Self::I32 => read!(i32),
+            Self::U32 => read!(u32),
+            Self::F32 => read!(f32),
+            Self::F64 => read!(f64),
+        })
+    }
+}
+
+/// A vertex of a PLY file, whose properties are given to the `attributes` function of [`KdMap::from_ply_reader_with`].
+#[derive(Debug, Clone, Copy)]
+pub struct PlyVertex<'a> {
+    names: &'a [String],
+    values: &'a [f64],
+}
+
+impl PlyVertex<'_> {
+    /// Returns the value of the property `name`, e.g. `"red"` or `"nx"`, converted to `f64`.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        let i = self.names.iter().position(|n| n == name)?;
+        Some(self.values[i])
+    }
+
+    /// Returns the names of the properties in the order of the header.
+    pub fn names(&self) -> &[String] {
+        self.names
+    }
+}
+
+fn read_line(reader: &mut impl BufRead) -> Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(invalid_data("unexpected end of the PLY header".to_string()));
+    }
+    Ok(line.trim_end().to_string())
+}
+
+/// Reads the vertices of a PLY file, calling `f` with the coordinates and the properties of each vertex.
+fn read_vertices(
+    mut reader: impl BufRead,
+    mut f: impl FnMut([f64; 3], PlyVertex<'_>),
+) -> Result<()> {
+    if read_line(&mut reader)? != "ply" {
+        return Err(invalid_data("not a PLY file".to_string()));
+    }
+    let mut format = None;
+    // the number of the vertices and their properties, once the vertex element is declared.
+    let mut vertex: Option<(usize, Vec<String>, Vec<PropertyType>)> = None;
+    // whether the properties being declared are of the vertex element.
+    let mut in_vertex = false;
+    let mut has_element = false;
+    loop {
+        let line = read_line(&mut reader)?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["end_header"] => break,
+            ["format", name, _] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::LittleEndian,
+                    "binary_big_endian" => Format::BigEndian,
+                    _ => return Err(invalid_data(format!("unknown PLY format {}", name))),
+                });
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            ["element", "vertex", count] if !has_element => {
+                let count = count
+                    .parse()
+                    .map_err(|_| invalid_data(format!("invalid vertex count {}", count)))?;
+                vertex = Some((count, Vec::new(), Vec::new()));
+                in_vertex = true;
+                has_element = true;
+            }
+            ["element", "vertex", _] => {
+                return Err(invalid_data(
+                    "the vertex element must be the first element".to_string(),
+                ));
+            }
+            ["element", _, _] => {
+                in_vertex = false;
+                has_element = true;
+            }
+            ["property", "list", ..] if in_vertex => {
+                return Err(invalid_data(
+                    "list properties of vertices are not supported".to_string(),
+                ));
+            }
+            ["property", ty, name] if in_vertex => {
+                if let Some((_, names, types)) = &mut vertex {
+                    names.push(name.to_string());
+                    types.push(PropertyType::parse(ty)?);
+                }
+            }
+            ["property", ..] => {}
+            _ => return Err(invalid_data(format!("invalid PLY header line: {}", line))),
+        }
+    }
+    let format = format.ok_or_else(|| invalid_data("missing PLY format".to_string()))?;
+    let (count, names, types) =
+        vertex.ok_or_else(|| invalid_data("missing vertex element".to_string()))?;
+    let position = |axis: &str| {
+        names
+            .iter()
+            .position(|name| name == axis)
+            .ok_or_else(|| invalid_data(format!("missing vertex property {}", axis)))
+    };
+    let xyz = [position("x")?, position("y")?, position("z")?];
+    let mut values = vec![0.0; types.len()];
+    let mut line = String::new();
+    for _ in 0..count {
+        if format == Format::Ascii {
+            line.clear();
+            reader.read_line(&mut line)?;
+            let mut words = line.split_whitespace();
+            for value in &mut values {
+                *value = words
+                    .next()
+                    .and_then(|word| word.parse().ok())
+                    .ok_or_else(|| invalid_data(format!("invalid vertex: {}", line.trim())))?;
+            }
+        } else {
+            for (value, ty) in values.iter_mut().zip(&types) {
+                *value = ty.read(&mut reader, format)?;
+            }
+        }
+        f(
+            xyz.map(|i| values[i]),
+            PlyVertex {
+                names: &names,
+                values: &values,
+            },
+        );
+    }
+    Ok(())
+}
+
+impl KdTree<[f64; 3]> {
+    /// Builds a tree of the vertices of a PLY file, in either the ASCII or the binary format.
+    ///
+    /// The vertex element must be the first element of the file, with the scalar properties `x`, `y` and `z`.
+    /// The other elements, such as faces, are ignored.
+    /// # Example
+    /// ```
+    /// let ply = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nend_header\n\
+    ///            1 2 3\n3 1 2\n2 3 1\n";
+    /// let kdtree = kd_tree::KdTree::from_ply_reader(ply.as_bytes()).unwrap();
+    /// assert_eq!(kdtree.nearest(&[3.1, 0.9, 2.1]).unwrap().item, &[3.0, 1.0, 2.0]);
+    /// ```
+    pub fn from_ply_reader(reader: impl BufRead) -> Result<Self> {
+        let mut points = Vec::new();
+        read_vertices(reader, |point, _| points.push(point))?;
+        Ok(Self::build_by_ordered_float(points))
+    }
+}
+
+impl<V> KdMap<[f64; 3], V> {
+    /// Same as [`KdTree::from_ply_reader`], but each vertex is associated with the value returned by `attributes`,
+    /// e.g. the color given by [`PlyVertex::get`].
+    pub fn from_ply_reader_with(
+        reader: impl BufRead,
+        mut attributes: impl FnMut(PlyVertex<'_>) -> V,
+    ) -> Result<Self> {
+        let mut entries = Vec::new();
+        read_vertices(reader, |point, vertex| {
+            entries.push((point, attributes(vertex)))
+        })?;
+        Ok(Self::build_by_ordered_float(entries))
+    }
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "bytemuck")]
+use crate::KdSliceN;
+use typenum::Unsigned;
+
+impl<T: bytemuck::Pod, N: Unsigned> KdSliceN<T, N> {
+    /// Returns the raw bytes of the items, e.g. to write the tree to a file.
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(self.items())
+    }
+
+    /// Reinterprets the bytes made by [`Self::as_bytes`] as a kd-tree without copying.
+    /// Returns an error if `bytes` is not aligned for `T` or its length is not a multiple of the size of `T`.
+    ///
+    /// The kd-tree invariant of the items is not verified, so `bytes` must be the one made by [`Self::as_bytes`].
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![[1.0, 2.0], [3.0, 1.0], [2.0, 3.0]]);
+    /// let bytes = kdtree.as_bytes().to_vec();
+    /// let kdslice = kd_tree::KdSlice::<[f64; 2]>::from_bytes(&bytes).unwrap();
+    /// assert_eq!(kdslice.nearest(&[3.1, 0.9]).unwrap().item, &[3.0, 1.0]);
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Result<&Self, bytemuck::PodCastError> {
+        let items = bytemuck::try_cast_slice(bytes)?;
+        // SAFETY: the bytes are expected to be written by `as_bytes` of a kd-tree.
+        Ok(unsafe { Self::new_unchecked(items) })
+    }
+
+    /// Writes the raw bytes of the items, which are read back by [`Self::from_bytes`] or [`crate::KdTreeFile::open`].
+    pub fn write_to(&self, mut writer: impl std::io::Write) -> std::io::Result<()> {
+        writer.write_all(self.as_bytes())
+    }
+}
+
+/// A kd-tree in a memory-mapped file written by [`KdSliceN::write_to`], which is queried without loading the items.
+/// This type implements [`std::ops::Deref`] to [`KdSliceN`].
+/// # Example
+/// ```
+/// let kdtree = kd_tree::KdTree::build(vec![[1, 2, 3], [3, 1, 2], [2, 3, 1]]);
+/// let path = std::env::temp_dir().join("kd-tree-doctest.bin");
+/// kdtree.write_to(std::fs::File::create(&path).unwrap()).unwrap();
+/// let file = unsafe { kd_tree::KdTreeFile::<[i32; 3], typenum::U3>::open(&path).unwrap() };
+/// assert_eq!(file.nearest(&[3, 1, 1]).unwrap().item, &[3, 1, 2]);
+/// ```
+#[cfg(feature = "mmap")]
+pub struct KdTreeFile<T, N: Unsigned> {
+    mmap: memmap2::Mmap,
+    _phantom: std::marker::PhantomData<(T, N)>,
+}
+
+#[cfg(feature = "mmap")]
+impl<T: bytemuck::Pod, N: Unsigned> KdTreeFile<T, N> {
+    /// Maps the file into memory.
+    /// Returns an error of kind `InvalidData` if the length of the file is not a multiple of the size of `T`.
+    ///
+    /// # Safety
+    /// The file must be written by [`KdSliceN::write_to`] with the same `T` and `N`,
+    /// and must not be modified while it is mapped, which is undefined behavior as in [`memmap2::Mmap::map`].
+    pub unsafe fn open(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let mmap = memmap2::Mmap::map(&file)?;
+        KdSliceN::<T, N>::from_bytes(&mmap).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{:?}", e))
+        })?;
+        Ok(Self {
+            mmap,
+            _phantom: std::marker::PhantomData,
+        })
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl<T: bytemuck::Pod, N: Unsigned> std::ops::Deref for KdTreeFile<T, N> {
+    type Target = KdSliceN<T, N>;
+    fn deref(&self) -> &Self::Target {
+        KdSliceN::from_bytes(&self.mmap).unwrap()
+    }
+}
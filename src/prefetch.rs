@@ -0,0 +1,26 @@
+/// Hints the CPU to load the midpoint item of `kdtree`, which is the first item visited in the subtree, into the cache.
+/// This is a no-op on architectures without a stable prefetch instruction.
+#[inline]
+pub fn prefetch_midpoint<T>(kdtree: &[T]) {
+    if let Some(item) = kdtree.get(kdtree.len() / 2) {
+        prefetch(item);
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[inline]
+fn prefetch<T>(item: &T) {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::{_mm_prefetch, _MM_HINT_T0};
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+    // SAFETY: prefetching never faults, and `item` is a valid reference anyway.
+    #[allow(unused_unsafe)]
+    unsafe {
+        _mm_prefetch::<_MM_HINT_T0>(item as *const T as *const i8);
+    }
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+#[inline]
+fn prefetch<T>(_item: &T) {}
@@ -0,0 +1,71 @@
+use crate::KdPoint;
+use std::marker::PhantomData;
+use typenum::Unsigned;
+
+/// A set of axes selected from a point, used by [`Project`].
+pub trait Axes {
+    type Dim: Unsigned;
+    /// Returns the axis of the original point which is used as the `k`-th axis.
+    fn axis(k: usize) -> usize;
+}
+
+macro_rules! define_axes {
+    ($($name:ident, $dim:ident, [$($a:ident),*];)*) => {
+        $(
+            /// Axes given by const generic parameters, in that order.
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+            pub struct $name<$(const $a: usize),*>;
+            impl<$(const $a: usize),*> Axes for $name<$($a),*> {
+                type Dim = typenum::$dim;
+                fn axis(k: usize) -> usize {
+                    [$($a),*][k]
+                }
+            }
+        )*
+    };
+}
+define_axes! {
+    Axes1, U1, [A0];
+    Axes2, U2, [A0, A1];
+    Axes3, U3, [A0, A1, A2];
+    Axes4, U4, [A0, A1, A2, A3];
+}
+
+/// An adapter which implements [`KdPoint`] over a subset or a permutation of the axes of another point.
+///
+/// [`KdPoint`] is also implemented for references of points,
+/// so `Project<&P, A>` can be used to build a tree which refers items owned elsewhere.
+/// # Example
+/// ```
+/// use kd_tree::{Axes2, KdTree, Project};
+/// let points = vec![[1.0, 2.0, 100.0], [3.0, 1.0, 200.0], [2.0, 3.0, 300.0]];
+/// // a tree over the XY plane of 3D points.
+/// let kdtree: KdTree<Project<&[f64; 3], Axes2<0, 1>>> =
+///     KdTree::build_by_ordered_float(points.iter().map(Project::new).collect());
+/// let found = kdtree.nearest(&[3.1, 0.9]).unwrap();
+/// assert_eq!(found.item.point(), &&[3.0, 1.0, 200.0]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Project<P, A>(P, PhantomData<A>);
+
+impl<P, A> Project<P, A> {
+    pub fn new(point: P) -> Self {
+        Self(point, PhantomData)
+    }
+
+    pub fn point(&self) -> &P {
+        &self.0
+    }
+
+    pub fn into_point(self) -> P {
+        self.0
+    }
+}
+
+impl<P: KdPoint, A: Axes> KdPoint for Project<P, A> {
+    type Scalar = P::Scalar;
+    type Dim = A::Dim;
+    fn at(&self, k: usize) -> Self::Scalar {
+        self.0.at(A::axis(k))
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{ItemAndDistance, KdPoint, KdSlice, KdTree, KdTreeN};
+
+impl<const D: usize, N: typenum::Unsigned> KdTreeN<[f32; D], N>
+where
+    [f32; D]: KdPoint<Scalar = f32, Dim = N>,
+{
+    /// Builds a kd-tree of `f32` points by casting each coordinate of `f64` points.
+    /// The relative error of each coordinate is bounded by `f32::EPSILON / 2`.
+    /// If the points are far from the origin, consider [`QuantizedKdTree`] which retains more precision.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::<[f32; 2]>::build_from_f64(vec![[1.0, 2.0], [3.0, 4.0]]);
+    /// assert_eq!(kdtree.nearest(&[2.9, 4.1]).unwrap().item, &[3.0, 4.0]);
+    /// ```
+    pub fn build_from_f64(points: Vec<[f64; D]>) -> Self {
+        Self::build_by_ordered_float(
+            points
+                .into_iter()
+                .map(|p| {
+                    let mut q = [0.0; D];
+                    for k in 0..D {
+                        q[k] = p[k] as f32;
+                    }
+                    q
+                })
+                .collect(),
+        )
+    }
+}
+
+/// An owned kd-tree of `f32` points built from `f64` points, which remembers the transform used in the conversion.
+///
+/// Each point is stored relative to the center of the bounding box of the input points,
+/// so the absolute error of each coordinate is bounded by `f32::EPSILON / 2` times the half width of the bounding box along the axis,
+/// regardless of how far the points are from the origin.
+/// Queries are given in the original `f64` coordinates and converted by the same transform.
+/// # Example
+/// ```
+/// use kd_tree::QuantizedKdTree;
+/// let kdtree = QuantizedKdTree::build_from_f64(vec![
+///     [1.0e6 + 0.25, 2.0e6],
+///     [1.0e6 + 0.5, 2.0e6],
+///     [1.0e6 + 0.75, 2.0e6],
+/// ]);
+/// let found = kdtree.nearest(&[1.0e6 + 0.45, 2.0e6]).unwrap();
+/// assert_eq!(kdtree.to_f64(found.item), [1.0e6 + 0.5, 2.0e6]);
+/// ```
+pub struct QuantizedKdTree<const D: usize>
+where
+    [f32; D]: KdPoint<Scalar = f32>,
+{
+    origin: [f64; D],
+    kdtree: KdTree<[f32; D]>,
+}
+
+impl<const D: usize> std::fmt::Debug for QuantizedKdTree<D>
+where
+    [f32; D]: KdPoint<Scalar = f32>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuantizedKdTree")
+            .field("origin", &self.origin)
+            .field("items", &self.kdtree.items())
+            .finish()
+    }
+}
+
+impl<const D: usize> Clone for QuantizedKdTree<D>
+where
+    [f32; D]: KdPoint<Scalar = f32>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            origin: self.origin,
+            kdtree: self.kdtree.to_owned(),
+        }
+    }
+}
+
+impl<const D: usize> PartialEq for QuantizedKdTree<D>
+where
+    [f32; D]: KdPoint<Scalar = f32>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.origin == other.origin && self.kdtree.items() == other.kdtree.items()
+    }
+}
+
+impl<const D: usize> QuantizedKdTree<D>
+where
+    [f32; D]: KdPoint<Scalar = f32>,
+{
+    /// Builds a kd-tree of `f32` points by translating `f64` points so that the center of their bounding box is the origin.
+    ///
+    /// The points are only translated, not scaled, so the distances are kept in the original unit.
+    /// Each stored coordinate is the offset from the center cast to `f32`, whose absolute error is bounded by
+    /// `f32::EPSILON / 2` times the half width of the bounding box along the axis.
+    /// The half width must be within the range of `f32`, about `3.4e38`, or the offsets become infinite,
+    /// and the points must be finite, or the center is not finite either.
+    /// The origin of an empty input is zero.
+    pub fn build_from_f64(points: Vec<[f64; D]>) -> Self {
+        let mut origin = [0.0; D];
+        if !points.is_empty() {
+            for (k, origin) in origin.iter_mut().enumerate() {
+                let min = points.iter().map(|p| p[k]).fold(f64::INFINITY, f64::min);
+                let max = points
+                    .iter()
+                    .map(|p| p[k])
+                    .fold(f64::NEG_INFINITY, f64::max);
+                *origin = (min + max) / 2.0;
+            }
+        }
+        let points = points.iter().map(|p| quantize(&origin, p)).collect();
+        Self {
+            origin,
+            kdtree: KdTree::build_by_ordered_float(points),
+        }
+    }
+
+    /// The point in the original coordinates which is mapped to the origin of the stored points.
+    pub fn origin(&self) -> &[f64; D] {
+        &self.origin
+    }
+
+    pub fn kdtree(&self) -> &KdSlice<[f32; D]> {
+        &self.kdtree
+    }
+
+    /// Converts a point in the original coordinates to the stored coordinates.
+    pub fn to_f32(&self, p: &[f64; D]) -> [f32; D] {
+        quantize(&self.origin, p)
+    }
+
+    /// Converts a stored point back to the original coordinates.
+    pub fn to_f64(&self, p: &[f32; D]) -> [f64; D] {
+        let mut q = [0.0; D];
+        for k in 0..D {
+            q[k] = f64::from(p[k]) + self.origin[k];
+        }
+        q
+    }
+
+    /// Returns the nearest stored point from the input point given in the original coordinates.
+    pub fn nearest(&self, query: &[f64; D]) -> Option<ItemAndDistance<'_, [f32; D], f32>> {
+        self.kdtree.nearest(&self.to_f32(query))
+    }
+
+    /// Returns kNN(k nearest neighbors) from the input point given in the original coordinates.
+    pub fn nearests(
+        &self,
+        query: &[f64; D],
+        num: usize,
+    ) -> Vec<ItemAndDistance<'_, [f32; D], f32>> {
+        self.kdtree.nearests(&self.to_f32(query), num)
+    }
+
+    /// search points within k-dimensional sphere given in the original coordinates.
+    pub fn within_radius(&self, query: &[f64; D], radius: f64) -> Vec<&[f32; D]> {
+        self.kdtree
+            .within_radius(&self.to_f32(query), radius as f32)
+    }
+}
+
+fn quantize<const D: usize>(origin: &[f64; D], p: &[f64; D]) -> [f32; D] {
+    let mut q = [0.0; D];
+    for k in 0..D {
+        q[k] = (p[k] - origin[k]) as f32;
+    }
+    q
+}
@@ -0,0 +1,97 @@
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use typenum::Unsigned;
+
+/// Constraints of the coordinates on some axes, e.g. "z between 0 and 2", used by `*_in_ranges` queries.
+///
+/// Unlike a predicate on items, the ranges are known to the traversal,
+/// which skips the subtrees lying outside the ranges entirely.
+/// # Example
+/// ```
+/// use kd_tree::{AxisRanges, KdTree};
+/// let kdtree = KdTree::build(vec![[0, 0, 5], [1, 1, 1], [3, 3, 2], [0, 1, -1]]);
+/// let ranges = AxisRanges::new().with(2, 0..=2);
+/// let found = kdtree.nearest_in_ranges(&[0, 0, 0], &ranges).unwrap();
+/// assert_eq!(found.item, &[1, 1, 1]);
+/// assert!(ranges.contains(&[3, 3, 2]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AxisRanges<Scalar, N> {
+    /// The lower and the upper bounds of each axis, indexed by the axis.
+    bounds: Vec<(Bound<Scalar>, Bound<Scalar>)>,
+    dim: PhantomData<N>,
+}
+
+impl<Scalar, N> Default for AxisRanges<Scalar, N> {
+    fn default() -> Self {
+        Self {
+            bounds: Vec::new(),
+            dim: PhantomData,
+        }
+    }
+}
+
+impl<Scalar: Copy + PartialOrd, N: Unsigned> AxisRanges<Scalar, N> {
+    /// Returns ranges which constrain no axes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Constrains the coordinate on `axis` to `range`, replacing the previous constraint on the axis.
+    /// # Panics
+    /// Panics if `axis` is not less than the dimension `N`.
+    pub fn with(mut self, axis: usize, range: impl RangeBounds<Scalar>) -> Self {
+        assert!(
+            axis < N::USIZE,
+            "axis {} is out of the dimension {}",
+            axis,
+            N::USIZE
+        );
+        if self.bounds.len() <= axis {
+            self.bounds
+                .resize(axis + 1, (Bound::Unbounded, Bound::Unbounded));
+        }
+        self.bounds[axis] = (range.start_bound().cloned(), range.end_bound().cloned());
+        self
+    }
+
+    /// Returns `true` if the coordinate `x` on `axis` is in the range.
+    pub fn contains_coord(&self, axis: usize, x: Scalar) -> bool {
+        match self.bounds.get(axis) {
+            Some(range) => range.contains(&x),
+            None => true,
+        }
+    }
+
+    /// Returns `true` if no axis is constrained.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    /// Returns `true` if the point is in the ranges on all axes.
+    pub fn contains(&self, point: &impl crate::KdPoint<Scalar = Scalar, Dim = N>) -> bool {
+        self.contains_by(point, |point, k| point.at(k))
+    }
+
+    pub(crate) fn contains_by<T>(&self, item: &T, get: impl Fn(&T, usize) -> Scalar) -> bool {
+        (0..self.bounds.len()).all(|k| self.contains_coord(k, get(item, k)))
+    }
+
+    /// Returns `true` if no coordinate on `axis` less than or equal to `x` is in the range.
+    pub(crate) fn is_below(&self, axis: usize, x: Scalar) -> bool {
+        match self.bounds.get(axis).map(|range| range.0) {
+            Some(Bound::Included(min)) => x < min,
+            Some(Bound::Excluded(min)) => x <= min,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if no coordinate on `axis` greater than or equal to `x` is in the range.
+    pub(crate) fn is_above(&self, axis: usize, x: Scalar) -> bool {
+        match self.bounds.get(axis).map(|range| range.1) {
+            Some(Bound::Included(max)) => x > max,
+            Some(Bound::Excluded(max)) => x >= max,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use crate::binary::{invalid_data, read_header, write_header};
+use crate::{BinaryScalar, ItemAndDistance, KdPoint, KdScalar, KdSliceN};
+use std::io::{Read, Result, Write};
+use std::marker::PhantomData;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use typenum::Unsigned;
+
+/// The magic bytes at the beginning of the binary format of [`QueryLog::write_to`].
+const MAGIC: [u8; 8] = *b"KDQUERY\0";
+/// The version of the binary format of [`QueryLog::write_to`].
+const VERSION: u32 = 1;
+
+/// A query recorded in [`QueryLog`], whose points are given by their coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordedQuery<S> {
+    Nearest { query: Vec<S> },
+    Nearests { query: Vec<S>, num: usize },
+    Within { min: Vec<S>, max: Vec<S> },
+    WithinRadius { query: Vec<S>, radius: S },
+}
+
+impl<S> RecordedQuery<S> {
+    fn kind(&self) -> u8 {
+        match self {
+            Self::Nearest { .. } => 0,
+            Self::Nearests { .. } => 1,
+            Self::Within { .. } => 2,
+            Self::WithinRadius { .. } => 3,
+        }
+    }
+}
+
+/// A log of the parameters of queries, without the items of the tree,
+/// which is written to a compact binary file and replayed against a tree to measure the performance.
+///
+/// The log is recorded by [`QueryRecorder`], or built by [`Self::push`].
+/// Attaching a log with the items (e.g. by [`KdSliceN::save_to`]) makes a reproducible performance case.
+/// # Example
+/// ```
+/// let kdtree = kd_tree::KdTree::build_by_ordered_float(vec![[1.0, 2.0], [3.0, 1.0], [2.0, 3.0]]);
+/// let recorder = kd_tree::QueryRecorder::new(&kdtree);
+/// assert_eq!(recorder.nearest(&[3.1, 0.9]).unwrap().item, &[3.0, 1.0]);
+/// assert_eq!(recorder.within_radius(&[2.0, 2.0], 1.5).len(), 3);
+///
+/// let mut bytes = Vec::new();
+/// recorder.into_log().write_to(&mut bytes).unwrap();
+/// let log = kd_tree::QueryLog::<f64, typenum::U2>::read_from(&bytes[..]).unwrap();
+/// let report = log.replay(&kdtree);
+/// assert_eq!(report.num_queries, 2);
+/// assert_eq!(report.num_found, 4);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryLog<S, N: Unsigned> {
+    queries: Vec<RecordedQuery<S>>,
+    _dim: PhantomData<N>,
+}
+
+impl<S, N: Unsigned> Default for QueryLog<S, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The coordinates of a recorded point, as a query point of the tree.
+struct Coords<'a, S, N>(&'a [S], PhantomData<N>);
+
+impl<'a, S, N> Coords<'a, S, N> {
+    fn new(coords: &'a [S]) -> Self {
+        Self(coords, PhantomData)
+    }
+}
+
+impl<S: KdScalar, N: Unsigned> KdPoint for Coords<'_, S, N> {
+    type Scalar = S;
+    type Dim = N;
+    fn at(&self, k: usize) -> S {
+        self.0[k]
+    }
+}
+
+fn coords<P: KdPoint>(point: &P) -> Vec<P::Scalar> {
+    (0..P::dim()).map(|k| point.at(k)).collect()
+}
+
+fn write_point<S: BinaryScalar>(writer: &mut impl Write, point: &[S]) -> Result<()> {
+    for &x in point {
+        x.write_le(writer)?;
+    }
+    Ok(())
+}
+
+fn read_point<S: BinaryScalar>(reader: &mut impl Read, dim: usize) -> Result<Vec<S>> {
+    (0..dim).map(|_| S::read_le(reader)).collect()
+}
+
+/// The result of [`QueryLog::replay`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayReport {
+    pub num_queries: usize,
+    /// The total number of the items found by the queries, which should be the same when replayed against the same tree.
+    pub num_found: usize,
+    /// The total time taken by the queries.
+    pub elapsed: Duration,
+}
+
+impl<S, N: Unsigned> QueryLog<S, N> {
+    pub fn new() -> Self {
+        Self {
+            queries: Vec::new(),
+            _dim: PhantomData,
+        }
+    }
+
+    pub fn queries(&self) -> &[RecordedQuery<S>] {
+        &self.queries
+    }
+
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+
+    /// Appends a query. Panics if a point of `query` does not have `N` coordinates.
+    pub fn push(&mut self, query: RecordedQuery<S>) {
+        let points: &[&Vec<S>] = match &query {
+            RecordedQuery::Nearest { query } => &[query],
+            RecordedQuery::Nearests { query, .. } => &[query],
+            RecordedQuery::Within { min, max } => &[min, max],
+            RecordedQuery::WithinRadius { query, .. } => &[query],
+        };
+        assert!(
+            points.iter().all(|p| p.len() == N::to_usize()),
+            "dimension mismatch"
+        );
+        self.queries.push(query);
+    }
+
+    /// Runs the queries against `kdtree` in the recorded order, measuring the time taken by them.
+    pub fn replay<T>(&self, kdtree: &KdSliceN<T, N>) -> ReplayReport
+    where
+        T: KdPoint<Scalar = S, Dim = N>,
+        S: KdScalar,
+    {
+        let mut num_found = 0;
+        let start = Instant::now();
+        for query in &self.queries {
+            num_found += match query {
+                RecordedQuery::Nearest { query } => {
+                    std::hint::black_box(kdtree.nearest(&Coords::<S, N>::new(query))).is_some()
+                        as usize
+                }
+                RecordedQuery::Nearests { query, num } => {
+                    std::hint::black_box(kdtree.nearests(&Coords::<S, N>::new(query), *num)).len()
+                }
+                RecordedQuery::Within { min, max } => {
+                    std::hint::black_box(kdtree.within(&[Coords::new(min), Coords::new(max)])).len()
+                }
+                RecordedQuery::WithinRadius { query, radius } => {
+                    std::hint::black_box(kdtree.within_radius(&Coords::<S, N>::new(query), *radius))
+                        .len()
+                }
+            };
+        }
+        ReplayReport {
+            num_queries: self.queries.len(),
+            num_found,
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+impl<S: BinaryScalar, N: Unsigned> QueryLog<S, N> {
+    /// Writes the log in a binary format, which is read back by [`Self::read_from`].
+    ///
+    /// The queries are preceded by a header of the magic bytes, the format version, the dimension, the scalar type and the number of queries.
+    /// Wrap `writer` in a [`std::io::BufWriter`] unless it is buffered.
+    pub fn write_to(&self, mut writer: impl Write) -> Result<()> {
+        write_header::<S, N>(&mut writer, &MAGIC, VERSION, self.queries.len())?;
+        for query in &self.queries {
+            writer.write_all(&[query.kind()])?;
+            match query {
+                RecordedQuery::Nearest { query } => write_point(&mut writer, query)?,
+                RecordedQuery::Nearests { query, num } => {
+                    write_point(&mut writer, query)?;
+                    writer.write_all(&(*num as u64).to_le_bytes())?;
+                }
+                RecordedQuery::Within { min, max } => {
+                    write_point(&mut writer, min)?;
+                    write_point(&mut writer, max)?;
+                }
+                RecordedQuery::WithinRadius { query, radius } => {
+                    write_point(&mut writer, query)?;
+                    radius.write_le(&mut writer)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a log written by [`Self::write_to`].
+    /// Returns an error of kind `InvalidData` if the header does not match the version of this crate, `N` or `S`.
+    pub fn read_from(mut reader: impl Read) -> Result<Self> {
+        let len = read_header::<S, N>(&mut reader, &MAGIC, VERSION, "query log")?;
+        let dim = N::to_usize();
+        let mut log = Self::new();
+        for _ in 0..len {
+            let query = match u8::read_le(&mut reader)? {
+                0 => RecordedQuery::Nearest {
+                    query: read_point(&mut reader, dim)?,
+                },
+                1 => RecordedQuery::Nearests {
+                    query: read_point(&mut reader, dim)?,
+                    num: u64::read_le(&mut reader)? as usize,
+                },
+                2 => RecordedQuery::Within {
+                    min: read_point(&mut reader, dim)?,
+                    max: read_point(&mut reader, dim)?,
+                },
+                3 => RecordedQuery::WithinRadius {
+                    query: read_point(&mut reader, dim)?,
+                    radius: S::read_le(&mut reader)?,
+                },
+                kind => return Err(invalid_data(format!("unknown query kind {}", kind))),
+            };
+            log.queries.push(query);
+        }
+        Ok(log)
+    }
+}
+
+/// A wrapper of a kd-tree which records the parameters of the queries into a [`QueryLog`].
+///
+/// The queries are forwarded to the tree, so this can replace the tree temporarily in an application
+/// to capture its workload. See [`QueryLog`] for an example.
+pub struct QueryRecorder<'a, T: KdPoint, N: Unsigned> {
+    kdtree: &'a KdSliceN<T, N>,
+    log: Mutex<QueryLog<T::Scalar, N>>,
+}
+
+impl<'a, T: KdPoint<Dim = N>, N: Unsigned> QueryRecorder<'a, T, N> {
+    pub fn new(kdtree: &'a KdSliceN<T, N>) -> Self {
+        Self {
+            kdtree,
+            log: Mutex::new(QueryLog::new()),
+        }
+    }
+
+    pub fn kdtree(&self) -> &'a KdSliceN<T, N> {
+        self.kdtree
+    }
+
+    /// Returns the log recorded so far.
+    pub fn log(&self) -> QueryLog<T::Scalar, N>
+    where
+        T::Scalar: Clone,
+    {
+        self.log.lock().unwrap().clone()
+    }
+
+    pub fn into_log(self) -> QueryLog<T::Scalar, N> {
+        self.log.into_inner().unwrap()
+    }
+
+    fn record(&self, query: RecordedQuery<T::Scalar>) {
+        self.log.lock().unwrap().queries.push(query);
+    }
+
+    pub fn nearest(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<ItemAndDistance<'a, T, T::Scalar>> {
+        self.record(RecordedQuery::Nearest {
+            query: coords(query),
+        });
+        self.kdtree.nearest(query)
+    }
+
+    pub fn nearests(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+    ) -> Vec<ItemAndDistance<'a, T, T::Scalar>> {
+        self.record(RecordedQuery::Nearests {
+            query: coords(query),
+            num,
+        });
+        self.kdtree.nearests(query, num)
+    }
+
+    pub fn within(&self, query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2]) -> Vec<&'a T> {
+        self.record(RecordedQuery::Within {
+            min: coords(&query[0]),
+            max: coords(&query[1]),
+        });
+        self.kdtree.within(query)
+    }
+
+    pub fn within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<&'a T> {
+        self.record(RecordedQuery::WithinRadius {
+            query: coords(query),
+            radius,
+        });
+        self.kdtree.within_radius(query, radius)
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{KdPoint, KdScalar, KdSliceN};
+use typenum::Unsigned;
+
+/// Reverse nearest neighbor (RNN) queries, which find the items whose nearest neighbor would be the query point,
+/// e.g. the customers who would switch to a new facility at the query point.
+///
+/// The squared distance from each item to its nearest neighbor among the other items is precomputed
+/// by the all-nearest-neighbor search, along with its maximum in each subtree,
+/// so that a query prunes the subtrees which are farther from the query point than the nearest neighbors of all their items.
+/// # Example
+/// ```
+/// let kdtree = kd_tree::KdTree::build(vec![[0, 0], [1, 0], [5, 0], [9, 0]]);
+/// let rnn = kd_tree::ReverseNearest::new(&kdtree);
+/// let mut found = rnn.query(&[6, 0]);
+/// found.sort();
+/// assert_eq!(found, vec![&[5, 0], &[9, 0]]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReverseNearest<'a, T: KdPoint, N: Unsigned> {
+    kdtree: &'a KdSliceN<T, N>,
+    /// The squared distance from each item to its nearest neighbor, or `None` if there are no other items.
+    radii: Vec<Option<T::Scalar>>,
+    /// The maximum of `radii` in each subtree, stored at the position of the median item of the subtree.
+    max_radii: Vec<Option<T::Scalar>>,
+}
+
+/// Returns the larger one, where `None` is regarded as infinity.
+fn max<S: PartialOrd>(a: Option<S>, b: Option<S>) -> Option<S> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if a < b { b } else { a }),
+        _ => None,
+    }
+}
+
+impl<'a, T: KdPoint<Dim = N>, N: Unsigned> ReverseNearest<'a, T, N> {
+    /// Precomputes the nearest neighbor of every item, which takes `O(n log n)` time.
+    pub fn new(kdtree: &'a KdSliceN<T, N>) -> Self {
+        fn recurse<S: Copy + PartialOrd>(radii: &[Option<S>], max_radii: &mut [Option<S>]) {
+            if radii.is_empty() {
+                return;
+            }
+            let mid = radii.len() / 2;
+            let (lower, upper) = max_radii.split_at_mut(mid);
+            recurse(&radii[..mid], lower);
+            recurse(&radii[mid + 1..], &mut upper[1..]);
+            let mut result = radii[mid];
+            if !lower.is_empty() {
+                result = max(result, lower[lower.len() / 2]);
+            }
+            if upper.len() > 1 {
+                result = max(result, upper[1 + (upper.len() - 1) / 2]);
+            }
+            upper[0] = result;
+        }
+        let radii: Vec<_> = kdtree
+            .iter()
+            .map(|item| {
+                kdtree
+                    .nearest_excluding(item)
+                    .map(|found| found.squared_distance)
+            })
+            .collect();
+        let mut max_radii = radii.clone();
+        recurse(&radii, &mut max_radii);
+        Self {
+            kdtree,
+            radii,
+            max_radii,
+        }
+    }
+
+    pub fn kdtree(&self) -> &'a KdSliceN<T, N> {
+        self.kdtree
+    }
+
+    /// Returns the items which are at least as near to the query point as to any other item,
+    /// i.e. the items whose nearest neighbor would be the query point if it were added to the tree.
+    pub fn query(&self, query: &impl KdPoint<Scalar = T::Scalar, Dim = N>) -> Vec<&'a T> {
+        fn recurse<'a, T: KdPoint>(
+            results: &mut Vec<&'a T>,
+            items: &'a [T],
+            radii: &[Option<T::Scalar>],
+            max_radii: &[Option<T::Scalar>],
+            query: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+            axis: usize,
+            bound: T::Scalar,
+        ) {
+            if items.is_empty() {
+                return;
+            }
+            let mid = items.len() / 2;
+            // `bound` is a lower bound of the squared distance from the query to the items in this subtree.
+            if max_radii[mid].is_some_and(|max_radius| max_radius < bound) {
+                return;
+            }
+            let item = &items[mid];
+            if radii[mid].map_or(true, |radius| {
+                crate::squared_distance(item, query) <= radius
+            }) {
+                results.push(item);
+            }
+            let diff = query.at(axis) - item.at(axis);
+            let far_bound = diff * diff;
+            let far_bound = if far_bound < bound { bound } else { far_bound };
+            let (lower_bound, upper_bound) = if diff < T::Scalar::zero() {
+                (bound, far_bound)
+            } else {
+                (far_bound, bound)
+            };
+            let next = (axis + 1) % T::dim();
+            let lower = (&items[..mid], &radii[..mid], &max_radii[..mid]);
+            let upper = (&items[mid + 1..], &radii[mid + 1..], &max_radii[mid + 1..]);
+            recurse(results, lower.0, lower.1, lower.2, query, next, lower_bound);
+            recurse(results, upper.0, upper.1, upper.2, query, next, upper_bound);
+        }
+        let mut results = Vec::new();
+        recurse(
+            &mut results,
+            self.kdtree.items(),
+            &self.radii,
+            &self.max_radii,
+            query,
+            0,
+            T::Scalar::zero(),
+        );
+        results
+    }
+}
+
+impl<T: KdPoint<Dim = N>, N: Unsigned> KdSliceN<T, N> {
+    /// Returns the items whose nearest neighbor among the items and the query point would be the query point,
+    /// i.e. the reverse nearest neighbors of the query point.
+    ///
+    /// This precomputes the nearest neighbors of all the items in `O(n log n)` time.
+    /// Use [`ReverseNearest`] to answer many queries with a tree.
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[0, 0], [1, 0], [5, 0], [9, 0]]);
+    /// assert_eq!(kdtree.reverse_nearest(&[0, 1]), vec![&[0, 0]]);
+    /// ```
+    pub fn reverse_nearest(&self, query: &impl KdPoint<Scalar = T::Scalar, Dim = N>) -> Vec<&T> {
+        ReverseNearest::new(self).query(query)
+    }
+}
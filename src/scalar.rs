@@ -0,0 +1,65 @@
+use std::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
+
+/// A trait to represent the scalar type of the coordinates of [`KdPoint`](crate::KdPoint).
+///
+/// This trait requires only the operations used by the queries,
+/// and is implemented for all types implementing `num_traits::NumAssign + Copy + PartialOrd`, such as primitive numbers.
+/// So a custom scalar type, e.g. a saturating integer or an interval of floats, can be used as coordinates
+/// by implementing this trait (and the arithmetic operators) instead of the whole `num-traits` stack.
+/// # Example
+/// ```
+/// use std::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
+/// #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+/// struct Saturating(u8);
+/// impl Add for Saturating {
+///     type Output = Self;
+///     fn add(self, other: Self) -> Self { Saturating(self.0.saturating_add(other.0)) }
+/// }
+/// impl Sub for Saturating {
+///     type Output = Self;
+///     fn sub(self, other: Self) -> Self { Saturating(self.0.saturating_sub(other.0)) }
+/// }
+/// impl Mul for Saturating {
+///     type Output = Self;
+///     fn mul(self, other: Self) -> Self { Saturating(self.0.saturating_mul(other.0)) }
+/// }
+/// impl AddAssign for Saturating { fn add_assign(&mut self, other: Self) { *self = *self + other; } }
+/// impl SubAssign for Saturating { fn sub_assign(&mut self, other: Self) { *self = *self - other; } }
+/// impl MulAssign for Saturating { fn mul_assign(&mut self, other: Self) { *self = *self * other; } }
+/// impl kd_tree::KdScalar for Saturating {
+///     fn zero() -> Self { Saturating(0) }
+///     fn one() -> Self { Saturating(1) }
+/// }
+/// let points = vec![[Saturating(0), Saturating(0)], [Saturating(200), Saturating(10)]];
+/// let kdtree = kd_tree::KdTree::build_by(points, |a, b, k| a[k].0.cmp(&b[k].0));
+/// let found = kdtree.nearest(&[Saturating(190), Saturating(20)]).unwrap();
+/// assert_eq!(found.item, &[Saturating(200), Saturating(10)]);
+/// ```
+pub trait KdScalar:
+    Copy
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+{
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn is_zero(&self) -> bool {
+        *self == Self::zero()
+    }
+}
+
+impl<T: num_traits::NumAssign + Copy + PartialOrd> KdScalar for T {
+    fn zero() -> Self {
+        num_traits::Zero::zero()
+    }
+    fn one() -> Self {
+        num_traits::One::one()
+    }
+    fn is_zero(&self) -> bool {
+        num_traits::Zero::is_zero(self)
+    }
+}
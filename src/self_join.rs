@@ -0,0 +1,135 @@
+use crate::augment::per_subtree;
+use crate::{squared_distance, Aabb, KdPoint, SubtreeBound};
+use generic_array::ArrayLength;
+
+/// Returns the pairs of the offsets `(i, j)` with `i < j` of all items closer than `radius`, sorted in ascending order.
+///
+/// Instead of querying each item, the tree is joined with itself in a single traversal over pairs of subtrees,
+/// which skips a pair of subtrees at once when their bounding boxes are not closer than `radius`.
+pub fn kd_neighbor_pairs<T: KdPoint>(kdtree: &[T], radius: T::Scalar) -> Vec<(usize, usize)>
+where
+    T::Dim: ArrayLength,
+{
+    /// A subtree given by its offset and its length.
+    #[derive(Clone, Copy)]
+    struct Subtree {
+        offset: usize,
+        len: usize,
+    }
+    impl Subtree {
+        fn mid(self) -> usize {
+            self.offset + self.len / 2
+        }
+        fn split(self) -> (Self, Self) {
+            let lower = Self {
+                offset: self.offset,
+                len: self.len / 2,
+            };
+            let upper = Self {
+                offset: self.mid() + 1,
+                len: self.len - self.len / 2 - 1,
+            };
+            (lower, upper)
+        }
+    }
+    struct Join<'a, T: KdPoint>
+    where
+        T::Dim: ArrayLength,
+    {
+        items: &'a [T],
+        aabbs: Vec<Aabb<T::Scalar, T::Dim>>,
+        squared_radius: T::Scalar,
+        pairs: Vec<(usize, usize)>,
+    }
+    impl<T: KdPoint> Join<'_, T>
+    where
+        T::Dim: ArrayLength,
+    {
+        fn push(&mut self, i: usize, j: usize) {
+            if squared_distance(&self.items[i], &self.items[j]) < self.squared_radius {
+                self.pairs.push(if i < j { (i, j) } else { (j, i) });
+            }
+        }
+        /// Finds the pairs within `subtree`.
+        fn join_self(&mut self, subtree: Subtree) {
+            if subtree.len < 2 {
+                return;
+            }
+            let (lower, upper) = subtree.split();
+            self.join_item(subtree.mid(), lower);
+            self.join_item(subtree.mid(), upper);
+            self.join_self(lower);
+            self.join_self(upper);
+            self.join(lower, upper);
+        }
+        /// Finds the pairs of the `i`-th item and the items in `subtree`.
+        fn join_item(&mut self, i: usize, subtree: Subtree) {
+            if subtree.len == 0
+                || SubtreeBound::<T>::min_squared_distance(
+                    &self.aabbs[subtree.mid()],
+                    &self.items[i],
+                ) >= self.squared_radius
+            {
+                return;
+            }
+            self.push(i, subtree.mid());
+            let (lower, upper) = subtree.split();
+            self.join_item(i, lower);
+            self.join_item(i, upper);
+        }
+        /// Finds the pairs across two disjoint subtrees.
+        fn join(&mut self, subtree1: Subtree, subtree2: Subtree) {
+            if subtree1.len == 0
+                || subtree2.len == 0
+                || aabb_squared_distance(&self.aabbs[subtree1.mid()], &self.aabbs[subtree2.mid()])
+                    >= self.squared_radius
+            {
+                return;
+            }
+            // split the larger one.
+            let (larger, smaller) = if subtree1.len >= subtree2.len {
+                (subtree1, subtree2)
+            } else {
+                (subtree2, subtree1)
+            };
+            self.join_item(larger.mid(), smaller);
+            let (lower, upper) = larger.split();
+            self.join(lower, smaller);
+            self.join(upper, smaller);
+        }
+    }
+    let mut join = Join {
+        items: kdtree,
+        aabbs: per_subtree(
+            kdtree,
+            <Aabb<T::Scalar, T::Dim> as SubtreeBound<T>>::from_items,
+        ),
+        squared_radius: radius * radius,
+        pairs: Vec::new(),
+    };
+    join.join_self(Subtree {
+        offset: 0,
+        len: kdtree.len(),
+    });
+    join.pairs.sort_unstable();
+    join.pairs
+}
+
+/// Returns the squared distance between two axis-aligned bounding boxes, which is zero if they intersect.
+fn aabb_squared_distance<S: crate::KdScalar, N: ArrayLength>(
+    aabb1: &Aabb<S, N>,
+    aabb2: &Aabb<S, N>,
+) -> S {
+    let mut squared_distance = S::zero();
+    for k in 0..aabb1.min.len() {
+        let diff = if aabb1.max[k] < aabb2.min[k] {
+            aabb2.min[k] - aabb1.max[k]
+        } else if aabb2.max[k] < aabb1.min[k] {
+            aabb1.min[k] - aabb2.max[k]
+        } else {
+            continue;
+        };
+        squared_distance += diff * diff;
+    }
+    squared_distance
+}
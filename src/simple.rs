@@ -0,0 +1,74 @@
+//! A simple interface for the most common cases: 2-D or 3-D points of `f32` or `f64`.
+//!
+//! The trees are plain [`crate::KdTree`]s of arrays, and the queries are plain functions which take and return points by value,
+//! so no trait or `typenum` type appears in user code. The generic API of the crate remains available for the same trees.
+//! # Example
+//! ```
+//! use kd_tree::simple;
+//! let tree: simple::Tree3 = simple::build(vec![[1.0, 2.0, 3.0], [3.0, 1.0, 2.0], [2.0, 3.0, 1.0]]);
+//! assert_eq!(simple::nearest(&tree, [3.1, 0.9, 2.1]), Some([3.0, 1.0, 2.0]));
+//! assert_eq!(simple::nearests(&tree, [1.5, 2.5, 1.8], 2), vec![[2.0, 3.0, 1.0], [1.0, 2.0, 3.0]]);
+//! assert_eq!(simple::radius_search(&tree, [2.0, 1.5, 2.5], 1.5).len(), 2);
+//! ```
+use crate::{KdPoint, KdTree};
+use ordered_float::FloatCore;
+
+/// A tree of 2-D points of `f64`.
+pub type Tree2 = KdTree<[f64; 2]>;
+/// A tree of 3-D points of `f64`.
+pub type Tree3 = KdTree<[f64; 3]>;
+/// A tree of 2-D points of `f32`.
+pub type Tree2f32 = KdTree<[f32; 2]>;
+/// A tree of 3-D points of `f32`.
+pub type Tree3f32 = KdTree<[f32; 3]>;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// The point types supported by this module: `[f64; 2]`, `[f64; 3]`, `[f32; 2]` and `[f32; 3]`.
+pub trait Point: KdPoint + Copy + private::Sealed {}
+
+macro_rules! impl_point {
+    ($($t:ty),*) => {
+        $(
+            impl private::Sealed for $t {}
+            impl Point for $t {}
+        )*
+    };
+}
+impl_point!([f64; 2], [f64; 3], [f32; 2], [f32; 3]);
+
+/// Builds a tree of `points`.
+pub fn build<P: Point>(points: Vec<P>) -> KdTree<P>
+where
+    P::Scalar: FloatCore,
+{
+    KdTree::build_by_ordered_float(points)
+}
+
+/// Returns the nearest point to `query`, or `None` if the tree is empty.
+pub fn nearest<P: Point>(tree: &KdTree<P>, query: P) -> Option<P> {
+    tree.nearest(&query).map(|found| *found.item)
+}
+
+/// Returns the `k` nearest points to `query`, nearest first.
+pub fn nearests<P: Point>(tree: &KdTree<P>, query: P, k: usize) -> Vec<P> {
+    tree.nearests(&query, k)
+        .into_iter()
+        .map(|found| *found.item)
+        .collect()
+}
+
+/// Returns the points whose distance from `query` is less than `radius`, in no particular order.
+pub fn radius_search<P: Point>(tree: &KdTree<P>, query: P, radius: P::Scalar) -> Vec<P> {
+    tree.within_radius(&query, radius)
+        .into_iter()
+        .copied()
+        .collect()
+}
+
+/// Returns the points within the axis-aligned box between the corners `min` and `max`, in no particular order.
+pub fn box_search<P: Point>(tree: &KdTree<P>, min: P, max: P) -> Vec<P> {
+    tree.within(&[min, max]).into_iter().copied().collect()
+}
@@ -0,0 +1,115 @@
+use crate::{KdPoint, KdSliceN};
+use typenum::Unsigned;
+
+/// Returns `true` if `p` dominates `q`, i.e. `p` is less than or equal to `q` on every axis and less on some axis.
+fn dominates<S: PartialOrd>(p: impl Fn(usize) -> S, q: impl Fn(usize) -> S, dim: usize) -> bool {
+    let mut less = false;
+    for k in 0..dim {
+        let (a, b) = (p(k), q(k));
+        if a > b {
+            return false;
+        }
+        less |= a < b;
+    }
+    less
+}
+
+/// Collects the non-dominated items within `region`, which are maintained in `skyline`.
+fn recurse<'a, T: KdPoint>(
+    skyline: &mut Vec<&'a T>,
+    items: &'a [T],
+    axis: usize,
+    // the lower corner of the subtree, where `None` is the negative infinity.
+    corner: &mut [Option<T::Scalar>],
+    region: Option<&[Vec<T::Scalar>; 2]>,
+) {
+    if items.is_empty() {
+        return;
+    }
+    // every item in the subtree is dominated if its lower corner is.
+    if corner.iter().all(Option::is_some)
+        && skyline
+            .iter()
+            .any(|p| dominates(|k| p.at(k), |k| corner[k].unwrap(), T::dim()))
+    {
+        return;
+    }
+    let mid = items.len() / 2;
+    let item = &items[mid];
+    let split = item.at(axis);
+    let next = (axis + 1) % T::dim();
+    if region.map_or(true, |[min, _]| min[axis] <= split) {
+        recurse(skyline, &items[..mid], next, corner, region);
+    }
+    let inside = region.map_or(true, |[min, max]| {
+        (0..T::dim()).all(|k| min[k] <= item.at(k) && item.at(k) <= max[k])
+    });
+    if inside
+        && !skyline
+            .iter()
+            .any(|p| dominates(|k| p.at(k), |k| item.at(k), T::dim()))
+    {
+        skyline.retain(|p| !dominates(|k| item.at(k), |k| p.at(k), T::dim()));
+        skyline.push(item);
+    }
+    if region.map_or(true, |[_, max]| split <= max[axis]) {
+        let saved = corner[axis];
+        if saved.map_or(true, |lower| lower < split) {
+            corner[axis] = Some(split);
+        }
+        recurse(skyline, &items[mid + 1..], next, corner, region);
+        corner[axis] = saved;
+    }
+}
+
+impl<T: KdPoint<Dim = N>, N: Unsigned> KdSliceN<T, N> {
+    /// Returns the skyline, i.e. the Pareto-optimal items which are not dominated by any other item,
+    /// where an item dominates another if it is less than or equal to the other on every axis and less on some axis.
+    ///
+    /// The items are returned in no particular order. Negate the coordinates on the axes where larger values are preferred.
+    /// The subtrees whose lower corners are dominated by the items found so far are pruned.
+    /// # Example
+    /// ```
+    /// // (price, distance) of hotels.
+    /// let kdtree = kd_tree::KdTree::build(vec![[50, 8], [80, 2], [60, 9], [90, 1], [70, 5], [100, 3]]);
+    /// let mut skyline = kdtree.skyline();
+    /// skyline.sort();
+    /// assert_eq!(skyline, vec![&[50, 8], &[70, 5], &[80, 2], &[90, 1]]);
+    /// ```
+    pub fn skyline(&self) -> Vec<&T> {
+        let mut skyline = Vec::new();
+        recurse(
+            &mut skyline,
+            self.items(),
+            0,
+            &mut vec![None; N::to_usize()],
+            None,
+        );
+        skyline
+    }
+
+    /// Returns the skyline of the items within a rectangular region. See [`Self::skyline`].
+    /// # Example
+    /// ```
+    /// let kdtree = kd_tree::KdTree::build(vec![[50, 8], [80, 2], [60, 9], [90, 1], [70, 5], [100, 3]]);
+    /// let mut skyline = kdtree.skyline_within(&[[55, 0], [100, 10]]);
+    /// skyline.sort();
+    /// assert_eq!(skyline, vec![&[60, 9], &[70, 5], &[80, 2], &[90, 1]]);
+    /// ```
+    pub fn skyline_within(
+        &self,
+        query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2],
+    ) -> Vec<&T> {
+        let region = [
+            (0..N::to_usize()).map(|k| query[0].at(k)).collect(),
+            (0..N::to_usize())
+                .map(|k| query[1].at(k))
+                .collect::<Vec<_>>(),
+        ];
+        // the items below the region are out of the region.
+        let mut corner: Vec<_> = region[0].iter().copied().map(Some).collect();
+        let mut skyline = Vec::new();
+        recurse(&mut skyline, self.items(), 0, &mut corner, Some(&region));
+        skyline
+    }
+}
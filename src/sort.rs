@@ -1,27 +1,315 @@
 use std::cmp::Ordering;
 
+/// Sorts `items` into a kd-tree, where `kd_compare(x, y, k)` compares `x` and `y` along the `k`-th axis.
+///
+/// Each subtree places its median item along the axis `depth % dim` at the middle,
+/// the lower half before it and the upper half after it.
 pub fn kd_sort_by<T>(
     items: &mut [T],
     dim: usize,
     kd_compare: impl Fn(&T, &T, usize) -> Ordering + Copy,
 ) {
+    kd_sort_by_from_axis(items, 0, dim, kd_compare);
+}
+
+fn kd_sort_by_from_axis<T>(
+    items: &mut [T],
+    axis: usize,
+    dim: usize,
+    kd_compare: impl Fn(&T, &T, usize) -> Ordering + Copy,
+) {
+    if items.len() >= 2 {
+        items.select_nth_unstable_by(items.len() / 2, |x, y| kd_compare(x, y, axis));
+        let mid = items.len() / 2;
+        let axis = (axis + 1) % dim;
+        kd_sort_by_from_axis(&mut items[..mid], axis, dim, kd_compare);
+        kd_sort_by_from_axis(&mut items[mid + 1..], axis, dim, kd_compare);
+    }
+}
+
+/// Returns the offset of an item which violates the order made by `kd_sort_by`, or `None` if `items` are sorted.
+///
+/// The items of each subtree must not be greater than its median item in the lower half, nor less than it in the upper half,
+/// along the axis of the subtree, which takes `O(n log n)` time to verify.
+pub fn kd_find_unsorted_by<T>(
+    items: &[T],
+    dim: usize,
+    kd_compare: impl Fn(&T, &T, usize) -> Ordering + Copy,
+) -> Option<usize> {
     fn recurse<T>(
-        items: &mut [T],
+        items: &[T],
+        offset: usize,
         axis: usize,
         dim: usize,
         kd_compare: impl Fn(&T, &T, usize) -> Ordering + Copy,
+    ) -> Option<usize> {
+        if items.len() < 2 {
+            return None;
+        }
+        let mid = items.len() / 2;
+        let (lower, upper) = (&items[..mid], &items[mid + 1..]);
+        if let Some(i) = lower
+            .iter()
+            .position(|item| kd_compare(item, &items[mid], axis) == Ordering::Greater)
+        {
+            return Some(offset + i);
+        }
+        if let Some(i) = upper
+            .iter()
+            .position(|item| kd_compare(item, &items[mid], axis) == Ordering::Less)
+        {
+            return Some(offset + mid + 1 + i);
+        }
+        let next_axis = (axis + 1) % dim;
+        recurse(lower, offset, next_axis, dim, kd_compare)
+            .or_else(|| recurse(upper, offset + mid + 1, next_axis, dim, kd_compare))
+    }
+    recurse(items, 0, 0, dim, kd_compare)
+}
+
+/// Inserts `item` into `items` sorted by `kd_sort_by`, keeping them sorted.
+///
+/// No subtree is sorted again: when the median index of a subtree moves by the insertion,
+/// the median is replaced by its neighbor along the axis, the greatest item of the lower half or the least one of the upper half,
+/// which is found by a search pruned along the axis,
+/// and the subtrees which lose or gain an item are repaired in the same way.
+/// The items after the inserted position are shifted as in [`Vec::insert`].
+pub fn kd_insert_by<T>(
+    items: &mut Vec<T>,
+    item: T,
+    dim: usize,
+    kd_compare: impl Fn(&T, &T, usize) -> Ordering + Copy,
+) {
+    items.push(item);
+    insert_last(items, 0, dim, kd_compare);
+}
+
+/// Sorts `items` whose last item is new and the others are sorted by `kd_sort_by_from_axis`.
+fn insert_last<T>(
+    items: &mut [T],
+    axis: usize,
+    dim: usize,
+    kd_compare: impl Fn(&T, &T, usize) -> Ordering + Copy,
+) {
+    let n = items.len() - 1;
+    if n == 0 {
+        return;
+    }
+    let mid = n / 2;
+    let next_axis = (axis + 1) % dim;
+    if n % 2 == 0 {
+        // the upper half grows, and the median stays at `mid`.
+        if kd_compare(&items[n], &items[mid], axis) == Ordering::Less {
+            // the median goes to the upper half, and the greatest of the lower half and the new item becomes the median.
+            items.swap(mid, n);
+            raise_median_from_lower(items, mid, axis, dim, kd_compare);
+        }
+        insert_last(&mut items[mid + 1..], next_axis, dim, kd_compare);
+    } else {
+        // the lower half grows, and the median moves to `mid + 1`.
+        items[mid..].rotate_right(1);
+        if kd_compare(&items[mid], &items[mid + 1], axis) == Ordering::Greater {
+            // the median goes to the lower half, and the least of the upper half and the new item becomes the median.
+            items.swap(mid, mid + 1);
+            lower_median_from_upper(items, mid + 1, axis, dim, kd_compare);
+        }
+        insert_last(&mut items[..=mid], next_axis, dim, kd_compare);
+    }
+}
+
+/// Sorts `items` sorted by `kd_sort_by_from_axis` except that the item at `index` is replaced.
+fn replace_at<T>(
+    items: &mut [T],
+    index: usize,
+    axis: usize,
+    dim: usize,
+    kd_compare: impl Fn(&T, &T, usize) -> Ordering + Copy,
+) {
+    if items.len() < 2 {
+        return;
+    }
+    let mid = items.len() / 2;
+    let next_axis = (axis + 1) % dim;
+    match index.cmp(&mid) {
+        Ordering::Equal => {
+            if !raise_median_from_lower(items, mid, axis, dim, kd_compare) {
+                lower_median_from_upper(items, mid, axis, dim, kd_compare);
+            }
+        }
+        Ordering::Less => {
+            if kd_compare(&items[index], &items[mid], axis) == Ordering::Greater {
+                items.swap(index, mid);
+                lower_median_from_upper(items, mid, axis, dim, kd_compare);
+            }
+            replace_at(&mut items[..mid], index, next_axis, dim, kd_compare);
+        }
+        Ordering::Greater => {
+            if kd_compare(&items[index], &items[mid], axis) == Ordering::Less {
+                items.swap(index, mid);
+                raise_median_from_lower(items, mid, axis, dim, kd_compare);
+            }
+            replace_at(
+                &mut items[mid + 1..],
+                index - mid - 1,
+                next_axis,
+                dim,
+                kd_compare,
+            );
+        }
+    }
+}
+
+/// Swaps the item at `mid` with the greatest item of `items[..mid]` if it is greater, and repairs the lower half.
+/// Returns whether they are swapped.
+fn raise_median_from_lower<T>(
+    items: &mut [T],
+    mid: usize,
+    axis: usize,
+    dim: usize,
+    kd_compare: impl Fn(&T, &T, usize) -> Ordering + Copy,
+) -> bool {
+    if mid == 0 {
+        return false;
+    }
+    let next_axis = (axis + 1) % dim;
+    let i = kd_extreme_by(
+        &items[..mid],
+        axis,
+        next_axis,
+        dim,
+        kd_compare,
+        Ordering::Greater,
+    );
+    if kd_compare(&items[i], &items[mid], axis) != Ordering::Greater {
+        return false;
+    }
+    items.swap(i, mid);
+    replace_at(&mut items[..mid], i, next_axis, dim, kd_compare);
+    true
+}
+
+/// Swaps the item at `mid` with the least item of `items[mid + 1..]` if it is less, and repairs the upper half.
+fn lower_median_from_upper<T>(
+    items: &mut [T],
+    mid: usize,
+    axis: usize,
+    dim: usize,
+    kd_compare: impl Fn(&T, &T, usize) -> Ordering + Copy,
+) {
+    if mid + 1 == items.len() {
+        return;
+    }
+    let next_axis = (axis + 1) % dim;
+    let i = mid
+        + 1
+        + kd_extreme_by(
+            &items[mid + 1..],
+            axis,
+            next_axis,
+            dim,
+            kd_compare,
+            Ordering::Less,
+        );
+    if kd_compare(&items[i], &items[mid], axis) != Ordering::Less {
+        return;
+    }
+    items.swap(i, mid);
+    replace_at(
+        &mut items[mid + 1..],
+        i - mid - 1,
+        next_axis,
+        dim,
+        kd_compare,
+    );
+}
+
+/// Returns the offset of the greatest item along the `target` axis if `extreme` is `Greater`, or the least one if it is `Less`,
+/// in non-empty `items` sorted by `kd_sort_by_from_axis` from `axis`.
+/// The half which can't contain it is skipped in the subtrees split along the `target` axis.
+fn kd_extreme_by<T>(
+    items: &[T],
+    target: usize,
+    axis: usize,
+    dim: usize,
+    kd_compare: impl Fn(&T, &T, usize) -> Ordering + Copy,
+    extreme: Ordering,
+) -> usize {
+    let mid = items.len() / 2;
+    let next_axis = (axis + 1) % dim;
+    let mut best = mid;
+    let halves = [
+        (0, mid, Ordering::Less),
+        (mid + 1, items.len(), Ordering::Greater),
+    ];
+    for (lo, hi, side) in halves {
+        if lo < hi && (axis != target || side == extreme) {
+            let i = lo + kd_extreme_by(&items[lo..hi], target, next_axis, dim, kd_compare, extreme);
+            if kd_compare(&items[i], &items[best], target) == extreme {
+                best = i;
+            }
+        }
+    }
+    best
+}
+
+/// Removes the items marked in `removed` from `items` sorted by `kd_sort_by`, keeping them sorted,
+/// and returns the removed items.
+///
+/// A subtree keeps its structure if its median item remains and the median index doesn't move,
+/// otherwise the remaining items of the subtree are sorted again.
+pub fn kd_remove_by<T>(
+    items: &mut Vec<T>,
+    removed: &[bool],
+    dim: usize,
+    kd_compare: impl Fn(&T, &T, usize) -> Ordering + Copy,
+) -> Vec<T> {
+    fn collect_unsorted(
+        unsorted: &mut Vec<(usize, usize, usize)>,
+        removed_before: &[usize],
+        lo: usize,
+        hi: usize,
+        axis: usize,
+        dim: usize,
     ) {
-        if items.len() >= 2 {
-            items.select_nth_unstable_by(items.len() / 2, |x, y| kd_compare(x, y, axis));
-            let mid = items.len() / 2;
-            let axis = (axis + 1) % dim;
-            recurse(&mut items[..mid], axis, dim, kd_compare);
-            recurse(&mut items[mid + 1..], axis, dim, kd_compare);
+        let count = |lo: usize, hi: usize| removed_before[hi] - removed_before[lo];
+        if count(lo, hi) == 0 {
+            return;
+        }
+        let mid = lo + (hi - lo) / 2;
+        let len = hi - lo - count(lo, hi);
+        if count(mid, mid + 1) == 0 && mid - lo - count(lo, mid) == len / 2 {
+            let next_axis = (axis + 1) % dim;
+            collect_unsorted(unsorted, removed_before, lo, mid, next_axis, dim);
+            collect_unsorted(unsorted, removed_before, mid + 1, hi, next_axis, dim);
+        } else {
+            unsorted.push((lo - removed_before[lo], hi - removed_before[hi], axis));
         }
     }
-    recurse(items, 0, dim, kd_compare);
+    let mut removed_before = Vec::with_capacity(items.len() + 1);
+    removed_before.push(0);
+    for &r in removed {
+        removed_before.push(removed_before.last().unwrap() + r as usize);
+    }
+    let mut unsorted = Vec::new();
+    collect_unsorted(&mut unsorted, &removed_before, 0, items.len(), 0, dim);
+    let num_removed = removed_before[items.len()];
+    let mut removed_items = Vec::with_capacity(num_removed);
+    let mut remaining = Vec::with_capacity(items.len() - num_removed);
+    for (item, &r) in items.drain(..).zip(removed) {
+        if r {
+            removed_items.push(item);
+        } else {
+            remaining.push(item);
+        }
+    }
+    for (lo, hi, axis) in unsorted {
+        kd_sort_by_from_axis(&mut remaining[lo..hi], axis, dim, kd_compare);
+    }
+    *items = remaining;
+    removed_items
 }
 
+/// Same as [`kd_sort_by`], but the subtrees are sorted in parallel.
 #[cfg(feature = "rayon")]
 pub fn kd_par_sort_by<T: Send>(
     items: &mut [T],
@@ -0,0 +1,173 @@
+use crate::{KdPoint, KdSliceN, KdTreeN};
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+use typenum::Unsigned;
+
+/// A kd-tree whose items have stable ids, which survive the reordering by the build and serialization,
+/// unlike the offsets in the slice.
+///
+/// The ids are either given by the user, or derived from the positions of the items in the input `Vec`.
+/// This type implements [`std::ops::Deref`] to [`KdSliceN`], so all the queries are available,
+/// and [`Self::id_of`] tells the id of a found item.
+/// With `serde` feature, this type implements `Serialize` and `Deserialize`.
+/// # Example
+/// ```
+/// let kdtree = kd_tree::StableKdTree::build(vec![[1, 2], [3, 1], [2, 3]]);
+/// let found = kdtree.nearest(&[3, 0]).unwrap();
+/// assert_eq!(kdtree.id_of(found.item), &1);
+/// assert_eq!(kdtree.find_by_id(&2), Some(&[2, 3]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StableKdTreeN<T, Id, N: Unsigned> {
+    kdtree: KdTreeN<T, N>,
+    /// The ids of the items, in the order of the items.
+    ids: Vec<Id>,
+    /// The offsets of the items, sorted by their ids.
+    index: Vec<usize>,
+}
+pub type StableKdTree<T, Id = usize> = StableKdTreeN<T, Id, <T as KdPoint>::Dim>;
+
+impl<T, Id, N: Unsigned> std::ops::Deref for StableKdTreeN<T, Id, N> {
+    type Target = KdSliceN<T, N>;
+    fn deref(&self) -> &Self::Target {
+        &self.kdtree
+    }
+}
+
+impl<T, Id: Ord, N: Unsigned> StableKdTreeN<T, Id, N> {
+    /// Builds a kd-tree of `items` whose ids are `ids` respectively.
+    ///
+    /// # Panics
+    /// Panics if `items` and `ids` have different lengths.
+    pub fn build_with_ids_by<F>(items: Vec<T>, ids: Vec<Id>, compare: F) -> Self
+    where
+        F: Fn(&T, &T, usize) -> Ordering + Copy,
+    {
+        assert_eq!(
+            items.len(),
+            ids.len(),
+            "items and ids must have the same length"
+        );
+        let pairs: Vec<(Id, T)> = ids.into_iter().zip(items).collect();
+        let kdtree =
+            KdTreeN::<_, N>::build_by(pairs, |(_, item1), (_, item2), k| compare(item1, item2, k));
+        let (ids, items) = kdtree.into_vec().into_iter().unzip();
+        Self::from_parts(KdTreeN(PhantomData, items), ids)
+    }
+
+    pub fn build_with_ids_by_ordered_float(points: Vec<T>, ids: Vec<Id>) -> Self
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        Self::build_with_ids_by(points, ids, |item1, item2, k| {
+            ordered_float::OrderedFloat(item1.at(k)).cmp(&ordered_float::OrderedFloat(item2.at(k)))
+        })
+    }
+
+    pub fn build_with_ids(points: Vec<T>, ids: Vec<Id>) -> Self
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: Ord,
+    {
+        Self::build_with_ids_by(points, ids, |item1, item2, k| item1.at(k).cmp(&item2.at(k)))
+    }
+
+    /// `ids` are in the order of the items of `kdtree`.
+    fn from_parts(kdtree: KdTreeN<T, N>, ids: Vec<Id>) -> Self {
+        let mut index: Vec<usize> = (0..ids.len()).collect();
+        index.sort_by(|&i, &j| ids[i].cmp(&ids[j]));
+        Self { kdtree, ids, index }
+    }
+
+    pub fn kdtree(&self) -> &KdTreeN<T, N> {
+        &self.kdtree
+    }
+
+    /// Returns the ids in the order of the items.
+    pub fn ids(&self) -> &[Id] {
+        &self.ids
+    }
+
+    /// Returns the id of `item`, which must be a reference to an item of this tree, e.g. a query result.
+    ///
+    /// # Panics
+    /// Panics if `item` is not an item of this tree.
+    pub fn id_of(&self, item: &T) -> &Id {
+        let range = self.kdtree.as_ptr_range();
+        assert!(
+            range.contains(&(item as *const T)),
+            "item is not an item of this tree"
+        );
+        &self.ids[self.kdtree.offset_of(item)]
+    }
+
+    /// Returns the item of `id`, or `None` if there is no such item.
+    /// If several items have the same id, one of them is returned.
+    pub fn find_by_id(&self, id: &Id) -> Option<&T> {
+        self.index
+            .binary_search_by(|&i| self.ids[i].cmp(id))
+            .ok()
+            .map(|i| &self.kdtree[self.index[i]])
+    }
+
+    /// Returns the pairs of the id and the item, in the order of the items.
+    pub fn into_vec(self) -> Vec<(Id, T)> {
+        self.ids.into_iter().zip(self.kdtree.into_vec()).collect()
+    }
+}
+
+impl<T, N: Unsigned> StableKdTreeN<T, usize, N> {
+    /// Builds a kd-tree whose ids are the positions of the items in `items`.
+    pub fn build_by<F>(items: Vec<T>, compare: F) -> Self
+    where
+        F: Fn(&T, &T, usize) -> Ordering + Copy,
+    {
+        let ids = (0..items.len()).collect();
+        Self::build_with_ids_by(items, ids, compare)
+    }
+
+    pub fn build_by_ordered_float(points: Vec<T>) -> Self
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        let ids = (0..points.len()).collect();
+        Self::build_with_ids_by_ordered_float(points, ids)
+    }
+
+    pub fn build(points: Vec<T>) -> Self
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: Ord,
+    {
+        let ids = (0..points.len()).collect();
+        Self::build_with_ids(points, ids)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize, Id: serde::Serialize, N: Unsigned> serde::Serialize
+    for StableKdTreeN<T, Id, N>
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (&self.kdtree, &self.ids).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>, Id: serde::Deserialize<'de> + Ord, N: Unsigned>
+    serde::Deserialize<'de> for StableKdTreeN<T, Id, N>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (kdtree, ids): (KdTreeN<T, N>, Vec<Id>) =
+            serde::Deserialize::deserialize(deserializer)?;
+        if kdtree.len() != ids.len() {
+            return Err(serde::de::Error::invalid_length(
+                ids.len(),
+                &"as many ids as the items",
+            ));
+        }
+        Ok(Self::from_parts(kdtree, ids))
+    }
+}
@@ -0,0 +1,116 @@
+use crate::sort::kd_sort_by;
+use crate::{KdIndexTreeN, KdPoint, KdTreeN};
+use std::cmp::Ordering;
+use typenum::Unsigned;
+
+impl<'a, T, N: Unsigned> KdIndexTreeN<'a, T, N> {
+    /// Builds an index tree of the items of `source` at `indices` only, e.g. of the currently visible items.
+    /// Panics if an index is out of range.
+    pub fn build_subset_by<F>(
+        source: &'a [T],
+        indices: impl IntoIterator<Item = usize>,
+        compare: F,
+    ) -> Self
+    where
+        F: Fn(&T, &T, usize) -> Ordering + Copy,
+    {
+        let mut kdtree = Self {
+            source,
+            kdtree: KdTreeN::default(),
+        };
+        kdtree.rebuild_subset_by(indices, compare);
+        kdtree
+    }
+
+    pub fn build_subset_by_key<Key, F>(
+        source: &'a [T],
+        indices: impl IntoIterator<Item = usize>,
+        kd_key: F,
+    ) -> Self
+    where
+        Key: Ord,
+        F: Fn(&T, usize) -> Key + Copy,
+    {
+        Self::build_subset_by(source, indices, |item1, item2, k| {
+            kd_key(item1, k).cmp(&kd_key(item2, k))
+        })
+    }
+
+    pub fn build_subset_by_ordered_float(
+        points: &'a [T],
+        indices: impl IntoIterator<Item = usize>,
+    ) -> Self
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        Self::build_subset_by_key(points, indices, |item, k| {
+            ordered_float::OrderedFloat(item.at(k))
+        })
+    }
+
+    /// # Example
+    /// ```
+    /// let items = vec![[1, 2], [3, 1], [2, 3], [5, 5]];
+    /// let visible = [true, false, true, true];
+    /// let mut kdtree = kd_tree::KdIndexTree::build_subset(&items, (0..4).filter(|&i| visible[i]));
+    /// assert_eq!(kdtree.nearest(&[3, 0]).unwrap().item, &0);
+    /// kdtree.rebuild_subset([1, 3]);
+    /// assert_eq!(kdtree.nearest(&[3, 0]).unwrap().item, &1);
+    /// ```
+    pub fn build_subset(points: &'a [T], indices: impl IntoIterator<Item = usize>) -> Self
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: Ord,
+    {
+        Self::build_subset_by_key(points, indices, |item, k| item.at(k))
+    }
+
+    /// Replaces the indexed items with those at `indices`, reusing the allocation of the indices.
+    /// Panics if an index is out of range.
+    pub fn rebuild_subset_by<F>(&mut self, indices: impl IntoIterator<Item = usize>, compare: F)
+    where
+        F: Fn(&T, &T, usize) -> Ordering + Copy,
+    {
+        let source = self.source;
+        let items = &mut self.kdtree.1;
+        items.clear();
+        items.extend(indices);
+        assert!(
+            items.iter().all(|&i| i < source.len()),
+            "index out of range"
+        );
+        kd_sort_by(items, N::to_usize(), |i1, i2, k| {
+            compare(&source[*i1], &source[*i2], k)
+        });
+    }
+
+    pub fn rebuild_subset_by_key<Key, F>(
+        &mut self,
+        indices: impl IntoIterator<Item = usize>,
+        kd_key: F,
+    ) where
+        Key: Ord,
+        F: Fn(&T, usize) -> Key + Copy,
+    {
+        self.rebuild_subset_by(indices, |item1, item2, k| {
+            kd_key(item1, k).cmp(&kd_key(item2, k))
+        })
+    }
+
+    pub fn rebuild_subset_by_ordered_float(&mut self, indices: impl IntoIterator<Item = usize>)
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        self.rebuild_subset_by_key(indices, |item, k| ordered_float::OrderedFloat(item.at(k)))
+    }
+
+    pub fn rebuild_subset(&mut self, indices: impl IntoIterator<Item = usize>)
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: Ord,
+    {
+        self.rebuild_subset_by_key(indices, |item, k| item.at(k))
+    }
+}
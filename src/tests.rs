@@ -22,6 +22,27 @@ fn test_nearests() {
     test_nearests_by(random3d_10th_generator());
 }
 
+#[test]
+fn test_nearests_not_pruned_before_num_found() {
+    // the lower branch is as far as the farthest of the two items found so far, but it must be visited to find the third one.
+    let kdtree = KdTree::build(vec![[0, 0], [10, 0], [11, 0]]);
+    let found = kdtree.nearests(&[12, 0], 3);
+    assert_eq!(
+        found.iter().map(|found| found.item).collect::<Vec<_>>(),
+        vec![&[11, 0], &[10, 0], &[0, 0]]
+    );
+    for leaf_size in [1, 2, 3] {
+        let config = QueryConfig {
+            leaf_size,
+            ..Default::default()
+        };
+        assert_eq!(
+            kdtree.nearests_with_config(&[12, 0], 3, &config).result,
+            found
+        );
+    }
+}
+
 fn test_nearests_by(mut gen3d: impl FnMut() -> [f64; 3]) {
     let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
     const NUM: usize = 5;
@@ -177,6 +198,89 @@ fn test_serde() {
     assert_eq!(src, dst);
 }
 
+#[cfg(feature = "rkyv")]
+#[test]
+fn test_rkyv() {
+    let mut gen3d = random3d_generator();
+    let src = KdTree::build_by_ordered_float(vec(1000, |_| gen3d()));
+    let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&src).unwrap();
+    let archived = rkyv::access::<ArchivedKdTree<[f64; 3]>, rkyv::rancor::Error>(&bytes).unwrap();
+    assert_eq!(archived.items().len(), src.len());
+    for _ in 0..100 {
+        let query = gen3d();
+        let expected = src.nearests(&query, 3);
+        let found = archived.nearests(&query, 3);
+        assert_eq!(found.len(), expected.len());
+        for (found, expected) in found.iter().zip(&expected) {
+            assert_eq!(found.item, expected.item);
+            assert_eq!(found.squared_distance, expected.squared_distance);
+        }
+        assert_eq!(
+            archived.within_radius(&query, 0.2).len(),
+            src.within_radius(&query, 0.2).len()
+        );
+    }
+    let dst = rkyv::deserialize::<KdTree<[f64; 3]>, rkyv::rancor::Error>(archived).unwrap();
+    assert_eq!(src, dst);
+
+    // a point reading the coordinates from its archived fields.
+    #[derive(rkyv::Archive, rkyv::Serialize)]
+    struct Item {
+        point: [i32; 2],
+        id: u64,
+    }
+    impl KdPoint for Item {
+        type Scalar = i32;
+        type Dim = typenum::U2;
+        fn at(&self, k: usize) -> i32 {
+            self.point[k]
+        }
+    }
+    impl ArchivedKdPoint for Item {
+        fn archived_at(item: &ArchivedItem, k: usize) -> i32 {
+            item.point[k].to_native()
+        }
+    }
+    let src = KdTree::build(
+        (0..100)
+            .map(|i| Item {
+                point: [i % 10, i / 10],
+                id: i as u64,
+            })
+            .collect(),
+    );
+    let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&src).unwrap();
+    let archived = rkyv::access::<ArchivedKdTree<Item>, rkyv::rancor::Error>(&bytes).unwrap();
+    assert_eq!(archived.nearest(&[3, 4]).unwrap().item.id, 43);
+    assert_eq!(archived.within(&[[2, 2], [3, 3]]).len(), 4);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_kdmap_serde_keys_or_values_only() {
+    let src: KdMap<[i32; 2], i32> = KdMap::build(vec![([3, 1], 4), ([1, 5], 6), ([2, 2], 4)]);
+
+    let keys = serde_json::to_string(&src.keys_only()).unwrap();
+    assert_eq!(keys, "[[1,5],[2,2],[3,1]]");
+    let values = serde_json::to_string(&src.values_only()).unwrap();
+    assert_eq!(values, "[6,4,4]");
+
+    let dst = KdMap::from_keys_with(serde_json::from_str(&keys).unwrap(), |p: &[i32; 2]| {
+        p[0] + p[1]
+    });
+    assert_eq!(Ok(src.clone()), dst);
+    let dst = KdMap::from_keys_and_values(
+        serde_json::from_str::<Vec<[i32; 2]>>(&keys).unwrap(),
+        serde_json::from_str(&values).unwrap(),
+    );
+    assert_eq!(Ok(src), dst);
+
+    // the keys out of order are rejected instead of making a broken tree.
+    let keys = vec![[3, 1], [1, 5], [2, 2]];
+    assert!(KdMap::from_keys_with(keys.clone(), |p: &[i32; 2]| p[0]).is_err());
+    assert!(KdMap::from_keys_and_values(keys, vec![4, 6, 4]).is_err());
+}
+
 #[cfg(feature = "nalgebra-serde")]
 #[test]
 fn test_nalgebra_serde() {
@@ -205,3 +309,3165 @@ fn test_rayon() {
     let kdtree2 = KdTree::par_build_by_ordered_float(points.clone());
     assert_eq!(kdtree1, kdtree2);
 }
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_rayon_par_iter() {
+    use rayon::prelude::*;
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(20000, |_| gen3d()));
+    fn sort(mut items: Vec<&[f64; 3]>) -> Vec<&[f64; 3]> {
+        items.sort_by_key(|p| ordered_float::OrderedFloat(p[0]));
+        items
+    }
+    assert_eq!(
+        sort((&kdtree).into_par_iter().collect()),
+        sort(kdtree.iter().collect())
+    );
+    for _ in 0..20 {
+        let query = gen3d();
+        let radius = 0.3;
+        assert_eq!(
+            sort(kdtree.par_iter_within_radius(&query, radius).collect()),
+            sort(kdtree.within_radius(&query, radius))
+        );
+        let [x, y, z] = gen3d();
+        let region = [[x - 0.3, y - 0.2, z - 0.4], [x + 0.2, y + 0.4, z + 0.3]];
+        assert_eq!(
+            sort(kdtree.par_iter_within(&region).collect()),
+            sort(kdtree.within(&region))
+        );
+        assert_eq!(
+            sort(kdtree.par_within(&region)),
+            sort(kdtree.within(&region))
+        );
+        assert_eq!(
+            sort(kdtree.par_within_radius(&query, radius)),
+            sort(kdtree.within_radius(&query, radius))
+        );
+    }
+}
+
+#[test]
+fn test_build_merging() {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let items = vec(1000, |_| ([rng.gen_range(0..10), rng.gen_range(0..10)], 1));
+    let kdmap: KdMap<[i32; 2], usize> = KdMap::build_merging(items.clone(), |v1, v2| v1 + v2);
+    let mut keys: Vec<_> = items.iter().map(|item| item.0).collect();
+    keys.sort();
+    keys.dedup();
+    assert_eq!(kdmap.len(), keys.len());
+    assert_eq!(kdmap.iter().map(|item| item.1).sum::<usize>(), items.len());
+    for (key, count) in kdmap.iter() {
+        assert_eq!(*count, items.iter().filter(|item| item.0 == *key).count());
+        assert_eq!(kdmap.nearest(key).unwrap().item.0, *key);
+    }
+
+    // merging within a tolerance conserves the values, and keeps the keys farther apart than it.
+    let mut gen3d = random3d_generator();
+    let items = vec(1000, |_| (gen3d(), 1));
+    let kdmap = KdMap::build_merging_within(items.clone(), 0.1, |v1, v2| v1 + v2);
+    assert_eq!(kdmap.iter().map(|item| item.1).sum::<usize>(), items.len());
+    assert!(kdmap.len() < items.len());
+    for (key, _) in kdmap.iter() {
+        assert_eq!(kdmap.within_radius(key, 0.1).len(), 1);
+        assert!(items.iter().any(|item| item.0 == *key));
+    }
+    let kdmap = KdMap::build_merging_within(items.clone(), 0.0, |v1, v2| v1 + v2);
+    assert_eq!(kdmap.len(), items.len());
+
+    // the tolerance is inclusive, so zero merges identical keys, and NaN keys are never merged.
+    let items = vec![
+        ([1.0, 2.0], 1),
+        ([f64::NAN, 0.0], 10),
+        ([1.0, 2.0], 100),
+        ([f64::NAN, 0.0], 1000),
+        ([1.5, 2.0], 10000),
+    ];
+    let kdmap = KdMap::build_merging_within(items.clone(), 0.0, |v1, v2| v1 + v2);
+    assert_eq!(kdmap.len(), 4);
+    assert!(kdmap.contains(&([1.0, 2.0], 101)));
+    let kdmap = KdMap::build_merging_within(items, 0.5, |v1, v2| v1 + v2);
+    assert_eq!(kdmap.len(), 3);
+    assert!(kdmap.contains(&([1.0, 2.0], 10101)));
+    assert_eq!(kdmap.iter().map(|item| item.1).sum::<usize>(), 11111);
+}
+
+#[test]
+fn test_weight_within_radius() {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let mut gen3d = random3d_generator();
+    let kdmap: KdMap<[f64; 3], f64> =
+        KdMap::build_by_ordered_float(vec(10000, |_| (gen3d(), rng.gen())));
+    const RADIUS: f64 = 0.1;
+    for _ in 0..100 {
+        let query = gen3d();
+        let expected: f64 = kdmap
+            .iter()
+            .filter(|(p, _)| squared_distance(p, &query) < RADIUS * RADIUS)
+            .map(|(_, w)| w)
+            .sum();
+        assert!((kdmap.weight_within_radius(&query, RADIUS) - expected).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_distance_matrix_within_radius() {
+    let mut gen3d = random3d_generator();
+    let points = vec(1000, |_| gen3d());
+    const RADIUS: f64 = 0.1;
+    let kdtree = KdIndexTree::build_by_ordered_float(&points);
+    let matrix = kdtree.distance_matrix_within_radius(RADIUS);
+    assert_eq!(matrix.num_rows(), points.len());
+    for (i, p) in points.iter().enumerate() {
+        let expected: Vec<usize> = (0..points.len())
+            .filter(|&j| j != i && squared_distance(p, &points[j]) < RADIUS * RADIUS)
+            .collect();
+        let (indices, values) = matrix.row(i);
+        assert_eq!(indices, expected.as_slice());
+        for (&j, &value) in indices.iter().zip(values) {
+            assert_eq!(value, squared_distance(p, &points[j]));
+        }
+    }
+}
+
+#[test]
+fn test_nearests_with_max_depth() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
+    const NUM: usize = 5;
+    for _ in 0..100 {
+        let query = gen3d();
+        let expected = kdtree.nearests(&query, NUM);
+        for max_depth in 0..20 {
+            let found = kdtree.nearests_with_max_depth(&query, NUM, max_depth);
+            assert!(found.result.len() <= NUM);
+            if found.is_exact {
+                assert_eq!(found.result, expected);
+            }
+            let nearest = kdtree.nearest_with_max_depth(&query, max_depth).unwrap();
+            if nearest.is_exact {
+                assert_eq!(nearest.result, kdtree.nearest(&query).unwrap());
+            }
+        }
+        assert!(kdtree.nearests_with_max_depth(&query, NUM, 20).is_exact);
+    }
+}
+
+#[test]
+fn test_nearests_against_small_tree() {
+    let kdtree = KdTree::build(vec![[0], [1], [2]]);
+    assert_eq!(kdtree.nearests(&[0], 3).len(), 3);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_indices_delta_serde() {
+    let mut points = {
+        let mut gen3d = random3d_generator();
+        vec(1000, |_| gen3d())
+    };
+    let base = KdIndexTree::build_by_ordered_float(&points)
+        .indices()
+        .to_owned();
+    points[0] = [0.5, 0.5, 0.5];
+    points.push([0.1, 0.2, 0.3]);
+    let kdtree = KdIndexTree::build_by_ordered_float(&points);
+
+    let json = serde_json::to_string(&kdtree.indices_delta(&base)).unwrap();
+    let delta: IndicesDelta = serde_json::from_str(&json).unwrap();
+    let restored = KdIndexTree::from_indices_delta(&points, &base, &delta).unwrap();
+    assert_eq!(restored, kdtree);
+}
+
+#[test]
+fn test_permuted_kdtree() {
+    let mut gen3d = random3d_generator();
+    let points = vec(10000, |_| {
+        let [x, y, z] = gen3d();
+        [x, y, z * 0.01]
+    });
+    let kdtree = PermutedKdTree::build_by_ordered_float(points.clone(), vec![2, 0, 1]);
+    const RADIUS: f64 = 0.1;
+    for _ in 0..100 {
+        let query = gen3d();
+        let expected = points
+            .iter()
+            .min_by_key(|p| ordered_float::OrderedFloat(squared_distance(p, &query)))
+            .unwrap();
+        assert_eq!(kdtree.nearest(&query).unwrap().item, expected);
+        let count = points
+            .iter()
+            .filter(|p| squared_distance(p, &query) < RADIUS * RADIUS)
+            .count();
+        assert_eq!(kdtree.within_radius(&query, RADIUS).len(), count);
+    }
+}
+
+#[test]
+fn test_within_radius_stratified() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
+    const RADIUS: f64 = 0.2;
+    for _ in 0..100 {
+        let query = gen3d();
+        let all = kdtree.within_radius(&query, RADIUS);
+        assert_eq!(
+            kdtree.within_radius_stratified(&query, RADIUS, 1, 1).len(),
+            all.len()
+        );
+        let found = kdtree.within_radius_stratified(&query, RADIUS, 16, 2);
+        assert!(found.len() <= all.len());
+        assert!(found.iter().all(|p| all.contains(p)));
+        assert!(all.is_empty() || !found.is_empty());
+    }
+}
+
+#[test]
+fn test_augmented_kdtree() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
+    let aabb: AugmentedKdTree<_, Aabb<f64, typenum::U3>> = AugmentedKdTree::new(kdtree.clone());
+    let sphere: AugmentedKdTree<_, BoundingSphere<f64, typenum::U3>> =
+        AugmentedKdTree::new(kdtree.clone());
+    const RADIUS: f64 = 0.1;
+    for _ in 0..100 {
+        let query = gen3d();
+        let expected = kdtree.nearest(&query).unwrap();
+        assert_eq!(aabb.nearest(&query).unwrap(), expected);
+        assert_eq!(sphere.nearest(&query).unwrap(), expected);
+        let count = kdtree.within_radius(&query, RADIUS).len();
+        assert_eq!(aabb.within_radius(&query, RADIUS).len(), count);
+        assert_eq!(sphere.within_radius(&query, RADIUS).len(), count);
+    }
+}
+
+#[test]
+fn test_query_config_leaf_size() {
+    let mut gen3d = random3d_generator();
+    let points = vec(10000, |_| gen3d());
+    let kdtree = KdTree::build_by_ordered_float(points.clone());
+    let index_tree = KdIndexTree::build_by_ordered_float(&points);
+    const NUM: usize = 5;
+    const RADIUS: f64 = 0.1;
+    for leaf_size in [0, 1, 4, 32, 20000] {
+        let config = QueryConfig {
+            leaf_size,
+            ..Default::default()
+        };
+        for _ in 0..20 {
+            let query = gen3d();
+            let found = kdtree.nearest_with_config(&query, &config).unwrap();
+            assert!(found.is_exact);
+            assert_eq!(found.result, kdtree.nearest(&query).unwrap());
+            let found = kdtree.nearests_with_config(&query, NUM, &config);
+            assert_eq!(found.result, kdtree.nearests(&query, NUM));
+            let found = index_tree.nearests_with_config(&query, NUM, &config);
+            assert_eq!(found.result, index_tree.nearests(&query, NUM));
+            let no_prefetch = QueryConfig {
+                prefetch: false,
+                ..config
+            };
+            let found = kdtree.nearests_with_config(&query, NUM, &no_prefetch);
+            assert_eq!(found.result, kdtree.nearests(&query, NUM));
+            let found = kdtree.within_radius_with_config(&query, RADIUS, &config);
+            assert_eq!(
+                found.result.len(),
+                kdtree.within_radius(&query, RADIUS).len()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_query_config_options() {
+    let mut gen3d = random3d_generator();
+    let points = vec(10000, |_| gen3d());
+    let kdtree = KdTree::build_by_ordered_float(points.clone());
+    const NUM: usize = 5;
+
+    // epsilon
+    let config = QueryConfig {
+        epsilon: 0.5,
+        ..Default::default()
+    };
+    for _ in 0..20 {
+        let query = gen3d();
+        let exact = kdtree.nearests(&query, NUM);
+        let found = kdtree.nearests_with_config(&query, NUM, &config).result;
+        assert_eq!(found.len(), NUM);
+        for (found, exact) in found.iter().zip(&exact) {
+            assert!(found.squared_distance <= exact.squared_distance * 2.25);
+        }
+        let found = kdtree.nearest_with_config(&query, &config).unwrap().result;
+        assert!(found.squared_distance <= exact[0].squared_distance * 2.25);
+    }
+    // the factor 4 of `epsilon = 1` doesn't overflow the squared distances of `i32` below `i32::MAX / 4`.
+    let config = QueryConfig {
+        epsilon: 1.0,
+        ..Default::default()
+    };
+    let integers = KdTree::build(vec(1000, |i| {
+        [(i as i32 * 7919) % 14000, (i as i32 * 104729) % 14000]
+    }));
+    for query in [[0, 0], [14000, 14000], [0, 14000], [7000, 7000]] {
+        let exact = integers.nearest(&query).unwrap();
+        let found = integers
+            .nearest_with_config(&query, &config)
+            .unwrap()
+            .result;
+        assert!(found.squared_distance <= exact.squared_distance * 4);
+        let found = integers.nearests_with_config(&query, NUM, &config).result;
+        assert_eq!(found.len(), NUM);
+    }
+
+    // node budget and instrumentation
+    let config = QueryConfig {
+        node_budget: 10,
+        instrument: true,
+        ..Default::default()
+    };
+    let query = gen3d();
+    let found = kdtree.nearest_with_config(&query, &config).unwrap();
+    assert!(!found.is_exact);
+    assert_eq!(found.stats.unwrap().visited_items, 10);
+    let found = kdtree.nearests_with_config(&query, NUM, &config);
+    assert!(!found.is_exact);
+    assert_eq!(found.result.len(), NUM);
+    assert_eq!(found.stats.unwrap().visited_items, 10);
+    let found = kdtree.within_radius_with_config(&query, 0.5, &config);
+    assert!(!found.is_exact);
+    assert_eq!(found.stats.unwrap().visited_items, 10);
+    let found = kdtree.nearests_with_config(&query, NUM, &QueryConfig::default());
+    assert!(found.is_exact);
+    assert!(found.stats.is_none());
+
+    // inclusivity
+    let grid = KdTree::build((0..10).flat_map(|x| (0..10).map(move |y| [x, y])).collect());
+    let config = QueryConfig {
+        inclusive: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        grid.within_radius_with_config(&[5, 5], 1, &Default::default())
+            .result
+            .len(),
+        1
+    );
+    assert_eq!(
+        grid.within_radius_with_config(&[5, 5], 1, &config)
+            .result
+            .len(),
+        5
+    );
+
+    // tie-breaking
+    for leaf_size in [1, 4, 32] {
+        let config = QueryConfig {
+            leaf_size,
+            tie_break: DistanceTieBreak::FirstItem,
+            ..Default::default()
+        };
+        for query in [[5, 5], [0, 0], [3, 7]] {
+            let mut expected: Vec<_> = grid.iter().collect();
+            expected.sort_by_key(|p| (crate::squared_distance(*p, &query), grid.offset_of(p)));
+            let found = grid.nearests_with_config(&query, 7, &config).result;
+            assert_eq!(
+                found.iter().map(|f| f.item).collect::<Vec<_>>(),
+                &expected[..7]
+            );
+            let found = grid.nearest_with_config(&query, &config).unwrap().result;
+            assert_eq!(found.item, expected[0]);
+        }
+    }
+
+    // accumulation
+    let config = QueryConfig {
+        accumulation: Accumulation::Compensated,
+        ..Default::default()
+    };
+    for _ in 0..20 {
+        let query = gen3d();
+        let found = kdtree.nearests_with_config(&query, NUM, &config).result;
+        let exact = kdtree.nearests(&query, NUM);
+        assert_eq!(
+            found.iter().map(|f| f.item).collect::<Vec<_>>(),
+            exact.iter().map(|f| f.item).collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn test_within_radius_many() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
+    let queries = vec(1000, |_| gen3d());
+    const RADIUS: f64 = 0.05;
+    let found = kdtree.within_radius_many(&queries, RADIUS);
+    assert_eq!(found.len(), queries.len());
+    for (query, found) in queries.iter().zip(found) {
+        let mut found: Vec<_> = found.into_iter().map(|p| kdtree.offset_of(p)).collect();
+        let mut expected: Vec<_> = kdtree
+            .within_radius(query, RADIUS)
+            .into_iter()
+            .map(|p| kdtree.offset_of(p))
+            .collect();
+        found.sort();
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+    assert!(kdtree
+        .within_radius_many::<[f64; 3]>(&[], RADIUS)
+        .is_empty());
+}
+
+#[test]
+fn test_gpu_layout() {
+    // a nearest neighbor search over the flat nodes, as a GPU kernel would do.
+    fn nearest(layout: &GpuLayout<[f64; 3]>, node: u32, query: &[f64; 3], best: &mut (f64, usize)) {
+        if node == GpuNode::<f64>::NONE {
+            return;
+        }
+        let node = &layout.nodes[node as usize];
+        if node.is_leaf() {
+            for i in node.item_begin..node.item_end {
+                let d = squared_distance(&layout.items[i as usize], query);
+                if d < best.0 {
+                    *best = (d, i as usize);
+                }
+            }
+            return;
+        }
+        let mid = (node.item_begin + (node.item_end - node.item_begin) / 2) as usize;
+        let d = squared_distance(&layout.items[mid], query);
+        if d < best.0 {
+            *best = (d, mid);
+        }
+        let diff = query[node.axis as usize] - node.split_value;
+        let (near, far) = if diff < 0.0 {
+            (node.left, node.right)
+        } else {
+            (node.right, node.left)
+        };
+        nearest(layout, near, query, best);
+        if diff * diff < best.0 {
+            nearest(layout, far, query, best);
+        }
+    }
+
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(1000, |_| gen3d()));
+    for leaf_size in [1, 7, 2000] {
+        let layout = kdtree.to_gpu_layout(leaf_size);
+        assert!(layout
+            .nodes
+            .iter()
+            .all(|node| node.is_leaf() == (node.item_end - node.item_begin <= leaf_size as u32)));
+        for _ in 0..100 {
+            let query = gen3d();
+            let mut best = (f64::INFINITY, 0);
+            nearest(&layout, 0, &query, &mut best);
+            assert_eq!(&layout.items[best.1], kdtree.nearest(&query).unwrap().item);
+        }
+        assert_eq!(KdTree::from_gpu_layout(layout).unwrap(), kdtree);
+    }
+    assert!(KdTree::<[f64; 3]>::from_gpu_layout(GpuLayout {
+        nodes: Vec::new(),
+        items: vec![[1.0, 0.0, 0.0], [0.0, 0.0, 0.0], [2.0, 0.0, 0.0]],
+    })
+    .is_err());
+    assert!(KdTree::<[f64; 3]>::build_by_ordered_float(Vec::new())
+        .to_gpu_layout(1)
+        .nodes
+        .is_empty());
+}
+
+#[test]
+fn test_as_dim() {
+    struct Item {
+        xyz: Vec<f64>,
+    }
+    impl KdPoint for Item {
+        type Scalar = f64;
+        type Dim = typenum::U3;
+        fn at(&self, k: usize) -> f64 {
+            self.xyz[k]
+        }
+    }
+    fn nearest_index<P: KdPoint, Q: KdPoint<Scalar = P::Scalar>>(
+        kdtree: &KdTree<P>,
+        query: &Q,
+    ) -> Option<usize> {
+        let query = query.try_as_dim::<P::Dim>().ok()?;
+        let found = kdtree.nearest(&query)?;
+        kdtree
+            .iter()
+            .position(|item| std::ptr::eq(item, found.item))
+    }
+
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(100, |_| gen3d()));
+    for _ in 0..100 {
+        let query = Item {
+            xyz: gen3d().to_vec(),
+        };
+        let expected = kdtree.nearest(&query.as_dim::<typenum::U3>()).unwrap().item;
+        let found = nearest_index(&kdtree, &query).unwrap();
+        assert_eq!(&kdtree[found], expected);
+        assert_eq!(query.as_dim::<typenum::U3>().point().xyz, query.xyz);
+    }
+    assert_eq!(nearest_index(&kdtree, &[1.0, 2.0]), None);
+    let error = [1.0, 2.0].try_as_dim::<typenum::U3>().unwrap_err();
+    assert_eq!(
+        error,
+        DimMismatch {
+            expected: 3,
+            actual: 2
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "the point has 2 axes, but 3 axes are required"
+    );
+}
+
+#[test]
+fn test_subtree_metadata() {
+    let mut gen3d = random3d_generator();
+    let items = vec(1000, |i| {
+        let [x, y, z] = gen3d();
+        // class 7 is rare and only in the corner of x < 0.1.
+        let class = if x < 0.1 && i % 5 == 0 { 7 } else { i % 4 };
+        ([x, y, z], class)
+    });
+    let kdmap = KdMap::build_by_ordered_float(items.clone());
+    // the histogram of the classes.
+    let kdtree: KdTreeMeta<_, [usize; 8]> =
+        KdTreeMeta::from_fold(kdmap, [0; 8], |mut histogram, (_, class)| {
+            histogram[*class] += 1;
+            histogram
+        });
+    let mut expected = [0; 8];
+    items.iter().for_each(|(_, class)| expected[*class] += 1);
+    assert_eq!(kdtree.metadata(), Some(&expected));
+
+    let mut visited = 0;
+    kdtree.visit(|items, _, histogram| {
+        assert_eq!(histogram.iter().sum::<usize>(), items.len());
+        visited += 1;
+        histogram[7] > 0
+    });
+    assert!(0 < visited && visited < items.len());
+
+    let is_rare = |item: &([f64; 3], usize)| item.1 == 7;
+    let rare: Vec<_> = items.iter().filter(|item| is_rare(item)).collect();
+    for _ in 0..100 {
+        let query = gen3d();
+        let found = kdtree
+            .nearests_by_metadata(&query, 3, |histogram| histogram[7] == 0, is_rare)
+            .into_iter()
+            .map(|found| found.squared_distance)
+            .collect::<Vec<_>>();
+        let mut distances: Vec<_> = rare
+            .iter()
+            .map(|item| crate::squared_distance(&item.0, &query))
+            .collect();
+        distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(found, distances[..3]);
+
+        let mut found =
+            kdtree.within_radius_by_metadata(&query, 0.3, |histogram| histogram[7] == 0, is_rare);
+        let mut expected: Vec<_> = rare
+            .iter()
+            .copied()
+            .filter(|item| crate::squared_distance(&item.0, &query) < 0.09)
+            .collect();
+        let key = |item: &&([f64; 3], usize)| ordered_float::OrderedFloat(item.0[0]);
+        found.sort_by_key(key);
+        expected.sort_by_key(key);
+        assert_eq!(found, expected);
+    }
+}
+
+#[test]
+fn test_skyline() {
+    fn brute_force<'a>(points: &'a [[i32; 3]], region: &[[i32; 3]; 2]) -> Vec<&'a [i32; 3]> {
+        let inside = |p: &[i32; 3]| (0..3).all(|k| region[0][k] <= p[k] && p[k] <= region[1][k]);
+        let mut skyline: Vec<_> = points
+            .iter()
+            .filter(|p| inside(p))
+            .filter(|p| {
+                !points.iter().any(|q| {
+                    inside(q) && (0..3).all(|k| q[k] <= p[k]) && (0..3).any(|k| q[k] < p[k])
+                })
+            })
+            .collect();
+        skyline.sort();
+        skyline
+    }
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    for _ in 0..20 {
+        // anti-correlated points have large skylines.
+        let points = vec(1000, |_| {
+            let x = rng.gen_range(0..100);
+            [x, 100 - x + rng.gen_range(0..20), rng.gen_range(0..100)]
+        });
+        let kdtree = KdTree::build(points.clone());
+        let mut skyline = kdtree.skyline();
+        skyline.sort();
+        assert_eq!(
+            skyline,
+            brute_force(&points, &[[i32::MIN; 3], [i32::MAX; 3]])
+        );
+        let min = [
+            rng.gen_range(0..50),
+            rng.gen_range(0..50),
+            rng.gen_range(0..50),
+        ];
+        let region = [min, [min[0] + 40, min[1] + 60, min[2] + 50]];
+        let mut skyline = kdtree.skyline_within(&region);
+        skyline.sort();
+        assert_eq!(skyline, brute_force(&points, &region));
+    }
+    let duplicates = KdTree::build(vec![[1, 1], [1, 1], [2, 0]]);
+    assert_eq!(duplicates.skyline().len(), 3);
+}
+
+#[test]
+fn test_reverse_nearest() {
+    let mut gen3d = random3d_generator();
+    let points = vec(500, |_| gen3d());
+    let kdtree = KdTree::build_by_ordered_float(points.clone());
+    let rnn = ReverseNearest::new(&kdtree);
+    let key = |p: &&[f64; 3]| ordered_float::OrderedFloat(p[0]);
+    for _ in 0..100 {
+        let query = gen3d();
+        let mut expected: Vec<_> = points
+            .iter()
+            .filter(|p| {
+                let radius = points
+                    .iter()
+                    .filter(|q| !std::ptr::eq(*p, *q))
+                    .map(|q| crate::squared_distance(*p, q))
+                    .fold(f64::INFINITY, f64::min);
+                crate::squared_distance(*p, &query) <= radius
+            })
+            .collect();
+        let mut found = rnn.query(&query);
+        expected.sort_by_key(key);
+        found.sort_by_key(key);
+        assert_eq!(found, expected);
+    }
+    assert!(kdtree.reverse_nearest(&points[0]).contains(&&points[0]));
+
+    let single = KdTree::build(vec![[1, 2]]);
+    assert_eq!(single.reverse_nearest(&[100, 100]), vec![&[1, 2]]);
+    let empty: KdTree<[i32; 2]> = KdTree::build(Vec::new());
+    assert!(empty.reverse_nearest(&[0, 0]).is_empty());
+}
+
+#[test]
+fn test_nearest_tracker() {
+    let mut gen3d = random3d_generator();
+    let queries = vec(300, |_| gen3d());
+    let mut items = vec(10, |_| gen3d());
+    let brute_force = |items: &[[f64; 3]], query: &[f64; 3]| {
+        items
+            .iter()
+            .map(|item| crate::squared_distance(item, query))
+            .fold(f64::INFINITY, f64::min)
+    };
+    let kdtree = KdTree::build_by_ordered_float(items.clone());
+    let mut tracker = NearestTracker::new(queries.clone(), |q| kdtree.nearest(q));
+    for step in 0..200 {
+        let mut changed = vec![];
+        if step % 4 == 3 {
+            let item = items.swap_remove(step % items.len());
+            let kdtree = KdTree::build_by_ordered_float(items.clone());
+            tracker.removed(&item, |q| kdtree.nearest(q), |i| changed.push(i));
+        } else {
+            let item = gen3d();
+            items.push(item);
+            tracker.inserted(&item, |i| changed.push(i));
+            for (i, query) in queries.iter().enumerate() {
+                let is_nearest =
+                    crate::squared_distance(&item, query) == brute_force(&items, query);
+                assert_eq!(changed.contains(&i), is_nearest);
+            }
+        }
+        for (i, query) in queries.iter().enumerate() {
+            let found = tracker.nearest(i).unwrap();
+            assert_eq!(found.squared_distance, brute_force(&items, query));
+            assert_eq!(
+                found.squared_distance,
+                crate::squared_distance(found.item, query)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_manhattan() {
+    test_metric::<Manhattan>(|p, q| (0..3).map(|k| (p[k] - q[k]).abs()).sum());
+}
+
+#[test]
+fn test_chebyshev() {
+    test_metric::<Chebyshev>(|p, q| (0..3).map(|k| (p[k] - q[k]).abs()).fold(0.0, f64::max));
+}
+
+fn test_metric<M: Metric<f64>>(distance: impl Fn(&[f64; 3], &[f64; 3]) -> f64) {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
+    const NUM: usize = 5;
+    const RADIUS: f64 = 0.1;
+    for _ in 0..20 {
+        let query = gen3d();
+        let mut expected: Vec<_> = kdtree.iter().collect();
+        expected.sort_by_key(|p| ordered_float::OrderedFloat(distance(p, &query)));
+        let found = kdtree.nearest_with_metric::<M>(&query).unwrap();
+        assert_eq!(found.item, expected[0]);
+        assert_eq!(found.distance, distance(expected[0], &query));
+        let found = kdtree.nearests_with_metric::<M>(&query, NUM);
+        assert_eq!(
+            found.iter().map(|found| found.item).collect::<Vec<_>>(),
+            expected[..NUM]
+        );
+        let count = kdtree
+            .iter()
+            .filter(|p| distance(p, &query) < RADIUS)
+            .count();
+        assert_eq!(
+            kdtree.within_radius_with_metric::<M>(&query, RADIUS).len(),
+            count
+        );
+    }
+}
+
+#[test]
+fn test_nearests_exact() {
+    use rand::seq::SliceRandom;
+    use rand::Rng;
+    // the squared distances of 64-bit coordinates exceed `i128::MAX` and `u128::MAX`.
+    let kdtree = KdTree::build(vec![[i64::MIN], [i64::MIN + 1]]);
+    let found = kdtree.nearest_by(&[Exact::from(i64::MAX)], |item, k| Exact::from(item[k]));
+    assert_eq!(found.unwrap().item, &[i64::MIN + 1]);
+    let kdtree = KdTree::build(vec![[0u64], [1]]);
+    let found = kdtree.nearest_by(&[Exact::from(u64::MAX)], |item, k| Exact::from(item[k]));
+    assert_eq!(found.unwrap().item, &[1]);
+    assert_eq!(
+        found.unwrap().squared_distance,
+        Exact::from(u64::MAX - 1) * Exact::from(u64::MAX - 1)
+    );
+
+    // compare with the squared distances as the pairs of the carries and the lower 128 bits.
+    let exact_squared_distance = |p: &[i64; 3], q: &[i64; 3]| {
+        let (mut carries, mut lo) = (0i128, 0u128);
+        for k in 0..3 {
+            let diff = (p[k] as i128 - q[k] as i128).unsigned_abs();
+            let (sum, carry) = lo.overflowing_add(diff * diff);
+            lo = sum;
+            carries += carry as i128;
+        }
+        (carries, lo)
+    };
+    let mut rng = rand::thread_rng();
+    let extremes = [i64::MIN, i64::MIN + 1, -1, 0, 1, i64::MAX - 1, i64::MAX];
+    let mut gen = || -> [i64; 3] {
+        std::array::from_fn(|_| {
+            if rng.gen_bool(0.5) {
+                *extremes.choose(&mut rng).unwrap()
+            } else {
+                rng.gen()
+            }
+        })
+    };
+    let kdtree: KdTree<[i64; 3]> = KdTree::build(vec(2000, |_| gen()));
+    const NUM: usize = 5;
+    let coord = |item: &[i64; 3], k: usize| Exact::from(item[k]);
+    for _ in 0..100 {
+        let query = gen();
+        let exact_query = query.map(Exact::from);
+        let found = kdtree.nearests_by(&exact_query, NUM, coord);
+        let mut expected: Vec<_> = kdtree
+            .iter()
+            .map(|p| exact_squared_distance(p, &query))
+            .collect();
+        expected.sort();
+        assert_eq!(
+            found
+                .iter()
+                .map(|found| (found.squared_distance.hi(), found.squared_distance.lo()))
+                .collect::<Vec<_>>(),
+            expected[..NUM]
+        );
+        assert_eq!(
+            kdtree
+                .nearest_by(&exact_query, coord)
+                .unwrap()
+                .squared_distance,
+            found[0].squared_distance
+        );
+    }
+}
+
+fn random_geo_generator() -> impl FnMut() -> [f64; 2] {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    move || {
+        let z: f64 = rng.gen_range(-1.0..1.0);
+        [z.asin().to_degrees(), rng.gen_range(-180.0..180.0)]
+    }
+}
+
+#[test]
+fn test_within_radius_geo() {
+    use geographic::haversine_distance;
+    let mut gen_geo = random_geo_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen_geo()));
+    for radius in [1.0e4, 1.0e5, 1.0e6, 1.0e7] {
+        for _ in 0..50 {
+            let query = gen_geo();
+            let count = kdtree
+                .iter()
+                .filter(|p| haversine_distance(*p, &query) < radius)
+                .count();
+            assert_eq!(kdtree.within_radius_geo(&query, radius).len(), count);
+        }
+        for query in [
+            [89.99, 10.0],
+            [-89.5, -170.0],
+            [60.0, 179.99],
+            [85.0, -179.0],
+        ] {
+            let count = kdtree
+                .iter()
+                .filter(|p| haversine_distance(*p, &query) < radius)
+                .count();
+            assert_eq!(kdtree.within_radius_geo(&query, radius).len(), count);
+        }
+    }
+}
+
+#[test]
+fn test_within_geo() {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let mut gen_geo = random_geo_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen_geo()));
+    for _ in 0..100 {
+        let lat0: f64 = rng.gen_range(-90.0..90.0);
+        let lat1: f64 = rng.gen_range(lat0..=90.0);
+        let lon0: f64 = rng.gen_range(-180.0..180.0);
+        let lon1: f64 = rng.gen_range(-180.0..180.0);
+        let found = kdtree.within_geo(&[[lat0, lon0], [lat1, lon1]]);
+        let count = kdtree
+            .iter()
+            .filter(|p| lat0 <= p[0] && p[0] <= lat1)
+            .filter(|p| {
+                if lon0 <= lon1 {
+                    lon0 <= p[1] && p[1] <= lon1
+                } else {
+                    lon0 <= p[1] || p[1] <= lon1
+                }
+            })
+            .count();
+        assert_eq!(found.len(), count);
+    }
+}
+
+#[test]
+fn test_nearests_geo() {
+    use geographic::haversine_distance;
+    let mut gen_geo = random_geo_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen_geo()));
+    const NUM: usize = 5;
+    let queries = [[89.9, 10.0], [-89.9, -170.0], [0.0, 180.0], [45.0, -179.99]];
+    for query in (0..50).map(|_| gen_geo()).chain(queries) {
+        let mut expected: Vec<_> = kdtree
+            .iter()
+            .map(|p| haversine_distance(p, &query))
+            .collect();
+        expected.sort_by_key(|d| ordered_float::OrderedFloat(*d));
+        assert_eq!(kdtree.nearest_geo(&query).unwrap().distance, expected[0]);
+        let found = kdtree.nearests_geo(&query, NUM);
+        assert_eq!(
+            found.iter().map(|found| found.distance).collect::<Vec<_>>(),
+            expected[..NUM]
+        );
+    }
+}
+
+#[test]
+fn test_within_corridor_geo() {
+    use geographic::haversine_segment_distance;
+    let mut gen_geo = random_geo_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen_geo()));
+    let tracks = [
+        vec![[10.0, 170.0], [12.0, -175.0], [20.0, -170.0]],
+        vec![[85.0, 0.0], [85.0, 180.0]],
+        vec![[-30.0, 20.0], [-31.0, 21.0], [-30.0, 22.0], [-40.0, 30.0]],
+    ];
+    for track in &tracks {
+        for distance in [1.0e4, 1.0e5, 3.0e5] {
+            let count = kdtree
+                .iter()
+                .filter(|p| {
+                    track
+                        .windows(2)
+                        .any(|s| haversine_segment_distance(*p, &s[0], &s[1]) < distance)
+                })
+                .count();
+            assert_eq!(kdtree.within_corridor_geo(track, distance).len(), count);
+        }
+    }
+}
+
+#[test]
+fn test_insert() {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let mut kdtree: KdTree<[i32; 2]> =
+        KdTree::build(vec(100, |_| [rng.gen_range(0..100), rng.gen_range(0..100)]));
+    for _ in 0..1000 {
+        kdtree.insert([rng.gen_range(0..100), rng.gen_range(0..100)]);
+    }
+    assert_eq!(kdtree.len(), 1100);
+    let mut items = kdtree.clone().into_vec();
+    for _ in 0..100 {
+        let query = [rng.gen_range(0..100), rng.gen_range(0..100)];
+        items.sort_by_key(|p| (p[0] - query[0]).pow(2) + (p[1] - query[1]).pow(2));
+        let expected = items[0];
+        let found = kdtree.nearest(&query).unwrap();
+        assert_eq!(
+            found.squared_distance,
+            (expected[0] - query[0]).pow(2) + (expected[1] - query[1]).pow(2)
+        );
+        let count = items
+            .iter()
+            .filter(|p| (p[0] - query[0]).abs() <= 10 && (p[1] - query[1]).abs() <= 10)
+            .count();
+        let bounds = [
+            [query[0] - 10, query[1] - 10],
+            [query[0] + 10, query[1] + 10],
+        ];
+        assert_eq!(kdtree.within(&bounds).len(), count);
+    }
+}
+
+#[test]
+fn test_tiled_kdtree() {
+    let mut gen3d = random3d_generator();
+    let points = vec(10000, |_| gen3d());
+    let tiled = TiledKdTree::build_by_ordered_float(points.clone(), 0.25);
+    assert_eq!(tiled.len(), points.len());
+    for (key, kdtree) in tiled.tiles() {
+        assert!(kdtree.iter().all(|p| &tiled.tile_key(p) == key));
+    }
+    let kdtree = KdTree::build_by_ordered_float(points);
+    const NUM: usize = 5;
+    for _ in 0..100 {
+        let query = gen3d();
+        let expected = kdtree.nearests(&query, NUM);
+        let found = tiled.nearests(&query, NUM);
+        assert_eq!(
+            found.iter().map(|f| f.squared_distance).collect::<Vec<_>>(),
+            expected
+                .iter()
+                .map(|e| e.squared_distance)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            tiled.within_radius(&query, 0.2).len(),
+            kdtree.within_radius(&query, 0.2).len()
+        );
+    }
+    // the radii whose tile keys overflow search all tiles.
+    let query = gen3d();
+    for radius in [f64::INFINITY, 1e30] {
+        assert_eq!(tiled.within_radius(&query, radius).len(), kdtree.len());
+    }
+}
+
+#[test]
+fn test_insert_cost() {
+    use crate::low_level::{kd_find_unsorted_by, kd_insert_by, kd_sort_by};
+    use rand::Rng;
+    use std::cell::Cell;
+    let mut rng = rand::thread_rng();
+    // the items stay sorted, including the ties.
+    for dim in 1..=3 {
+        for range in [4, 1000] {
+            let mut items = Vec::new();
+            for _ in 0..500 {
+                let item: [i32; 3] = std::array::from_fn(|_| rng.gen_range(0..range));
+                kd_insert_by(&mut items, item, dim, |a, b, k| a[k].cmp(&b[k]));
+                assert_eq!(
+                    kd_find_unsorted_by(&items, dim, |a, b, k| a[k].cmp(&b[k])),
+                    None
+                );
+            }
+        }
+    }
+    // an insertion compares far fewer items than rebuilding the tree.
+    let count = Cell::new(0usize);
+    let compare = |a: &[f64; 3], b: &[f64; 3], k: usize| {
+        count.set(count.get() + 1);
+        a[k].partial_cmp(&b[k]).unwrap()
+    };
+    let mut gen3d = random3d_generator();
+    let mut items = vec(10000, |_| gen3d());
+    kd_sort_by(&mut items, 3, compare);
+    let rebuild = count.replace(0);
+    let mut worst = 0;
+    for _ in 0..1000 {
+        kd_insert_by(&mut items, gen3d(), 3, compare);
+        worst = worst.max(count.replace(0));
+    }
+    assert!(worst * 10 < rebuild);
+    assert_eq!(kd_find_unsorted_by(&items, 3, compare), None);
+}
+
+#[test]
+fn test_remove() {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let mut kdtree: KdTree<[i32; 2]> = KdTree::build(vec(2000, |_| {
+        [rng.gen_range(0..100), rng.gen_range(0..100)]
+    }));
+    let removed = kdtree.remove_if(|p| p[0] < 30 && p[1] < 30);
+    assert!(removed.iter().all(|p| p[0] < 30 && p[1] < 30));
+    assert_eq!(removed.len() + kdtree.len(), 2000);
+    for _ in 0..200 {
+        let query = [rng.gen_range(0..100), rng.gen_range(0..100)];
+        let count = kdtree.iter().filter(|p| **p == query).count();
+        assert_eq!(kdtree.remove(&query).is_some(), count > 0);
+    }
+    let items = kdtree.clone().into_vec();
+    for _ in 0..100 {
+        let query = [rng.gen_range(0..100), rng.gen_range(0..100)];
+        let expected = items
+            .iter()
+            .map(|p| (p[0] - query[0]).pow(2) + (p[1] - query[1]).pow(2))
+            .min()
+            .unwrap();
+        assert_eq!(kdtree.nearest(&query).unwrap().squared_distance, expected);
+        let count = items
+            .iter()
+            .filter(|p| (p[0] - query[0]).abs() <= 10 && (p[1] - query[1]).abs() <= 10)
+            .count();
+        let bounds = [
+            [query[0] - 10, query[1] - 10],
+            [query[0] + 10, query[1] + 10],
+        ];
+        assert_eq!(kdtree.within(&bounds).len(), count);
+    }
+}
+
+#[test]
+fn test_remove_nan() {
+    // NaN matches NaN only, neither the other coordinates nor the other items.
+    let mut kdtree = KdTree::build_by_ordered_float(vec![[3.0, 1.0], [1.0, 2.0], [2.0, 3.0]]);
+    assert_eq!(kdtree.remove_by_ordered_float(&[f64::NAN, f64::NAN]), None);
+    assert_eq!(kdtree.remove_by_ordered_float(&[3.0, f64::NAN]), None);
+    assert_eq!(kdtree.len(), 3);
+    kdtree.insert_by_ordered_float([3.0, f64::NAN]);
+    let removed = kdtree.remove_by_ordered_float(&[3.0, f64::NAN]).unwrap();
+    assert!(removed[1].is_nan());
+    assert_eq!(
+        kdtree.remove_by_ordered_float(&[3.0, 1.0]),
+        Some([3.0, 1.0])
+    );
+    assert_eq!(kdtree.len(), 2);
+
+    let mut tiled = TiledKdTree::build_by_ordered_float(vec![[0.5, 0.5], [3.0, 1.0]], 1.0);
+    assert_eq!(tiled.remove_by_ordered_float(&[f64::NAN, f64::NAN]), None);
+    assert_eq!(tiled.remove_by_ordered_float(&[3.0, f64::NAN]), None);
+    assert_eq!(tiled.len(), 2);
+}
+
+#[test]
+fn test_dynamic_kdtree() {
+    let mut gen3d = random3d_generator();
+    let points = vec(1000, |_| gen3d());
+    let mut dynamic = DynamicKdTree::new();
+    for (i, p) in points.iter().enumerate() {
+        dynamic.insert_by_ordered_float(*p);
+        assert_eq!(dynamic.buckets().count(), (i + 1).count_ones() as usize);
+    }
+    let kdtree = KdTree::build_by_ordered_float(points);
+    const NUM: usize = 5;
+    for _ in 0..100 {
+        let query = gen3d();
+        let expected = kdtree.nearests(&query, NUM);
+        let found = dynamic.nearests(&query, NUM);
+        assert_eq!(
+            found.iter().map(|f| f.squared_distance).collect::<Vec<_>>(),
+            expected
+                .iter()
+                .map(|e| e.squared_distance)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            dynamic.nearest(&query).unwrap().squared_distance,
+            expected[0].squared_distance
+        );
+        assert_eq!(
+            dynamic.within_radius(&query, 0.2).len(),
+            kdtree.within_radius(&query, 0.2).len()
+        );
+    }
+}
+
+#[test]
+fn test_dedup_shards() {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let points = vec(500, |_| [rng.gen_range(0..20), rng.gen_range(0..20)]);
+    let mut dynamic = DynamicKdTree::new();
+    for p in points.iter().chain(&points) {
+        dynamic.insert(*p);
+    }
+    let mut unique = points.clone();
+    unique.sort();
+    unique.dedup();
+    let kdtree = KdTree::build(unique);
+    for _ in 0..100 {
+        let query = [rng.gen_range(0..20), rng.gen_range(0..20)];
+        for tie in [TieBreak::FirstShard, TieBreak::LastShard] {
+            let found = dynamic.nearests_dedup(&query, 10, tie);
+            let expected = kdtree.nearests(&query, 10);
+            assert_eq!(
+                found.iter().map(|f| f.squared_distance).collect::<Vec<_>>(),
+                expected
+                    .iter()
+                    .map(|e| e.squared_distance)
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                dynamic.within_radius_dedup(&query, 5, tie).len(),
+                kdtree.within_radius(&query, 5).len()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_tombstone_kdtree() {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let points: Vec<[i32; 2]> = vec(2000, |_| [rng.gen_range(0..100), rng.gen_range(0..100)]);
+    let mut kdtree = TombstoneKdTree::build(points.clone());
+    kdtree.set_rebuild_threshold(0.2);
+    let mut alive = points;
+    for _ in 0..1000 {
+        let query = [rng.gen_range(0..100), rng.gen_range(0..100)];
+        let removed = kdtree.remove(&query);
+        let i = alive.iter().position(|p| *p == query);
+        assert_eq!(removed, i.is_some());
+        if let Some(i) = i {
+            alive.swap_remove(i);
+        }
+        assert!(
+            kdtree.deleted_count() as f64 <= 0.2 * (kdtree.len() + kdtree.deleted_count()) as f64
+        );
+    }
+    assert_eq!(kdtree.len(), alive.len());
+    for _ in 0..100 {
+        let query = [rng.gen_range(0..100), rng.gen_range(0..100)];
+        let expected = alive
+            .iter()
+            .map(|p| (p[0] - query[0]).pow(2) + (p[1] - query[1]).pow(2))
+            .min()
+            .unwrap();
+        assert_eq!(kdtree.nearest(&query).unwrap().squared_distance, expected);
+        let count = alive
+            .iter()
+            .filter(|p| (p[0] - query[0]).pow(2) + (p[1] - query[1]).pow(2) < 100)
+            .count();
+        assert_eq!(kdtree.within_radius(&query, 10).len(), count);
+    }
+}
+
+#[test]
+fn test_nearest_iter() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(1000, |_| gen3d()));
+    for _ in 0..20 {
+        let query = gen3d();
+        let found: Vec<_> = kdtree.nearest_iter(&query).collect();
+        assert_eq!(found.len(), kdtree.len());
+        assert!(found
+            .windows(2)
+            .all(|w| w[0].squared_distance <= w[1].squared_distance));
+        let expected = kdtree.nearests(&query, 10);
+        assert_eq!(
+            found[..10]
+                .iter()
+                .map(|f| f.squared_distance)
+                .collect::<Vec<_>>(),
+            expected
+                .iter()
+                .map(|e| e.squared_distance)
+                .collect::<Vec<_>>()
+        );
+    }
+    // the yielded items outlive the query.
+    let items: Vec<&[f64; 3]> = {
+        let query = gen3d();
+        kdtree
+            .nearest_iter(&query)
+            .take(3)
+            .map(|f| f.item)
+            .collect()
+    };
+    assert_eq!(items.len(), 3);
+}
+
+#[test]
+fn test_moving_nearest() {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
+    let mut moving = MovingNearest::new(&kdtree);
+    let mut query = gen3d();
+    for _ in 0..1000 {
+        for x in query.iter_mut() {
+            *x += rng.gen_range(-0.01..0.01);
+        }
+        let expected = kdtree.nearest(&query).unwrap();
+        let found = moving.nearest(&query).unwrap();
+        assert_eq!(found.squared_distance, expected.squared_distance);
+    }
+}
+
+#[test]
+fn test_nearests_with_bound() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
+    const NUM: usize = 10;
+    for _ in 0..100 {
+        let query = gen3d();
+        let expected = kdtree.nearests(&query, NUM);
+        let bound = expected[NUM / 2].squared_distance;
+        let found = kdtree.nearests_with_bound(&query, NUM, bound);
+        assert_eq!(found, expected[..NUM / 2]);
+        let radius = bound.sqrt();
+        let found = kdtree.nearests_within(&query, NUM, radius);
+        let filtered: Vec<_> = expected
+            .iter()
+            .filter(|e| e.squared_distance < radius * radius)
+            .copied()
+            .collect();
+        assert_eq!(found, filtered);
+        let found = kdtree.nearest_with_bound(&query, bound).unwrap();
+        assert_eq!(found, expected[0]);
+        assert!(kdtree
+            .nearest_with_bound(&query, expected[0].squared_distance)
+            .is_none());
+    }
+}
+
+#[test]
+fn test_count_within() {
+    use rand::Rng;
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
+    for _ in 0..100 {
+        let query = gen3d();
+        let bounds = [query.map(|x| x - 0.1), query.map(|x| x + 0.1)];
+        assert_eq!(kdtree.count_within(&bounds), kdtree.within(&bounds).len());
+        assert_eq!(
+            kdtree.count_within_radius(&query, 0.2),
+            kdtree.within_radius(&query, 0.2).len()
+        );
+        let mut found = Vec::new();
+        kdtree.for_each_within_radius(&query, 0.2, |item, d| {
+            assert_eq!(d, squared_distance(item, &query));
+            found.push(item);
+        });
+        let mut expected = kdtree.within_radius(&query, 0.2);
+        let key = |p: &&[f64; 3]| p.map(ordered_float::OrderedFloat);
+        found.sort_by_key(key);
+        expected.sort_by_key(key);
+        assert_eq!(found, expected);
+    }
+    // large boxes, in which many subtrees are counted without visiting them.
+    let mut rng = rand::thread_rng();
+    let points = vec(10000, |_| [rng.gen_range(0..20), rng.gen_range(0..20)]);
+    let kdtree = KdTree::build(points.clone());
+    for _ in 0..100 {
+        let (x, y) = (rng.gen_range(-5..20), rng.gen_range(-5..20));
+        let (w, h) = (rng.gen_range(0..20), rng.gen_range(0..20));
+        let bounds = [[x, y], [x + w, y + h]];
+        let expected = points
+            .iter()
+            .filter(|p| x <= p[0] && p[0] <= x + w && y <= p[1] && p[1] <= y + h)
+            .count();
+        assert_eq!(kdtree.count_within(&bounds), expected);
+    }
+}
+
+#[test]
+fn test_kdmap_grouped() {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let squared_distance = |p: &[i32; 2], q: &[i32; 2]| (p[0] - q[0]).pow(2) + (p[1] - q[1]).pow(2);
+    let items = vec(1000, |i| ([rng.gen_range(0..10), rng.gen_range(0..10)], i));
+    let kdmap = KdMap::build(items.clone());
+    for _ in 0..100 {
+        let query: [i32; 2] = [rng.gen_range(-2..12), rng.gen_range(-2..12)];
+        let mut values = kdmap.get_all(&query);
+        values.sort();
+        let expected: Vec<_> = items
+            .iter()
+            .filter(|(p, _)| *p == query)
+            .map(|(_, i)| i)
+            .collect();
+        assert_eq!(values, expected);
+
+        let groups = kdmap.nearests_grouped(&query, 5);
+        assert_eq!(groups.len(), 5);
+        let keys: Vec<_> = groups.iter().map(|group| *group.key).collect();
+        let mut expected: Vec<_> = items.iter().map(|(p, _)| *p).collect();
+        expected.sort_by_key(|p| (squared_distance(p, &query), *p));
+        expected.dedup();
+        assert!(keys.windows(2).all(|w| w[0] != w[1]));
+        for (group, expected) in groups.iter().zip(&expected) {
+            assert_eq!(group.squared_distance, squared_distance(expected, &query));
+            assert_eq!(group.values.len(), kdmap.get_all(group.key).len());
+        }
+
+        let groups = kdmap.within_radius_grouped(&query, 3);
+        let count: usize = groups.iter().map(|group| group.values.len()).sum();
+        assert_eq!(count, kdmap.within_radius(&query, 3).len());
+        assert!(groups.windows(2).all(|w| w[0].key != w[1].key));
+    }
+}
+
+#[test]
+fn test_within_by_predicates() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
+    for _ in 0..100 {
+        let center = gen3d();
+        let radius = 0.2;
+        // a spherical shell, which can't be expressed by `within_by_cmp`.
+        let accept = |p: &[f64; 3]| {
+            let d = squared_distance(p, &center);
+            d < radius * radius && d >= radius * radius / 4.0
+        };
+        let mut found = kdtree.within_by_predicates(
+            |p, k| {
+                if p[k] < center[k] - radius {
+                    Ordering::Less
+                } else if p[k] > center[k] + radius {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            },
+            accept,
+        );
+        let mut expected: Vec<_> = kdtree.iter().filter(|p| accept(p)).collect();
+        let key = |p: &&[f64; 3]| p.map(ordered_float::OrderedFloat);
+        found.sort_by_key(key);
+        expected.sort_by_key(key);
+        assert_eq!(found, expected);
+    }
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_knn_graph_chunked() {
+    let mut gen3d = random3d_generator();
+    let points = vec(1000, |_| gen3d());
+    let kdtree = KdIndexTree::build_by_ordered_float(&points);
+    let k = 5;
+    let mut num_rows = 0;
+    kdtree.par_knn_graph_chunked(k, 1000, |first_row, chunk| {
+        assert_eq!(first_row, num_rows);
+        assert!(chunk.num_rows() * k * 16 <= 1000);
+        for i in 0..chunk.num_rows() {
+            let p = &points[first_row + i];
+            let (indices, distances) = chunk.row(i);
+            assert_eq!(indices.len(), k);
+            assert!(!indices.contains(&(first_row + i)));
+            let mut expected: Vec<_> = (0..points.len())
+                .filter(|&j| j != first_row + i)
+                .map(|j| squared_distance(p, &points[j]))
+                .collect();
+            expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let mut distances = distances.to_vec();
+            distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            assert_eq!(distances, expected[..k]);
+        }
+        num_rows += chunk.num_rows();
+    });
+    assert_eq!(num_rows, points.len());
+}
+
+#[test]
+fn test_nearests_excluding() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(1000, |_| gen3d()));
+    for item in kdtree.iter().take(100) {
+        let found = kdtree.nearests_excluding(item, 3);
+        let expected = kdtree.nearests(item, 4);
+        assert!(found.iter().all(|found| !std::ptr::eq(found.item, item)));
+        let distances: Vec<_> = found.iter().map(|found| found.squared_distance).collect();
+        let expected: Vec<_> = expected[1..]
+            .iter()
+            .map(|found| found.squared_distance)
+            .collect();
+        assert_eq!(distances, expected);
+        assert_eq!(
+            kdtree.nearest_excluding(item).unwrap().squared_distance,
+            expected[0]
+        );
+    }
+}
+
+#[test]
+fn test_all_nearests() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(3000, |_| gen3d()));
+    let all = kdtree.all_nearests(3);
+    assert_eq!(all.len(), kdtree.len());
+    for (item, nearests) in kdtree.iter().zip(&all) {
+        assert_eq!(*nearests, kdtree.nearests_excluding(item, 3));
+    }
+    #[cfg(feature = "rayon")]
+    assert_eq!(kdtree.par_all_nearests(3), all);
+}
+
+#[test]
+fn test_query_config_small_tree_size() {
+    let mut gen3d = random3d_generator();
+    for len in [1, 10, 64, 65, 200] {
+        let kdtree = KdTree::build_by_ordered_float(vec(len, |_| gen3d()));
+        for small_tree_size in [0, 64, usize::MAX] {
+            let config = QueryConfig {
+                small_tree_size,
+                ..Default::default()
+            };
+            for _ in 0..20 {
+                let query = gen3d();
+                let found = kdtree.nearest_with_config(&query, &config).unwrap();
+                assert!(found.is_exact);
+                assert_eq!(found.result, kdtree.nearest(&query).unwrap());
+                let found = kdtree.nearests_with_config(&query, 5, &config);
+                assert_eq!(found.result, kdtree.nearests(&query, 5));
+                let found = kdtree.within_radius_with_config(&query, 0.3, &config);
+                assert_eq!(found.result.len(), kdtree.within_radius(&query, 0.3).len());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_nearest_pairs() {
+    let mut gen3d = random3d_generator();
+    let a = KdTree::build_by_ordered_float(vec(1000, |_| gen3d()));
+    let b = KdTree::build_by_ordered_float(vec(500, |_| gen3d()));
+    let pairs = a.nearest_pairs(&b);
+    assert_eq!(pairs.len(), a.len());
+    for ((item, found), expected) in pairs.iter().zip(a.iter()) {
+        assert!(std::ptr::eq(*item, expected));
+        assert_eq!(
+            found.squared_distance,
+            b.nearest(*item).unwrap().squared_distance
+        );
+    }
+    assert!(a
+        .nearest_pairs(&KdTree::<[f64; 3]>::build_by_ordered_float(vec![]))
+        .is_empty());
+}
+
+#[test]
+fn test_nearests_const() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(1000, |_| gen3d()));
+    for _ in 0..100 {
+        let query = gen3d();
+        assert_eq!(
+            kdtree
+                .nearests_const::<1>(&query)
+                .map(Option::unwrap)
+                .to_vec(),
+            kdtree.nearests(&query, 1)
+        );
+        assert_eq!(
+            kdtree
+                .nearests_const::<8>(&query)
+                .map(Option::unwrap)
+                .to_vec(),
+            kdtree.nearests(&query, 8)
+        );
+        assert_eq!(kdtree.nearests_const::<0>(&query), []);
+    }
+    let small = KdTree::build(vec![[1, 2, 3], [3, 1, 2]]);
+    let found = small.nearests_const::<5>(&[0, 0, 0]);
+    assert_eq!(found.iter().flatten().count(), 2);
+    assert_eq!(found[..2].iter().flatten().count(), 2);
+}
+
+#[test]
+fn test_closest_pair() {
+    let mut gen3d = random3d_generator();
+    for len in [0, 1, 2, 3, 10, 1000] {
+        let points = vec(len, |_| gen3d());
+        let kdtree = KdTree::build_by_ordered_float(points.clone());
+        let expected = (0..len)
+            .flat_map(|i| (i + 1..len).map(move |j| (i, j)))
+            .map(|(i, j)| squared_distance(&points[i], &points[j]))
+            .min_by(|a, b| a.partial_cmp(b).unwrap());
+        let found = kdtree.closest_pair();
+        assert_eq!(found.map(|(_, _, d)| d), expected);
+        if let Some((a, b, d)) = found {
+            assert!(!std::ptr::eq(a, b));
+            assert_eq!(squared_distance(a, b), d);
+        }
+    }
+}
+
+#[test]
+fn test_closest_pair_collinear() {
+    // every item lies on the splitting planes of one of the axes,
+    // which must not make the pairs in the slabs compared exhaustively.
+    for points in [
+        (0..100_000i64).map(|i| [i * 3, 7]).collect::<Vec<_>>(),
+        (0..100_000i64).map(|i| [7, i * 3]).collect(),
+        (0..100_000i64).map(|i| [i * 3, i * 3]).collect(),
+    ] {
+        let mut points = points;
+        points.push([points[50_000][0] + 1, points[50_000][1] + 1]);
+        let kdtree = KdTree::build(points);
+        let (_, _, d) = kdtree.closest_pair().unwrap();
+        assert_eq!(d, 2);
+    }
+}
+
+#[test]
+fn test_indexed_queries() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(1000, |_| gen3d()));
+    for _ in 0..100 {
+        let query = gen3d();
+        let (i, found) = kdtree.nearest_indexed(&query).unwrap();
+        assert!(std::ptr::eq(&kdtree[i], found.item));
+        for (i, found) in kdtree.nearests_indexed(&query, 5) {
+            assert!(std::ptr::eq(&kdtree[i], found.item));
+        }
+        for (i, item) in kdtree.within_radius_indexed(&query, 0.2) {
+            assert!(std::ptr::eq(&kdtree[i], item));
+        }
+    }
+
+    // zero-sized items are found, but their offsets are indistinguishable.
+    #[derive(Debug, PartialEq)]
+    struct Origin;
+    impl KdPoint for Origin {
+        type Scalar = i32;
+        type Dim = typenum::U2;
+        fn at(&self, _: usize) -> i32 {
+            0
+        }
+    }
+    let kdtree = KdTree::build_by(vec![Origin, Origin, Origin], |_, _, _| {
+        std::cmp::Ordering::Equal
+    });
+    assert_eq!(kdtree.nearest(&[1, 1]).unwrap().item, &Origin);
+    assert!(std::panic::catch_unwind(|| kdtree.nearest_indexed(&[1, 1])).is_err());
+}
+
+#[test]
+fn test_stable_kdtree() {
+    let mut gen3d = random3d_generator();
+    let points = vec(1000, |_| gen3d());
+    let kdtree = StableKdTree::build_by_ordered_float(points.clone());
+    for (id, p) in points.iter().enumerate() {
+        assert_eq!(kdtree.find_by_id(&id), Some(p));
+    }
+    assert_eq!(kdtree.find_by_id(&points.len()), None);
+    for _ in 0..100 {
+        let found = kdtree.nearest(&gen3d()).unwrap();
+        assert_eq!(&points[*kdtree.id_of(found.item)], found.item);
+    }
+
+    let names = vec(points.len(), |i| format!("p{}", i));
+    let kdtree = StableKdTree::build_with_ids_by_ordered_float(points.clone(), names);
+    assert_eq!(kdtree.find_by_id(&"p42".to_string()), Some(&points[42]));
+
+    #[cfg(feature = "serde")]
+    {
+        let kdtree = StableKdTree::build_with_ids(
+            vec![[3, 1], [1, 2], [2, 3]],
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        );
+        let json = serde_json::to_string(&kdtree).unwrap();
+        let restored: StableKdTree<[i32; 2], String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, kdtree);
+        assert_eq!(restored.find_by_id(&"a".to_string()), Some(&[3, 1]));
+    }
+}
+
+#[test]
+fn test_kdtree_agg() {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let items = vec(1000, |_| {
+        (
+            [rng.gen_range(0..50i32), rng.gen_range(0..50)],
+            rng.gen_range(1..10),
+        )
+    });
+    let weights: KdTreeAgg<_, WeightSum> = KdTreeAgg::new(KdMap::build(items.clone()));
+    let counts: KdTreeAgg<_, Count> = KdTreeAgg::new(KdMap::build(items.clone()));
+    assert_eq!(
+        weights.aggregate(),
+        items.iter().map(|item| item.1).sum::<i32>()
+    );
+    assert_eq!(counts.aggregate(), items.len());
+    for _ in 0..100 {
+        let (x, y) = (rng.gen_range(-5..50), rng.gen_range(-5..50));
+        let (w, h) = (rng.gen_range(0..30), rng.gen_range(0..30));
+        let inside: Vec<_> = items
+            .iter()
+            .filter(|(p, _)| x <= p[0] && p[0] <= x + w && y <= p[1] && p[1] <= y + h)
+            .collect();
+        let bounds = [[x, y], [x + w, y + h]];
+        assert_eq!(
+            weights.aggregate_within(&bounds),
+            inside.iter().map(|item| item.1).sum::<i32>()
+        );
+        assert_eq!(counts.aggregate_within(&bounds), inside.len());
+
+        let radius = rng.gen_range(0..20);
+        let inside: Vec<_> = items
+            .iter()
+            .filter(|(p, _)| (p[0] - x).pow(2) + (p[1] - y).pow(2) < radius * radius)
+            .collect();
+        assert_eq!(
+            weights.aggregate_within_radius(&[x, y], radius),
+            inside.iter().map(|item| item.1).sum::<i32>()
+        );
+        assert_eq!(
+            counts.aggregate_within_radius(&[x, y], radius),
+            inside.len()
+        );
+    }
+}
+
+#[test]
+fn test_transformed_kdtree() {
+    let mut gen3d = random3d_generator();
+    let points = vec(10000, |_| {
+        let [x, y, z] = gen3d();
+        [x.exp(), y * 100.0, z]
+    });
+    let transform = |x: f64, k: usize| match k {
+        0 => x.ln(),
+        1 => x / 100.0,
+        _ => x,
+    };
+    let transformed = |p: &[f64; 3]| [transform(p[0], 0), transform(p[1], 1), p[2]];
+    let kdtree = TransformedKdTree::build_by_ordered_float(points.clone(), transform);
+    const RADIUS: f64 = 0.1;
+    for _ in 0..100 {
+        let [x, y, z] = gen3d();
+        let query = [x.exp(), y * 100.0, z];
+        let expected = points
+            .iter()
+            .min_by_key(|p| {
+                ordered_float::OrderedFloat(squared_distance(&transformed(p), &[x, y, z]))
+            })
+            .unwrap();
+        assert_eq!(kdtree.nearest(&query).unwrap().item, expected);
+        let count = points
+            .iter()
+            .filter(|p| squared_distance(&transformed(p), &[x, y, z]) < RADIUS * RADIUS)
+            .count();
+        assert_eq!(kdtree.within_radius(&query, RADIUS).len(), count);
+        let min = [(x - 0.1).exp(), (y - 0.1) * 100.0, z - 0.1];
+        let max = [(x + 0.1).exp(), (y + 0.1) * 100.0, z + 0.1];
+        let count = points
+            .iter()
+            .filter(|p| (0..3).all(|k| min[k] <= p[k] && p[k] <= max[k]))
+            .count();
+        assert_eq!(kdtree.within(&[min, max]).len(), count);
+    }
+}
+
+#[test]
+fn test_brute_force() {
+    // NaN distances are ordered last.
+    let nan_points = [[f64::NAN, 0.0], [2.0, 0.0], [1.0, 0.0]];
+    assert_eq!(
+        brute_force::nearest(&nan_points, &[0.0, 0.0]).unwrap().item,
+        &[1.0, 0.0]
+    );
+    assert!(brute_force::nearests(&nan_points, &[0.0, 0.0], 3)[2].item[0].is_nan());
+
+    let mut gen3d = random3d_generator();
+    let points = vec(3000, |_| gen3d());
+    let kdtree = KdTree::build_by_ordered_float(points.clone());
+    const NUM: usize = 5;
+    const RADIUS: f64 = 0.2;
+    for _ in 0..50 {
+        let query = gen3d();
+        assert_eq!(
+            brute_force::nearest(&points, &query).unwrap().item,
+            kdtree.nearest(&query).unwrap().item
+        );
+        let expected = brute_force::nearests(&points, &query, NUM);
+        assert_eq!(expected.len(), NUM);
+        assert!(expected
+            .iter()
+            .all(|found| squared_distance(found.item, &query) == found.squared_distance));
+        assert_eq!(
+            kdtree
+                .nearests(&query, NUM)
+                .iter()
+                .map(|found| found.item)
+                .collect::<Vec<_>>(),
+            expected.iter().map(|found| found.item).collect::<Vec<_>>()
+        );
+        let expected = brute_force::nearests_by_metric::<Manhattan, _>(&points, &query, NUM);
+        assert_eq!(
+            kdtree
+                .nearests_with_metric::<Manhattan>(&query, NUM)
+                .iter()
+                .map(|found| found.item)
+                .collect::<Vec<_>>(),
+            expected.iter().map(|found| found.item).collect::<Vec<_>>()
+        );
+        let mut found = kdtree.within_radius(&query, RADIUS);
+        found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mut expected = brute_force::within_radius(&points, &query, RADIUS);
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(found, expected);
+        let (p1, p2) = (query.map(|x| x - RADIUS), query.map(|x| x + RADIUS));
+        assert_eq!(
+            kdtree.within(&[p1, p2]).len(),
+            brute_force::within(&points, &[p1, p2]).len()
+        );
+    }
+    assert!(brute_force::nearest::<[f64; 3]>(&[], &[0.0; 3]).is_none());
+}
+
+#[test]
+fn test_simple() {
+    let mut gen3d = random3d_generator();
+    let points = vec(1000, |_| gen3d());
+    let tree: simple::Tree3 = simple::build(points.clone());
+    let points2: Vec<[f32; 2]> = points.iter().map(|p| [p[0] as f32, p[1] as f32]).collect();
+    let tree2: simple::Tree2f32 = simple::build(points2.clone());
+    for _ in 0..20 {
+        let query = gen3d();
+        assert_eq!(
+            simple::nearest(&tree, query),
+            brute_force::nearest(&points, &query).map(|found| *found.item)
+        );
+        assert_eq!(simple::nearests(&tree, query, 3).len(), 3);
+        assert_eq!(
+            simple::radius_search(&tree, query, 0.2).len(),
+            brute_force::within_radius(&points, &query, 0.2).len()
+        );
+        let query2 = [query[0] as f32, query[1] as f32];
+        let (min, max) = (query2.map(|x| x - 0.1), query2.map(|x| x + 0.1));
+        assert_eq!(
+            simple::box_search(&tree2, min, max).len(),
+            brute_force::within(&points2, &[min, max]).len()
+        );
+    }
+    assert_eq!(simple::nearest(&simple::build(vec![]), [0.0, 0.0]), None);
+}
+
+#[test]
+fn test_within_capsule() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
+    const RADIUS: f64 = 0.1;
+    let segment_distance = |p: &[f64; 3], a: &[f64; 3], b: &[f64; 3]| {
+        let d = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let w = [p[0] - a[0], p[1] - a[1], p[2] - a[2]];
+        let t = ((0..3).map(|k| w[k] * d[k]).sum::<f64>()
+            / (0..3).map(|k| d[k] * d[k]).sum::<f64>())
+        .clamp(0.0, 1.0);
+        squared_distance(p, &[a[0] + t * d[0], a[1] + t * d[1], a[2] + t * d[2]]).sqrt()
+    };
+    for _ in 0..100 {
+        let (a, b) = (gen3d(), gen3d());
+        let found = kdtree.within_capsule(&a, &b, RADIUS);
+        // the points on the boundary may be on either side by rounding errors.
+        let is_inside = |p: &[f64; 3]| segment_distance(p, &a, &b) < RADIUS - 1e-9;
+        let is_outside = |p: &[f64; 3]| segment_distance(p, &a, &b) > RADIUS + 1e-9;
+        assert!(found.iter().all(|p| !is_outside(p)));
+        assert_eq!(
+            found.iter().filter(|p| is_inside(p)).count(),
+            kdtree.iter().filter(|p| is_inside(p)).count()
+        );
+    }
+    // a degenerated segment is a sphere.
+    let query = gen3d();
+    assert_eq!(
+        kdtree.within_capsule(&query, &query, RADIUS).len(),
+        kdtree.within_radius(&query, RADIUS).len()
+    );
+}
+
+#[test]
+fn test_within_capsule_integer() {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    // the points far from the capsules must not overflow the squared distances.
+    let points = vec(10000, |_| {
+        [rng.gen_range(-2000..2000), rng.gen_range(-2000..2000)]
+    });
+    let kdtree = KdTree::build(points.clone());
+    let segment_distance_exceeds = |p: &[i32; 2], a: &[i32; 2], b: &[i32; 2], r: i32| {
+        let [px, py, ax, ay, bx, by, r] = [p[0], p[1], a[0], a[1], b[0], b[1], r].map(i128::from);
+        let (dx, dy, wx, wy) = (bx - ax, by - ay, px - ax, py - ay);
+        let (dot, len) = (wx * dx + wy * dy, dx * dx + dy * dy);
+        if dot <= 0 {
+            wx * wx + wy * wy >= r * r
+        } else if dot >= len {
+            (px - bx).pow(2) + (py - by).pow(2) >= r * r
+        } else {
+            (wx * dy - wy * dx).pow(2) >= r * r * len
+        }
+    };
+    for _ in 0..100 {
+        let a = [rng.gen_range(-2000..2000), rng.gen_range(-2000..2000)];
+        let b = [a[0] + rng.gen_range(-50..50), a[1] + rng.gen_range(-50..50)];
+        let r = rng.gen_range(1..30);
+        let mut found = kdtree.within_capsule(&a, &b, r);
+        let mut expected: Vec<_> = points
+            .iter()
+            .filter(|p| !segment_distance_exceeds(p, &a, &b, r))
+            .collect();
+        found.sort();
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+    let segment = [[0, 0], [30, 40]];
+    let kdtree = KdTree::build(vec![[24, 32], [-3, -4], [33, 44], [-4, 3], [28, 41]]);
+    let mut found = kdtree.within_capsule(&segment[0], &segment[1], 5);
+    found.sort();
+    assert_eq!(found, vec![&[24, 32], &[28, 41]]);
+}
+
+#[test]
+fn test_knn_graph() {
+    let mut gen3d = random3d_10th_generator();
+    let mut points = vec(3000, |_| gen3d());
+    points.extend_from_within(..100);
+    const K: usize = 4;
+    let kdtree = KdTree::build_by_ordered_float(points.clone());
+    let graph = kdtree.knn_graph(K);
+    assert_eq!(graph.num_rows(), kdtree.len());
+    for (i, item) in kdtree.iter().enumerate() {
+        let (indices, squared_distances) = graph.row(i);
+        assert_eq!(indices.len(), K);
+        assert!(!indices.contains(&i));
+        let mut found = squared_distances.to_vec();
+        found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let expected = kdtree.nearests_excluding(item, K);
+        assert_eq!(
+            found,
+            expected
+                .iter()
+                .map(|found| found.squared_distance)
+                .collect::<Vec<_>>()
+        );
+    }
+    let index_tree = KdIndexTree::build_by_ordered_float(&points);
+    let graph = index_tree.knn_graph(K);
+    for (i, item) in points.iter().enumerate() {
+        let (indices, squared_distances) = graph.row(i);
+        assert!(!indices.contains(&i));
+        assert!(indices
+            .iter()
+            .zip(squared_distances)
+            .all(|(&j, &d)| squared_distance(item, &points[j]) == d));
+        let mut found = squared_distances.to_vec();
+        found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mut expected: Vec<_> = (0..points.len())
+            .filter(|&j| j != i)
+            .map(|j| squared_distance(item, &points[j]))
+            .collect();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        expected.truncate(K);
+        assert_eq!(found, expected);
+    }
+    assert_eq!(KdTree::build(vec![[0, 0]]).knn_graph(3).num_entries(), 0);
+    assert_eq!(
+        KdTree::build(vec![[0, 0], [1, 1]])
+            .knn_graph(3)
+            .num_entries(),
+        2
+    );
+}
+
+#[cfg(feature = "glam")]
+#[test]
+fn test_glam_vector() {
+    use ::glam::{DVec3, IVec2, Vec3A};
+
+    let mut gen3d = random3d_generator();
+    let points = vec(1000, |_| gen3d());
+    let kdtree: KdTree<DVec3> =
+        KdTree::build_by_ordered_float(points.iter().map(|&p| p.into()).collect());
+    let kdtree_a: KdTree<Vec3A> =
+        KdTree::build_by_ordered_float(points.iter().map(|&p| DVec3::from(p).as_vec3a()).collect());
+    for _ in 0..100 {
+        let query = gen3d();
+        let expected = brute_force::nearest(&points, &query).unwrap().item;
+        let found = kdtree.nearest(&DVec3::from(query)).unwrap().item;
+        assert_eq!(found.to_array(), *expected);
+        let query_a = DVec3::from(query).as_vec3a();
+        let found = kdtree_a.nearest(&query_a).unwrap();
+        let nearest = kdtree_a
+            .iter()
+            .map(|p| p.distance_squared(query_a))
+            .fold(f32::INFINITY, f32::min);
+        assert_eq!(found.squared_distance, nearest);
+    }
+    let kdtree = KdTree::build(vec![IVec2::new(1, 2), IVec2::new(3, 1)]);
+    assert_eq!(
+        kdtree.nearest(&IVec2::new(3, 2)).unwrap().item,
+        &IVec2::new(3, 1)
+    );
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_arrow() {
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float32Type, Float64Type};
+    use arrow_array::{ArrayRef, FixedSizeListArray, Float64Array, StructArray};
+    use std::convert::TryFrom;
+    use std::sync::Arc;
+
+    let mut gen3d = random3d_generator();
+    let points = vec(1000, |_| gen3d());
+    let list = FixedSizeListArray::from_iter_primitive::<Float64Type, _, _>(
+        points.iter().map(|p| Some(p.iter().map(|&x| Some(x)))),
+        3,
+    );
+    let columns: Vec<ArrayRef> = (0..3)
+        .map(|k| Arc::new(Float64Array::from_iter_values(points.iter().map(|p| p[k]))) as ArrayRef)
+        .collect();
+    let fields: Vec<(&str, ArrayRef)> = ["x", "y", "z"].iter().copied().zip(columns).collect();
+    let structs = StructArray::try_from(fields).unwrap();
+
+    let sliced = list.slice(100, 500);
+    let list_tree: KdIndexTree<[f64; 3]> =
+        KdIndexTree::from_fixed_size_list_by_ordered_float::<Float64Type>(&sliced).unwrap();
+    let struct_tree: KdTree3<usize> =
+        KdTree3::from_struct_array_by_ordered_float::<Float64Type>(&structs).unwrap();
+    let coord = |&i: &usize, k: usize| structs.column(k).as_primitive::<Float64Type>().value(i);
+    for _ in 0..100 {
+        let query = gen3d();
+        let expected = brute_force::nearest(&points[100..600], &query)
+            .unwrap()
+            .item;
+        assert_eq!(
+            &points[100 + *list_tree.nearest(&query).unwrap().item],
+            expected
+        );
+        let expected = brute_force::nearest(&points, &query).unwrap().item;
+        assert_eq!(
+            &points[*struct_tree.nearest_by(&query, coord).unwrap().item],
+            expected
+        );
+    }
+
+    assert!(
+        KdIndexTree::<[f64; 2]>::from_fixed_size_list_by_ordered_float::<Float64Type>(&list)
+            .is_err()
+    );
+    assert!(
+        KdIndexTree::<[f32; 3]>::from_fixed_size_list_by_ordered_float::<Float32Type>(&list)
+            .is_err()
+    );
+    assert!(KdTree2::<usize>::from_struct_array_by_ordered_float::<Float64Type>(&structs).is_err());
+    let with_null = FixedSizeListArray::from_iter_primitive::<Float64Type, _, _>(
+        vec![Some(vec![Some(1.0), Some(2.0), Some(3.0)]), None],
+        3,
+    );
+    assert!(
+        KdIndexTree::<[f64; 3]>::from_fixed_size_list_by_ordered_float::<Float64Type>(&with_null)
+            .is_err()
+    );
+}
+
+#[cfg(feature = "geo")]
+#[test]
+fn test_geo_types() {
+    use geo_types::{coord, point, MultiPoint, Point};
+
+    let mut gen3d = random3d_generator();
+    let points = vec(1000, |_| {
+        let [x, y, _] = gen3d();
+        [x, y]
+    });
+    let multi_point: MultiPoint<f64> = points.iter().map(|&[x, y]| point!(x: x, y: y)).collect();
+    let kdtree = KdTree2::from_multi_point_by_ordered_float(multi_point);
+    let coords: KdTree2<_> = KdTree2::build_by_ordered_float(
+        points.iter().map(|&[x, y]| coord! { x: x, y: y }).collect(),
+    );
+    for _ in 0..100 {
+        let [x, y, _] = gen3d();
+        let expected = brute_force::nearest(&points, &[x, y]).unwrap().item;
+        let found = kdtree.nearest(&point!(x: x, y: y)).unwrap().item;
+        assert_eq!(found.x_y(), (expected[0], expected[1]));
+        let found = coords.nearest(&coord! { x: x, y: y }).unwrap().item;
+        assert_eq!(found.x_y(), (expected[0], expected[1]));
+    }
+    let kdtree =
+        KdTree2::from_multi_point(MultiPoint::new(vec![Point::new(1, 2), Point::new(3, 1)]));
+    assert_eq!(
+        kdtree.nearest(&point!(x: 3, y: 2)).unwrap().item,
+        &Point::new(3, 1)
+    );
+}
+
+#[cfg(feature = "ply")]
+#[test]
+fn test_ply_reader() {
+    let mut gen3d = random3d_generator();
+    let points = vec(1000, |_| gen3d());
+    let header = |format: &str| {
+        format!(
+            "ply\nformat {} 1.0\ncomment test\nelement vertex {}\nproperty double x\nproperty double y\n\
+             property double z\nproperty uchar red\nelement face 0\nproperty list uchar int vertex_indices\nend_header\n",
+            format,
+            points.len()
+        )
+    };
+    let mut ascii = header("ascii").into_bytes();
+    let mut little = header("binary_little_endian").into_bytes();
+    let mut big = header("binary_big_endian").into_bytes();
+    for (i, p) in points.iter().enumerate() {
+        ascii.extend(format!("{} {} {} {}\n", p[0], p[1], p[2], i % 256).bytes());
+        for x in p {
+            little.extend(x.to_le_bytes());
+            big.extend(x.to_be_bytes());
+        }
+        little.push((i % 256) as u8);
+        big.push((i % 256) as u8);
+    }
+    for bytes in [&ascii, &little, &big] {
+        let kdtree = KdTree::from_ply_reader(bytes.as_slice()).unwrap();
+        let kdmap =
+            KdMap::from_ply_reader_with(bytes.as_slice(), |v| v.get("red").unwrap() as usize)
+                .unwrap();
+        assert_eq!(kdtree.len(), points.len());
+        for _ in 0..100 {
+            let query = gen3d();
+            let expected = brute_force::nearest(&points, &query).unwrap().item;
+            assert_eq!(kdtree.nearest(&query).unwrap().item, expected);
+            let found = kdmap.nearest(&query).unwrap().item;
+            assert_eq!(&found.0, expected);
+            assert_eq!(
+                found.1,
+                points.iter().position(|p| p == expected).unwrap() % 256
+            );
+        }
+    }
+    assert!(KdTree::from_ply_reader("ply\nformat ascii 1.0\nend_header\n".as_bytes()).is_err());
+}
+
+#[cfg(feature = "las")]
+#[test]
+fn test_las_reader() {
+    let points = [[1.5, 2.25, -3.0], [10.0, 0.5, 4.75], [3.0, 3.0, 3.0]];
+    // LAS 1.2, point data record format 2 with a variable length record of 54 + 10 bytes.
+    let mut bytes = vec![0u8; 227];
+    bytes[0..4].copy_from_slice(b"LASF");
+    bytes[24] = 1;
+    bytes[25] = 2;
+    bytes[94..96].copy_from_slice(&227u16.to_le_bytes());
+    bytes[96..100].copy_from_slice(&(227u32 + 64).to_le_bytes());
+    bytes[104] = 2;
+    bytes[105..107].copy_from_slice(&26u16.to_le_bytes());
+    bytes[107..111].copy_from_slice(&(points.len() as u32).to_le_bytes());
+    for k in 0..3 {
+        bytes[131 + 8 * k..139 + 8 * k].copy_from_slice(&0.25f64.to_le_bytes());
+        bytes[155 + 8 * k..163 + 8 * k].copy_from_slice(&100f64.to_le_bytes());
+    }
+    bytes.extend([0; 64]);
+    for (i, p) in points.iter().enumerate() {
+        for x in p {
+            bytes.extend((((x - 100.0) / 0.25) as i32).to_le_bytes());
+        }
+        bytes.extend((i as u16 * 100).to_le_bytes());
+        bytes.extend([1, i as u8 + 1, 0, 0, 0, 0]);
+        for c in 0..3 {
+            bytes.extend((i as u16 + c).to_le_bytes());
+        }
+    }
+    let kdtree = KdTree::from_las_reader(bytes.as_slice()).unwrap();
+    assert_eq!(kdtree.len(), points.len());
+    assert_eq!(kdtree.nearest(&[9.0, 1.0, 4.0]).unwrap().item, &points[1]);
+    let kdmap = KdMap::from_las_reader_with(bytes.as_slice(), |p| *p).unwrap();
+    let found = kdmap.nearest(&[3.1, 2.9, 3.0]).unwrap().item;
+    assert_eq!(found.1.position, points[2]);
+    assert_eq!(found.1.intensity, 200);
+    assert_eq!(found.1.return_number, 1);
+    assert_eq!(found.1.classification, 3);
+    assert_eq!(found.1.color, Some([2, 3, 4]));
+
+    bytes[104] |= 0x80;
+    assert!(KdTree::from_las_reader(bytes.as_slice()).is_err());
+}
+
+#[cfg(feature = "ndarray")]
+#[test]
+fn test_ndarray() {
+    let mut gen3d = random3d_generator();
+    let points = vec(1000, |_| gen3d());
+    let array = ::ndarray::Array2::from_shape_fn((1000, 3), |(i, k)| points[i][k]);
+    let kdtree: KdTree<[f64; 3]> = KdTree::from_array2_by_ordered_float(array.view());
+    assert_eq!(kdtree, KdTree::build_by_ordered_float(points.clone()));
+    assert_eq!(
+        kdtree
+            .to_array2()
+            .rows()
+            .into_iter()
+            .map(|row| [row[0], row[1], row[2]])
+            .collect::<Vec<_>>(),
+        kdtree.items()
+    );
+    let queries = ::ndarray::Array2::from_shape_fn((20, 3), |(i, k)| (i * 3 + k) as f64 / 60.0);
+    let (offsets, squared_distances) = kdtree.nearests_array2(queries.view(), 4);
+    let within = kdtree.within_radius_array2(queries.view(), 0.2);
+    for (i, query) in queries.rows().into_iter().enumerate() {
+        let query = [query[0], query[1], query[2]];
+        let expected = kdtree.nearests(&query, 4);
+        for (j, e) in expected.iter().enumerate() {
+            assert_eq!(&kdtree[offsets[[i, j]]], e.item);
+            assert_eq!(squared_distances[[i, j]], e.squared_distance);
+        }
+        assert_eq!(within[i].len(), kdtree.within_radius(&query, 0.2).len());
+    }
+    let empty: KdTree<[f64; 3]> =
+        KdTree::from_array2_by_ordered_float(array.slice(::ndarray::s![..0, ..]));
+    assert_eq!(empty.nearests_array2(queries.view(), 4).0.shape(), &[20, 0]);
+
+    let index_tree: KdIndexTree<[f64; 3]> = KdIndexTree::from_array2_by_ordered_float(array.view());
+    assert_eq!(index_tree.source(), points.as_slice());
+    for query in queries.rows() {
+        let query = [query[0], query[1], query[2]];
+        let found = index_tree.nearest(&query).unwrap();
+        assert_eq!(&points[*found.item], kdtree.nearest(&query).unwrap().item);
+    }
+    let transposed = ::ndarray::Array2::from_shape_fn((3, 3), |(i, k)| (i + k) as f64);
+    let result = std::panic::catch_unwind(|| {
+        KdIndexTree::<[f64; 3]>::from_array2_by_ordered_float(transposed.t())
+            .indices()
+            .len()
+    });
+    assert!(result.is_err());
+}
+
+#[cfg(feature = "nalgebra")]
+#[test]
+fn test_within_oriented_box() {
+    use ::nalgebra as na;
+
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
+    for _ in 0..100 {
+        let center = na::Point3::from(gen3d());
+        let half_extents = na::Vector3::from(gen3d()) * 0.2;
+        let [roll, pitch, yaw] = gen3d();
+        let rotation = na::Rotation3::from_euler_angles(roll * 6.0, pitch * 6.0, yaw * 6.0);
+        let contains = |p: &[f64; 3]| {
+            let local = rotation.inverse() * (na::Point3::from(*p) - center);
+            (0..3).all(|k| local[k].abs() <= half_extents[k])
+        };
+        let found = kdtree.within_oriented_box(&center, &half_extents, &rotation);
+        assert!(found.iter().all(|p| contains(p)));
+        assert_eq!(found.len(), kdtree.iter().filter(|p| contains(p)).count());
+    }
+}
+
+#[test]
+fn test_nearests_batch() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
+    // clustered queries in a random order.
+    let centers = vec(10, |_| gen3d());
+    let queries = vec(2000, |i| {
+        let [x, y, z] = gen3d();
+        let c = centers[i % centers.len()];
+        [c[0] + x * 0.01, c[1] + y * 0.01, c[2] + z * 0.01]
+    });
+    const NUM: usize = 5;
+    let expected: Vec<_> = queries.iter().map(|q| kdtree.nearests(q, NUM)).collect();
+    let distances = |found: &Vec<Vec<ItemAndDistance<[f64; 3], f64>>>| -> Vec<Vec<f64>> {
+        found
+            .iter()
+            .map(|found| found.iter().map(|found| found.squared_distance).collect())
+            .collect()
+    };
+    for order in [QueryOrder::Input, QueryOrder::Morton] {
+        let found = kdtree.nearests_batch(&queries, NUM, order);
+        assert_eq!(distances(&found), distances(&expected));
+        #[cfg(feature = "rayon")]
+        assert_eq!(
+            distances(&kdtree.par_nearests_batch(&queries, NUM, order)),
+            distances(&expected)
+        );
+    }
+    assert!(kdtree
+        .nearests_batch::<[f64; 3]>(&[], NUM, QueryOrder::Morton)
+        .is_empty());
+}
+
+#[test]
+fn test_squared_distance_to_hull() {
+    let mut gen3d = random3d_generator();
+    let points = vec(1000, |_| gen3d().map(|x| x * 0.5));
+    let kdtree = KdTree::build_by_ordered_float(points.clone());
+    let augmented: AugmentedKdTree<_, Aabb<f64, typenum::U3>> =
+        AugmentedKdTree::new(kdtree.clone());
+    for _ in 0..100 {
+        let query = gen3d().map(|x| x * 2.0 - 0.5);
+        let bound = kdtree.squared_distance_to_hull(&query).unwrap();
+        assert_eq!(augmented.squared_distance_to_hull(&query), Some(bound));
+        assert!(bound <= kdtree.nearest(&query).unwrap().squared_distance);
+        for (items, aabb) in augmented.subtrees_at_depth(3) {
+            let bound = SubtreeBound::<[f64; 3]>::min_squared_distance(aabb, &query);
+            assert!(items.iter().all(|p| bound <= squared_distance(p, &query)));
+        }
+    }
+    let subtrees = augmented.subtrees_at_depth(2);
+    assert_eq!(subtrees.len(), 4);
+    assert_eq!(
+        subtrees.iter().map(|(items, _)| items.len()).sum::<usize>(),
+        1000 - 3
+    );
+    let empty: KdTree<[f64; 3]> = KdTree::build_by_ordered_float(vec![]);
+    assert_eq!(empty.squared_distance_to_hull(&[0.0; 3]), None);
+}
+
+#[test]
+fn test_neighbor_pairs() {
+    let mut gen3d = random3d_generator();
+    let mut points = vec(2000, |_| gen3d());
+    points.extend_from_within(..50);
+    let kdtree = KdTree::build_by_ordered_float(points);
+    for radius in [0.0, 0.02, 0.1, 0.3] {
+        let mut expected = Vec::new();
+        for i in 0..kdtree.len() {
+            for j in i + 1..kdtree.len() {
+                if squared_distance(&kdtree[i], &kdtree[j]) < radius * radius {
+                    expected.push((i, j));
+                }
+            }
+        }
+        assert_eq!(kdtree.neighbor_pairs(radius), expected);
+    }
+    let empty: KdTree<[f64; 3]> = KdTree::build_by_ordered_float(vec![]);
+    assert!(empty.neighbor_pairs(1.0).is_empty());
+}
+
+#[test]
+fn test_nearests_in_ranges() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
+    const NUM: usize = 5;
+    for _ in 0..100 {
+        let query = gen3d();
+        let [a, b, _] = gen3d();
+        let ranges = AxisRanges::new()
+            .with(2, a.min(b)..a.max(b))
+            .with(0, ..=0.5);
+        let mut expected: Vec<_> = kdtree.iter().filter(|p| ranges.contains(*p)).collect();
+        expected.sort_by_key(|p| ordered_float::OrderedFloat(squared_distance(p, &query)));
+        expected.truncate(NUM);
+        let found = kdtree.nearests_in_ranges(&query, NUM, &ranges);
+        assert_eq!(
+            found.iter().map(|found| found.item).collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(
+            kdtree
+                .nearest_in_ranges(&query, &ranges)
+                .map(|found| found.item),
+            expected.first().copied()
+        );
+    }
+    let ranges = AxisRanges::new().with(1, 2.0..);
+    assert!(kdtree.nearest_in_ranges(&[0.0; 3], &ranges).is_none());
+    assert!(
+        std::panic::catch_unwind(|| AxisRanges::<f64, typenum::U3>::new().with(3, 0.0..)).is_err()
+    );
+}
+
+#[test]
+fn test_estimate_within_radius_count() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(20000, |_| gen3d()));
+    for _ in 0..50 {
+        let query = gen3d();
+        for radius in [0.05, 0.3, 1.0] {
+            let count = kdtree.count_within_radius(&query, radius);
+            for max_rel_error in [0.0, 0.01, 0.1] {
+                let found = kdtree.estimate_within_radius_count(&query, radius, max_rel_error);
+                assert!(found.lower <= count && count <= found.upper);
+                assert!(found.estimate().abs_diff(count) as f64 <= max_rel_error * count as f64);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_nearests_with() {
+    let mut gen3d = random3d_generator();
+    let points = vec(3000, |_| gen3d());
+    let kdtree = KdTree::build_by_ordered_float(points.clone());
+    let index_tree = KdIndexTree::build_by_ordered_float(&points);
+    const NUM: usize = 5;
+    const RADIUS: f64 = 0.2;
+    for _ in 0..50 {
+        let query = gen3d();
+        let kd_difference = |p: &[f64; 3], k: usize| query[k] - p[k];
+        assert_eq!(
+            kdtree.nearest_with(kd_difference).unwrap().item,
+            kdtree.nearest(&query).unwrap().item
+        );
+        assert_eq!(
+            kdtree.nearests_with(NUM, kd_difference),
+            kdtree.nearests(&query, NUM)
+        );
+        assert_eq!(
+            kdtree.within_radius_with(RADIUS, kd_difference),
+            kdtree.within_radius(&query, RADIUS)
+        );
+        assert_eq!(
+            index_tree.nearest_with(kd_difference).unwrap().item,
+            index_tree.nearest(&query).unwrap().item
+        );
+        assert_eq!(
+            index_tree.nearests_with(NUM, kd_difference),
+            index_tree.nearests(&query, NUM)
+        );
+        assert_eq!(
+            index_tree.within_radius_with(RADIUS, kd_difference),
+            index_tree.within_radius(&query, RADIUS)
+        );
+    }
+    let empty: KdTree<[f64; 3]> = KdTree::build_by_ordered_float(vec![]);
+    assert!(empty.nearest_with(|p, k| -p[k]).is_none());
+}
+
+#[test]
+fn test_distance_histogram() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
+    let bin_edges = [0.1, 0.2, 0.25, 0.5, 0.5, 1.0];
+    for _ in 0..50 {
+        let query = gen3d();
+        let mut expected = vec![0; bin_edges.len() - 1];
+        for p in kdtree.iter() {
+            let d = squared_distance(p, &query);
+            if let Some(i) = (0..expected.len()).find(|&i| {
+                bin_edges[i] * bin_edges[i] <= d && d < bin_edges[i + 1] * bin_edges[i + 1]
+            }) {
+                expected[i] += 1;
+            }
+        }
+        assert_eq!(kdtree.distance_histogram(&query, &bin_edges), expected);
+    }
+    assert!(kdtree.distance_histogram(&[0.0; 3], &[1.0]).is_empty());
+}
+
+#[test]
+fn test_nearest_on_each_side() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(10000, |_| gen3d()));
+    for _ in 0..100 {
+        let query = gen3d();
+        let [position, r, _] = gen3d();
+        let axis = (r * 3.0) as usize;
+        let nearest_where = |pred: &dyn Fn(&[f64; 3]) -> bool| {
+            kdtree
+                .iter()
+                .filter(|p| pred(p))
+                .min_by_key(|p| ordered_float::OrderedFloat(squared_distance(p, &query)))
+        };
+        let [below, above] = kdtree.nearest_on_each_side(&query, axis, position);
+        assert_eq!(
+            below.map(|found| found.item),
+            nearest_where(&|p| p[axis] < position)
+        );
+        assert_eq!(
+            above.map(|found| found.item),
+            nearest_where(&|p| p[axis] >= position)
+        );
+    }
+    let [below, above] = kdtree.nearest_on_each_side(&[0.0; 3], 2, 2.0);
+    assert!(below.is_some() && above.is_none());
+}
+
+#[test]
+fn test_filtered_kdslice() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(2000, |_| gen3d()));
+    // reject most of the items in the region x < 0.5.
+    let filter = |p: &[f64; 3]| p[0] >= 0.5 || p[1] < 0.05;
+    let alive: Vec<&[f64; 3]> = kdtree.iter().filter(|p| filter(p)).collect();
+    let plain = kdtree.filtered(filter);
+    let hinted = kdtree.filtered(filter).with_count_hints();
+    assert_eq!(plain.len(), alive.len());
+    assert_eq!(hinted.len(), alive.len());
+    for _ in 0..100 {
+        let query = gen3d();
+        let mut expected: Vec<f64> = alive.iter().map(|p| squared_distance(p, &query)).collect();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        expected.truncate(5);
+        for view in [&plain, &hinted] {
+            let found: Vec<f64> = view
+                .nearests(&query, 5)
+                .into_iter()
+                .map(|found| found.squared_distance)
+                .collect();
+            assert_eq!(found, expected);
+            let count = alive
+                .iter()
+                .filter(|p| squared_distance(p, &query) < 0.04)
+                .count();
+            assert_eq!(view.within_radius(&query, 0.2).len(), count);
+        }
+    }
+}
+
+#[test]
+fn test_build_partitioned() {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let items: Vec<([f64; 2], usize)> =
+        vec(1000, |_| ([rng.gen(), rng.gen()], rng.gen_range(0..4)));
+    let trees =
+        KdIndexTreeN::<_, typenum::U2>::build_partitioned_by_ordered_float(&items, |item| item.1);
+    assert_eq!(trees.len(), 4);
+    assert_eq!(
+        trees
+            .values()
+            .map(|tree| tree.indices().len())
+            .sum::<usize>(),
+        items.len()
+    );
+    for _ in 0..100 {
+        let query = [rng.gen::<f64>(), rng.gen::<f64>()];
+        for (&category, tree) in &trees {
+            let found = tree.nearest_by(&query, |item, k| item.0[k]).unwrap().item;
+            let expected = (0..items.len())
+                .filter(|&i| items[i].1 == category)
+                .min_by_key(|&i| {
+                    let [x, y] = items[i].0;
+                    ordered_float::OrderedFloat((x - query[0]).powi(2) + (y - query[1]).powi(2))
+                })
+                .unwrap();
+            assert_eq!(*found, expected);
+        }
+    }
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_kdtree_file() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(1000, |_| gen3d()));
+    let path = std::env::temp_dir().join(format!("kd-tree-test-{}.bin", std::process::id()));
+    kdtree
+        .write_to(std::fs::File::create(&path).unwrap())
+        .unwrap();
+    let file = unsafe { KdTreeFile::<[f64; 3], typenum::U3>::open(&path).unwrap() };
+    assert_eq!(file.items(), kdtree.items());
+    for _ in 0..100 {
+        let query = gen3d();
+        assert_eq!(file.nearests(&query, 3), kdtree.nearests(&query, 3));
+    }
+    drop(file);
+    std::fs::write(&path, [0u8; 10]).unwrap();
+    assert!(unsafe { KdTreeFile::<[f64; 3], typenum::U3>::open(&path) }.is_err());
+    std::fs::remove_file(&path).unwrap();
+    assert!(KdSlice::<[f64; 3]>::from_bytes(&kdtree.as_bytes()[1..25]).is_err());
+}
+
+#[test]
+fn test_cloned_queries() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(1000, |_| gen3d()));
+    let queries = vec(20, |_| gen3d());
+    let found: Vec<_> = std::thread::scope(|scope| {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        for query in &queries {
+            let sender = sender.clone();
+            let kdtree = &kdtree;
+            scope.spawn(move || {
+                sender
+                    .send((*query, kdtree.nearests_cloned(query, 3)))
+                    .unwrap()
+            });
+        }
+        drop(sender);
+        receiver.into_iter().collect()
+    });
+    assert_eq!(found.len(), queries.len());
+    for (query, found) in found {
+        let expected = kdtree.nearests(&query, 3);
+        assert_eq!(
+            found,
+            expected
+                .iter()
+                .map(|found| found.cloned())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(kdtree.nearest_cloned(&query), Some(expected[0].cloned()));
+        let mut within = kdtree.within_radius_cloned(&query, 0.2);
+        within.sort_by(|a, b| a.squared_distance.partial_cmp(&b.squared_distance).unwrap());
+        assert_eq!(within.len(), kdtree.within_radius(&query, 0.2).len());
+        assert!(within
+            .iter()
+            .all(|found| found.squared_distance == squared_distance(&found.item, &query)));
+    }
+}
+
+#[test]
+fn test_save_load() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(1000, |_| gen3d()));
+    let mut bytes = Vec::new();
+    kdtree.save_to(&mut bytes).unwrap();
+    let loaded = KdTree::<[f64; 3]>::load_from(&bytes[..]).unwrap();
+    assert_eq!(loaded, kdtree);
+
+    let kind = |bytes: &[u8]| KdTree::<[f64; 3]>::load_from(bytes).unwrap_err().kind();
+    assert_eq!(
+        kind(&bytes[..bytes.len() - 1]),
+        std::io::ErrorKind::UnexpectedEof
+    );
+    assert_eq!(kind(&bytes[1..]), std::io::ErrorKind::InvalidData);
+    let mut newer = bytes.clone();
+    newer[8] += 1;
+    assert_eq!(kind(&newer), std::io::ErrorKind::InvalidData);
+    assert!(KdTree::<[f32; 3]>::load_from(&bytes[..]).is_err());
+
+    let empty: KdTree<[u8; 2]> = KdTree::build(vec![]);
+    bytes.clear();
+    empty.save_to(&mut bytes).unwrap();
+    assert_eq!(KdTree::<[u8; 2]>::load_from(&bytes[..]).unwrap(), empty);
+}
+
+#[test]
+fn test_nearests_batch_large_coords() {
+    // the bounds by the previous queries must not overflow the squared distances of distant queries.
+    let kdtree = KdTree::build(vec![[0, 0], [1, 1], [20000, 19999]]);
+    let found = kdtree.nearests_batch(&[[0, 0], [20000, 20000]], 1, QueryOrder::Input);
+    assert_eq!(found[0][0].item, &[0, 0]);
+    assert_eq!(found[1][0].item, &[20000, 19999]);
+    let points = vec![
+        [0, 0],
+        [1, 0],
+        [10000, 10000],
+        [-10000, -10000],
+        [10000, -10000],
+    ];
+    let kdtree = KdTree::build(points);
+    let graph = kdtree.knn_graph(2);
+    for (i, item) in kdtree.iter().enumerate() {
+        let mut found = graph.row(i).1.to_vec();
+        found.sort();
+        let expected: Vec<_> = kdtree
+            .nearests_excluding(item, 2)
+            .iter()
+            .map(|found| found.squared_distance)
+            .collect();
+        assert_eq!(found, expected);
+    }
+}
+
+#[test]
+fn test_nearests_batch_chunks() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(1000, |_| gen3d()));
+    let queries = vec(250, |_| gen3d());
+    let expected = kdtree.nearests_batch(&queries, 3, QueryOrder::Input);
+    for order in [QueryOrder::Input, QueryOrder::Morton] {
+        let chunks: Vec<_> = kdtree
+            .nearests_batch_chunks(&queries, 3, order, 64)
+            .collect();
+        assert_eq!(
+            chunks.iter().map(Vec::len).collect::<Vec<_>>(),
+            [64, 64, 64, 58]
+        );
+        assert_eq!(chunks.concat(), expected);
+    }
+}
+
+#[test]
+fn test_check_order() {
+    let mut gen3d = random3d_generator();
+    let mut points = vec(1000, |_| gen3d());
+    points[10][1] = f64::NAN;
+    points[20] = [f64::NAN; 3];
+    let kdtree = KdTree::build_by_ordered_float(points);
+    assert_eq!(kdtree.check_order(), Ok(()));
+    let mut items = kdtree.into_vec();
+    items.swap(100, 900);
+    let offset = KdTree::try_from_sorted(items.clone()).unwrap_err().offset;
+    assert!(offset == 100 || offset == 900);
+    items.swap(100, 900);
+    assert!(KdTree::try_from_sorted(items).is_ok());
+    let empty: Vec<[i32; 2]> = Vec::new();
+    assert!(KdTree::try_from_sorted(empty).is_ok());
+}
+
+#[test]
+fn test_build_subset() {
+    let mut gen3d = random3d_generator();
+    let items = vec(1000, |_| gen3d());
+    let mut kdtree = KdIndexTree::build_subset_by_ordered_float(&items, (0..1000).step_by(3));
+    for step in [3, 5] {
+        if step == 5 {
+            kdtree.rebuild_subset_by_ordered_float((0..1000).step_by(5));
+        }
+        assert_eq!(kdtree.indices().len(), 1000usize.div_ceil(step));
+        for _ in 0..100 {
+            let query = gen3d();
+            let expected = (0..1000)
+                .step_by(step)
+                .min_by_key(|&i| ordered_float::OrderedFloat(squared_distance(&items[i], &query)))
+                .unwrap();
+            assert_eq!(*kdtree.nearest(&query).unwrap().item, expected);
+        }
+    }
+}
+
+#[test]
+fn test_try_build_by_float() {
+    let mut gen3d = random3d_generator();
+    let points = vec(100, |_| gen3d());
+    let kdtree = KdTree::try_build_by_float(points.clone()).unwrap();
+    assert_eq!(kdtree, KdTree::build_by_ordered_float(points.clone()));
+    for (value, index, axis) in [
+        (f64::NAN, 10, 2),
+        (f64::INFINITY, 20, 0),
+        (f64::NEG_INFINITY, 99, 1),
+    ] {
+        let mut points = points.clone();
+        points[index][axis] = value;
+        let error = KdTree::try_build_by_float(points).unwrap_err();
+        assert_eq!(error, BuildError::NonFinite { index, axis });
+        assert!(error.to_string().contains("not finite"));
+    }
+}
+
+#[test]
+fn test_nan_policy() {
+    let mut gen3d = random3d_generator();
+    let mut points = vec(100, |_| gen3d());
+    points[10][2] = f64::NAN;
+    points[20][0] = f64::INFINITY;
+    let finite: Vec<_> = points
+        .iter()
+        .copied()
+        .filter(|p| p.iter().all(|x| x.is_finite()))
+        .collect();
+    let kdtree = KdTree::build_by_float_with_policy(points.clone(), NanPolicy::FilterOut).unwrap();
+    assert_eq!(kdtree, KdTree::build_by_ordered_float(finite));
+    assert_eq!(
+        KdTree::build_by_float_with_policy(points.clone(), NanPolicy::default()),
+        Err(BuildError::NonFinite { index: 10, axis: 2 })
+    );
+    let result = std::panic::catch_unwind(|| {
+        KdTree::build_by_float_with_policy(points, NanPolicy::PanicDebugOnly)
+    });
+    assert_eq!(result.is_err(), cfg!(debug_assertions));
+}
+
+#[test]
+fn test_geometry_helpers() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(1000, |_| gen3d()));
+    for _ in 0..100 {
+        let query = gen3d();
+        let found = kdtree.nearest(&query).unwrap();
+        let offset = found.offset_from(&query);
+        let direction = found.direction_from(&query).unwrap();
+        for k in 0..3 {
+            assert!((query[k] + offset[k] - found.point()[k]).abs() < 1e-12);
+            assert!((direction[k] * found.distance() - offset[k]).abs() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn test_cached_kdtree() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(1000, |_| gen3d()));
+    let cached = CachedKdTree::new(kdtree.clone(), 10, 0.0);
+    let queries = vec(20, |_| gen3d());
+    for _ in 0..2 {
+        for query in &queries {
+            assert_eq!(cached.nearests(query, 3), kdtree.nearests(query, 3));
+            assert_eq!(
+                cached.within_radius(query, 0.2),
+                kdtree.within_radius(query, 0.2)
+            );
+        }
+        assert_eq!(cached.cache_len(), 10);
+    }
+    // the queries in the same cell share the cached items.
+    let cached = CachedKdTree::new(kdtree, 10, 1.0);
+    let found = cached.nearest(&[0.1, 0.1, 0.1]).unwrap();
+    let shared = cached.nearest(&[0.9, 0.9, 0.9]).unwrap();
+    assert_eq!(found.item, shared.item);
+    assert_eq!(
+        shared.squared_distance,
+        squared_distance(shared.item, &[0.9, 0.9, 0.9])
+    );
+    assert_eq!(cached.cache_len(), 1);
+    // the shared items are sorted by the distances from the later query.
+    let kdtree =
+        KdTree::build_by_ordered_float(vec![[0.0, 0.0], [0.5, 0.0], [1.0, 0.0], [9.0, 9.0]]);
+    let cached = CachedKdTree::new(kdtree, 10, 1.0);
+    let found = cached.nearests(&[0.1, 0.0], 3);
+    assert_eq!(found[0].item, &[0.0, 0.0]);
+    let shared = cached.nearests(&[0.9, 0.0], 3);
+    assert_eq!(
+        shared.iter().map(|found| found.item).collect::<Vec<_>>(),
+        vec![&[1.0, 0.0], &[0.5, 0.0], &[0.0, 0.0]]
+    );
+    // the different radii never share the cached items, even in the same cell.
+    let kdtree = KdTree::build_by_ordered_float(vec![[0.0, 0.0], [0.5, 0.0]]);
+    let cached = CachedKdTree::new(kdtree, 10, 1.0);
+    assert_eq!(cached.within_radius(&[0.0, 0.0], 0.25).len(), 1);
+    assert_eq!(cached.within_radius(&[0.0, 0.0], 0.75).len(), 2);
+    assert_eq!(cached.cache_len(), 2);
+    // the integers beyond the precision of `f64` are keyed exactly at the resolution zero.
+    let big = 1i64 << 60;
+    let kdtree = KdTree::build(vec![[big], [big + 1]]);
+    let cached = CachedKdTree::new(kdtree, 10, 0.0);
+    assert_eq!(cached.nearest(&[big]).unwrap().item, &[big]);
+    assert_eq!(cached.nearest(&[big + 1]).unwrap().item, &[big + 1]);
+    assert_eq!(cached.within_radius(&[big], 1), vec![&[big]]);
+    assert_eq!(cached.within_radius(&[big + 1], 1), vec![&[big + 1]]);
+    assert_eq!(cached.cache_len(), 4);
+}
+
+#[cfg(feature = "half")]
+#[test]
+fn test_half() {
+    use half::{bf16, f16};
+    let mut gen3d = random3d_generator();
+    let points = vec(1000, |_| gen3d());
+    let points16: Vec<[f16; 3]> = points.iter().map(|p| p.map(f16::from_f64)).collect();
+    let kdtree = KdTree::build_by_ordered_float(points16.clone());
+    let kdtree_bf =
+        KdTree::build_by_ordered_float(points.iter().map(|p| p.map(bf16::from_f64)).collect());
+    for _ in 0..100 {
+        let query = gen3d().map(f16::from_f64);
+        let found = kdtree.nearest(&query).unwrap();
+        let to_f32 = |p: &[f16; 3], q: &[f16; 3]| -> f32 {
+            (0..3)
+                .map(|k| (p[k].to_f32() - q[k].to_f32()).powi(2))
+                .sum()
+        };
+        let expected = points16
+            .iter()
+            .map(|p| ordered_float::OrderedFloat(to_f32(p, &query)))
+            .min()
+            .unwrap();
+        // the squared distance in f16 is rounded, so compare it loosely in f32.
+        assert!((to_f32(found.item, &query) - expected.0).abs() < 1e-2);
+        let query_bf = query.map(|x| bf16::from_f32(x.to_f32()));
+        assert!(kdtree_bf.nearest(&query_bf).is_some());
+        let radius = f16::from_f32(0.2);
+        assert_eq!(
+            kdtree.within_radius(&query, radius).len(),
+            brute_force::within_radius(&points16, &query, radius).len()
+        );
+        // the squared distance itself is computed in f16, within its relative precision.
+        let exact = to_f32(found.item, &query);
+        assert!((found.squared_distance.to_f32() - exact).abs() <= exact * 4e-3 + 1e-6);
+    }
+
+    // a squared distance beyond `f16::MAX` overflows to infinity, while `bf16` has the range of `f32`.
+    let far = |x: f32| [x, x, 0.0];
+    let kdtree = KdTree::build_by_ordered_float(vec![far(200.0).map(f16::from_f32)]);
+    let found = kdtree.nearest(&far(0.0).map(f16::from_f32)).unwrap();
+    assert!(found.squared_distance.is_infinite());
+    let kdtree_bf = KdTree::build_by_ordered_float(vec![far(200.0).map(bf16::from_f32)]);
+    let found = kdtree_bf.nearest(&far(0.0).map(bf16::from_f32)).unwrap();
+    assert!((found.squared_distance.to_f32() - 80000.0).abs() < 80000.0 * 1e-2);
+}
+
+#[test]
+fn test_tiled_kdtree_update() {
+    let mut gen3d = random3d_generator();
+    let points = vec(2000, |_| gen3d());
+    for tile_size in [0.05, 0.3, 2.0] {
+        let mut tiled = TiledKdTree::build_by_ordered_float(points[..1000].to_vec(), tile_size);
+        for p in &points[1000..] {
+            tiled.insert_by_ordered_float(*p);
+        }
+        for p in &points[..500] {
+            assert_eq!(tiled.remove_by_ordered_float(p), Some(*p));
+        }
+        assert_eq!(tiled.remove_by_ordered_float(&points[0]), None);
+        assert_eq!(tiled.len(), 1500);
+        assert!(tiled.tiles().windows(2).all(|w| w[0].0 < w[1].0));
+        let kdtree = KdTree::build_by_ordered_float(points[500..].to_vec());
+        for _ in 0..50 {
+            // include the queries far from the points.
+            let query = gen3d().map(|x| x * 4.0 - 2.0);
+            let distances = |found: Vec<ItemAndDistance<[f64; 3], f64>>| {
+                found.iter().map(|f| f.squared_distance).collect::<Vec<_>>()
+            };
+            assert_eq!(
+                distances(tiled.nearests(&query, 5)),
+                distances(kdtree.nearests(&query, 5))
+            );
+            assert_eq!(
+                tiled.within_radius(&query, 0.3).len(),
+                kdtree.within_radius(&query, 0.3).len()
+            );
+        }
+    }
+
+    // the keys out of the range of `i64` and NaN are saturated instead of panicking.
+    let mut tiled = TiledKdTree::build_by_ordered_float(vec![[0.5, 0.5], [1e300, 0.5]], 1.0);
+    tiled.insert_by_ordered_float([-1e300, f64::NAN]);
+    assert_eq!(tiled.tile_key(&[1e300, 0.5]), vec![i64::MAX, 0]);
+    assert_eq!(
+        tiled.tile_key(&[-1e300, f64::NAN]),
+        vec![i64::MIN, i64::MAX]
+    );
+    assert_eq!(tiled.nearest(&[1e300, 0.5]).unwrap().item, &[1e300, 0.5]);
+    assert_eq!(tiled.nearest(&[2e300, 0.0]).unwrap().item, &[1e300, 0.5]);
+    assert_eq!(tiled.nearest(&[1.0, 1.0]).unwrap().item, &[0.5, 0.5]);
+    assert_eq!(tiled.within_radius(&[1e300, 0.0], 1.0), vec![&[1e300, 0.5]]);
+    assert_eq!(tiled.nearests(&[f64::NAN, 0.0], 3).len(), 3);
+    assert_eq!(
+        tiled.remove_by_ordered_float(&[1e300, 0.5]),
+        Some([1e300, 0.5])
+    );
+    assert_eq!(tiled.len(), 2);
+}
+
+#[test]
+fn test_empty_kdtree_queries() {
+    let kdtree: KdTree<[f64; 2]> = KdTree::build_by_ordered_float(vec![]);
+    let query = [1.0, 2.0];
+    assert!(kdtree.nearest(&query).is_none());
+    assert!(kdtree.nearest_by(&query, |item, k| item[k]).is_none());
+    assert!(kdtree.nearest_with(|item, k| item[k] - query[k]).is_none());
+    assert!(kdtree
+        .nearest_with_config(&query, &QueryConfig::default())
+        .is_none());
+    assert!(kdtree.nearest_with_max_depth(&query, 0).is_none());
+    assert!(kdtree
+        .nearest_with_progress(&query, &QueryConfig::default(), |_| {})
+        .is_none());
+    assert!(kdtree.nearest_with_bound(&query, 1.0).is_none());
+    assert!(kdtree.nearest_excluding(&query).is_none());
+    assert!(kdtree.nearest_indexed(&query).is_none());
+    assert!(kdtree.nearest_cloned(&query).is_none());
+    assert!(kdtree.nearest_iter(&query).next().is_none());
+    assert!(kdtree.closest_pair().is_none());
+    assert_eq!(kdtree.nearest_on_each_side(&query, 0, 0.0), [None, None]);
+    assert!(kdtree.nearests(&query, 3).is_empty());
+    assert!(kdtree.nearests_by(&query, 3, |item, k| item[k]).is_empty());
+    assert_eq!(kdtree.nearests_const::<3>(&query), [None; 3]);
+    assert!(kdtree
+        .nearests_with_config(&query, 3, &QueryConfig::default())
+        .result
+        .is_empty());
+    assert!(kdtree.within(&[[0.0, 0.0], [3.0, 3.0]]).is_empty());
+    assert!(kdtree.within_radius(&query, 1.0).is_empty());
+    assert!(kdtree.within_radius_sorted(&query, 1.0).is_empty());
+    assert!(kdtree.within_capsule(&query, &[3.0, 3.0], 1.0).is_empty());
+    assert_eq!(kdtree.count_within_radius(&query, 1.0), 0);
+    assert!(kdtree.all_nearests(3).is_empty());
+    assert!(kdtree.neighbor_pairs(1.0).is_empty());
+    assert!(kdtree.nearest_pairs(&kdtree).is_empty());
+    let points = [[1.0, 2.0]];
+    let other = KdTree::build_by_ordered_float(points.to_vec());
+    assert!(other.nearest_pairs(&kdtree).is_empty());
+    assert!(low_level::kd_nearest(kdtree.items(), &query).is_none());
+    assert!(low_level::kd_nearests(kdtree.items(), &query, 3).is_empty());
+}
+
+#[test]
+fn test_custom_kd_scalar() {
+    use std::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
+    /// A fixed-point number with 8 fractional bits, which doesn't implement `num_traits`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    struct Fixed(i64);
+    impl Add for Fixed {
+        type Output = Self;
+        fn add(self, other: Self) -> Self {
+            Fixed(self.0 + other.0)
+        }
+    }
+    impl Sub for Fixed {
+        type Output = Self;
+        fn sub(self, other: Self) -> Self {
+            Fixed(self.0 - other.0)
+        }
+    }
+    impl Mul for Fixed {
+        type Output = Self;
+        fn mul(self, other: Self) -> Self {
+            Fixed((self.0 * other.0) >> 8)
+        }
+    }
+    impl AddAssign for Fixed {
+        fn add_assign(&mut self, other: Self) {
+            *self = *self + other;
+        }
+    }
+    impl SubAssign for Fixed {
+        fn sub_assign(&mut self, other: Self) {
+            *self = *self - other;
+        }
+    }
+    impl MulAssign for Fixed {
+        fn mul_assign(&mut self, other: Self) {
+            *self = *self * other;
+        }
+    }
+    impl KdScalar for Fixed {
+        fn zero() -> Self {
+            Fixed(0)
+        }
+        fn one() -> Self {
+            Fixed(1 << 8)
+        }
+    }
+    let fixed = |p: [i64; 2]| p.map(|x| Fixed(x << 8));
+    let points: Vec<[i64; 2]> = (0..100).map(|i| [i % 10, i / 10]).collect();
+    let kdtree = KdTree::build(points.iter().map(|&p| fixed(p)).collect());
+    let found = kdtree.nearest(&fixed([3, 4])).unwrap();
+    assert_eq!(found.item, &fixed([3, 4]));
+    assert!(found.squared_distance.is_zero());
+    assert_eq!(kdtree.nearests(&fixed([0, 0]), 3).len(), 3);
+    assert_eq!(kdtree.within_radius(&fixed([5, 5]), Fixed(3 << 7)).len(), 9);
+    assert_eq!(kdtree.within(&[fixed([2, 2]), fixed([4, 3])]).len(), 6);
+}
+
+#[test]
+fn test_verified_queries() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(3000, |_| gen3d()));
+    for _ in 0..50 {
+        let query = gen3d();
+        let found = kdtree.within_radius_verified(&query, 0.2);
+        let expected = kdtree.within_radius(&query, 0.2);
+        assert!(found.certain.iter().all(|p| expected.contains(p)));
+        assert!(expected.iter().all(|p| found.possible.contains(p)));
+        assert!(found.possible.len() <= expected.len() + 1);
+
+        let found = kdtree.nearests_verified(&query, 5);
+        let expected = kdtree.nearests(&query, 5);
+        assert!(found
+            .certain
+            .iter()
+            .all(|c| expected.iter().any(|e| e.item == c.item)));
+        assert!(expected
+            .iter()
+            .all(|e| found.possible.iter().any(|c| c.item == e.item)));
+        for c in &found.possible {
+            assert!(c
+                .squared_distance
+                .contains(squared_distance(c.item, &query)));
+        }
+    }
+
+    // the distances on a grid are exact ties.
+    let grid = KdTree::build_by_ordered_float(vec(25, |i| [(i % 5) as f64, (i / 5) as f64]));
+    let found = grid.within_radius_verified(&[2.0, 2.0], 1.0);
+    assert_eq!(found.certain, vec![&[2.0, 2.0]]);
+    assert_eq!(found.possible.len(), 5);
+    let found = grid.nearests_verified(&[2.0, 2.0], 3);
+    assert_eq!(found.certain.len(), 1);
+    assert_eq!(found.possible.len(), 5);
+    let found = grid.nearests_verified(&[2.0, 2.0], 5);
+    assert_eq!(found.certain.len(), 5);
+    assert!(KdTree::<[f64; 2]>::build_by_ordered_float(vec![])
+        .nearests_verified(&[0.0, 0.0], 1)
+        .possible
+        .is_empty());
+}
+
+#[test]
+fn test_interval_overflow() {
+    // the squares overflowing to infinity are bounded by the largest finite value from below.
+    let square = Interval::point(1e200).square();
+    assert_eq!(square.lo, f64::MAX);
+    assert_eq!(square.hi, f64::INFINITY);
+    let sum = Interval::point(f64::MAX) + Interval::point(f64::MAX);
+    assert_eq!((sum.lo, sum.hi), (f64::MAX, f64::INFINITY));
+    let difference = Interval::point(-f64::MAX) - Interval::point(f64::MAX);
+    assert_eq!(
+        (difference.lo, difference.hi),
+        (f64::NEG_INFINITY, -f64::MAX)
+    );
+    let product = Interval::new(1e200, 2e200) * Interval::new(-1e200, 1.0);
+    assert_eq!(product.lo, f64::NEG_INFINITY);
+    assert!(product.contains(2e200) && product.hi < 2.1e200);
+
+    let kdtree = KdTree::build_by_ordered_float(vec![[1e200, 0.0], [0.0, 1e200], [1.0, 0.0]]);
+    let found = kdtree.within_radius_verified(&[0.0, 0.0], 1e300);
+    assert_eq!(found.certain, vec![&[1.0, 0.0]]);
+    assert_eq!(found.possible.len(), 3);
+    let found = kdtree.nearests_verified(&[0.0, 0.0], 2);
+    assert_eq!(found.certain.len(), 1);
+    assert_eq!(found.possible.len(), 3);
+    let found = kdtree.nearests_verified(&[f64::INFINITY, 0.0], 1);
+    assert_eq!(found.possible.len(), 3);
+}
+
+#[test]
+fn test_query_log_replay() {
+    let mut gen3d = random3d_generator();
+    let kdtree = KdTree::build_by_ordered_float(vec(1000, |_| gen3d()));
+    let recorder = QueryRecorder::new(&kdtree);
+    let mut num_found = 0;
+    for i in 0..40 {
+        let query = gen3d();
+        num_found += match i % 4 {
+            0 => recorder.nearest(&query).is_some() as usize,
+            1 => recorder.nearests(&query, 5).len(),
+            2 => recorder.within(&[query, query.map(|x| x + 0.2)]).len(),
+            _ => recorder.within_radius(&query, 0.2).len(),
+        };
+    }
+    let log = recorder.log();
+    assert_eq!(log.len(), 40);
+    assert_eq!(log, recorder.into_log());
+
+    let mut bytes = Vec::new();
+    log.write_to(&mut bytes).unwrap();
+    let loaded = QueryLog::<f64, typenum::U3>::read_from(&bytes[..]).unwrap();
+    assert_eq!(loaded, log);
+    let report = loaded.replay(&kdtree);
+    assert_eq!(report.num_queries, 40);
+    assert_eq!(report.num_found, num_found);
+
+    assert!(QueryLog::<f32, typenum::U3>::read_from(&bytes[..]).is_err());
+    assert!(QueryLog::<f64, typenum::U2>::read_from(&bytes[..]).is_err());
+    assert!(QueryLog::<f64, typenum::U3>::read_from(&bytes[..bytes.len() - 1]).is_err());
+
+    let mut log = QueryLog::<i32, typenum::U2>::new();
+    log.push(RecordedQuery::Nearests {
+        query: vec![1, 2],
+        num: 2,
+    });
+    let kdtree = KdTree::build(vec![[1, 2], [3, 1], [2, 3]]);
+    assert_eq!(log.replay(&kdtree).num_found, 2);
+}
+
+#[test]
+fn test_autotune() {
+    let mut gen3d = random3d_generator();
+    // flat data, whose spread along the axis 2 is small.
+    let points = vec(3000, |_| {
+        let [x, y, z] = gen3d();
+        [x * 10.0, y, z * 0.01]
+    });
+    let queries = vec(50, |_| {
+        let [x, y, z] = gen3d();
+        [x * 10.0, y, z * 0.01]
+    });
+    assert_eq!(SplitRule::SpreadOrder.axis_order(&points), vec![0, 1, 2]);
+    let config = BuildConfig::autotune_by_ordered_float(&points, &queries, 5);
+    assert!([1, 4, 8, 16, 32].contains(&config.query.leaf_size));
+    assert_eq!(
+        BuildConfig::autotune_by_ordered_float(&points, &queries, 5),
+        config
+    );
+    let kdtree = PermutedKdTree::build_by_ordered_float(
+        points.clone(),
+        config.split_rule.axis_order(&points),
+    );
+    for query in &queries {
+        let found = kdtree.nearests_with_config(query, 5, &config.query);
+        assert!(found.is_exact);
+        assert_eq!(
+            found
+                .result
+                .iter()
+                .map(|f| f.squared_distance)
+                .collect::<Vec<_>>(),
+            brute_force::nearests(&points, query, 5)
+                .iter()
+                .map(|f| f.squared_distance)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    let points = vec(1000, |_| {
+        let [x, y, z] = gen3d();
+        [x, y * 10.0, z]
+    });
+    assert_eq!(SplitRule::SpreadOrder.axis_order(&points)[0], 1);
+    assert_eq!(
+        BuildConfig::autotune_by_ordered_float(&points, &queries[..0], 5)
+            .query
+            .max_depth,
+        usize::MAX
+    );
+    assert_eq!(
+        BuildConfig::autotune_by_ordered_float(&points[..0], &queries, 5).split_rule,
+        SplitRule::Cyclic
+    );
+}
+
+#[test]
+fn test_kdtree_builder() {
+    let mut gen3d = random3d_generator();
+    let mut points = vec(1000, |_| gen3d());
+    let builder = KdTreeBuilder::by_ordered_float();
+    assert_eq!(
+        builder.build(points.clone()).unwrap(),
+        KdTree::build_by_ordered_float(points.clone())
+    );
+    points[10][1] = f64::NAN;
+    points[20][2] = f64::INFINITY;
+    assert!(matches!(
+        builder.nan_policy(NanPolicy::Reject).build(points.clone()),
+        Err(BuildError::NonFinite { index: 10, axis: 1 })
+    ));
+    let builder = builder.nan_policy(NanPolicy::FilterOut);
+    let kdtree = builder.build(points.clone()).unwrap();
+    assert_eq!(kdtree.len(), 998);
+    let index_tree = builder.build_index(&points).unwrap();
+    assert_eq!(index_tree.indices().len(), 998);
+    assert!(!index_tree.indices().contains(&10) && !index_tree.indices().contains(&20));
+    let query = gen3d();
+    assert_eq!(
+        &points[*index_tree.nearest(&query).unwrap().item],
+        kdtree.nearest(&query).unwrap().item
+    );
+    #[cfg(feature = "rayon")]
+    {
+        assert_eq!(builder.par_build(points.clone()).unwrap(), kdtree);
+        assert_eq!(
+            builder.par_build_index(&points).unwrap().indices(),
+            index_tree.indices()
+        );
+    }
+}
@@ -0,0 +1,431 @@
+use crate::dedup::{compare_coords, dedup_shards, nearests_dedup};
+use crate::{ItemAndDistance, KdPoint, KdTreeN, TieBreak};
+use num_traits::{Float, NumCast, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use typenum::Unsigned;
+
+/// A set of kd-trees, each of which contains the points in an axis-aligned cubic tile of `tile_size`.
+///
+/// The tile of a point is `floor(p[k] / tile_size)` on each axis `k`, saturated to the range of `i64`
+/// where NaN is regarded as greater than any number, so the outermost tiles extend to infinity.
+/// Each tile can be used on its own, e.g. for out-of-core processing, and the queries of this type search over all tiles.
+/// # Example
+/// ```
+/// use kd_tree::TiledKdTree;
+/// let tiled: TiledKdTree<[f64; 2]> = TiledKdTree::build_by_ordered_float(
+///     vec![[0.5, 0.5], [1.5, 0.5], [1.6, 0.6], [-0.5, 2.5]],
+///     1.0,
+/// );
+/// assert_eq!(tiled.tiles().len(), 3);
+/// assert_eq!(tiled.tile(&[1, 0]).unwrap().len(), 2);
+/// assert_eq!(tiled.nearest(&[1.2, 0.4]).unwrap().item, &[1.5, 0.5]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TiledKdTreeN<T: KdPoint, N: Unsigned> {
+    tile_size: T::Scalar,
+    tiles: Vec<(Vec<i64>, KdTreeN<T, N>)>,
+}
+pub type TiledKdTree<T> = TiledKdTreeN<T, <T as KdPoint>::Dim>;
+
+impl<T: KdPoint<Dim = N>, N: Unsigned> TiledKdTreeN<T, N>
+where
+    T::Scalar: Float + ordered_float::FloatCore,
+{
+    /// Returns the key of the tile which contains `point`.
+    pub fn tile_key(&self, point: &impl KdPoint<Scalar = T::Scalar, Dim = N>) -> Vec<i64> {
+        tile_key(point, self.tile_size)
+    }
+
+    /// Splits `points` into tiles and builds a kd-tree for each tile.
+    ///
+    /// # Panics
+    /// Panics if `tile_size` is not positive.
+    pub fn build_by_ordered_float(points: Vec<T>, tile_size: T::Scalar) -> Self {
+        let tiles = split_into_tiles(points, tile_size)
+            .into_iter()
+            .map(|(key, points)| (key, KdTreeN::build_by_ordered_float(points)))
+            .collect();
+        Self { tile_size, tiles }
+    }
+
+    pub fn tile_size(&self) -> T::Scalar {
+        self.tile_size
+    }
+
+    /// Returns the tiles which contain at least one point, sorted by their keys.
+    pub fn tiles(&self) -> &[(Vec<i64>, KdTreeN<T, N>)] {
+        &self.tiles
+    }
+
+    pub fn into_tiles(self) -> Vec<(Vec<i64>, KdTreeN<T, N>)> {
+        self.tiles
+    }
+
+    /// Returns the kd-tree of the tile of `key`, or `None` if the tile has no points.
+    pub fn tile(&self, key: &[i64]) -> Option<&KdTreeN<T, N>> {
+        self.tiles
+            .binary_search_by(|(k, _)| k.as_slice().cmp(key))
+            .ok()
+            .map(|i| &self.tiles[i].1)
+    }
+
+    /// Returns the number of points in all tiles.
+    pub fn len(&self) -> usize {
+        self.tiles.iter().map(|(_, kdtree)| kdtree.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Returns the nearest point from the input point over all tiles. Returns `None` if `self.is_empty()`.
+    pub fn nearest(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<ItemAndDistance<'_, T, T::Scalar>> {
+        self.nearests(query, 1).pop()
+    }
+
+    /// Returns kNN(k nearest neighbors) from the input point over all tiles.
+    ///
+    /// The tiles are looked up ring by ring around the tile of the input point, and the search stops
+    /// as soon as the next ring is farther than the k-th nearest point found, which visits only a few tiles for uniformly distributed points.
+    /// If the rings would visit more cells than the occupied tiles, the tiles are visited in order of their distance instead.
+    pub fn nearests(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>> {
+        let center = self.tile_key(query);
+        let mut nearests: Vec<ItemAndDistance<'_, T, T::Scalar>> = Vec::with_capacity(num);
+        let mut visited_cells = 0usize;
+        for r in 0i64.. {
+            if num == 0 {
+                break;
+            }
+            if nearests.len() == num && r > 0 {
+                // a tile in the ring `r` is farther than `r - 1` tiles on some axis.
+                let gap = <T::Scalar as NumCast>::from(r - 1).unwrap() * self.tile_size;
+                if nearests[num - 1].squared_distance <= gap * gap {
+                    break;
+                }
+            }
+            let ring_cells = ring_len(r, N::to_u32());
+            visited_cells = visited_cells.saturating_add(ring_cells);
+            if visited_cells > self.tiles.len() {
+                return self.nearests_by_tile_distance(query, num);
+            }
+            for_each_in_ring(&center, r, |key| {
+                if let Some(kdtree) = self.tile(key) {
+                    merge_nearests(&mut nearests, kdtree.nearests(query, num), num);
+                }
+            });
+        }
+        nearests
+    }
+
+    fn nearests_by_tile_distance(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>> {
+        let mut tiles: Vec<_> = self
+            .tiles
+            .iter()
+            .map(|(key, kdtree)| (self.min_squared_distance(key, query), kdtree))
+            .collect();
+        tiles.sort_by(|(d1, _), (d2, _)| d1.partial_cmp(d2).unwrap_or(Ordering::Equal));
+        let mut nearests: Vec<ItemAndDistance<'_, T, T::Scalar>> = Vec::with_capacity(num);
+        for (bound, kdtree) in tiles {
+            if num == 0 || (nearests.len() == num && nearests[num - 1].squared_distance <= bound) {
+                break;
+            }
+            merge_nearests(&mut nearests, kdtree.nearests(query, num), num);
+        }
+        nearests
+    }
+
+    /// search points within a sphere over all tiles.
+    ///
+    /// The tiles overlapping the bounding box of the sphere are looked up by their keys,
+    /// unless there are more such cells than the occupied tiles.
+    pub fn within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<&T> {
+        // the keys out of the range of `i64`, e.g. for an infinite radius, fall back to filtering all tiles.
+        let bounds = shifted_tile_key(query, -radius, self.tile_size).zip(shifted_tile_key(
+            query,
+            radius,
+            self.tile_size,
+        ));
+        let cells = bounds.as_ref().and_then(|(min, max)| {
+            min.iter().zip(max).try_fold(1usize, |cells, (lo, hi)| {
+                let len = hi.checked_sub(*lo)?.checked_add(1)?.to_usize()?;
+                cells.checked_mul(len)
+            })
+        });
+        match (bounds, cells) {
+            (Some((min, max)), Some(cells)) if cells <= self.tiles.len() => {
+                let mut found = Vec::new();
+                let mut key = min.clone();
+                loop {
+                    if let Some(kdtree) = self.tile(&key) {
+                        if self.min_squared_distance(&key, query) < radius * radius {
+                            found.extend(kdtree.within_radius(query, radius));
+                        }
+                    }
+                    if !next_key(&mut key, &min, &max) {
+                        break;
+                    }
+                }
+                found
+            }
+            _ => self
+                .tiles
+                .iter()
+                .filter(|(key, _)| self.min_squared_distance(key, query) < radius * radius)
+                .flat_map(|(_, kdtree)| kdtree.within_radius(query, radius))
+                .collect(),
+        }
+    }
+
+    /// Inserts a point, rebuilding the kd-tree of its tile only.
+    /// # Example
+    /// ```
+    /// let mut tiled = kd_tree::TiledKdTree::build_by_ordered_float(vec![[0.5, 0.5], [1.5, 0.5]], 1.0);
+    /// tiled.insert_by_ordered_float([1.2, 2.5]);
+    /// assert_eq!(tiled.tiles().len(), 3);
+    /// assert_eq!(tiled.remove_by_ordered_float(&[0.5, 0.5]), Some([0.5, 0.5]));
+    /// assert_eq!(tiled.tiles().len(), 2);
+    /// assert_eq!(tiled.nearest(&[0.0, 0.0]).unwrap().item, &[1.5, 0.5]);
+    /// ```
+    pub fn insert_by_ordered_float(&mut self, point: T) {
+        let key = self.tile_key(&point);
+        match self.tiles.binary_search_by(|(k, _)| k.cmp(&key)) {
+            Ok(i) => self.tiles[i].1.insert_by_ordered_float(point),
+            Err(i) => self
+                .tiles
+                .insert(i, (key, KdTreeN::build_by_ordered_float(vec![point]))),
+        }
+    }
+
+    /// Removes a point at the same position as `query`, rebuilding the kd-tree of its tile only.
+    /// Returns `None` if there is no such point.
+    pub fn remove_by_ordered_float(
+        &mut self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<T> {
+        let key = self.tile_key(query);
+        let i = self.tiles.binary_search_by(|(k, _)| k.cmp(&key)).ok()?;
+        let removed = self.tiles[i].1.remove_by_ordered_float(query);
+        if self.tiles[i].1.is_empty() {
+            self.tiles.remove(i);
+        }
+        removed
+    }
+
+    /// Same as [`Self::nearests`], but the items which are equal by `compare` are regarded as duplicates,
+    /// and only one of them chosen by `tie` is returned.
+    pub fn nearests_dedup_by(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+        compare: impl Fn(&T, &T) -> Ordering,
+        tie: TieBreak,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>> {
+        let shards: Vec<_> = self.tiles.iter().map(|(_, kdtree)| &**kdtree).collect();
+        nearests_dedup(&shards, query, num, compare, tie)
+    }
+
+    /// Same as [`Self::nearests`], but the points at the same position are returned only once.
+    pub fn nearests_dedup(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+        tie: TieBreak,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>> {
+        self.nearests_dedup_by(query, num, compare_coords, tie)
+    }
+
+    /// Same as [`Self::within_radius`], but the items which are equal by `compare` are regarded as duplicates,
+    /// and only one of them chosen by `tie` is returned.
+    pub fn within_radius_dedup_by(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+        compare: impl Fn(&T, &T) -> Ordering,
+        tie: TieBreak,
+    ) -> Vec<&T> {
+        let found = self
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, (key, _))| self.min_squared_distance(key, query) < radius * radius)
+            .flat_map(|(i, (_, kdtree))| {
+                kdtree
+                    .within_radius(query, radius)
+                    .into_iter()
+                    .map(move |item| (i, item))
+            })
+            .collect();
+        dedup_shards(found, |a, b| compare(a, b), tie)
+    }
+
+    /// Same as [`Self::within_radius`], but the points at the same position are returned only once.
+    pub fn within_radius_dedup(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+        tie: TieBreak,
+    ) -> Vec<&T> {
+        self.within_radius_dedup_by(query, radius, compare_coords, tie)
+    }
+
+    fn min_squared_distance(
+        &self,
+        key: &[i64],
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> T::Scalar {
+        let mut squared_distance = <T::Scalar as Zero>::zero();
+        for (k, &i) in key.iter().enumerate() {
+            // the saturated tiles extend to infinity.
+            let min = match i {
+                i64::MIN => Float::neg_infinity(),
+                _ => <T::Scalar as NumCast>::from(i).unwrap() * self.tile_size,
+            };
+            let max = match i {
+                i64::MAX => Float::infinity(),
+                _ => <T::Scalar as NumCast>::from(i).unwrap() * self.tile_size + self.tile_size,
+            };
+            let q = query.at(k);
+            let diff = Float::max(Float::max(min - q, q - max), <T::Scalar as Zero>::zero());
+            squared_distance += diff * diff;
+        }
+        squared_distance
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: KdPoint<Dim = N> + Send, N: Unsigned + Send> TiledKdTreeN<T, N>
+where
+    T::Scalar: Float + ordered_float::FloatCore,
+{
+    /// Same as [`Self::build_by_ordered_float`], but the kd-trees of the tiles are built in parallel.
+    pub fn par_build_by_ordered_float(points: Vec<T>, tile_size: T::Scalar) -> Self {
+        use rayon::prelude::*;
+        let tiles = split_into_tiles(points, tile_size)
+            .into_par_iter()
+            .map(|(key, points)| (key, KdTreeN::build_by_ordered_float(points)))
+            .collect();
+        Self { tile_size, tiles }
+    }
+}
+
+fn tile_key<P: KdPoint>(point: &P, tile_size: P::Scalar) -> Vec<i64>
+where
+    P::Scalar: Float,
+{
+    (0..P::dim())
+        .map(|k| {
+            let key = Float::floor(point.at(k) / tile_size);
+            key.to_i64().unwrap_or(if key < P::Scalar::zero() {
+                i64::MIN
+            } else {
+                i64::MAX
+            })
+        })
+        .collect()
+}
+
+fn split_into_tiles<T: KdPoint>(points: Vec<T>, tile_size: T::Scalar) -> Vec<(Vec<i64>, Vec<T>)>
+where
+    T::Scalar: Float,
+{
+    assert!(
+        tile_size > <T::Scalar as Zero>::zero(),
+        "tile_size must be positive"
+    );
+    let mut keyed: Vec<_> = points
+        .into_iter()
+        .map(|p| (tile_key(&p, tile_size), p))
+        .collect();
+    keyed.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+    let mut tiles: Vec<(Vec<i64>, Vec<T>)> = Vec::new();
+    for (key, p) in keyed {
+        match tiles.last_mut() {
+            Some((last, points)) if *last == key => points.push(p),
+            _ => tiles.push((key, vec![p])),
+        }
+    }
+    tiles
+}
+
+/// Merges `found` into `nearests` sorted by the distances, keeping the `num` nearest ones.
+fn merge_nearests<'a, T, S: PartialOrd>(
+    nearests: &mut Vec<ItemAndDistance<'a, T, S>>,
+    found: Vec<ItemAndDistance<'a, T, S>>,
+    num: usize,
+) {
+    nearests.extend(found);
+    nearests.sort_by(|a, b| {
+        a.squared_distance
+            .partial_cmp(&b.squared_distance)
+            .unwrap_or(Ordering::Equal)
+    });
+    nearests.truncate(num);
+}
+
+/// Returns the number of the cells whose Chebyshev distance from a cell is `r`, in `dim` dimensions.
+fn ring_len(r: i64, dim: u32) -> usize {
+    let side = |n: i64| (n.max(0) as usize).saturating_pow(dim);
+    side(2 * r + 1).saturating_sub(side(2 * r - 1))
+}
+
+/// Calls `f` with the keys of the cells whose Chebyshev distance from `center` is `r`.
+fn for_each_in_ring(center: &[i64], r: i64, mut f: impl FnMut(&[i64])) {
+    // the ring is clipped by the saturated tiles.
+    let min: Vec<i64> = center.iter().map(|c| c.saturating_sub(r)).collect();
+    let max: Vec<i64> = center.iter().map(|c| c.saturating_add(r)).collect();
+    let mut key = min.clone();
+    loop {
+        if key.iter().zip(center).any(|(k, c)| (k - c).abs() == r) {
+            f(&key);
+        } else {
+            // skip the interior of the ring along the last axis.
+            let last = key.len() - 1;
+            key[last] = center[last].saturating_add(r - 1);
+        }
+        if !next_key(&mut key, &min, &max) {
+            break;
+        }
+    }
+}
+
+/// Advances `key` to the next cell in the box `[min, max]` in lexicographic order, returning `false` after the last one.
+fn next_key(key: &mut [i64], min: &[i64], max: &[i64]) -> bool {
+    for k in (0..key.len()).rev() {
+        if key[k] < max[k] {
+            key[k] += 1;
+            return true;
+        }
+        key[k] = min[k];
+    }
+    false
+}
+
+/// Returns the key of the tile which contains the point shifted by `offset` on every axis,
+/// or `None` if the key is out of the range of `i64`.
+fn shifted_tile_key<P: KdPoint>(
+    point: &P,
+    offset: P::Scalar,
+    tile_size: P::Scalar,
+) -> Option<Vec<i64>>
+where
+    P::Scalar: Float,
+{
+    (0..P::dim())
+        .map(|k| Float::floor((point.at(k) + offset) / tile_size).to_i64())
+        .collect()
+}
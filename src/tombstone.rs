@@ -0,0 +1,244 @@
+use crate::nearests::kd_nearests_by_filtered;
+use crate::sort::kd_remove_by;
+use crate::{ItemAndDistance, KdPoint, KdTreeN, QueryConfig};
+use std::cmp::Ordering;
+use typenum::Unsigned;
+
+/// A kd-tree which supports soft deletion.
+///
+/// A removed item is only marked as deleted in a bitset owned by the tree, and skipped by queries.
+/// When the fraction of deleted items exceeds `rebuild_threshold`, the deleted items are dropped and the tree is rebuilt
+/// by the comparison function given on construction, so that a removal takes amortized `O(log n)` time.
+/// # Example
+/// ```
+/// let mut kdtree = kd_tree::TombstoneKdTree::build(vec![[1, 2], [3, 1], [2, 3], [5, 5]]);
+/// assert!(kdtree.remove(&[3, 1]));
+/// assert!(!kdtree.remove(&[3, 1]));
+/// assert_eq!(kdtree.len(), 3);
+/// assert_eq!(kdtree.deleted_count(), 1);
+/// assert_eq!(kdtree.nearest(&[3, 0]).unwrap().item, &[1, 2]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TombstoneKdTreeN<T, N: Unsigned> {
+    kdtree: KdTreeN<T, N>,
+    deleted: BitSet,
+    deleted_count: usize,
+    rebuild_threshold: f64,
+    compare: fn(&T, &T, usize) -> Ordering,
+}
+pub type TombstoneKdTree<T> = TombstoneKdTreeN<T, <T as KdPoint>::Dim>;
+
+/// A fixed-size set of indices, packed into bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BitSet(Vec<u64>);
+
+impl BitSet {
+    fn new(len: usize) -> Self {
+        Self(vec![0; len.div_ceil(64)])
+    }
+
+    fn contains(&self, i: usize) -> bool {
+        self.0[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn insert(&mut self, i: usize) {
+        self.0[i / 64] |= 1 << (i % 64);
+    }
+}
+
+impl<T, N: Unsigned> TombstoneKdTreeN<T, N> {
+    /// The default value of [`Self::rebuild_threshold`].
+    pub const DEFAULT_REBUILD_THRESHOLD: f64 = 0.5;
+
+    pub fn build_by(items: Vec<T>, compare: fn(&T, &T, usize) -> Ordering) -> Self {
+        let kdtree = KdTreeN::build_by(items, compare);
+        Self {
+            deleted: BitSet::new(kdtree.len()),
+            kdtree,
+            deleted_count: 0,
+            rebuild_threshold: Self::DEFAULT_REBUILD_THRESHOLD,
+            compare,
+        }
+    }
+
+    pub fn build_by_ordered_float(points: Vec<T>) -> Self
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: ordered_float::FloatCore,
+    {
+        Self::build_by(points, |item1, item2, k| {
+            ordered_float::OrderedFloat(item1.at(k)).cmp(&ordered_float::OrderedFloat(item2.at(k)))
+        })
+    }
+
+    pub fn build(points: Vec<T>) -> Self
+    where
+        T: KdPoint<Dim = N>,
+        T::Scalar: Ord,
+    {
+        Self::build_by(points, |item1, item2, k| item1.at(k).cmp(&item2.at(k)))
+    }
+
+    /// The fraction of deleted items above which the tree is rebuilt.
+    pub fn rebuild_threshold(&self) -> f64 {
+        self.rebuild_threshold
+    }
+
+    pub fn set_rebuild_threshold(&mut self, rebuild_threshold: f64) {
+        self.rebuild_threshold = rebuild_threshold;
+        self.rebuild_if_needed();
+    }
+
+    /// Returns the number of alive items.
+    pub fn len(&self) -> usize {
+        self.kdtree.len() - self.deleted_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of deleted items which are not dropped yet.
+    pub fn deleted_count(&self) -> usize {
+        self.deleted_count
+    }
+
+    /// Returns the underlying kd-tree, which contains deleted items as well.
+    pub fn kdtree(&self) -> &KdTreeN<T, N> {
+        &self.kdtree
+    }
+
+    /// Returns the alive items.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.kdtree.iter().enumerate().filter_map(move |(i, item)| {
+            if self.deleted.contains(i) {
+                None
+            } else {
+                Some(item)
+            }
+        })
+    }
+
+    pub fn into_vec(mut self) -> Vec<T> {
+        self.rebuild();
+        self.kdtree.into_vec()
+    }
+
+    /// Marks the items which satisfy `pred` as deleted, and returns the number of them.
+    pub fn remove_if(&mut self, mut pred: impl FnMut(&T) -> bool) -> usize {
+        let mut count = 0;
+        for (i, item) in self.kdtree.iter().enumerate() {
+            if !self.deleted.contains(i) && pred(item) {
+                self.deleted.insert(i);
+                count += 1;
+            }
+        }
+        self.deleted_count += count;
+        self.rebuild_if_needed();
+        count
+    }
+
+    /// Marks an alive item at the same position as `query` as deleted.
+    /// Returns `false` if there is no such item.
+    pub fn remove(&mut self, query: &impl KdPoint<Scalar = T::Scalar, Dim = N>) -> bool
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let found = self.kdtree.within_by_cmp(|item, k| {
+            item.at(k)
+                .partial_cmp(&query.at(k))
+                .unwrap_or(Ordering::Equal)
+        });
+        let index = found
+            .into_iter()
+            .map(|item| self.kdtree.offset_of(item))
+            .find(|&i| !self.deleted.contains(i));
+        match index {
+            Some(i) => {
+                self.deleted.insert(i);
+                self.deleted_count += 1;
+                self.rebuild_if_needed();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops the deleted items, and rebuilds the subtrees whose structure is broken by them.
+    pub fn rebuild(&mut self) {
+        if self.deleted_count > 0 {
+            let deleted: Vec<bool> = (0..self.kdtree.len())
+                .map(|i| self.deleted.contains(i))
+                .collect();
+            kd_remove_by(&mut self.kdtree.1, &deleted, N::to_usize(), self.compare);
+            self.deleted = BitSet::new(self.kdtree.len());
+            self.deleted_count = 0;
+        }
+    }
+
+    fn rebuild_if_needed(&mut self) {
+        if self.deleted_count as f64 > self.rebuild_threshold * self.kdtree.len() as f64 {
+            self.rebuild();
+        }
+    }
+
+    fn is_alive(&self, item: &T) -> bool {
+        !self.deleted.contains(self.kdtree.offset_of(item))
+    }
+
+    /// Returns the nearest alive item from the input point. Returns `None` if `self.is_empty()`.
+    pub fn nearest(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        self.nearests(query, 1).pop()
+    }
+
+    /// Returns kNN(k nearest neighbors) of alive items from the input point.
+    pub fn nearests(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        kd_nearests_by_filtered(
+            self.kdtree.items(),
+            query,
+            num,
+            &QueryConfig::default(),
+            |item, k| item.at(k),
+            None,
+            |item| self.is_alive(item),
+        )
+        .result
+    }
+
+    /// search alive points within a rectangular region.
+    pub fn within(&self, query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2]) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let mut results = self.kdtree.within(query);
+        results.retain(|item| self.is_alive(item));
+        results
+    }
+
+    /// search alive points within a sphere.
+    pub fn within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<&T>
+    where
+        T: KdPoint<Dim = N>,
+    {
+        let mut results = self.kdtree.within_radius(query, radius);
+        results.retain(|item| self.is_alive(item));
+        results
+    }
+}
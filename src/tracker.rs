@@ -0,0 +1,224 @@
+use crate::{kd_sort_by, ItemAndDistance, KdPoint, KdScalar};
+use std::cmp::Ordering;
+
+/// Keeps track of the nearest item of each query point while items are inserted into or removed from a tree.
+///
+/// The tracker doesn't own any tree. Instead, the owner of the tree notifies it of each update
+/// by [`Self::inserted`] or [`Self::removed`], and only the affected query points are updated.
+///
+/// The query points are kept in a kd-tree along with the maximum squared distance to their nearest items in each subtree,
+/// so that an insertion visits only the subtrees containing query points which may be nearer to the inserted item
+/// than to their current nearest items.
+/// # Example
+/// ```
+/// use kd_tree::{KdTree, NearestTracker};
+/// let mut items = vec![[0, 0], [10, 10]];
+/// let kdtree = KdTree::build(items.clone());
+/// let mut tracker = NearestTracker::new(vec![[1, 1], [9, 9]], |q| kdtree.nearest(q));
+/// assert_eq!(tracker.nearest(0).unwrap().item, &[0, 0]);
+///
+/// // insert an item
+/// items.push([1, 2]);
+/// let mut changed = vec![];
+/// tracker.inserted(&[1, 2], |i| changed.push(i));
+/// assert_eq!(changed, vec![0]);
+/// assert_eq!(tracker.nearest(0).unwrap().item, &[1, 2]);
+///
+/// // remove an item
+/// items.retain(|p| p != &[10, 10]);
+/// let kdtree = KdTree::build(items.clone());
+/// let mut changed = vec![];
+/// tracker.removed(&[10, 10], |q| kdtree.nearest(q), |i| changed.push(i));
+/// assert_eq!(changed, vec![1]);
+/// assert_eq!(tracker.nearest(1).unwrap().item, &[1, 2]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct NearestTracker<Q, T: KdPoint> {
+    queries: Vec<Q>,
+    nearests: Vec<Option<(T, T::Scalar)>>,
+    /// The indices of `queries` sorted as a kd-tree.
+    order: Vec<usize>,
+    /// The maximum squared distance to the nearest items in each subtree of `order`,
+    /// stored at the position of the median of the subtree, where `None` is regarded as infinity.
+    max_radii: Vec<Option<T::Scalar>>,
+}
+
+/// Returns the larger one, where `None` is regarded as infinity.
+fn max<S: PartialOrd>(a: Option<S>, b: Option<S>) -> Option<S> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if a < b { b } else { a }),
+        _ => None,
+    }
+}
+
+impl<Q, T> NearestTracker<Q, T>
+where
+    Q: KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+    T: KdPoint + Clone,
+{
+    /// Creates a tracker of `queries`, whose initial nearest items are searched by `nearest`.
+    pub fn new<'a>(
+        queries: Vec<Q>,
+        mut nearest: impl FnMut(&Q) -> Option<ItemAndDistance<'a, T, T::Scalar>>,
+    ) -> Self
+    where
+        T: 'a,
+    {
+        let nearests = queries
+            .iter()
+            .map(|q| nearest(q).map(|found| (found.item.clone(), found.squared_distance)))
+            .collect();
+        let mut order: Vec<usize> = (0..queries.len()).collect();
+        kd_sort_by(&mut order, T::dim(), |&i, &j, k| {
+            queries[i]
+                .at(k)
+                .partial_cmp(&queries[j].at(k))
+                .unwrap_or(Ordering::Equal)
+        });
+        let mut tracker = Self {
+            queries,
+            nearests,
+            max_radii: vec![None; order.len()],
+            order,
+        };
+        tracker.update_max_radii();
+        tracker
+    }
+
+    /// Returns the squared distance from the query point at `i` in `order` to its nearest item.
+    fn radius(&self, i: usize) -> Option<T::Scalar> {
+        self.nearests[self.order[i]]
+            .as_ref()
+            .map(|(_, squared_distance)| *squared_distance)
+    }
+
+    /// Returns the maximum radius of the subtree `range` of `order`, given those of its children.
+    fn max_radius(&self, range: std::ops::Range<usize>) -> Option<T::Scalar> {
+        let mid = range.start + range.len() / 2;
+        let mut result = self.radius(mid);
+        if range.start < mid {
+            result = max(
+                result,
+                self.max_radii[range.start + (mid - range.start) / 2],
+            );
+        }
+        if mid + 1 < range.end {
+            result = max(result, self.max_radii[mid + 1 + (range.end - mid - 1) / 2]);
+        }
+        result
+    }
+
+    fn update_max_radii(&mut self) {
+        fn recurse<Q, T: KdPoint + Clone>(
+            tracker: &mut NearestTracker<Q, T>,
+            range: std::ops::Range<usize>,
+        ) where
+            Q: KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+        {
+            if range.is_empty() {
+                return;
+            }
+            let mid = range.start + range.len() / 2;
+            recurse(tracker, range.start..mid);
+            recurse(tracker, mid + 1..range.end);
+            tracker.max_radii[mid] = tracker.max_radius(range);
+        }
+        recurse(self, 0..self.order.len());
+    }
+
+    pub fn queries(&self) -> &[Q] {
+        &self.queries
+    }
+
+    /// Returns the current nearest item of the `i`-th query point.
+    pub fn nearest(&self, i: usize) -> Option<ItemAndDistance<'_, T, T::Scalar>> {
+        self.nearests[i]
+            .as_ref()
+            .map(|(item, squared_distance)| ItemAndDistance {
+                item,
+                squared_distance: *squared_distance,
+            })
+    }
+
+    /// Updates the tracked nearest items after `item` has been inserted into the tree.
+    /// `on_change` is called with the index of each query point whose nearest item has changed.
+    pub fn inserted(&mut self, item: &T, mut on_change: impl FnMut(usize)) {
+        /// `bound` is a lower bound of the squared distance from `item` to the query points in the subtree.
+        fn recurse<Q, T: KdPoint + Clone>(
+            tracker: &mut NearestTracker<Q, T>,
+            item: &T,
+            on_change: &mut impl FnMut(usize),
+            range: std::ops::Range<usize>,
+            axis: usize,
+            bound: T::Scalar,
+        ) where
+            Q: KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+        {
+            if range.is_empty() {
+                return;
+            }
+            let mid = range.start + range.len() / 2;
+            if tracker.max_radii[mid].is_some_and(|max_radius| max_radius <= bound) {
+                return;
+            }
+            let i = tracker.order[mid];
+            let query = &tracker.queries[i];
+            let squared_distance = crate::squared_distance(item, query);
+            if tracker.nearests[i]
+                .as_ref()
+                .map_or(true, |(_, current)| squared_distance < *current)
+            {
+                tracker.nearests[i] = Some((item.clone(), squared_distance));
+                on_change(i);
+            }
+            let diff = item.at(axis) - query.at(axis);
+            let far_bound = diff * diff;
+            let far_bound = if far_bound < bound { bound } else { far_bound };
+            let (lower_bound, upper_bound) = if diff < T::Scalar::zero() {
+                (bound, far_bound)
+            } else {
+                (far_bound, bound)
+            };
+            let next = (axis + 1) % T::dim();
+            recurse(
+                tracker,
+                item,
+                on_change,
+                range.start..mid,
+                next,
+                lower_bound,
+            );
+            recurse(
+                tracker,
+                item,
+                on_change,
+                mid + 1..range.end,
+                next,
+                upper_bound,
+            );
+            tracker.max_radii[mid] = tracker.max_radius(range);
+        }
+        let len = self.order.len();
+        recurse(self, item, &mut on_change, 0..len, 0, T::Scalar::zero());
+    }
+
+    /// Updates the tracked nearest items after `item` has been removed from the tree.
+    /// Only the query points whose nearest item was `item` are searched again by `nearest`, the search on the updated tree.
+    /// `on_change` is called with the index of each of those query points.
+    pub fn removed<'a>(
+        &mut self,
+        item: &T,
+        mut nearest: impl FnMut(&Q) -> Option<ItemAndDistance<'a, T, T::Scalar>>,
+        mut on_change: impl FnMut(usize),
+    ) where
+        T: PartialEq + 'a,
+    {
+        for (i, (query, current)) in self.queries.iter().zip(&mut self.nearests).enumerate() {
+            if current.as_ref().is_some_and(|(found, _)| found == item) {
+                *current = nearest(query).map(|found| (found.item.clone(), found.squared_distance));
+                on_change(i);
+            }
+        }
+        self.update_max_radii();
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{ItemAndDistance, KdPoint, KdTreeN};
+use typenum::Unsigned;
+
+/// An owned kd-tree which applies a coordinate transform, such as log-scaling or a unit conversion,
+/// to the stored points on build and to the query points on query.
+///
+/// The transform `F` maps the coordinate on the `k`-th axis as `transform(x, k)`,
+/// so the tree and the queries can never disagree about the units.
+/// Distances, including the radius of [`Self::within_radius`], are measured in the transformed space.
+/// [`Self::within`] expects the transform to be monotonically increasing on each axis.
+/// # Example
+/// ```
+/// use kd_tree::TransformedKdTree;
+/// // points given in meters, searched in kilometers.
+/// let kdtree = TransformedKdTree::build_by_ordered_float(
+///     vec![[1000.0, 2000.0], [3000.0, 1000.0], [2000.0, 3000.0]],
+///     |x: f64, _k: usize| x / 1000.0,
+/// );
+/// let found = kdtree.nearest(&[3100.0, 900.0]).unwrap();
+/// assert_eq!(found.item, &[3000.0, 1000.0]);
+/// assert_eq!(kdtree.within_radius(&[2000.0, 2000.0], 1.1).len(), 2);
+/// ```
+#[derive(Clone)]
+pub struct TransformedKdTreeN<T, F, N: Unsigned> {
+    transform: F,
+    kdtree: KdTreeN<T, N>,
+}
+pub type TransformedKdTree<T, F> = TransformedKdTreeN<T, F, <T as KdPoint>::Dim>;
+
+/// A query point whose coordinates are transformed.
+struct Transformed<'a, P, F> {
+    point: &'a P,
+    transform: &'a F,
+}
+
+impl<'a, P: KdPoint, F: Fn(P::Scalar, usize) -> P::Scalar> KdPoint for Transformed<'a, P, F> {
+    type Scalar = P::Scalar;
+    type Dim = P::Dim;
+    fn at(&self, k: usize) -> Self::Scalar {
+        (self.transform)(self.point.at(k), k)
+    }
+}
+
+impl<T: KdPoint<Dim = N>, F: Fn(T::Scalar, usize) -> T::Scalar, N: Unsigned>
+    TransformedKdTreeN<T, F, N>
+{
+    pub fn build_by_key<Key, G>(items: Vec<T>, transform: F, kd_key: G) -> Self
+    where
+        Key: Ord,
+        G: Fn(T::Scalar) -> Key + Copy,
+    {
+        let kdtree = {
+            let transform = &transform;
+            KdTreeN::build_by(items, |item1, item2, k| {
+                kd_key(transform(item1.at(k), k)).cmp(&kd_key(transform(item2.at(k), k)))
+            })
+        };
+        Self { transform, kdtree }
+    }
+
+    pub fn build_by_ordered_float(points: Vec<T>, transform: F) -> Self
+    where
+        T::Scalar: ordered_float::FloatCore,
+    {
+        Self::build_by_key(points, transform, ordered_float::OrderedFloat)
+    }
+
+    pub fn build(points: Vec<T>, transform: F) -> Self
+    where
+        T::Scalar: Ord,
+    {
+        Self::build_by_key(points, transform, |x| x)
+    }
+
+    pub fn transform(&self) -> &F {
+        &self.transform
+    }
+
+    /// Returns the items in their original coordinates.
+    pub fn items(&self) -> &[T] {
+        self.kdtree.items()
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.kdtree.into_vec()
+    }
+
+    fn transformed<'a, P>(&'a self, point: &'a P) -> Transformed<'a, P, F> {
+        Transformed {
+            point,
+            transform: &self.transform,
+        }
+    }
+
+    fn coord(&self, item: &T, k: usize) -> T::Scalar {
+        (self.transform)(item.at(k), k)
+    }
+
+    /// Returns the nearest item from the input point. Returns `None` if `self.is_empty()`.
+    pub fn nearest(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+    ) -> Option<ItemAndDistance<'_, T, T::Scalar>> {
+        self.kdtree
+            .nearest_by(&self.transformed(query), |item, k| self.coord(item, k))
+    }
+
+    /// Returns kNN(k nearest neighbors) from the input point.
+    pub fn nearests(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        num: usize,
+    ) -> Vec<ItemAndDistance<'_, T, T::Scalar>> {
+        self.kdtree
+            .nearests_by(&self.transformed(query), num, |item, k| self.coord(item, k))
+    }
+
+    /// search points within a rectangular region
+    pub fn within(&self, query: &[impl KdPoint<Scalar = T::Scalar, Dim = N>; 2]) -> Vec<&T> {
+        self.kdtree.within_by(
+            &[self.transformed(&query[0]), self.transformed(&query[1])],
+            |item, k| self.coord(item, k),
+        )
+    }
+
+    /// search points within k-dimensional sphere, whose radius is measured in the transformed space
+    pub fn within_radius(
+        &self,
+        query: &impl KdPoint<Scalar = T::Scalar, Dim = N>,
+        radius: T::Scalar,
+    ) -> Vec<&T> {
+        self.kdtree
+            .within_radius_by(&self.transformed(query), radius, |item, k| {
+                self.coord(item, k)
+            })
+    }
+}
+
+impl<T: std::fmt::Debug, F, N: Unsigned> std::fmt::Debug for TransformedKdTreeN<T, F, N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransformedKdTreeN")
+            .field("items", &self.kdtree.items())
+            .finish_non_exhaustive()
+    }
+}
@@ -1,42 +1,683 @@
+use crate::config::Traversal;
+use crate::{CountEstimate, KdPoint, KdScalar, PartialResult, QueryConfig};
 use std::cmp::Ordering;
 
+/// Returns the items in `kdtree` for which `compare(item, k)` returns `Ordering::Equal` on every axis `k`.
+/// `compare(item, k)` returns `Ordering::Less` if `item` is below the query region along the `k`-th axis,
+/// and `Ordering::Greater` if above it.
 pub fn kd_within_by_cmp<T>(
     kdtree: &[T],
     dim: usize,
     compare: impl Fn(&T, usize) -> Ordering + Copy,
 ) -> Vec<&T> {
+    kd_within_by_cmp_with_config(kdtree, dim, compare, &QueryConfig::default()).result
+}
+
+/// Same as [`kd_within_by_cmp`], but with tuning parameters and options given by `config`.
+pub fn kd_within_by_cmp_with_config<'a, T>(
+    kdtree: &'a [T],
+    dim: usize,
+    compare: impl Fn(&T, usize) -> Ordering + Copy,
+    config: &QueryConfig,
+) -> PartialResult<Vec<&'a T>> {
+    /// The hooks of `traversal` are called only if `TRACKED`.
+    struct Search<C, const TRACKED: bool> {
+        dim: usize,
+        compare: C,
+        leaf_size: usize,
+        traversal: Traversal,
+    }
+    impl<C, const TRACKED: bool> Search<C, TRACKED> {
+        fn recurse<'a, T>(
+            &mut self,
+            results: &mut Vec<&'a T>,
+            kdtree: &'a [T],
+            axis: usize,
+            depth: usize,
+        ) where
+            C: Fn(&T, usize) -> Ordering + Copy,
+        {
+            if kdtree.is_empty() {
+                return;
+            }
+            if depth == 0 || (TRACKED && self.traversal.is_exhausted()) {
+                self.traversal.is_exact = false;
+                return;
+            }
+            if TRACKED {
+                self.traversal.enter_subtree();
+            }
+            let compare = self.compare;
+            if kdtree.len() <= self.leaf_size {
+                for item in kdtree {
+                    if TRACKED && !self.traversal.visit_item() {
+                        return;
+                    }
+                    if (0..self.dim).all(|k| compare(item, k) == Ordering::Equal) {
+                        results.push(item);
+                    }
+                }
+                return;
+            }
+            let axis = axis % self.dim;
+            let (lower, item, upper) = {
+                let mid = kdtree.len() / 2;
+                (&kdtree[..mid], &kdtree[mid], &kdtree[mid + 1..])
+            };
+            if TRACKED {
+                self.traversal.visit_item();
+            }
+            match compare(item, axis) {
+                Ordering::Equal => {
+                    if (1..self.dim)
+                        .all(|k| compare(item, (axis + k) % self.dim) == Ordering::Equal)
+                    {
+                        results.push(item);
+                    }
+                    self.recurse(results, lower, axis + 1, depth - 1);
+                    self.recurse(results, upper, axis + 1, depth - 1);
+                }
+                Ordering::Less => {
+                    self.recurse(results, upper, axis + 1, depth - 1);
+                }
+                Ordering::Greater => {
+                    self.recurse(results, lower, axis + 1, depth - 1);
+                }
+            }
+        }
+    }
+    let mut results = Vec::new();
+    let depth = config.max_depth.saturating_add(1);
+    let traversal = if config.tracks_traversal() {
+        let mut search = Search::<_, true> {
+            dim,
+            compare,
+            leaf_size: config.leaf_size_for(kdtree.len()),
+            traversal: Traversal::new(config),
+        };
+        search.recurse(&mut results, kdtree, 0, depth);
+        search.traversal
+    } else {
+        let mut search = Search::<_, false> {
+            dim,
+            compare,
+            leaf_size: config.leaf_size_for(kdtree.len()),
+            traversal: Traversal::new(config),
+        };
+        search.recurse(&mut results, kdtree, 0, depth);
+        search.traversal
+    };
+    traversal.finish(results)
+}
+
+/// Same as [`kd_within_by_cmp`], except that items are accepted by `accept` and
+/// at most `max_per_cell` items are collected from each cell, a subtree with at most `cell_size` items.
+/// Each splitting item above the cells forms a cell by itself.
+pub fn kd_within_stratified_by<T>(
+    kdtree: &[T],
+    dim: usize,
+    compare: impl Fn(&T, usize) -> Ordering + Copy,
+    accept: impl Fn(&T) -> bool + Copy,
+    cell_size: usize,
+    max_per_cell: usize,
+) -> Vec<&T> {
+    #[allow(clippy::too_many_arguments)]
     fn recurse<'a, T>(
         results: &mut Vec<&'a T>,
         kdtree: &'a [T],
         axis: usize,
         dim: usize,
         compare: impl Fn(&T, usize) -> Ordering + Copy,
+        accept: impl Fn(&T) -> bool + Copy,
+        cell: (usize, usize),
+        remaining: Option<&mut usize>,
     ) {
+        let (cell_size, max_per_cell) = cell;
         if kdtree.is_empty() {
             return;
         }
+        let mut remaining = match remaining {
+            None if kdtree.len() <= cell_size => {
+                let mut remaining = max_per_cell;
+                recurse(
+                    results,
+                    kdtree,
+                    axis,
+                    dim,
+                    compare,
+                    accept,
+                    cell,
+                    Some(&mut remaining),
+                );
+                return;
+            }
+            Some(&mut 0) => return,
+            remaining => remaining,
+        };
         let axis = axis % dim;
         let (lower, item, upper) = {
             let mid = kdtree.len() / 2;
             (&kdtree[..mid], &kdtree[mid], &kdtree[mid + 1..])
         };
-        match compare(item, axis) {
-            Ordering::Equal => {
-                if (1..dim).all(|k| compare(item, (axis + k) % dim) == Ordering::Equal) {
+        let ordering = compare(item, axis);
+        if ordering == Ordering::Equal && accept(item) {
+            match remaining.as_deref_mut() {
+                Some(remaining) => {
                     results.push(item);
+                    *remaining -= 1;
+                }
+                None if max_per_cell > 0 => results.push(item),
+                None => {}
+            }
+        }
+        if ordering != Ordering::Less {
+            recurse(
+                results,
+                lower,
+                axis + 1,
+                dim,
+                compare,
+                accept,
+                cell,
+                remaining.as_deref_mut(),
+            );
+        }
+        if ordering != Ordering::Greater {
+            recurse(
+                results,
+                upper,
+                axis + 1,
+                dim,
+                compare,
+                accept,
+                cell,
+                remaining,
+            );
+        }
+    }
+    let mut results = Vec::new();
+    recurse(
+        &mut results,
+        kdtree,
+        0,
+        dim,
+        compare,
+        accept,
+        (cell_size, max_per_cell),
+        None,
+    );
+    results
+}
+
+/// Same as [`kd_within_by_cmp`], but `f` is called for each item found instead of collecting them into a `Vec`.
+pub fn kd_for_each_within_by_cmp<'a, T>(
+    kdtree: &'a [T],
+    dim: usize,
+    compare: impl Fn(&T, usize) -> Ordering + Copy,
+    f: impl FnMut(&'a T),
+) {
+    kd_for_each_within_by_predicates(
+        kdtree,
+        dim,
+        compare,
+        |item| (0..dim).all(|k| compare(item, k) == Ordering::Equal),
+        f,
+    )
+}
+
+/// Calls `f` for each item which satisfies `accept`.
+/// `prune(item, axis)` returns `Ordering::Less` if no accepted items are less than or equal to `item` along `axis`,
+/// `Ordering::Greater` if no accepted items are greater than or equal to `item` along `axis`, and `Ordering::Equal` otherwise.
+pub fn kd_for_each_within_by_predicates<'a, T>(
+    kdtree: &'a [T],
+    dim: usize,
+    prune: impl Fn(&T, usize) -> Ordering + Copy,
+    accept: impl Fn(&T) -> bool + Copy,
+    mut f: impl FnMut(&'a T),
+) {
+    fn recurse<'a, T>(
+        kdtree: &'a [T],
+        axis: usize,
+        dim: usize,
+        prune: impl Fn(&T, usize) -> Ordering + Copy,
+        accept: impl Fn(&T) -> bool + Copy,
+        f: &mut impl FnMut(&'a T),
+    ) {
+        if kdtree.is_empty() {
+            return;
+        }
+        let axis = axis % dim;
+        let (lower, item, upper) = {
+            let mid = kdtree.len() / 2;
+            (&kdtree[..mid], &kdtree[mid], &kdtree[mid + 1..])
+        };
+        match prune(item, axis) {
+            Ordering::Equal => {
+                if accept(item) {
+                    f(item);
+                }
+                recurse(lower, axis + 1, dim, prune, accept, f);
+                recurse(upper, axis + 1, dim, prune, accept, f);
+            }
+            Ordering::Less => recurse(upper, axis + 1, dim, prune, accept, f),
+            Ordering::Greater => recurse(lower, axis + 1, dim, prune, accept, f),
+        }
+    }
+    recurse(kdtree, 0, dim, prune, accept, &mut f);
+}
+
+/// Returns the number of items within the box `[query[0], query[1]]`.
+///
+/// The region of each subtree is tracked by the splitting planes above it,
+/// and a subtree whose region is contained in the box is counted by its length without visiting its items.
+pub fn kd_count_within<T: KdPoint>(
+    kdtree: &[T],
+    query: &[impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>; 2],
+) -> usize {
+    /// The lower and upper bounds of the region on each axis, which are `None` if unbounded.
+    type Region<S> = Vec<(Option<S>, Option<S>)>;
+    fn recurse<T: KdPoint>(
+        kdtree: &[T],
+        axis: usize,
+        region: &mut Region<T::Scalar>,
+        query: &[impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>; 2],
+    ) -> usize {
+        if kdtree.is_empty() {
+            return 0;
+        }
+        let is_contained = region.iter().enumerate().all(|(k, bounds)| match bounds {
+            (Some(lower), Some(upper)) => query[0].at(k) <= *lower && *upper <= query[1].at(k),
+            _ => false,
+        });
+        if is_contained {
+            return kdtree.len();
+        }
+        let mid_idx = kdtree.len() / 2;
+        let item = &kdtree[mid_idx];
+        let mid_pos = item.at(axis);
+        let is_inside =
+            (0..T::dim()).all(|k| query[0].at(k) <= item.at(k) && item.at(k) <= query[1].at(k));
+        let mut count = is_inside as usize;
+        let next_axis = (axis + 1) % T::dim();
+        let bounds = region[axis];
+        if query[0].at(axis) <= mid_pos {
+            region[axis].1 = Some(mid_pos);
+            count += recurse(&kdtree[..mid_idx], next_axis, region, query);
+            region[axis] = bounds;
+        }
+        if mid_pos <= query[1].at(axis) {
+            region[axis].0 = Some(mid_pos);
+            count += recurse(&kdtree[mid_idx + 1..], next_axis, region, query);
+            region[axis] = bounds;
+        }
+        count
+    }
+    let mut region = vec![(None, None); T::dim()];
+    recurse(kdtree, 0, &mut region, query)
+}
+
+/// Returns the items whose distance from the segment between `a` and `b` is less than `radius`.
+///
+/// The region of each subtree is tracked by the splitting planes above it, clipped by the bounding box of the capsule,
+/// and a subtree is pruned if its region is farther than `radius` from the segment, which is bounded from below without division
+/// by the distance to the nearer end of the segment if the region is beyond it,
+/// and by the distance to the line through the segment projected onto each pair of axes.
+pub fn kd_within_capsule<'a, T: KdPoint>(
+    kdtree: &'a [T],
+    a: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+    b: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+    radius: T::Scalar,
+) -> Vec<&'a T> {
+    struct Capsule<S> {
+        a: Vec<S>,
+        b: Vec<S>,
+        /// `b - a`
+        dir: Vec<S>,
+        squared_length: S,
+        squared_radius: S,
+    }
+    impl<S: crate::KdScalar> Capsule<S> {
+        fn min_max(x: S, y: S) -> (S, S) {
+            if x < y {
+                (x, y)
+            } else {
+                (y, x)
+            }
+        }
+
+        /// The squared distance from `p` to the region.
+        fn region_squared_distance(region: &[(S, S)], p: &[S]) -> S {
+            let mut squared_distance = S::zero();
+            for (&(lower, upper), &x) in region.iter().zip(p) {
+                let diff = if x < lower {
+                    lower - x
+                } else if x > upper {
+                    x - upper
+                } else {
+                    S::zero()
+                };
+                squared_distance += diff * diff;
+            }
+            squared_distance
+        }
+
+        /// Returns `false` if the region is entirely out of the capsule.
+        fn may_intersect(&self, region: &[(S, S)]) -> bool {
+            if region.iter().any(|(lower, upper)| upper < lower) {
+                return false;
+            }
+            // the range of the dot product of `p - a` and `b - a` for the points `p` in the region.
+            let (mut min_dot, mut max_dot) = (S::zero(), S::zero());
+            for (k, &(lower, upper)) in region.iter().enumerate() {
+                let (min, max) = Self::min_max(
+                    (lower - self.a[k]) * self.dir[k],
+                    (upper - self.a[k]) * self.dir[k],
+                );
+                min_dot += min;
+                max_dot += max;
+            }
+            if max_dot <= S::zero() {
+                return Self::region_squared_distance(region, &self.a) < self.squared_radius;
+            }
+            if min_dot >= self.squared_length {
+                return Self::region_squared_distance(region, &self.b) < self.squared_radius;
+            }
+            // the projection onto the `i`-th and `j`-th axes doesn't make the distance longer.
+            for i in 0..region.len() {
+                for j in i + 1..region.len() {
+                    let squared_length = self.dir[i] * self.dir[i] + self.dir[j] * self.dir[j];
+                    if squared_length.is_zero() {
+                        continue;
+                    }
+                    // the cross product of `p - a` and `b - a`, projected onto the axes.
+                    let term = |k: usize, l: usize| {
+                        let (lower, upper) = region[k];
+                        Self::min_max(
+                            (lower - self.a[k]) * self.dir[l],
+                            (upper - self.a[k]) * self.dir[l],
+                        )
+                    };
+                    let ((min_i, max_i), (min_j, max_j)) = (term(i, j), term(j, i));
+                    let (min_cross, max_cross) = (min_i - max_j, max_i - min_j);
+                    let bound = self.squared_radius * squared_length;
+                    if (min_cross > S::zero() && min_cross * min_cross >= bound)
+                        || (max_cross < S::zero() && max_cross * max_cross >= bound)
+                    {
+                        return false;
+                    }
                 }
-                recurse(results, lower, axis + 1, dim, compare);
-                recurse(results, upper, axis + 1, dim, compare);
             }
-            Ordering::Less => {
-                recurse(results, upper, axis + 1, dim, compare);
+            true
+        }
+
+        fn contains(&self, region: &[(S, S)], item: &impl KdPoint<Scalar = S>) -> bool {
+            if !(0..region.len()).all(|k| region[k].0 <= item.at(k) && item.at(k) <= region[k].1) {
+                return false;
+            }
+            // `dot / squared_length` is the parameter of the projection of `item` onto the line through `a` and `b`.
+            let mut dot = S::zero();
+            let (mut to_a, mut to_b) = (S::zero(), S::zero());
+            for k in 0..region.len() {
+                let (diff_a, diff_b) = (item.at(k) - self.a[k], item.at(k) - self.b[k]);
+                dot += diff_a * self.dir[k];
+                to_a += diff_a * diff_a;
+                to_b += diff_b * diff_b;
+            }
+            if dot <= S::zero() {
+                to_a < self.squared_radius
+            } else if dot >= self.squared_length {
+                to_b < self.squared_radius
+            } else {
+                to_a * self.squared_length - dot * dot < self.squared_radius * self.squared_length
+            }
+        }
+
+        fn recurse<'a, T: KdPoint<Scalar = S>>(
+            &self,
+            results: &mut Vec<&'a T>,
+            kdtree: &'a [T],
+            axis: usize,
+            region: &mut [(S, S)],
+            bounds: &[(S, S)],
+        ) {
+            if kdtree.is_empty() || !self.may_intersect(region) {
+                return;
+            }
+            let mid_idx = kdtree.len() / 2;
+            let item = &kdtree[mid_idx];
+            if self.contains(bounds, item) {
+                results.push(item);
+            }
+            let mid_pos = item.at(axis);
+            let next_axis = (axis + 1) % T::dim();
+            let saved = region[axis];
+            if mid_pos < region[axis].1 {
+                region[axis].1 = mid_pos;
             }
-            Ordering::Greater => {
-                recurse(results, lower, axis + 1, dim, compare);
+            self.recurse(results, &kdtree[..mid_idx], next_axis, region, bounds);
+            region[axis] = saved;
+            if region[axis].0 < mid_pos {
+                region[axis].0 = mid_pos;
             }
+            self.recurse(results, &kdtree[mid_idx + 1..], next_axis, region, bounds);
+            region[axis] = saved;
         }
     }
+    let a: Vec<_> = (0..T::dim()).map(|k| a.at(k)).collect();
+    let b: Vec<_> = (0..T::dim()).map(|k| b.at(k)).collect();
+    let dir: Vec<_> = (0..T::dim()).map(|k| b[k] - a[k]).collect();
+    let capsule = Capsule {
+        squared_length: dir.iter().fold(T::Scalar::zero(), |sum, &d| sum + d * d),
+        squared_radius: radius * radius,
+        a,
+        b,
+        dir,
+    };
+    // the bounding box of the capsule, which clips the regions of the subtrees.
+    let bounds: Vec<_> = (0..T::dim())
+        .map(|k| {
+            let (min, max) = Capsule::min_max(capsule.a[k], capsule.b[k]);
+            (min - radius, max + radius)
+        })
+        .collect();
     let mut results = Vec::new();
-    recurse(&mut results, kdtree, 0, dim, compare);
+    capsule.recurse(&mut results, kdtree, 0, &mut bounds.clone(), &bounds);
     results
 }
+
+/// Estimates the number of items within the sphere, refining the subtrees partially overlapping the sphere
+/// from the largest one until the relative error is guaranteed to be at most `max_rel_error`.
+///
+/// As in `kd_count_within`, the region of each subtree is tracked by the splitting planes above it.
+/// The items of the subtrees entirely inside the sphere are counted by their lengths into `lower`,
+/// and the items of the partially overlapping subtrees are counted into `upper` as well.
+pub fn kd_estimate_within_radius_count<T: KdPoint>(
+    kdtree: &[T],
+    query: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+    radius: T::Scalar,
+    max_rel_error: f64,
+) -> CountEstimate {
+    /// The lower and upper bounds of the region on each axis, which are `None` if unbounded.
+    type Region<S> = Vec<(Option<S>, Option<S>)>;
+    /// A subtree partially overlapping the sphere, ordered by its length.
+    struct Subtree<S> {
+        offset: usize,
+        len: usize,
+        axis: usize,
+        region: Region<S>,
+    }
+    impl<S> PartialEq for Subtree<S> {
+        fn eq(&self, other: &Self) -> bool {
+            self.len == other.len
+        }
+    }
+    impl<S> Eq for Subtree<S> {}
+    impl<S> PartialOrd for Subtree<S> {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+    impl<S> Ord for Subtree<S> {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.len.cmp(&other.len)
+        }
+    }
+    let squared_radius = radius * radius;
+    let mut estimate = CountEstimate {
+        lower: 0,
+        upper: kdtree.len(),
+    };
+    let mut queue = std::collections::BinaryHeap::new();
+    if !kdtree.is_empty() {
+        queue.push(Subtree {
+            offset: 0,
+            len: kdtree.len(),
+            axis: 0,
+            region: vec![(None, None); T::dim()],
+        });
+    }
+    while let Some(subtree) = queue.pop() {
+        // the error of `estimate.estimate()` is at most a half of the uncertain items, and `lower` is at most the exact count.
+        let max_error = (estimate.upper - estimate.lower).div_ceil(2);
+        if max_error as f64 <= max_rel_error * estimate.lower as f64 {
+            break;
+        }
+        let mid_idx = subtree.offset + subtree.len / 2;
+        let item = &kdtree[mid_idx];
+        let mid_pos = item.at(subtree.axis);
+        if crate::squared_distance(item, query) < squared_radius {
+            estimate.lower += 1;
+        } else {
+            estimate.upper -= 1;
+        }
+        let next_axis = (subtree.axis + 1) % T::dim();
+        let mut lower_region = subtree.region.clone();
+        lower_region[subtree.axis].1 = Some(mid_pos);
+        let mut upper_region = subtree.region;
+        upper_region[subtree.axis].0 = Some(mid_pos);
+        let children = [
+            (subtree.offset, subtree.len / 2, lower_region),
+            (mid_idx + 1, subtree.len - subtree.len / 2 - 1, upper_region),
+        ];
+        for (offset, len, region) in children {
+            if len == 0 {
+                continue;
+            }
+            match region_squared_distances(&region, query) {
+                (min, _) if min >= squared_radius => estimate.upper -= len,
+                (_, Some(max)) if max < squared_radius => estimate.lower += len,
+                _ => queue.push(Subtree {
+                    offset,
+                    len,
+                    axis: next_axis,
+                    region,
+                }),
+            }
+        }
+    }
+    estimate
+}
+
+/// Counts the items in each bin of the distances from the query, where the `i`-th bin is `[edges[i], edges[i + 1])`
+/// given by the squared edges.
+///
+/// As in `kd_count_within`, the region of each subtree is tracked by the splitting planes above it,
+/// and a subtree whose region falls in a single bin is counted by its length without visiting its items.
+pub fn kd_distance_histogram<T: KdPoint>(
+    kdtree: &[T],
+    query: &impl KdPoint<Scalar = T::Scalar, Dim = T::Dim>,
+    squared_edges: &[T::Scalar],
+) -> Vec<usize> {
+    /// The lower and upper bounds of the region on each axis, which are `None` if unbounded.
+    type Region<S> = Vec<(Option<S>, Option<S>)>;
+    struct Histogram<'a, S, Q> {
+        query: &'a Q,
+        squared_edges: &'a [S],
+        counts: Vec<usize>,
+    }
+    impl<S: crate::KdScalar, Q: KdPoint<Scalar = S>> Histogram<'_, S, Q> {
+        /// Returns the index of the bin of a squared distance plus one, which is zero or `counts.len() + 1` out of the bins.
+        fn bin(&self, squared_distance: S) -> usize {
+            self.squared_edges
+                .partition_point(|&edge| edge <= squared_distance)
+        }
+        fn recurse<T: KdPoint<Scalar = S, Dim = Q::Dim>>(
+            &mut self,
+            kdtree: &[T],
+            axis: usize,
+            region: &mut Region<S>,
+        ) {
+            if kdtree.is_empty() {
+                return;
+            }
+            let (min, max) = region_squared_distances(region, self.query);
+            let min_bin = self.bin(min);
+            if min_bin > self.counts.len() {
+                return;
+            }
+            if let Some(max) = max {
+                let max_bin = self.bin(max);
+                if max_bin == 0 {
+                    return;
+                }
+                if min_bin == max_bin {
+                    self.counts[min_bin - 1] += kdtree.len();
+                    return;
+                }
+            }
+            let mid_idx = kdtree.len() / 2;
+            let item = &kdtree[mid_idx];
+            let bin = self.bin(crate::squared_distance(item, self.query));
+            if 0 < bin && bin <= self.counts.len() {
+                self.counts[bin - 1] += 1;
+            }
+            let mid_pos = item.at(axis);
+            let next_axis = (axis + 1) % T::dim();
+            let bounds = region[axis];
+            region[axis].1 = Some(mid_pos);
+            self.recurse(&kdtree[..mid_idx], next_axis, region);
+            region[axis] = (Some(mid_pos), bounds.1);
+            self.recurse(&kdtree[mid_idx + 1..], next_axis, region);
+            region[axis] = bounds;
+        }
+    }
+    let mut histogram = Histogram {
+        query,
+        squared_edges,
+        counts: vec![0; squared_edges.len().saturating_sub(1)],
+    };
+    histogram.recurse(kdtree, 0, &mut vec![(None, None); T::dim()]);
+    histogram.counts
+}
+
+/// Returns the squared distances from the query to the nearest and the farthest points of the region,
+/// given by the lower and upper bounds on each axis which are `None` if unbounded.
+/// The latter is `None` if the region is unbounded.
+fn region_squared_distances<S: crate::KdScalar>(
+    region: &[(Option<S>, Option<S>)],
+    query: &impl KdPoint<Scalar = S>,
+) -> (S, Option<S>) {
+    let mut min = S::zero();
+    let mut max = Some(S::zero());
+    for (k, bounds) in region.iter().enumerate() {
+        let x = query.at(k);
+        let diff = match *bounds {
+            (Some(lower), _) if x < lower => lower - x,
+            (_, Some(upper)) if x > upper => x - upper,
+            _ => S::zero(),
+        };
+        min += diff * diff;
+        max = match (max, *bounds) {
+            (Some(max), (Some(lower), Some(upper))) => {
+                let diff = if x - lower > upper - x {
+                    x - lower
+                } else {
+                    upper - x
+                };
+                Some(max + diff * diff)
+            }
+            _ => None,
+        };
+    }
+    (min, max)
+}